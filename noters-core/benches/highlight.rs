@@ -0,0 +1,43 @@
+// rough timings for the per-keystroke and per-frame paths, run with `cargo bench -p noters-core`
+use noters_core::note::{Note, highlight_parse};
+use std::hint::black_box;
+use std::time::Instant;
+
+fn sample(sections: usize) -> String {
+    let mut text = String::new();
+    for i in 0..sections {
+        text += &format!("# Heading {}\n", i);
+        text += "Some **bold** and _italic_ text with a @@link/to/note and `code`.\n";
+        text += "## Sub heading\nA plain paragraph that goes on for a while without markup.\n";
+        text += "```\nlet x = 1;\n```\n";
+    }
+    return text;
+}
+
+fn bench(name: &str, iterations: u32, mut run: impl FnMut()) {
+    run();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        run();
+    }
+    println!(
+        "{:<24} {:>10.1?} per run",
+        name,
+        start.elapsed() / iterations
+    );
+}
+
+fn main() {
+    let text = sample(200);
+    bench("highlight_parse", 200, || {
+        black_box(highlight_parse(black_box(&text)));
+    });
+    bench("Note::new", 200, || {
+        black_box(Note::new(black_box(text.clone())));
+    });
+    let mut note = Note::new(text.clone());
+    bench("insert_text", 200, || {
+        note.insert_text("x", 20);
+        note.delete_char_range(20..21);
+    });
+}
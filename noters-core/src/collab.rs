@@ -0,0 +1,303 @@
+use automerge::sync::{self, SyncDoc};
+use automerge::transaction::Transactable;
+use automerge::{AutoCommit, Cursor, ObjId, ObjType, ROOT, ReadDoc, TextEncoding};
+use std::collections::HashMap;
+use std::ops::Range;
+
+// collaborative editing of one note: its full text lives in an automerge document that every
+//   instance keeps a copy of, and they swap sync messages until the copies agree
+//   the transport is left to whoever holds the session, a peer is just a number to it
+
+// the change that turns one text into another, as a char range of the old text and what replaces it
+pub fn splice(old: &str, new: &str) -> Option<(Range<usize>, String)> {
+    if old == new {
+        return None;
+    }
+    let old: Vec<char> = old.chars().collect();
+    let new: Vec<char> = new.chars().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let inserted: String = new[prefix..new.len() - suffix].iter().collect();
+    return Some((prefix..old.len() - suffix, inserted));
+}
+
+// where a position ends up after a splice, positions inside the replaced text go to its end
+pub fn moved(pos: usize, range: &Range<usize>, inserted: usize) -> usize {
+    if pos >= range.end {
+        return pos - range.len() + inserted;
+    }
+    if pos > range.start {
+        return range.start + inserted;
+    }
+    return pos;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    // an automerge sync message
+    Sync(Vec<u8>),
+    // where someone's caret is, as an automerge cursor that survives edits around it
+    Cursor { name: String, cursor: Vec<u8> },
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            Message::Sync(sync) => {
+                bytes.push(0);
+                bytes.extend_from_slice(sync);
+            }
+            Message::Cursor { name, cursor } => {
+                bytes.push(1);
+                bytes.push(name.len().min(255) as u8);
+                bytes.extend_from_slice(&name.as_bytes()[..name.len().min(255)]);
+                bytes.extend_from_slice(cursor);
+            }
+        }
+        return bytes;
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (tag, rest) = bytes.split_first()?;
+        return match tag {
+            0 => Some(Message::Sync(rest.to_vec())),
+            1 => {
+                let (len, rest) = rest.split_first()?;
+                let len = *len as usize;
+                if rest.len() < len {
+                    return None;
+                }
+                let name = String::from_utf8_lossy(&rest[..len]).into_owned();
+                Some(Message::Cursor {
+                    name,
+                    cursor: rest[len..].to_vec(),
+                })
+            }
+            _ => None,
+        };
+    }
+}
+
+pub struct Session {
+    doc: AutoCommit,
+    // the text object, a session that's joining doesn't have it until the first sync
+    text: Option<ObjId>,
+    peers: HashMap<u64, sync::State>,
+    // the text as of the last local change or remote merge
+    last: String,
+    // other people's carets by name
+    cursors: HashMap<String, Vec<u8>>,
+}
+
+impl Session {
+    // share a note that's open here
+    pub fn start(text: &str) -> Self {
+        let mut doc = AutoCommit::new_with_encoding(TextEncoding::UnicodeCodePoint);
+        let obj = doc
+            .put_object(ROOT, "text", ObjType::Text)
+            .expect("a new document takes a text object");
+        doc.splice_text(&obj, 0, 0, text)
+            .expect("a new text object takes text");
+        return Self {
+            doc,
+            text: Some(obj),
+            peers: HashMap::new(),
+            last: text.to_string(),
+            cursors: HashMap::new(),
+        };
+    }
+
+    // join someone else's note, the text arrives with their first sync message
+    pub fn join() -> Self {
+        return Self {
+            doc: AutoCommit::new_with_encoding(TextEncoding::UnicodeCodePoint),
+            text: None,
+            peers: HashMap::new(),
+            last: String::new(),
+            cursors: HashMap::new(),
+        };
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        return self.text.as_ref().map(|_| self.last.as_str());
+    }
+
+    pub fn add_peer(&mut self, peer: u64) {
+        self.peers.insert(peer, sync::State::new());
+    }
+
+    pub fn remove_peer(&mut self, peer: u64) {
+        self.peers.remove(&peer);
+    }
+
+    // record an edit made here, the text is the note's full text afterwards
+    pub fn local_change(&mut self, text: &str) {
+        let Some(obj) = &self.text else {
+            return;
+        };
+        let Some((range, inserted)) = splice(&self.last, text) else {
+            return;
+        };
+        match self
+            .doc
+            .splice_text(obj, range.start, range.len() as isize, &inserted)
+        {
+            Ok(()) => self.last = text.to_string(),
+            Err(e) => tracing::warn!("couldn't record an edit for the session: {}", e),
+        }
+    }
+
+    // the messages each peer needs to catch up, empty once everyone agrees
+    pub fn outgoing(&mut self) -> Vec<(u64, Message)> {
+        let mut messages = Vec::new();
+        for (peer, state) in self.peers.iter_mut() {
+            if let Some(message) = self.doc.sync().generate_sync_message(state) {
+                messages.push((*peer, Message::Sync(message.encode())));
+            }
+        }
+        return messages;
+    }
+
+    // take in a message from a peer, returns the new full text if it changed the note
+    pub fn receive(&mut self, peer: u64, message: Message) -> Option<String> {
+        match message {
+            Message::Cursor { name, cursor } => {
+                self.cursors.insert(name, cursor);
+                return None;
+            }
+            Message::Sync(bytes) => {
+                let message = match sync::Message::decode(&bytes) {
+                    Ok(message) => message,
+                    Err(e) => {
+                        tracing::warn!("bad sync message from peer {}: {}", peer, e);
+                        return None;
+                    }
+                };
+                let state = self.peers.entry(peer).or_default();
+                if let Err(e) = self.doc.sync().receive_sync_message(state, message) {
+                    tracing::warn!("couldn't apply a sync message from peer {}: {}", peer, e);
+                    return None;
+                }
+            }
+        }
+
+        if self.text.is_none() {
+            self.text = match self.doc.get(ROOT, "text") {
+                Ok(Some((_, obj))) => Some(obj),
+                _ => return None,
+            };
+        }
+        let text = self.doc.text(self.text.as_ref()?).ok()?;
+        if text == self.last {
+            return None;
+        }
+        self.last = text.clone();
+        return Some(text);
+    }
+
+    // a caret position here, as a message for the others
+    pub fn cursor(&self, name: &str, pos: usize) -> Option<Message> {
+        let obj = self.text.as_ref()?;
+        let cursor = if pos >= self.last.chars().count() {
+            self.doc
+                .get_cursor(obj, automerge::CursorPosition::End, None)
+        } else {
+            self.doc.get_cursor(obj, pos, None)
+        };
+        return Some(Message::Cursor {
+            name: name.to_string(),
+            cursor: cursor.ok()?.to_bytes(),
+        });
+    }
+
+    // everyone else's carets as char positions in the full text
+    pub fn remote_cursors(&self) -> Vec<(String, usize)> {
+        let Some(obj) = &self.text else {
+            return Vec::new();
+        };
+        let mut cursors: Vec<(String, usize)> = self
+            .cursors
+            .iter()
+            .filter_map(|(name, bytes)| {
+                let cursor = Cursor::try_from(bytes.as_slice()).ok()?;
+                let pos = self.doc.get_cursor_position(obj, &cursor, None).ok()?;
+                return Some((name.clone(), pos));
+            })
+            .collect();
+        cursors.sort();
+        return cursors;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collab::{Message, Session, moved, splice};
+
+    // pass messages both ways until neither side has anything left to say
+    fn exchange(a: &mut Session, b: &mut Session) -> (Option<String>, Option<String>) {
+        let (mut a_text, mut b_text) = (None, None);
+        for _ in 0..10 {
+            let to_b = a.outgoing();
+            let to_a = b.outgoing();
+            if to_a.is_empty() && to_b.is_empty() {
+                break;
+            }
+            for (_, message) in to_b {
+                b_text = b.receive(1, message).or(b_text);
+            }
+            for (_, message) in to_a {
+                a_text = a.receive(2, message).or(a_text);
+            }
+        }
+        return (a_text, b_text);
+    }
+
+    #[test]
+    fn test_splice() {
+        assert_eq!(None, splice("same", "same"));
+        assert_eq!(Some((2..2, "ü".to_string())), splice("abc", "abüc"));
+        assert_eq!(Some((1..3, "X".to_string())), splice("aaab", "aXb"));
+        assert_eq!(7, moved(5, &(1..3), 4));
+        assert_eq!(6, moved(3, &(2..4), 4));
+        assert_eq!(1, moved(1, &(2..4), 0));
+    }
+
+    #[test]
+    fn test_concurrent_edits() {
+        let mut host = Session::start("# Shared\nhello\n");
+        host.add_peer(2);
+        let mut guest = Session::join();
+        guest.add_peer(1);
+        assert_eq!(None, guest.text());
+        let (_, joined) = exchange(&mut host, &mut guest);
+        assert_eq!(Some("# Shared\nhello\n".to_string()), joined);
+
+        host.local_change("# Shared\nhello world\n");
+        guest.local_change("# Shared!\nhello\n");
+        let (host_text, guest_text) = exchange(&mut host, &mut guest);
+        assert_eq!(Some("# Shared!\nhello world\n".to_string()), host_text);
+        assert_eq!(host_text, guest_text);
+    }
+
+    #[test]
+    fn test_cursors() {
+        let mut host = Session::start("abcdef");
+        host.add_peer(2);
+        let mut guest = Session::join();
+        guest.add_peer(1);
+        exchange(&mut host, &mut guest);
+
+        let message = guest.cursor("guest", 3).unwrap();
+        let bytes = message.encode();
+        assert_eq!(Some(message.clone()), Message::decode(&bytes));
+        host.receive(2, message);
+        host.local_change("XYabcdef");
+        assert_eq!(vec![("guest".to_string(), 5)], host.remote_cursors());
+    }
+}
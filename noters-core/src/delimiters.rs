@@ -0,0 +1,347 @@
+use crate::note::{MarkdownType, highlight_parse};
+use std::ops::Range;
+
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+// the markers around the cursor that belong together, and whether they're actually paired up
+//   inline markup comes from the highlighter so it only ever reports matched pairs,
+//   brackets next to or around the cursor on its line can come back unmatched
+pub fn matching(text: &str, cursor: usize) -> Vec<(Range<usize>, bool)> {
+    let mut output = Vec::new();
+
+    let mut pos = 0;
+    for s in highlight_parse(text) {
+        let end = pos + s.text.len();
+        let marker = match s.mdtype {
+            MarkdownType::Bold | MarkdownType::Strikethrough => 2,
+            MarkdownType::Italic | MarkdownType::Monospace => 1,
+            MarkdownType::Code(_) => 3,
+            _ => 0,
+        };
+        if marker > 0 && pos <= cursor && cursor <= end {
+            output.push((pos..pos + marker, true));
+            output.push((end - marker..end, true));
+        }
+        pos = end;
+    }
+
+    let line_start = text[..cursor].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[cursor..].find('\n').map_or(text.len(), |i| cursor + i);
+    let line = &text[line_start..line_end];
+    let cursor = cursor - line_start;
+
+    // a bracket right at the cursor wins, otherwise look for the innermost one around it
+    let at = line[cursor..]
+        .chars()
+        .next()
+        .filter(|c| is_bracket(*c))
+        .map(|_| cursor);
+    let before = line[..cursor]
+        .chars()
+        .next_back()
+        .filter(|c| is_bracket(*c))
+        .map(|c| cursor - c.len_utf8());
+    let bracket = at.or(before).or_else(|| enclosing(line, cursor));
+
+    if let Some(b) = bracket {
+        let found = partner(line, b);
+        output.push((line_start + b..line_start + b + 1, found.is_some()));
+        if let Some(p) = found {
+            output.push((line_start + p..line_start + p + 1, true));
+        }
+    }
+    return output;
+}
+
+// the markup characters of every span, for hiding them outside the line being edited
+pub fn markers(text: &str) -> Vec<Range<usize>> {
+    let mut output = Vec::new();
+    let mut pos = 0;
+    for s in highlight_parse(text) {
+        let end = pos + s.text.len();
+        match s.mdtype {
+            MarkdownType::Heading1 | MarkdownType::Heading2 | MarkdownType::Heading3 => {
+                let hashes = s.text.len() - s.text.trim_start_matches('#').len();
+                let space = s.text[hashes..].starts_with(' ') as usize;
+                output.push(pos..pos + hashes + space);
+            }
+            MarkdownType::Bold => {
+                output.push(pos..pos + 2);
+                output.push(end - 2..end);
+            }
+            MarkdownType::Italic => {
+                output.push(pos..pos + 1);
+                output.push(end - 1..end);
+            }
+            MarkdownType::Link if s.text.starts_with("[[") => {
+                output.push(pos..pos + 2);
+                output.push(end - 2..end);
+            }
+            MarkdownType::Link => output.push(pos..pos + 2),
+            _ => {}
+        }
+        pos = end;
+    }
+    return output;
+}
+
+// the markup the formatting shortcuts put on and take off again
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Bold,
+    Italic,
+    Code,
+}
+
+impl Format {
+    fn marker(self) -> &'static str {
+        return match self {
+            Format::Bold => "**",
+            Format::Italic => "_",
+            Format::Code => "`",
+        };
+    }
+}
+
+// the text inside a span's markup, None for the kinds that aren't formatting or links
+fn inside(text: &str, mdtype: &MarkdownType) -> Option<Range<usize>> {
+    let len = text.len();
+    return match mdtype {
+        MarkdownType::Bold => Some(2..len - 2),
+        MarkdownType::Italic | MarkdownType::Monospace => Some(1..len - 1),
+        MarkdownType::Link if text.starts_with("[[") => Some(2..len - 2),
+        MarkdownType::Link => Some(2..len),
+        // a bare url has no markup to take away
+        MarkdownType::WebLink => Some(1..text.find("](")?),
+        _ => None,
+    };
+}
+
+// the span the selection sits in whose kind passes `kind`, and the text inside its markup
+fn span_around(
+    text: &str,
+    range: &Range<usize>,
+    kind: impl Fn(&MarkdownType) -> bool,
+) -> Option<(Range<usize>, Range<usize>)> {
+    let mut pos = 0;
+    for s in highlight_parse(text) {
+        let end = pos + s.text.len();
+        if pos <= range.start
+            && range.end <= end
+            && kind(&s.mdtype)
+            && let Some(inner) = inside(s.text, &s.mdtype)
+        {
+            return Some((pos..end, pos + inner.start..pos + inner.end));
+        }
+        pos = end;
+    }
+    return None;
+}
+
+// the span's markup taken away, as the range to replace, what goes there and the selection after
+fn unwrap(
+    text: &str,
+    range: Range<usize>,
+    whole: Range<usize>,
+    inner: Range<usize>,
+) -> (Range<usize>, String, Range<usize>) {
+    let keep = |pos: usize| whole.start + pos.clamp(inner.start, inner.end) - inner.start;
+    let selection = keep(range.start)..keep(range.end);
+    return (whole, text[inner].to_string(), selection);
+}
+
+// the word around a position, empty between words
+pub fn word_at(text: &str, pos: usize) -> Range<usize> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let start = text[..pos]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word(*c))
+        .last()
+        .map_or(pos, |(i, _)| i);
+    let end = text[pos..]
+        .char_indices()
+        .find(|(_, c)| !is_word(*c))
+        .map_or(text.len(), |(i, _)| pos + i);
+    return start..end;
+}
+
+// what toggling a format does to the text
+#[derive(Debug, PartialEq)]
+pub enum Toggled {
+    // the span's markup is taken off: the byte range to replace, what goes there and the selection after
+    Unwrap(Range<usize>, String, Range<usize>),
+    // the marker goes on both ends of a byte range, nothing inside it is touched
+    Wrap(Range<usize>, &'static str),
+}
+
+// take the markup off the span the selection is in, or put it around the selection or the word at
+//   the cursor, None for a selection over several lines since markup can't span them
+pub fn toggle(text: &str, range: Range<usize>, format: Format) -> Option<Toggled> {
+    let kind = |mdtype: &MarkdownType| match format {
+        Format::Bold => *mdtype == MarkdownType::Bold,
+        Format::Italic => *mdtype == MarkdownType::Italic,
+        Format::Code => *mdtype == MarkdownType::Monospace,
+    };
+    if text[range.clone()].contains('\n') {
+        return None;
+    }
+    if let Some((whole, inner)) = span_around(text, &range, kind) {
+        let (whole, with, selection) = unwrap(text, range, whole, inner);
+        return Some(Toggled::Unwrap(whole, with, selection));
+    }
+    let range = if range.is_empty() {
+        word_at(text, range.start)
+    } else {
+        range
+    };
+    return Some(Toggled::Wrap(range, format.marker()));
+}
+
+// the link the selection is in swapped for its label, or its target where it has none
+pub fn unlink(text: &str, range: Range<usize>) -> Option<(Range<usize>, String, Range<usize>)> {
+    let kind = |mdtype: &MarkdownType| {
+        return matches!(mdtype, MarkdownType::Link | MarkdownType::WebLink);
+    };
+    let (whole, inner) = span_around(text, &range, kind)?;
+    return Some(unwrap(text, range, whole, inner));
+}
+
+fn is_bracket(c: char) -> bool {
+    BRACKETS
+        .iter()
+        .any(|(open, close)| c == *open || c == *close)
+}
+
+// innermost opening bracket before pos that isn't closed before pos
+fn enclosing(line: &str, pos: usize) -> Option<usize> {
+    let mut depth = [0; 3];
+    for (i, c) in line[..pos].char_indices().rev() {
+        for (k, (open, close)) in BRACKETS.iter().enumerate() {
+            if c == *close {
+                depth[k] += 1;
+            } else if c == *open {
+                if depth[k] == 0 {
+                    return Some(i);
+                }
+                depth[k] -= 1;
+            }
+        }
+    }
+    return None;
+}
+
+// the bracket that pairs with the one at pos
+fn partner(line: &str, pos: usize) -> Option<usize> {
+    let c = line[pos..].chars().next()?;
+    let mut depth = 0;
+    for (open, close) in BRACKETS {
+        if c == open {
+            for (i, d) in line[pos..].char_indices() {
+                if d == open {
+                    depth += 1;
+                } else if d == close {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(pos + i);
+                    }
+                }
+            }
+        } else if c == close {
+            for (i, d) in line[..=pos].char_indices().rev() {
+                if d == close {
+                    depth += 1;
+                } else if d == open {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+            }
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::delimiters::{Format, Toggled, markers, matching, toggle, unlink, word_at};
+
+    #[test]
+    fn test_matching() {
+        assert_eq!(
+            vec![(3..5, true), (9..11, true)],
+            matching("hi **bold** x", 6)
+        );
+        assert_eq!(vec![(0..1, true), (5..6, true)], matching("[a(b)]", 0));
+        assert_eq!(vec![(2..3, true), (4..5, true)], matching("[a(b)]", 3));
+        assert_eq!(vec![(0..1, false)], matching("(open\n)", 3));
+        assert!(matching("plain", 2).is_empty());
+    }
+
+    #[test]
+    fn test_markers() {
+        assert_eq!(
+            vec![0..3, 8..10, 11..13, 14..15, 16..17, 18..20],
+            markers("## Head\n**b** _i_ @@x")
+        );
+    }
+
+    #[test]
+    fn test_toggle() {
+        let toggled = |text: &str, range, format| {
+            let mut text = text.to_string();
+            let selection = match toggle(&text, range, format).unwrap() {
+                Toggled::Unwrap(replace, with, selection) => {
+                    text.replace_range(replace, &with);
+                    selection
+                }
+                Toggled::Wrap(range, marker) => {
+                    text.insert_str(range.end, marker);
+                    text.insert_str(range.start, marker);
+                    range.start + marker.len()..range.end + marker.len()
+                }
+            };
+            return (text, selection);
+        };
+        assert_eq!(
+            ("a **word** b".to_string(), 4..8),
+            toggled("a word b", 4..4, Format::Bold)
+        );
+        assert_eq!(
+            ("_two words_".to_string(), 1..10),
+            toggled("two words", 0..9, Format::Italic)
+        );
+        assert_eq!(
+            ("a word b".to_string(), 4..4),
+            toggled("a **word** b", 6..6, Format::Bold)
+        );
+        assert_eq!(
+            ("run it".to_string(), 4..6),
+            toggled("run `it`", 4..8, Format::Code)
+        );
+        // italics inside bold are added rather than the bold taken away
+        assert_eq!(
+            ("**a _b_**".to_string(), 5..6),
+            toggled("**a b**", 4..5, Format::Italic)
+        );
+        assert_eq!(("****".to_string(), 2..2), toggled("", 0..0, Format::Bold));
+        // markup doesn't carry over a line break
+        assert_eq!(None, toggle("# A\n# B\n", 2..7, Format::Bold));
+
+        assert_eq!(3..10, word_at("an éclair", 5));
+        assert_eq!(
+            Some((3..23, "label".to_string(), 4..4)),
+            unlink("go [label](https://x.y) now", 5..5)
+        );
+        assert_eq!(
+            Some((4..12, "note".to_string(), 5..5)),
+            unlink("see [[note]]", 7..7)
+        );
+        assert_eq!(
+            Some((0..6, "note".to_string(), 1..1)),
+            unlink("@@note x", 3..3)
+        );
+        assert_eq!(None, unlink("plain https://x.y", 8..8));
+    }
+}
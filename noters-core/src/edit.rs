@@ -0,0 +1,83 @@
+use std::fmt;
+use std::ops::Range;
+
+// a change to a note, positions are characters in the displayed text as it was before the edit
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    Insert {
+        pos: usize,
+        text: String,
+    },
+    Delete(Range<usize>),
+    Replace {
+        range: Range<usize>,
+        text: String,
+    },
+    // put markers around a range, like ** for bold
+    Wrap {
+        range: Range<usize>,
+        before: String,
+        after: String,
+    },
+    // swap the lines covered by a range with the line above or below, folded sections move whole
+    MoveLines {
+        range: Range<usize>,
+        up: bool,
+    },
+    // several edits applied together, they all refer to the text from before any of them
+    Batch(Vec<Edit>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditError {
+    // a position past the end of the note, or lines that can't move any further
+    OutOfRange,
+    // two edits in a batch touch the same text
+    Overlapping,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::OutOfRange => write!(f, "edit is outside the note"),
+            EditError::Overlapping => write!(f, "edits overlap"),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+// order the replacements in the full text and make sure none of them step on each other,
+//   inserts at the same spot keep the order they were given in
+pub fn sort_changes(
+    mut changes: Vec<(Range<usize>, String)>,
+) -> Result<Vec<(Range<usize>, String)>, EditError> {
+    changes.sort_by_key(|(range, _)| range.start);
+    for pair in changes.windows(2) {
+        if pair[0].0.end > pair[1].0.start {
+            return Err(EditError::Overlapping);
+        }
+    }
+    return Ok(changes);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::edit::{EditError, sort_changes};
+
+    #[test]
+    fn test_sort_changes() {
+        let changes = vec![
+            (5..5, "b".to_string()),
+            (0..2, "".to_string()),
+            (5..5, "c".to_string()),
+            (2..5, "a".to_string()),
+        ];
+        let sorted = sort_changes(changes).unwrap();
+        let order: Vec<(usize, &str)> = sorted.iter().map(|(r, t)| (r.start, t.as_str())).collect();
+        assert_eq!(vec![(0, ""), (2, "a"), (5, "b"), (5, "c")], order);
+
+        let changes = vec![(0..3, "".to_string()), (2..2, "x".to_string())];
+        assert_eq!(Err(EditError::Overlapping), sort_changes(changes));
+    }
+}
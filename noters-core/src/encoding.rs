@@ -0,0 +1,110 @@
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+// how a note was stored on disk, so saving can put it back the same way
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Encoding {
+    pub bom: bool,
+    pub line_ending: LineEnding,
+    // some bytes weren't valid utf-8 and were replaced while decoding
+    pub lossy: bool,
+}
+
+// turn the bytes of a file into note text with plain \n line endings
+//   a file counts as crlf when most of its lines end that way
+pub fn decode(bytes: &[u8]) -> (String, Encoding) {
+    let bom = bytes.starts_with(BOM);
+    let bytes = if bom { &bytes[BOM.len()..] } else { bytes };
+
+    let (text, lossy) = match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), false),
+        Err(_) => (String::from_utf8_lossy(bytes).into_owned(), true),
+    };
+
+    let lines = text.matches('\n').count();
+    let crlf = text.matches("\r\n").count();
+    let line_ending = if crlf > 0 && crlf * 2 >= lines {
+        LineEnding::Crlf
+    } else {
+        LineEnding::Lf
+    };
+
+    let text = match line_ending {
+        LineEnding::Crlf => text.replace("\r\n", "\n"),
+        LineEnding::Lf => text,
+    };
+    return (
+        text,
+        Encoding {
+            bom,
+            line_ending,
+            lossy,
+        },
+    );
+}
+
+// the bytes to write for note text, restoring the file's bom and line endings
+pub fn encode(text: &str, encoding: &Encoding) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len() + BOM.len());
+    if encoding.bom {
+        bytes.extend_from_slice(BOM);
+    }
+    match encoding.line_ending {
+        LineEnding::Lf => bytes.extend_from_slice(text.as_bytes()),
+        LineEnding::Crlf => {
+            for (i, line) in text.split('\n').enumerate() {
+                if i > 0 {
+                    bytes.extend_from_slice(b"\r\n");
+                }
+                bytes.extend_from_slice(line.as_bytes());
+            }
+        }
+    }
+    return bytes;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::{Encoding, LineEnding, decode, encode};
+
+    #[test]
+    fn test_decode() {
+        let (text, encoding) = decode(b"# A\nabc\n");
+        assert_eq!("# A\nabc\n", text);
+        assert_eq!(Encoding::default(), encoding);
+
+        let (text, encoding) = decode(b"\xEF\xBB\xBF# A\r\nabc\r\n");
+        assert_eq!("# A\nabc\n", text);
+        assert!(encoding.bom);
+        assert_eq!(LineEnding::Crlf, encoding.line_ending);
+        assert!(!encoding.lossy);
+
+        // mostly lf, the stray \r stays in the text
+        let (text, encoding) = decode(b"a\nb\nc\r\nd\n");
+        assert_eq!("a\nb\nc\r\nd\n", text);
+        assert_eq!(LineEnding::Lf, encoding.line_ending);
+
+        let (text, encoding) = decode(b"caf\xE9\n");
+        assert_eq!("caf\u{FFFD}\n", text);
+        assert!(encoding.lossy);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for bytes in [
+            b"# A\nabc\n".as_slice(),
+            b"\xEF\xBB\xBF# A\r\nabc\r\n",
+            b"# A\r\nabc",
+            b"",
+        ] {
+            let (text, encoding) = decode(bytes);
+            assert_eq!(bytes, encode(&text, &encoding).as_slice());
+        }
+    }
+}
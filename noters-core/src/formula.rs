@@ -0,0 +1,408 @@
+use std::ops::Range;
+
+// spreadsheet-lite formulas in markdown table cells, a cell starting with = is worked out from
+//   the others. rows count from 1 with the header as row 1, columns are letters from A
+
+struct Cell {
+    // where the trimmed cell text is in the note
+    range: Range<usize>,
+    text: String,
+}
+
+// the rows of one table, the |---| separator row left out
+type Table = Vec<Vec<Cell>>;
+
+// split a table line on the pipes that aren't escaped, each cell's range is its trimmed text
+fn cells(line: &str, offset: usize) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    let mut start = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if c == '|' && !escaped {
+            if let Some(start) = start {
+                let raw: &str = &line[start..i];
+                let lead = raw.len() - raw.trim_start().len();
+                let trimmed = raw.trim();
+                let from = offset + start + lead;
+                cells.push(Cell {
+                    range: from..from + trimmed.len(),
+                    text: trimmed.replace("\\|", "|"),
+                });
+            }
+            start = Some(i + 1);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    return cells;
+}
+
+fn is_separator(cells: &[Cell]) -> bool {
+    return !cells.is_empty()
+        && cells.iter().all(|cell| {
+            let dashes = cell.text.trim_matches(':');
+            !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+        });
+}
+
+// every run of lines starting with a pipe
+fn tables(text: &str) -> Vec<Table> {
+    let mut tables = Vec::new();
+    let mut table: Table = Vec::new();
+    let mut offset = 0;
+    for line in text.split('\n') {
+        if line.trim_start().starts_with('|') {
+            let row = cells(line, offset);
+            if !is_separator(&row) {
+                table.push(row);
+            }
+        } else if !table.is_empty() {
+            tables.push(std::mem::take(&mut table));
+        }
+        offset += line.len() + 1;
+    }
+    if !table.is_empty() {
+        tables.push(table);
+    }
+    return tables;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Op(char),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                number.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Number(number.parse().map_err(|_| "#NUM")?));
+        } else if c.is_ascii_alphabetic() {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric()) {
+                name.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Name(name.to_ascii_uppercase()));
+        } else if "+-*/(),:".contains(c) {
+            tokens.push(Token::Op(c));
+            chars.next();
+        } else {
+            return Err("#ERR".to_string());
+        }
+    }
+    return Ok(tokens);
+}
+
+// B3 is (row 2, column 1) counted from 0
+fn cell_ref(name: &str) -> Option<(usize, usize)> {
+    let letters = name.chars().take_while(|c| c.is_ascii_alphabetic()).count();
+    if letters == 0 || letters == name.len() {
+        return None;
+    }
+    let column = name[..letters]
+        .bytes()
+        .fold(0, |acc, b| acc * 26 + (b - b'A' + 1) as usize);
+    let row: usize = name[letters..].parse().ok()?;
+    return Some((row.checked_sub(1)?, column - 1));
+}
+
+struct Sheet<'a> {
+    table: &'a Table,
+    values: Vec<Vec<Option<Result<f64, String>>>>,
+    // formulas part way through being worked out, reaching one again is a cycle
+    working: Vec<Vec<bool>>,
+}
+
+impl Sheet<'_> {
+    fn value(&mut self, row: usize, column: usize) -> Result<f64, String> {
+        let Some(cell) = self.table.get(row).and_then(|r| r.get(column)) else {
+            return Err("#REF".to_string());
+        };
+        if let Some(value) = &self.values[row][column] {
+            return value.clone();
+        }
+        let value = if let Some(formula) = cell.text.strip_prefix('=') {
+            if self.working[row][column] {
+                return Err("#CYCLE".to_string());
+            }
+            self.working[row][column] = true;
+            let value = tokenize(formula).and_then(|tokens| {
+                let mut parser = Parser {
+                    tokens,
+                    pos: 0,
+                    row,
+                    column,
+                };
+                let value = parser.expression(self)?;
+                if parser.pos != parser.tokens.len() {
+                    return Err("#ERR".to_string());
+                }
+                return Ok(value);
+            });
+            self.working[row][column] = false;
+            value
+        } else if cell.text.is_empty() {
+            Ok(0.0)
+        } else {
+            cell.text
+                .replace(',', "")
+                .parse()
+                .map_err(|_| "#VALUE".to_string())
+        };
+        self.values[row][column] = Some(value.clone());
+        return value;
+    }
+
+    // the numbers among some cells, text and empty cells are skipped like a spreadsheet does
+    fn numbers(&mut self, cells: Vec<(usize, usize)>) -> Result<Vec<f64>, String> {
+        let mut numbers = Vec::new();
+        for (row, column) in cells {
+            let Some(cell) = self.table.get(row).and_then(|r| r.get(column)) else {
+                continue;
+            };
+            let is_formula = cell.text.starts_with('=');
+            match self.value(row, column) {
+                Ok(n) if is_formula || !cell.text.is_empty() => numbers.push(n),
+                Err(e) if is_formula => return Err(e),
+                _ => {}
+            }
+        }
+        return Ok(numbers);
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    // the cell the formula is in, for above and left
+    row: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        return token;
+    }
+
+    fn eat(&mut self, op: char) -> bool {
+        if self.tokens.get(self.pos) == Some(&Token::Op(op)) {
+            self.pos += 1;
+            return true;
+        }
+        return false;
+    }
+
+    fn expression(&mut self, sheet: &mut Sheet) -> Result<f64, String> {
+        let mut value = self.term(sheet)?;
+        loop {
+            if self.eat('+') {
+                value += self.term(sheet)?;
+            } else if self.eat('-') {
+                value -= self.term(sheet)?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self, sheet: &mut Sheet) -> Result<f64, String> {
+        let mut value = self.factor(sheet)?;
+        loop {
+            if self.eat('*') {
+                value *= self.factor(sheet)?;
+            } else if self.eat('/') {
+                let divisor = self.factor(sheet)?;
+                if divisor == 0.0 {
+                    return Err("#DIV/0".to_string());
+                }
+                value /= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn factor(&mut self, sheet: &mut Sheet) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Number(n)) => return Ok(n),
+            Some(Token::Op('-')) => return Ok(-self.factor(sheet)?),
+            Some(Token::Op('(')) => {
+                let value = self.expression(sheet)?;
+                if !self.eat(')') {
+                    return Err("#ERR".to_string());
+                }
+                return Ok(value);
+            }
+            Some(Token::Name(name)) if self.eat('(') => return self.function(sheet, &name),
+            Some(Token::Name(name)) => {
+                let (row, column) = cell_ref(&name).ok_or("#NAME")?;
+                return sheet.value(row, column);
+            }
+            _ => return Err("#ERR".to_string()),
+        }
+    }
+
+    // the cells a function argument covers: above, left, a range like B2:B5 or a single cell
+    fn cells(&mut self) -> Option<Vec<(usize, usize)>> {
+        let Some(Token::Name(name)) = self.tokens.get(self.pos).cloned() else {
+            return None;
+        };
+        let cells = match name.as_str() {
+            "ABOVE" => (0..self.row).map(|r| (r, self.column)).collect(),
+            "LEFT" => (0..self.column).map(|c| (self.row, c)).collect(),
+            _ => {
+                let from = cell_ref(&name)?;
+                if self.tokens.get(self.pos + 1) != Some(&Token::Op(':')) {
+                    return None;
+                }
+                let Some(Token::Name(to)) = self.tokens.get(self.pos + 2) else {
+                    return None;
+                };
+                let to = cell_ref(to)?;
+                self.pos += 2;
+                let rows = from.0.min(to.0)..=from.0.max(to.0);
+                let columns = from.1.min(to.1)..=from.1.max(to.1);
+                rows.flat_map(|r| columns.clone().map(move |c| (r, c)))
+                    .filter(|cell| *cell != (self.row, self.column))
+                    .collect()
+            }
+        };
+        self.pos += 1;
+        return Some(cells);
+    }
+
+    fn function(&mut self, sheet: &mut Sheet, name: &str) -> Result<f64, String> {
+        let mut numbers = Vec::new();
+        if !self.eat(')') {
+            loop {
+                match self.cells() {
+                    Some(cells) => numbers.extend(sheet.numbers(cells)?),
+                    None => numbers.push(self.expression(sheet)?),
+                }
+                if self.eat(')') {
+                    break;
+                }
+                if !self.eat(',') {
+                    return Err("#ERR".to_string());
+                }
+            }
+        }
+        let count = numbers.len() as f64;
+        return match name {
+            "SUM" => Ok(numbers.iter().sum()),
+            "AVG" | "AVERAGE" if count > 0.0 => Ok(numbers.iter().sum::<f64>() / count),
+            "AVG" | "AVERAGE" => Err("#DIV/0".to_string()),
+            "MIN" => Ok(numbers.into_iter().reduce(f64::min).unwrap_or(0.0)),
+            "MAX" => Ok(numbers.into_iter().reduce(f64::max).unwrap_or(0.0)),
+            "COUNT" => Ok(count),
+            _ => Err("#NAME".to_string()),
+        };
+    }
+}
+
+// a number the way a table cell shows it, whole numbers without a point and at most 4 places
+pub fn display(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{:.0}", value);
+    }
+    let text = format!("{:.4}", value);
+    return text.trim_end_matches('0').trim_end_matches('.').to_string();
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Computed {
+    // the formula's text, = included
+    pub range: Range<usize>,
+    // the number, or a spreadsheet style error like #REF
+    pub value: Result<f64, String>,
+}
+
+impl Computed {
+    pub fn text(&self) -> String {
+        return match &self.value {
+            Ok(value) => display(*value),
+            Err(e) => e.clone(),
+        };
+    }
+}
+
+// the value of every formula in the text's tables
+pub fn evaluate(text: &str) -> Vec<Computed> {
+    let mut computed = Vec::new();
+    for table in tables(text) {
+        let mut sheet = Sheet {
+            table: &table,
+            values: table.iter().map(|r| vec![None; r.len()]).collect(),
+            working: table.iter().map(|r| vec![false; r.len()]).collect(),
+        };
+        for (row, cells) in table.iter().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                if cell.text.starts_with('=') {
+                    computed.push(Computed {
+                        range: cell.range.clone(),
+                        value: sheet.value(row, column),
+                    });
+                }
+            }
+        }
+    }
+    return computed;
+}
+
+// the text with each formula swapped for its value
+pub fn bake(text: &str) -> String {
+    let mut output = text.to_string();
+    for computed in evaluate(text).iter().rev() {
+        output.replace_range(computed.range.clone(), &computed.text());
+    }
+    return output;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formula::{bake, display, evaluate};
+
+    const TABLE: &str = "intro\n| item | qty | price | total |\n|---|---|---|---|\n| pen | 2 | 1.5 | =B2*C2 |\n| ink | 1 | 3 | =B3*C3 |\n| sum | =sum(above) | =avg(C2:C3) | =sum(above) |\n";
+
+    #[test]
+    fn test_evaluate() {
+        let values: Vec<String> = evaluate(TABLE).iter().map(|c| c.text()).collect();
+        assert_eq!(vec!["3", "3", "3", "2.25", "6"], values);
+        let first = &evaluate(TABLE)[0];
+        assert_eq!("=B2*C2", &TABLE[first.range.clone()]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let text = "| a | b |\n|---|---|\n| =B2 | =A2 |\n| x | =A3+1 |\n| =1/0 | =foo(1) |\n| =Z9 | =2 * (3 |\n";
+        let values: Vec<String> = evaluate(text).iter().map(|c| c.text()).collect();
+        assert_eq!(
+            vec![
+                "#CYCLE", "#CYCLE", "#VALUE", "#DIV/0", "#NAME", "#REF", "#ERR"
+            ],
+            values
+        );
+    }
+
+    #[test]
+    fn test_bake() {
+        assert_eq!(
+            "| a | b |\n|---|---|\n| 2 | 4 |\n| 1 | 3 |\n",
+            bake("| a | b |\n|---|---|\n| 2 | =A2*2 |\n| 1 | =sum(left, 2) |\n")
+        );
+        assert_eq!("0.3333", display(1.0 / 3.0));
+        assert_eq!("-12", display(-12.0));
+    }
+}
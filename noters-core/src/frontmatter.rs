@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+// the `---` fenced block of yaml some notes start with, holding things like tags and aliases
+//   only the flat part of yaml notes use is understood: `key: value` lines whose values are
+//   scalars, [inline, lists] or `- item` lines under the key, anything nested deeper is skipped
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        return match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        };
+    }
+
+    // the strings in a list, or the one string, which is how tags and aliases tend to be written
+    pub fn strings(&self) -> Vec<&str> {
+        return match self {
+            Value::String(s) => vec![s.as_str()],
+            Value::List(items) => items.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+    }
+}
+
+// how many bytes at the start of the text are frontmatter, fences and all
+pub fn len(text: &str) -> Option<usize> {
+    let first = text.split_inclusive('\n').next()?;
+    if first.trim_end() != "---" || !first.ends_with('\n') {
+        return None;
+    }
+    let mut pos = first.len();
+    for line in text[pos..].split_inclusive('\n') {
+        pos += line.len();
+        if matches!(line.trim_end(), "---" | "...") {
+            return Some(pos);
+        }
+    }
+    return None;
+}
+
+// a comment after an unquoted value goes, it has to follow a space so urls keep their #s
+fn strip_comment(value: &str) -> &str {
+    return match value.find(" #") {
+        Some(i) => value[..i].trim_end(),
+        None => value,
+    };
+}
+
+fn scalar(value: &str) -> Value {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return Value::String(value[1..value.len() - 1].to_string());
+        }
+    }
+    let value = strip_comment(value);
+    if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        if inner.trim().is_empty() {
+            return Value::List(Vec::new());
+        }
+        return Value::List(inner.split(',').map(scalar).collect());
+    }
+    return match value {
+        "" | "~" | "null" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => match value.parse::<f64>() {
+            Ok(n) if !value.starts_with('+') => Value::Number(n),
+            _ => Value::String(value.to_string()),
+        },
+    };
+}
+
+// the keys and values in a frontmatter block, the fences can be there or not
+pub fn parse(block: &str) -> HashMap<String, Value> {
+    let mut metadata = HashMap::new();
+    // the key whose value is still being read from the lines under it
+    let mut open: Option<(String, Vec<Value>)> = None;
+    for line in block.lines() {
+        let content = line.trim();
+        if content.is_empty() || content.starts_with('#') || content == "---" || content == "..." {
+            continue;
+        }
+        if line.starts_with([' ', '\t', '-']) {
+            if let (Some((_, items)), Some(item)) = (&mut open, content.strip_prefix("- ")) {
+                items.push(scalar(item));
+            }
+            continue;
+        }
+        if let Some((key, items)) = open.take() {
+            let value = if items.is_empty() {
+                Value::Null
+            } else {
+                Value::List(items)
+            };
+            metadata.insert(key, value);
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_string();
+        if strip_comment(value.trim()).is_empty() {
+            open = Some((key, Vec::new()));
+        } else {
+            metadata.insert(key, scalar(value));
+        }
+    }
+    if let Some((key, items)) = open {
+        let value = if items.is_empty() {
+            Value::Null
+        } else {
+            Value::List(items)
+        };
+        metadata.insert(key, value);
+    }
+    return metadata;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frontmatter::{Value, len, parse};
+
+    #[test]
+    fn test_len() {
+        assert_eq!(Some(17), len("---\ntitle: a\n---\n# Heading"));
+        assert_eq!(Some(12), len("---\na: b\n..."));
+        assert_eq!(None, len("---\ntitle: a\n"));
+        assert_eq!(None, len("# ---\n---\n"));
+        assert_eq!(None, len("---"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let metadata = parse(
+            "---\n\
+             title: \"A: note\"\n\
+             # a comment\n\
+             tags: [work, 'ideas']\n\
+             aliases:\n  - first\n  - second\n\
+             count: 3 # three\n\
+             draft: false\n\
+             url: https://x.org/#top\n\
+             nested:\n  key: value\n\
+             empty:\n\
+             ---\n",
+        );
+        assert_eq!(Some("A: note"), metadata["title"].as_str());
+        assert_eq!(vec!["work", "ideas"], metadata["tags"].strings());
+        assert_eq!(vec!["first", "second"], metadata["aliases"].strings());
+        assert_eq!(Value::Number(3.0), metadata["count"]);
+        assert_eq!(Value::Bool(false), metadata["draft"]);
+        assert_eq!(Some("https://x.org/#top"), metadata["url"].as_str());
+        assert_eq!(Value::Null, metadata["nested"]);
+        assert_eq!(Value::Null, metadata["empty"]);
+        assert_eq!(8, metadata.len());
+    }
+}
@@ -0,0 +1,64 @@
+// subsequence matching used by the pickers
+
+// score how well `query` matches `candidate`, None when the characters don't all appear in order
+//   consecutive characters and characters starting a word score higher
+pub fn score(query: &str, candidate: &str) -> Option<i32> {
+    let mut total = 0;
+    let mut last_match: Option<usize> = None;
+    let mut chars = candidate.chars().enumerate().peekable();
+    let mut prev = ' ';
+
+    for q in query.chars().flat_map(char::to_lowercase) {
+        if q.is_whitespace() {
+            continue;
+        }
+        loop {
+            let (i, c) = chars.next()?;
+            let boundary = !prev.is_alphanumeric() || (prev.is_lowercase() && c.is_uppercase());
+            prev = c;
+            if c.to_lowercase().next() == Some(q) {
+                total += 1;
+                if boundary {
+                    total += 8;
+                }
+                match last_match {
+                    Some(l) if l + 1 == i => total += 5,
+                    Some(l) => total -= (i - l).min(10) as i32,
+                    None => total -= i.min(10) as i32,
+                }
+                last_match = Some(i);
+                break;
+            }
+        }
+    }
+    return Some(total);
+}
+
+// indices of the matching candidates, best first
+pub fn rank<'a>(query: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<usize> {
+    let mut scored: Vec<(i32, usize)> = candidates
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, c)| score(query, c).map(|s| (s, i)))
+        .collect();
+    // stable, so equal scores keep their original order
+    scored.sort_by_key(|(s, _)| std::cmp::Reverse(*s));
+    return scored.into_iter().map(|(_, i)| i).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fuzzy::{rank, score};
+
+    #[test]
+    fn test_score() {
+        assert_eq!(None, score("xyz", "index"));
+        assert!(score("", "anything").is_some());
+        assert!(score("proj", "projects/index") > score("proj", "people/rust/jobs"));
+        assert_eq!(
+            vec![2],
+            rank("mee", ["games/mario", "index", "work/meetings"])
+        );
+        assert_eq!(vec![1, 0], rank("gm", ["programs", "games/mario"]));
+    }
+}
@@ -0,0 +1,85 @@
+use std::ops::Range;
+use unicode_segmentation::GraphemeCursor;
+
+// the GUI counts positions in characters while the tree works in bytes,
+//   these convert between the two without landing inside a grapheme cluster
+
+pub fn byte_index(text: &str, char_index: usize) -> usize {
+    return text
+        .char_indices()
+        .nth(char_index)
+        .map_or(text.len(), |(i, _)| i);
+}
+
+pub fn char_index(text: &str, byte: usize) -> usize {
+    return text[..floor_char(text, byte)].chars().count();
+}
+
+pub(crate) fn floor_char(text: &str, byte: usize) -> usize {
+    let mut byte = byte.min(text.len());
+    while !text.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    return byte;
+}
+
+// the start of the cluster containing a byte position
+pub fn cluster_start(text: &str, byte: usize) -> usize {
+    let byte = floor_char(text, byte);
+    let mut cursor = GraphemeCursor::new(byte, text.len(), true);
+    if cursor.is_boundary(text, 0).unwrap_or(true) {
+        return byte;
+    }
+    return cursor.prev_boundary(text, 0).ok().flatten().unwrap_or(0);
+}
+
+// the end of the cluster containing a byte position, or the position itself on a boundary
+pub fn cluster_end(text: &str, byte: usize) -> usize {
+    let byte = floor_char(text, byte);
+    let mut cursor = GraphemeCursor::new(byte, text.len(), true);
+    if cursor.is_boundary(text, 0).unwrap_or(true) {
+        return byte;
+    }
+    return cursor
+        .next_boundary(text, 0)
+        .ok()
+        .flatten()
+        .unwrap_or(text.len());
+}
+
+// a character position as a byte position, moved back out of any cluster it splits
+pub fn cluster_byte(text: &str, char_index: usize) -> usize {
+    return cluster_start(text, byte_index(text, char_index));
+}
+
+// a character range as bytes, grown to cover every cluster it touches
+pub fn cluster_range(text: &str, chars: Range<usize>) -> Range<usize> {
+    let start = cluster_start(text, byte_index(text, chars.start));
+    let end = cluster_end(text, byte_index(text, chars.end));
+    return start..end.max(start);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphemes::{byte_index, char_index, cluster_byte, cluster_range};
+
+    #[test]
+    fn test_conversions() {
+        // e + combining acute, a three person family, then CJK
+        let text = "e\u{301}x👨\u{200d}👩\u{200d}👧y漢字";
+        assert_eq!(3, byte_index(text, 2));
+        assert_eq!(2, char_index(text, 3));
+        assert_eq!(3, char_index(text, 4));
+        assert_eq!(3, char_index(text, 5));
+
+        assert_eq!(0, cluster_byte(text, 1));
+        assert_eq!(3, cluster_byte(text, 2));
+        assert_eq!(4, cluster_byte(text, 5));
+        assert_eq!(22, cluster_byte(text, 8));
+        assert_eq!(23, cluster_byte(text, 9));
+
+        assert_eq!(0..3, cluster_range(text, 1..1));
+        assert_eq!(4..22, cluster_range(text, 4..5));
+        assert_eq!(23..29, cluster_range(text, 9..11));
+    }
+}
@@ -0,0 +1,29 @@
+// note parsing and editing, plus the vault and text helpers, without any GUI
+pub mod collab;
+pub mod delimiters;
+pub mod edit;
+pub mod encoding;
+pub mod formula;
+pub mod frontmatter;
+pub mod fuzzy;
+pub mod graphemes;
+pub mod links;
+pub mod lists;
+pub mod math;
+pub mod mdns;
+pub mod merge;
+pub mod note;
+pub mod pairs;
+pub mod reminders;
+pub mod render;
+pub mod replica;
+pub mod search;
+pub mod sketch;
+pub mod table;
+pub mod tags;
+pub mod templates;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod timestamp;
+pub mod typography;
+pub mod vault;
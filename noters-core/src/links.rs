@@ -0,0 +1,460 @@
+use regex::Regex;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+// [label](target), images (a ! in front) are left to their own handling
+static FILE_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(^|[^!])\[([^\]\n]*)\]\(([^)\n]+)\)").unwrap());
+
+static IMAGE_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!\[([^\]\n]*)\]\(([^)\n]+)\)").unwrap());
+
+// @@path or @@path#heading, the same pattern the highlighter uses
+static AT_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"@@([\\/A-Za-z0-9_-]+(?:#[\p{L}\p{N}_-]+)?)").unwrap());
+
+// an @@path still being typed, running up to the cursor
+static TYPED_AT_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"@@([\\/A-Za-z0-9_-]*)$").unwrap());
+
+// [[path]] or [[path|label]]
+static WIKI_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\[([^\]|\n]+)(?:\|([^\]\n]+))?\]\]").unwrap());
+
+// a bare url in the text, stopping before closing punctuation
+static URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"https?://[^\s<>()\[\]]*[^\s<>()\[\].,;:!?'\x22]").unwrap());
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileLink<'a> {
+    // the whole [label](target) in the text
+    pub range: Range<usize>,
+    pub label: &'a str,
+    pub target: &'a str,
+}
+
+impl FileLink<'_> {
+    pub fn is_web(&self) -> bool {
+        return self.target.starts_with("http://") || self.target.starts_with("https://");
+    }
+
+    pub fn is_pdf(&self) -> bool {
+        return !self.is_web() && self.target.to_lowercase().ends_with(".pdf");
+    }
+}
+
+// every markdown style link in the text
+pub fn file_links(text: &str) -> Vec<FileLink<'_>> {
+    return FILE_LINK
+        .captures_iter(text)
+        .map(|caps| {
+            let whole = caps.get(0).unwrap();
+            let start = whole.start() + caps[1].len();
+            return FileLink {
+                range: start..whole.end(),
+                label: caps.get(2).unwrap().as_str(),
+                target: caps.get(3).unwrap().as_str().trim(),
+            };
+        })
+        .collect();
+}
+
+// every ![alt](target) image embed, the label is the alt text
+pub fn image_links(text: &str) -> Vec<FileLink<'_>> {
+    return IMAGE_LINK
+        .captures_iter(text)
+        .map(|caps| FileLink {
+            range: caps.get(0).unwrap().range(),
+            label: caps.get(1).unwrap().as_str(),
+            target: caps.get(2).unwrap().as_str().trim(),
+        })
+        .collect();
+}
+
+// the web address under a byte position, from a [label](url) link or written out bare,
+//   along with where the whole link ends
+pub fn web_link_at(text: &str, pos: usize) -> Option<(&str, usize)> {
+    let inside = |range: &Range<usize>| range.start <= pos && pos <= range.end;
+    if let Some(link) = file_links(text)
+        .into_iter()
+        .find(|link| link.is_web() && inside(&link.range))
+    {
+        return Some((link.target, link.range.end));
+    }
+    return URL
+        .find_iter(text)
+        .find(|m| inside(&m.range()))
+        .map(|m| (m.as_str(), m.end()));
+}
+
+// the ways a note can link to another, a vault picks one for the links it writes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LinkSyntax {
+    // @@path
+    #[default]
+    At,
+    // [[path]]
+    Wiki,
+    // [text](path)
+    Markdown,
+}
+
+impl LinkSyntax {
+    pub const ALL: [LinkSyntax; 3] = [LinkSyntax::At, LinkSyntax::Wiki, LinkSyntax::Markdown];
+
+    // how it's written in the vault settings
+    pub fn key(self) -> &'static str {
+        return match self {
+            LinkSyntax::At => "at",
+            LinkSyntax::Wiki => "wiki",
+            LinkSyntax::Markdown => "markdown",
+        };
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        return Self::ALL.into_iter().find(|s| s.key() == key.trim());
+    }
+
+    // what it looks like, for choosing between them
+    pub fn example(self) -> &'static str {
+        return match self {
+            LinkSyntax::At => "@@path",
+            LinkSyntax::Wiki => "[[wikilink]]",
+            LinkSyntax::Markdown => "[text](path)",
+        };
+    }
+
+    // a link to a note, the label is left out where the syntax has nowhere to put it
+    //   and kept in front of the link instead
+    pub fn write(self, target: &str, label: &str) -> String {
+        let labelled = !label.is_empty() && label != target;
+        return match self {
+            LinkSyntax::At if labelled => format!("{} @@{}", label, target),
+            LinkSyntax::At => format!("@@{}", target),
+            LinkSyntax::Wiki if labelled => format!("[[{}|{}]]", target, label),
+            LinkSyntax::Wiki => format!("[[{}]]", target),
+            LinkSyntax::Markdown if labelled => format!("[{}]({})", label, target),
+            LinkSyntax::Markdown => format!("[{0}]({0})", target),
+        };
+    }
+}
+
+// a link from one note to another in any of the syntaxes
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoteLink<'a> {
+    pub range: Range<usize>,
+    // the note linked to, without an .md extension
+    pub target: &'a str,
+    // empty when the link has no text of its own
+    pub label: &'a str,
+    pub syntax: LinkSyntax,
+}
+
+// a markdown link target that's another note rather than a file or a web page
+fn markdown_note(target: &str) -> Option<&str> {
+    if target.contains("://") || target.starts_with('#') {
+        return None;
+    }
+    if let Some(note) = target.strip_suffix(".md") {
+        return Some(note);
+    }
+    // a heading in the note can follow, note.md#heading keeps its extension
+    let path = split_anchor(target).0;
+    if path.ends_with(".md") {
+        return Some(target);
+    }
+    let file = path.rsplit('/').next().unwrap_or(path);
+    return (!file.contains('.')).then_some(target);
+}
+
+// the note a link target goes to and the heading in it after a #, if there is one
+pub fn split_anchor(target: &str) -> (&str, Option<&str>) {
+    let (note, anchor) = match target.split_once('#') {
+        Some((note, anchor)) => (note, Some(anchor.trim()).filter(|a| !a.is_empty())),
+        None => (target, None),
+    };
+    return (note.strip_suffix(".md").unwrap_or(note), anchor);
+}
+
+// a heading's title as anchors write it, lowercase with dashes for spaces
+//   @@ links can't hold spaces, so underscores stand in for them too
+fn slug(text: &str) -> String {
+    return text
+        .replace('_', " ")
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-')
+        .collect();
+}
+
+// whether an anchor names a heading, written out as it is or as a slug
+pub fn anchor_matches(title: &str, anchor: &str) -> bool {
+    return title.trim().eq_ignore_ascii_case(anchor.trim()) || slug(title) == slug(anchor);
+}
+
+// every note link in the text, in order
+pub fn note_links(text: &str) -> Vec<NoteLink<'_>> {
+    let mut found: Vec<NoteLink> = AT_LINK
+        .captures_iter(text)
+        .map(|caps| NoteLink {
+            range: caps.get(0).unwrap().range(),
+            target: caps.get(1).unwrap().as_str(),
+            label: "",
+            syntax: LinkSyntax::At,
+        })
+        .collect();
+    found.extend(WIKI_LINK.captures_iter(text).map(|caps| NoteLink {
+        range: caps.get(0).unwrap().range(),
+        target: caps.get(1).unwrap().as_str().trim(),
+        label: caps.get(2).map_or("", |label| label.as_str().trim()),
+        syntax: LinkSyntax::Wiki,
+    }));
+    for link in file_links(text) {
+        if let Some(target) = markdown_note(link.target) {
+            found.push(NoteLink {
+                range: link.range,
+                target,
+                label: link.label,
+                syntax: LinkSyntax::Markdown,
+            });
+        }
+    }
+    found.sort_by_key(|link| link.range.start);
+    // an @@ inside a wikilink or markdown link belongs to that link
+    let mut links: Vec<NoteLink> = Vec::new();
+    for link in found {
+        if links
+            .last()
+            .is_some_and(|last| link.range.start < last.range.end)
+        {
+            continue;
+        }
+        links.push(link);
+    }
+    return links;
+}
+
+// the note a link under a byte position goes to
+pub fn note_link_at(text: &str, pos: usize) -> Option<&str> {
+    return note_links(text)
+        .into_iter()
+        .find(|link| link.range.start <= pos && pos < link.range.end)
+        .map(|link| link.target);
+}
+
+// the @@ link being typed at the end of the text before the cursor, for completing it
+//   gives the byte the @@ starts at and the part of the path typed so far
+pub fn typed_link(before: &str) -> Option<(usize, &str)> {
+    let caps = TYPED_AT_LINK.captures(before)?;
+    return Some((caps.get(0).unwrap().start(), caps.get(1).unwrap().as_str()));
+}
+
+// the note a highlighted link span goes to, @@path or [[path|label]]
+pub fn link_target(span: &str) -> &str {
+    if let Some(inner) = span.strip_prefix("[[").and_then(|s| s.strip_suffix("]]")) {
+        return inner.split('|').next().unwrap_or(inner).trim();
+    }
+    return span.trim_start_matches("@@");
+}
+
+// what a highlighted link span shows, the label of [[path|label]] or else the path
+pub fn link_text(span: &str) -> &str {
+    if let Some(inner) = span.strip_prefix("[[").and_then(|s| s.strip_suffix("]]")) {
+        return inner.rsplit('|').next().unwrap_or(inner).trim();
+    }
+    return span.trim_start_matches("@@");
+}
+
+// rewrite the note links written in one syntax, or any but the new one, into another
+//   returns the new text and how many links changed
+pub fn convert(text: &str, from: Option<LinkSyntax>, to: LinkSyntax) -> (String, usize) {
+    let mut out = String::new();
+    let mut last = 0;
+    let mut changed = 0;
+    for link in note_links(text) {
+        if link.syntax == to || from.is_some_and(|from| from != link.syntax) {
+            continue;
+        }
+        out.push_str(&text[last..link.range.start]);
+        out.push_str(&to.write(link.target, link.label));
+        last = link.range.end;
+        changed += 1;
+    }
+    out.push_str(&text[last..]);
+    return (out, changed);
+}
+
+// point the links to one note at another, each link keeps its syntax and label
+//   returns the new text and how many links changed
+pub fn retarget(text: &str, from: &str, to: &str) -> (String, usize) {
+    let mut out = String::new();
+    let mut last = 0;
+    let mut changed = 0;
+    for link in note_links(text) {
+        let note = split_anchor(link.target).0;
+        if note != from {
+            continue;
+        }
+        // the target is a slice of the text, so where it sits is known exactly
+        //   any #heading after the note is kept
+        let start = link.target.as_ptr() as usize - text.as_ptr() as usize;
+        out.push_str(&text[last..start]);
+        out.push_str(to);
+        last = start + note.len();
+        changed += 1;
+    }
+    out.push_str(&text[last..]);
+    return (out, changed);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::links::{
+        FileLink, LinkSyntax, anchor_matches, convert, file_links, image_links, link_target,
+        link_text, note_link_at, note_links, retarget, split_anchor, typed_link, web_link_at,
+    };
+
+    #[test]
+    fn test_file_links() {
+        let text = "see [the paper](docs/paper.PDF) and [site](https://a.b/c.pdf)\n![img](a.png)";
+        let links = file_links(text);
+        assert_eq!(
+            vec![
+                FileLink {
+                    range: 4..31,
+                    label: "the paper",
+                    target: "docs/paper.PDF",
+                },
+                FileLink {
+                    range: 36..61,
+                    label: "site",
+                    target: "https://a.b/c.pdf",
+                },
+            ],
+            links
+        );
+        assert!(links[0].is_pdf());
+        assert!(!links[1].is_pdf());
+        assert!(links[1].is_web());
+    }
+
+    #[test]
+    fn test_web_link_at() {
+        let text = "read [this](https://a.b/post) or https://c.d/e?f=1, then [notes](notes.pdf)";
+        assert_eq!(Some(("https://a.b/post", 29)), web_link_at(text, 7));
+        assert_eq!(Some(("https://c.d/e?f=1", 50)), web_link_at(text, 40));
+        assert_eq!(None, web_link_at(text, 2));
+        assert_eq!(None, web_link_at(text, 60));
+    }
+
+    #[test]
+    fn test_image_links() {
+        let text = "shot ![login page](shots/a.png) and [not](b.png)";
+        assert_eq!(
+            vec![FileLink {
+                range: 5..31,
+                label: "login page",
+                target: "shots/a.png",
+            }],
+            image_links(text)
+        );
+    }
+
+    #[test]
+    fn test_note_links() {
+        let text = "see @@work/plan, [[ideas|my ideas]] and [the list](todo.md), not [pdf](a.pdf) or [[a @@b]]";
+        let links = note_links(text);
+        let found: Vec<(&str, &str, LinkSyntax)> = links
+            .iter()
+            .map(|link| (link.target, link.label, link.syntax))
+            .collect();
+        assert_eq!(
+            vec![
+                ("work/plan", "", LinkSyntax::At),
+                ("ideas", "my ideas", LinkSyntax::Wiki),
+                ("todo", "the list", LinkSyntax::Markdown),
+                ("a @@b", "", LinkSyntax::Wiki),
+            ],
+            found
+        );
+        assert_eq!(Some("ideas"), note_link_at(text, 20));
+        assert_eq!(None, note_link_at(text, 0));
+        assert_eq!("ideas", link_target("[[ideas|my ideas]]"));
+        assert_eq!("work/plan", link_target("@@work/plan"));
+        assert_eq!("my ideas", link_text("[[ideas|my ideas]]"));
+        assert_eq!("ideas", link_text("[[ideas]]"));
+    }
+
+    #[test]
+    fn test_anchors() {
+        let text = "@@plan#Next_steps and [[plan#Next steps|next]] and [x](plan.md#goals)";
+        let targets: Vec<&str> = note_links(text).iter().map(|link| link.target).collect();
+        assert_eq!(
+            vec!["plan#Next_steps", "plan#Next steps", "plan.md#goals"],
+            targets
+        );
+        assert_eq!(("plan", Some("goals")), split_anchor("plan.md#goals"));
+        assert_eq!(("work/plan", None), split_anchor("work/plan"));
+        assert_eq!(("plan", None), split_anchor("plan#"));
+        assert!(anchor_matches("Next steps", "Next steps"));
+        assert!(anchor_matches("Next steps", "next-steps"));
+        assert!(anchor_matches("What's next?", "whats-next"));
+        assert!(anchor_matches("Next steps", "Next_steps"));
+        assert!(!anchor_matches("Next steps", "Next"));
+        assert_eq!(
+            "@@work/plan#Next_steps and [[work/plan#Next steps|next]] and [x](work/plan.md#goals)",
+            retarget(text, "plan", "work/plan").0
+        );
+    }
+
+    #[test]
+    fn test_typed_link() {
+        assert_eq!(Some((4, "")), typed_link("see @@"));
+        assert_eq!(Some((4, "work/pl")), typed_link("see @@work/pl"));
+        assert_eq!(Some((1, "a")), typed_link("@@@a"));
+        assert_eq!(None, typed_link("see @@work and"));
+        assert_eq!(None, typed_link("see @"));
+    }
+
+    #[test]
+    fn test_convert() {
+        let text = "@@a and [[b|Bee]] and [c](c.md) and [site](https://x.y)";
+        assert_eq!(
+            (
+                "[[a]] and [[b|Bee]] and [[c]] and [site](https://x.y)".to_string(),
+                2
+            ),
+            convert(text, None, LinkSyntax::Wiki)
+        );
+        assert_eq!(
+            (
+                "[a](a) and [Bee](b) and [c](c.md) and [site](https://x.y)".to_string(),
+                2
+            ),
+            convert(text, None, LinkSyntax::Markdown)
+        );
+        assert_eq!(
+            (
+                "@@a and Bee @@b and [c](c.md) and [site](https://x.y)".to_string(),
+                1
+            ),
+            convert(text, Some(LinkSyntax::Wiki), LinkSyntax::At)
+        );
+        assert_eq!(Some(LinkSyntax::Wiki), LinkSyntax::from_key("wiki\n"));
+    }
+
+    #[test]
+    fn test_retarget() {
+        let text = "@@old and @@older, [[old|Old one]], [old](old.md) and [[old]] and @@new";
+        let (retargeted, count) = retarget(text, "old", "work/new");
+        assert_eq!(
+            "@@work/new and @@older, [[work/new|Old one]], [old](work/new.md) and [[work/new]] and @@new",
+            retargeted
+        );
+        assert_eq!(4, count);
+        assert_eq!((text.to_string(), 0), retarget(text, "missing", "x"));
+    }
+}
@@ -0,0 +1,100 @@
+// carrying lists and quotes on to the next line when Enter is pressed in them
+
+use crate::typography::in_code;
+
+// the markup a line starts with that goes on to the next one: its indentation, quote markers, a
+//   list marker with the number counted up and a task box left unticked,
+//   returns how many bytes of the line it takes and what the next line starts with
+fn prefix(line: &str) -> Option<(usize, String)> {
+    let mut rest = line.trim_start_matches([' ', '\t']);
+    let mut next = line[..line.len() - rest.len()].to_string();
+    let mut found = false;
+    while let Some(after) = rest.strip_prefix('>') {
+        rest = after.strip_prefix(' ').unwrap_or(after);
+        next.push_str("> ");
+        found = true;
+    }
+
+    let mut listed = false;
+    if let Some(after) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| rest.strip_prefix(marker))
+    {
+        next.push_str(&rest[..2]);
+        rest = after;
+        listed = true;
+    } else {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if (1..10).contains(&digits)
+            && let Some(after) = rest[digits..]
+                .strip_prefix(". ")
+                .or_else(|| rest[digits..].strip_prefix(") "))
+        {
+            let number: u64 = rest[..digits].parse().ok()?;
+            next.push_str(&format!("{}{} ", number + 1, &rest[digits..digits + 1]));
+            rest = after;
+            listed = true;
+        }
+    }
+    if listed
+        && let Some(after) = ["[ ] ", "[x] ", "[X] "]
+            .iter()
+            .find_map(|task| rest.strip_prefix(task))
+    {
+        next.push_str("[ ] ");
+        rest = after;
+    }
+
+    if !found && !listed {
+        return None;
+    }
+    return Some((line.len() - rest.len(), next));
+}
+
+// what pressing Enter with `before` the cursor and `after` it on the same line should do instead
+//   of starting a bare line, returns how many characters before the cursor to remove and the text
+//   to insert, an item with nothing in it ends the list by losing its marker
+pub fn on_enter(before: &str, after: &str) -> Option<(usize, String)> {
+    if in_code(before) {
+        return None;
+    }
+    let line = match before.rfind('\n') {
+        Some(i) => &before[i + 1..],
+        None => before,
+    };
+    let (len, next) = prefix(line)?;
+    if len == line.len() && after.trim().is_empty() {
+        return Some((line.chars().count(), String::new()));
+    }
+    return Some((0, format!("\n{}", next)));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lists::on_enter;
+
+    #[test]
+    fn test_on_enter() {
+        let next = |text: &str| Some((0, text.to_string()));
+        assert_eq!(next("\n- "), on_enter("- milk", ""));
+        assert_eq!(next("\n  * "), on_enter("list\n  * eggs", ""));
+        assert_eq!(next("\n10. "), on_enter("9. nine", ""));
+        assert_eq!(next("\n3) "), on_enter("2) two", ""));
+        assert_eq!(next("\n- [ ] "), on_enter("- [x] done", ""));
+        assert_eq!(next("\n> "), on_enter(">quoted", ""));
+        assert_eq!(next("\n> > 2. "), on_enter("> > 1. deep", ""));
+        // the rest of the line goes with the new item
+        assert_eq!(next("\n- "), on_enter("- half", " and half"));
+
+        // an empty item ends the list
+        assert_eq!(Some((6, String::new())), on_enter("a\n- [ ] ", ""));
+        assert_eq!(Some((2, String::new())), on_enter("> ", " "));
+        assert_eq!(next("\n- "), on_enter("- ", "more"));
+
+        assert_eq!(None, on_enter("plain", ""));
+        assert_eq!(None, on_enter("  indented", ""));
+        assert_eq!(None, on_enter("**bold**", ""));
+        assert_eq!(None, on_enter("1.5 litres", ""));
+        assert_eq!(None, on_enter("```\n- code", ""));
+    }
+}
@@ -0,0 +1,335 @@
+// laying out $...$ math as plain unicode text, close enough to read without a tex engine
+//   commands become their symbols, scripts become super and subscript characters where unicode
+//   has them, fractions and roots are written out on one line
+
+const SYMBOLS: &[(&str, &str)] = &[
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("varepsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("vartheta", "ϑ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "φ"),
+    ("varphi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("times", "×"),
+    ("cdot", "·"),
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("div", "÷"),
+    ("leq", "≤"),
+    ("le", "≤"),
+    ("geq", "≥"),
+    ("ge", "≥"),
+    ("neq", "≠"),
+    ("ne", "≠"),
+    ("approx", "≈"),
+    ("equiv", "≡"),
+    ("sim", "∼"),
+    ("propto", "∝"),
+    ("infty", "∞"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("int", "∫"),
+    ("oint", "∮"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("subset", "⊂"),
+    ("subseteq", "⊆"),
+    ("supset", "⊃"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("emptyset", "∅"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("neg", "¬"),
+    ("land", "∧"),
+    ("lor", "∨"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("Rightarrow", "⇒"),
+    ("Leftarrow", "⇐"),
+    ("Leftrightarrow", "⇔"),
+    ("iff", "⇔"),
+    ("mapsto", "↦"),
+    ("ldots", "…"),
+    ("dots", "…"),
+    ("cdots", "⋯"),
+    ("circ", "∘"),
+    ("angle", "∠"),
+    ("perp", "⊥"),
+    ("parallel", "∥"),
+    ("hbar", "ℏ"),
+    ("ell", "ℓ"),
+    ("langle", "⟨"),
+    ("rangle", "⟩"),
+    ("lfloor", "⌊"),
+    ("rfloor", "⌋"),
+    ("lceil", "⌈"),
+    ("rceil", "⌉"),
+    ("{", "{"),
+    ("}", "}"),
+    ("$", "$"),
+    ("%", "%"),
+    ("\\", " "),
+    (",", " "),
+    (";", " "),
+    (":", " "),
+    ("!", ""),
+    ("quad", "  "),
+    ("qquad", "    "),
+    ("left", ""),
+    ("right", ""),
+];
+
+const SUPERSCRIPTS: &[(char, char)] = &[
+    ('0', '⁰'),
+    ('1', '¹'),
+    ('2', '²'),
+    ('3', '³'),
+    ('4', '⁴'),
+    ('5', '⁵'),
+    ('6', '⁶'),
+    ('7', '⁷'),
+    ('8', '⁸'),
+    ('9', '⁹'),
+    ('+', '⁺'),
+    ('-', '⁻'),
+    ('=', '⁼'),
+    ('(', '⁽'),
+    (')', '⁾'),
+    ('n', 'ⁿ'),
+    ('i', 'ⁱ'),
+    ('T', 'ᵀ'),
+    ('*', '*'),
+    ('′', '′'),
+];
+
+const SUBSCRIPTS: &[(char, char)] = &[
+    ('0', '₀'),
+    ('1', '₁'),
+    ('2', '₂'),
+    ('3', '₃'),
+    ('4', '₄'),
+    ('5', '₅'),
+    ('6', '₆'),
+    ('7', '₇'),
+    ('8', '₈'),
+    ('9', '₉'),
+    ('+', '₊'),
+    ('-', '₋'),
+    ('=', '₌'),
+    ('(', '₍'),
+    (')', '₎'),
+    ('a', 'ₐ'),
+    ('e', 'ₑ'),
+    ('o', 'ₒ'),
+    ('x', 'ₓ'),
+    ('h', 'ₕ'),
+    ('i', 'ᵢ'),
+    ('j', 'ⱼ'),
+    ('k', 'ₖ'),
+    ('l', 'ₗ'),
+    ('m', 'ₘ'),
+    ('n', 'ₙ'),
+    ('p', 'ₚ'),
+    ('r', 'ᵣ'),
+    ('s', 'ₛ'),
+    ('t', 'ₜ'),
+    ('u', 'ᵤ'),
+    ('v', 'ᵥ'),
+];
+
+const BLACKBOARD: &[(char, char)] = &[('N', 'ℕ'), ('Z', 'ℤ'), ('Q', 'ℚ'), ('R', 'ℝ'), ('C', 'ℂ')];
+
+// the text between the $ signs, or all of it if they aren't there
+pub fn source(span: &str) -> &str {
+    let inner = span.strip_prefix("$$").and_then(|s| s.strip_suffix("$$"));
+    return inner
+        .or_else(|| span.strip_prefix('$').and_then(|s| s.strip_suffix('$')))
+        .unwrap_or(span);
+}
+
+// the math in a $...$ or $$...$$ span laid out on one line
+pub fn layout(span: &str) -> String {
+    let tex: Vec<char> = source(span).trim().chars().collect();
+    let mut pos = 0;
+    let mut output = String::new();
+    while pos < tex.len() {
+        output += &item(&tex, &mut pos);
+    }
+    return output.split_whitespace().collect::<Vec<_>>().join(" ");
+}
+
+// a braced group's contents, or the one thing that follows
+fn argument(tex: &[char], pos: &mut usize) -> String {
+    while *pos < tex.len() && tex[*pos] == ' ' {
+        *pos += 1;
+    }
+    if *pos < tex.len() && tex[*pos] == '{' {
+        *pos += 1;
+        let mut output = String::new();
+        while *pos < tex.len() && tex[*pos] != '}' {
+            output += &item(tex, pos);
+        }
+        *pos += 1;
+        return output;
+    }
+    if *pos >= tex.len() {
+        return String::new();
+    }
+    return item(tex, pos);
+}
+
+// the characters of a script if unicode has them all, otherwise the script written out
+fn script(text: &str, table: &[(char, char)], marker: char) -> String {
+    let mapped: Option<String> = text
+        .chars()
+        .map(|c| table.iter().find(|(from, _)| *from == c).map(|(_, to)| *to))
+        .collect();
+    return match mapped {
+        Some(mapped) => mapped,
+        None if text.chars().count() == 1 => format!("{}{}", marker, text),
+        None => format!("{}({})", marker, text),
+    };
+}
+
+// wrapped in brackets unless it's a single thing
+fn grouped(text: &str) -> String {
+    if text.chars().count() <= 1 || text.chars().all(|c| c.is_alphanumeric()) {
+        return text.to_string();
+    }
+    return format!("({})", text);
+}
+
+fn item(tex: &[char], pos: &mut usize) -> String {
+    let c = tex[*pos];
+    *pos += 1;
+    match c {
+        '^' => return script(&argument(tex, pos), SUPERSCRIPTS, '^'),
+        '_' => return script(&argument(tex, pos), SUBSCRIPTS, '_'),
+        '{' => {
+            *pos -= 1;
+            return argument(tex, pos);
+        }
+        '}' => return String::new(),
+        '\\' => {}
+        _ => return c.to_string(),
+    }
+
+    let start = *pos;
+    while *pos < tex.len() && tex[*pos].is_ascii_alphabetic() {
+        *pos += 1;
+    }
+    // a command is a run of letters, or else one symbol
+    if *pos == start && *pos < tex.len() {
+        *pos += 1;
+    }
+    let name: String = tex[start..*pos].iter().collect();
+    match name.as_str() {
+        "frac" | "dfrac" | "tfrac" => {
+            let top = argument(tex, pos);
+            let bottom = argument(tex, pos);
+            return format!("{}/{}", grouped(&top), grouped(&bottom));
+        }
+        "sqrt" => {
+            let mut root = String::new();
+            if *pos < tex.len() && tex[*pos] == '[' {
+                let end = tex[*pos..]
+                    .iter()
+                    .position(|c| *c == ']')
+                    .map_or(tex.len(), |i| *pos + i);
+                root = script(
+                    &tex[*pos + 1..end].iter().collect::<String>(),
+                    SUPERSCRIPTS,
+                    '^',
+                );
+                *pos = (end + 1).min(tex.len());
+            }
+            return format!("{}√{}", root, grouped(&argument(tex, pos)));
+        }
+        "mathbb" => {
+            let text = argument(tex, pos);
+            return text
+                .chars()
+                .map(|c| {
+                    BLACKBOARD
+                        .iter()
+                        .find(|(from, _)| *from == c)
+                        .map_or(c, |(_, to)| *to)
+                })
+                .collect();
+        }
+        "text" | "mathrm" | "mathbf" | "mathit" | "operatorname" => return argument(tex, pos),
+        _ => {}
+    }
+    if let Some((_, symbol)) = SYMBOLS.iter().find(|(command, _)| *command == name) {
+        return symbol.to_string();
+    }
+    // functions like \sin are written as they are, anything unknown keeps its backslash
+    if [
+        "sin", "cos", "tan", "log", "ln", "exp", "lim", "max", "min", "det",
+    ]
+    .contains(&name.as_str())
+    {
+        return name;
+    }
+    return format!("\\{}", name);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::{layout, source};
+
+    #[test]
+    fn test_layout() {
+        assert_eq!("x", source("$x$"));
+        assert_eq!("x", source("$$x$$"));
+        assert_eq!("E = mc²", layout("$E = mc^2$"));
+        assert_eq!("x₁₀ + αᵢ", layout("$x_{10} + \\alpha_i$"));
+        assert_eq!("(a+b)/2", layout("$\\frac{a+b}{2}$"));
+        assert_eq!(
+            "∑ₙ 1/n² = π²/6",
+            layout("$$\\sum_n \\frac{1}{n^2} = \\frac{\\pi^2}{6}$$")
+        );
+        assert_eq!("³√x ≤ √(x+1)", layout("$\\sqrt[3]{x} \\leq \\sqrt{x+1}$"));
+        assert_eq!("x^y ∈ ℝ", layout("$x^y \\in \\mathbb{R}$"));
+        assert_eq!(
+            "e^(iπ) sin x \\unknown",
+            layout("$e^{i\\pi} \\sin x \\unknown$")
+        );
+        assert_eq!("if x > 0", layout("$\\text{if } x > 0$"));
+        // unfinished bits don't trip it up
+        assert_eq!("x^/", layout("$x^ \\frac{$"));
+    }
+}
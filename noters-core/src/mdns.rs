@@ -0,0 +1,320 @@
+use std::net::Ipv4Addr;
+
+// just enough multicast dns to find other NoteRs instances on the local network without setting
+//   anything up: a query for the service, and answers naming an instance with its port and address
+//   the sockets are the caller's business, this only builds and reads packets
+
+pub const GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+pub const PORT: u16 = 5353;
+
+const PTR: u16 = 12;
+const TXT: u16 = 16;
+const SRV: u16 = 33;
+const A: u16 = 1;
+const ANY: u16 = 255;
+const IN: u16 = 1;
+// records about ourselves replace older copies in caches, per rfc 6762
+const FLUSH: u16 = 0x8000;
+const TTL: u32 = 120;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Service {
+    // the instance's own label, e.g. the machine's name
+    pub instance: String,
+    pub host: String,
+    pub port: u16,
+    pub addrs: Vec<Ipv4Addr>,
+    pub txt: Vec<String>,
+}
+
+fn write_name(packet: &mut Vec<u8>, name: &str) {
+    for label in name.trim_end_matches('.').split('.') {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label);
+    }
+    packet.push(0);
+}
+
+fn write_record(packet: &mut Vec<u8>, name: &str, kind: u16, class: u16, data: &[u8]) {
+    write_name(packet, name);
+    packet.extend_from_slice(&kind.to_be_bytes());
+    packet.extend_from_slice(&class.to_be_bytes());
+    packet.extend_from_slice(&TTL.to_be_bytes());
+    packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    packet.extend_from_slice(data);
+}
+
+fn header(flags: u16, questions: u16, answers: u16) -> Vec<u8> {
+    let mut packet = vec![0, 0];
+    for field in [flags, questions, answers, 0, 0] {
+        packet.extend_from_slice(&field.to_be_bytes());
+    }
+    return packet;
+}
+
+// ask who offers a service, e.g. _noters-sync._tcp.local
+pub fn query(service: &str) -> Vec<u8> {
+    let mut packet = header(0, 1, 0);
+    write_name(&mut packet, service);
+    packet.extend_from_slice(&PTR.to_be_bytes());
+    packet.extend_from_slice(&IN.to_be_bytes());
+    return packet;
+}
+
+// say that we offer it, sent as the answer to a query and on our own now and then
+pub fn announce(service: &str, me: &Service) -> Vec<u8> {
+    let full = format!("{}.{}", me.instance, service);
+    let mut packet = header(0x8400, 0, 3 + me.addrs.len() as u16);
+
+    let mut name = Vec::new();
+    write_name(&mut name, &full);
+    write_record(&mut packet, service, PTR, IN, &name);
+
+    let mut srv = Vec::new();
+    srv.extend_from_slice(&[0, 0, 0, 0]);
+    srv.extend_from_slice(&me.port.to_be_bytes());
+    write_name(&mut srv, &me.host);
+    write_record(&mut packet, &full, SRV, IN | FLUSH, &srv);
+
+    let mut txt = Vec::new();
+    for entry in &me.txt {
+        let entry = &entry.as_bytes()[..entry.len().min(255)];
+        txt.push(entry.len() as u8);
+        txt.extend_from_slice(entry);
+    }
+    if txt.is_empty() {
+        txt.push(0);
+    }
+    write_record(&mut packet, &full, TXT, IN | FLUSH, &txt);
+
+    for addr in &me.addrs {
+        write_record(&mut packet, &me.host, A, IN | FLUSH, &addr.octets());
+    }
+    return packet;
+}
+
+struct Reader<'a> {
+    packet: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.packet.get(self.pos..self.pos + 2)?;
+        self.pos += 2;
+        return Some(u16::from_be_bytes([bytes[0], bytes[1]]));
+    }
+
+    fn skip(&mut self, n: usize) -> Option<()> {
+        if self.pos + n > self.packet.len() {
+            return None;
+        }
+        self.pos += n;
+        return Some(());
+    }
+
+    // a name, following compression pointers back into the packet
+    fn name(&mut self) -> Option<String> {
+        let mut labels = Vec::new();
+        let mut pos = self.pos;
+        let mut end = None;
+        // pointers can loop in a malicious packet
+        for _ in 0..64 {
+            let len = *self.packet.get(pos)? as usize;
+            if len == 0 {
+                self.pos = end.unwrap_or(pos + 1);
+                return Some(labels.join("."));
+            }
+            if len & 0xc0 == 0xc0 {
+                let low = *self.packet.get(pos + 1)? as usize;
+                end.get_or_insert(pos + 2);
+                pos = ((len & 0x3f) << 8) | low;
+                continue;
+            }
+            let label = self.packet.get(pos + 1..pos + 1 + len)?;
+            labels.push(String::from_utf8_lossy(label).into_owned());
+            pos += 1 + len;
+        }
+        return None;
+    }
+}
+
+// a name asked about and the record type wanted
+type Question = (String, u16);
+
+struct Record {
+    name: String,
+    kind: u16,
+    // where the data starts in the packet and how long it is
+    data: usize,
+    len: usize,
+}
+
+fn same(a: &str, b: &str) -> bool {
+    return a
+        .trim_end_matches('.')
+        .eq_ignore_ascii_case(b.trim_end_matches('.'));
+}
+
+// the questions and records in a packet, None if it doesn't parse
+fn parse(packet: &[u8]) -> Option<(Vec<Question>, Vec<Record>)> {
+    let mut reader = Reader { packet, pos: 4 };
+    let questions = reader.u16()?;
+    let mut records = 0;
+    for _ in 0..3 {
+        records += reader.u16()? as usize;
+    }
+    let mut asked = Vec::new();
+    for _ in 0..questions {
+        let name = reader.name()?;
+        let kind = reader.u16()?;
+        reader.u16()?;
+        asked.push((name, kind));
+    }
+    let mut found = Vec::new();
+    for _ in 0..records {
+        let name = reader.name()?;
+        let kind = reader.u16()?;
+        reader.skip(6)?;
+        let len = reader.u16()? as usize;
+        let data = reader.pos;
+        reader.skip(len)?;
+        found.push(Record {
+            name,
+            kind,
+            data,
+            len,
+        });
+    }
+    return Some((asked, found));
+}
+
+// true when a packet is a query we should answer
+pub fn asks_for(packet: &[u8], service: &str) -> bool {
+    let is_query = packet.get(2).is_some_and(|flags| flags & 0x80 == 0);
+    let Some((asked, _)) = parse(packet).filter(|_| is_query) else {
+        return false;
+    };
+    return asked
+        .iter()
+        .any(|(name, kind)| same(name, service) && (*kind == PTR || *kind == ANY));
+}
+
+// the instances of a service a packet tells us about
+pub fn services(packet: &[u8], service: &str) -> Vec<Service> {
+    let Some((_, records)) = parse(packet) else {
+        return Vec::new();
+    };
+    let reader = |pos| Reader { packet, pos };
+    let suffix = format!(".{}", service.trim_end_matches('.'));
+
+    let mut services = Vec::new();
+    for record in records
+        .iter()
+        .filter(|r| r.kind == PTR && same(&r.name, service))
+    {
+        let Some(full) = reader(record.data).name() else {
+            continue;
+        };
+        let mut found = Service {
+            instance: full
+                .strip_suffix(&suffix)
+                .unwrap_or(full.as_str())
+                .to_string(),
+            host: String::new(),
+            port: 0,
+            addrs: Vec::new(),
+            txt: Vec::new(),
+        };
+        for other in records.iter().filter(|r| same(&r.name, &full)) {
+            match other.kind {
+                SRV if other.len > 6 => {
+                    let mut srv = reader(other.data + 4);
+                    found.port = srv.u16().unwrap_or(0);
+                    found.host = srv.name().unwrap_or_default();
+                }
+                TXT => {
+                    let data = &packet[other.data..other.data + other.len];
+                    let mut i = 0;
+                    while let Some(&len) = data.get(i) {
+                        let entry = data.get(i + 1..i + 1 + len as usize).unwrap_or(&[]);
+                        if !entry.is_empty() {
+                            found.txt.push(String::from_utf8_lossy(entry).into_owned());
+                        }
+                        i += 1 + len as usize;
+                    }
+                }
+                _ => {}
+            }
+        }
+        for addr in records
+            .iter()
+            .filter(|r| r.kind == A && r.len == 4 && same(&r.name, &found.host))
+        {
+            let o = &packet[addr.data..addr.data + 4];
+            found.addrs.push(Ipv4Addr::new(o[0], o[1], o[2], o[3]));
+        }
+        if found.port != 0 {
+            services.push(found);
+        }
+    }
+    return services;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mdns::{Service, announce, asks_for, query, services};
+    use std::net::Ipv4Addr;
+
+    const SERVICE: &str = "_noters-sync._tcp.local";
+
+    #[test]
+    fn test_round_trip() {
+        let me = Service {
+            instance: "laptop".to_string(),
+            host: "laptop.local".to_string(),
+            port: 7879,
+            addrs: vec![Ipv4Addr::new(192, 168, 1, 20)],
+            txt: vec!["id=abc".to_string()],
+        };
+        assert!(asks_for(&query(SERVICE), SERVICE));
+        assert!(!asks_for(&query("_http._tcp.local"), SERVICE));
+
+        let packet = announce(SERVICE, &me);
+        assert!(!asks_for(&packet, SERVICE));
+        assert_eq!(vec![me], services(&packet, SERVICE));
+        assert!(services(&packet, "_other._tcp.local").is_empty());
+        assert!(services(&packet[..20], SERVICE).is_empty());
+    }
+
+    #[test]
+    fn test_compressed_names() {
+        // a PTR answer whose target points back at the question name
+        let mut packet = vec![0, 0, 0x84, 0, 0, 1, 0, 3, 0, 0, 0, 0];
+        for label in ["_noters-sync", "_tcp", "local"] {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.extend_from_slice(&[0, 0, 12, 0, 1]);
+        // PTR: name -> pointer to 12, data "desk" + pointer
+        packet.extend_from_slice(&[0xc0, 12, 0, 12, 0, 1, 0, 0, 0, 120, 0, 7]);
+        let full = packet.len();
+        packet.extend_from_slice(&[4, b'd', b'e', b's', b'k', 0xc0, 12]);
+        // SRV on the instance, target "desk" and then a pointer to "local" in the question
+        packet.extend_from_slice(&[0xc0, full as u8, 0, 33, 0, 1, 0, 0, 0, 120, 0, 13]);
+        packet.extend_from_slice(&[0, 0, 0, 0, 0x1e, 0xd7]);
+        let host = packet.len();
+        packet.extend_from_slice(&[4, b'd', b'e', b's', b'k', 0xc0, 30]);
+        // A for the host
+        packet.extend_from_slice(&[
+            0xc0, host as u8, 0, 1, 0, 1, 0, 0, 0, 120, 0, 4, 10, 0, 0, 2,
+        ]);
+
+        let found = services(&packet, SERVICE);
+        assert_eq!(1, found.len());
+        assert_eq!("desk", found[0].instance);
+        assert_eq!(7895, found[0].port);
+        assert_eq!(vec![Ipv4Addr::new(10, 0, 0, 2)], found[0].addrs);
+    }
+}
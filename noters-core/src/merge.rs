@@ -0,0 +1,105 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Line<'a> {
+    Both(&'a str),
+    Mine(&'a str),
+    Theirs(&'a str),
+}
+
+// line by line differences between two versions of a note, using the longest common subsequence
+pub fn diff_lines<'a>(mine: &'a str, theirs: &'a str) -> Vec<Line<'a>> {
+    let a: Vec<&str> = mine.split('\n').collect();
+    let b: Vec<&str> = theirs.split('\n').collect();
+
+    // common[i][j] is the longest common run of a[i..] and b[j..]
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push(Line::Both(a[i]));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(Line::Mine(a[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Theirs(b[j]));
+            j += 1;
+        }
+    }
+    return lines;
+}
+
+fn flush<'a>(out: &mut Vec<&'a str>, ours: &mut Vec<&'a str>, others: &mut Vec<&'a str>) {
+    if ours.is_empty() && others.is_empty() {
+        return;
+    }
+    out.push("<<<<<<< mine");
+    out.append(ours);
+    out.push("=======");
+    out.append(others);
+    out.push(">>>>>>> on disk");
+}
+
+// both versions in one text, with git style markers around the parts that differ
+pub fn conflict_markers(mine: &str, theirs: &str) -> String {
+    let mut out: Vec<&str> = Vec::new();
+    let mut ours: Vec<&str> = Vec::new();
+    let mut others: Vec<&str> = Vec::new();
+
+    for line in diff_lines(mine, theirs) {
+        match line {
+            Line::Both(text) => {
+                flush(&mut out, &mut ours, &mut others);
+                out.push(text);
+            }
+            Line::Mine(text) => ours.push(text),
+            Line::Theirs(text) => others.push(text),
+        }
+    }
+    flush(&mut out, &mut ours, &mut others);
+    return out.join("\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merge::{Line, conflict_markers, diff_lines};
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(
+            vec![
+                Line::Both("# A"),
+                Line::Mine("mine"),
+                Line::Theirs("theirs"),
+                Line::Both("end"),
+                Line::Theirs("more"),
+            ],
+            diff_lines("# A\nmine\nend", "# A\ntheirs\nend\nmore")
+        );
+        assert_eq!(vec![Line::Both("same")], diff_lines("same", "same"));
+    }
+
+    #[test]
+    fn test_conflict_markers() {
+        assert_eq!("a\nb", conflict_markers("a\nb", "a\nb"));
+        assert_eq!(
+            "# A\n<<<<<<< mine\nmine\n=======\ntheirs\n>>>>>>> on disk\nend",
+            conflict_markers("# A\nmine\nend", "# A\ntheirs\nend")
+        );
+        assert_eq!(
+            "a\n<<<<<<< mine\n=======\nadded\n>>>>>>> on disk",
+            conflict_markers("a", "a\nadded")
+        );
+    }
+}
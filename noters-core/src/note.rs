@@ -0,0 +1,2920 @@
+use crate::edit::{Edit, EditError, sort_changes};
+use crate::frontmatter::{self, Value};
+use crate::graphemes::{char_index, cluster_byte, cluster_range, floor_char};
+use regex::Regex;
+use ropey::Rope;
+use slotmap::SlotMap;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::LazyLock;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarkdownType {
+    None,
+    Heading1,
+    Heading2,
+    Heading3,
+    Paragraph,
+    Bold,
+    Italic,
+    Strikethrough,
+    Link,
+    // a web address, written bare or as [text](url)
+    WebLink,
+    Monospace,
+    // a fenced block and the language named after its opening fence, empty when there isn't one
+    Code(String),
+    // the marker starting a list item with whatever indents it, and how deeply it's nested
+    Bullet(usize),
+    Numbered(usize),
+    // a `- [ ]` or `- [x]` marker, and whether it's ticked
+    Task(bool),
+    // $...$ in a line, or $$...$$ set apart when it's true
+    Math(bool),
+    // the --- block of metadata at the very start of a note
+    Frontmatter,
+    // a #tag, the text includes the #
+    Tag,
+    // a {{toc}} line, which gets a table of contents put after it
+    Toc,
+    // text made from the rest of the note, shown but never saved or edited
+    Generated,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SortOptions {
+    pub case_insensitive: bool,
+    pub reverse: bool,
+    pub unique: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextCase {
+    Upper,
+    Lower,
+    Title,
+}
+
+impl TextCase {
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            TextCase::Upper => text.to_uppercase(),
+            TextCase::Lower => text.to_lowercase(),
+            TextCase::Title => {
+                let mut output = String::with_capacity(text.len());
+                let mut word_start = true;
+                for c in text.chars() {
+                    if word_start {
+                        output.extend(c.to_uppercase());
+                    } else {
+                        output.extend(c.to_lowercase());
+                    }
+                    word_start = !(c.is_alphanumeric() || c == '\'');
+                }
+                output
+            }
+        }
+    }
+}
+
+// a heading anywhere in the tree, folded or not
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heading {
+    pub title: String,
+    pub level: usize,
+    pub path: Vec<usize>,
+    // position in the full text
+    pub pos: usize,
+    // every section above it is expanded, so it's on screen
+    pub visible: bool,
+    pub expanded: bool,
+    // the checkboxes anywhere under it, None if there aren't any
+    pub progress: Option<Progress>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+// how many `- [x]` task lines there are out of all the `- [ ]` and `- [x]` ones
+pub fn task_progress(text: &str) -> Option<Progress> {
+    let mut progress = Progress { done: 0, total: 0 };
+    for caps in TASK.captures_iter(text) {
+        progress.total += 1;
+        if &caps[1] != " " {
+            progress.done += 1;
+        }
+    }
+    if progress.total == 0 {
+        return None;
+    }
+    return Some(progress);
+}
+
+// the table of contents a {{toc}} line gets, a line for every heading indented by its level
+//   it starts on the line after the {{toc}}, which keeps its own newline after the list
+pub fn toc_text(headings: &[Heading]) -> String {
+    let mut toc = String::new();
+    for heading in headings {
+        toc.push('\n');
+        toc.push_str(&"  ".repeat(heading.level.saturating_sub(1)));
+        toc.push_str("• ");
+        toc.push_str(&heading.title);
+    }
+    return toc;
+}
+
+slotmap::new_key_type! {
+    // a node in a note's tree
+    pub struct NodeId;
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Text(MarkdownString),
+    Section(Section),
+}
+
+#[derive(Clone, Debug)]
+pub struct Section {
+    heading: String,
+    expanded: bool,
+    level: usize,
+    mdtype: MarkdownType,
+    children: Vec<NodeId>,
+    // lengths with everything expanded and as displayed, so position lookups don't walk the subtree
+    //   anything that changes the text or folding below here has to call update_len on the way out
+    full_len: usize,
+    display_len: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct MarkdownString {
+    pub text: String,
+    pub mdtype: MarkdownType,
+    pub expanded: bool,
+}
+
+#[derive(Debug)]
+pub struct MarkdownStr<'a> {
+    pub text: &'a str,
+    pub mdtype: MarkdownType,
+}
+
+impl MarkdownString {
+    pub fn new(content: String) -> Self {
+        return Self {
+            text: content,
+            mdtype: MarkdownType::Paragraph,
+            expanded: true,
+        };
+    }
+}
+
+impl Default for Section {
+    fn default() -> Self {
+        Self {
+            heading: String::new(),
+            expanded: true,
+            level: 0,
+            mdtype: MarkdownType::None,
+            children: Vec::new(),
+            full_len: 0,
+            display_len: 0,
+        }
+    }
+}
+
+// every node of a note lives in one arena and sections refer to their children by id,
+//   so the tree is a couple of flat allocations and a snapshot is a single clone
+#[derive(Clone, Debug)]
+pub struct Tree {
+    nodes: SlotMap<NodeId, Node>,
+    root: NodeId,
+}
+
+impl Default for Tree {
+    fn default() -> Self {
+        let mut nodes = SlotMap::with_key();
+        let root = nodes.insert(Node::Section(Section::default()));
+        return Self { nodes, root };
+    }
+}
+
+impl Tree {
+    fn new(text: &str) -> Self {
+        let mut tree = Self::default();
+        tree.reset(text);
+        return tree;
+    }
+
+    // throw away everything under the root and parse the full text again
+    //   frontmatter is kept out of the parse so its comments aren't taken for headings
+    fn reset(&mut self, text: &str) {
+        let root = self.root;
+        self.nodes.retain(|id, _| id == root);
+        let mut children = Vec::new();
+        let mut text = text;
+        if let Some(len) = frontmatter::len(text) {
+            children.push(self.nodes.insert(Node::Text(MarkdownString {
+                text: text[..len].to_string(),
+                mdtype: MarkdownType::Frontmatter,
+                expanded: true,
+            })));
+            text = &text[len..];
+        }
+        children.extend(self.parse(text.to_string()));
+        if let Node::Section(section) = &mut self.nodes[root] {
+            section.children = children;
+        }
+        self.update_len(root);
+    }
+
+    fn frontmatter(&self) -> Option<&str> {
+        let first = *self.section(self.root)?.children.first()?;
+        return match &self.nodes[first] {
+            Node::Text(s) if s.mdtype == MarkdownType::Frontmatter => Some(&s.text),
+            _ => None,
+        };
+    }
+
+    fn add_section(&mut self, heading: String, level: usize, children: Vec<NodeId>) -> NodeId {
+        let id = self.nodes.insert(Node::Section(Section {
+            heading,
+            expanded: true,
+            level,
+            mdtype: match level {
+                1 => MarkdownType::Heading1,
+                2 => MarkdownType::Heading2,
+                _ => MarkdownType::Heading3,
+            },
+            children,
+            full_len: 0,
+            display_len: 0,
+        }));
+        self.update_len(id);
+        return id;
+    }
+
+    fn section(&self, id: NodeId) -> Option<&Section> {
+        match &self.nodes[id] {
+            Node::Section(section) => Some(section),
+            Node::Text(_) => None,
+        }
+    }
+
+    // recompute a section's cached lengths from its heading and its children's cached lengths
+    fn update_len(&mut self, id: NodeId) {
+        let Some(section) = self.section(id) else {
+            return;
+        };
+        let mut length = 0;
+        if section.level > 0 {
+            length = section.level /*+ 1*/ + section.heading.len();
+        }
+        let full = length
+            + section
+                .children
+                .iter()
+                .map(|n| self.len_of(*n, true))
+                .sum::<usize>();
+        let mut display = length;
+        if section.expanded {
+            display += section
+                .children
+                .iter()
+                .map(|n| self.len_of(*n, false))
+                .sum::<usize>();
+        }
+        if let Node::Section(section) = &mut self.nodes[id] {
+            section.full_len = full;
+            section.display_len = display;
+        }
+    }
+
+    fn len_of(&self, id: NodeId, flatten: bool) -> usize {
+        match &self.nodes[id] {
+            // generated text is only there to be seen
+            Node::Text(text) if flatten && text.mdtype == MarkdownType::Generated => 0,
+            Node::Text(text) => text.text.len(),
+            Node::Section(section) if flatten => section.full_len,
+            Node::Section(section) => section.display_len,
+        }
+    }
+
+    fn len(&self, flatten: bool) -> usize {
+        return self.len_of(self.root, flatten);
+    }
+
+    fn string(&self, full: bool) -> String {
+        let mut output = String::with_capacity(self.len(full));
+        self.write(self.root, full, &mut output);
+        return output;
+    }
+
+    fn write(&self, id: NodeId, full: bool, output: &mut String) {
+        match &self.nodes[id] {
+            Node::Text(text) if full && text.mdtype == MarkdownType::Generated => {}
+            Node::Text(text) => output.push_str(&text.text),
+            Node::Section(section) => {
+                for _ in 0..section.level {
+                    output.push('#');
+                }
+                output.push_str(&section.heading);
+                if full || section.expanded {
+                    for n in &section.children {
+                        self.write(*n, full, output);
+                    }
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, text: &str, pos: usize) -> bool {
+        return self.insert_at(self.root, text, pos);
+    }
+
+    fn insert_at(&mut self, id: NodeId, text: &str, pos: usize) -> bool {
+        let section = match &mut self.nodes[id] {
+            Node::Text(string) if string.mdtype == MarkdownType::Generated => return false,
+            Node::Text(string) => {
+                string.text.insert_str(pos, text);
+                return true;
+            }
+            Node::Section(section) => section,
+        };
+
+        let mut cur = pos;
+        if section.level > 0 {
+            // TODO: handle reparse if it editing the heading marker
+            if cur < section.level {
+                return false;
+            }
+            cur -= section.level;
+
+            if cur < section.heading.len() {
+                section.heading.insert_str(cur, text);
+                self.update_len(id);
+                return true;
+            }
+            cur -= section.heading.len();
+        }
+
+        for n in section.children.clone() {
+            let len = self.len_of(n, false);
+            if cur < len {
+                if !self.insert_at(n, text, cur) {
+                    return false;
+                }
+                self.update_len(id);
+                return true;
+            }
+            cur -= len;
+        }
+        return false;
+    }
+
+    fn translate(&self, pos: usize) -> usize {
+        return self.translate_at(self.root, pos);
+    }
+
+    fn translate_at(&self, id: NodeId, pos: usize) -> usize {
+        let Some(section) = self.section(id) else {
+            return pos;
+        };
+        let mut cur = 0;
+        if section.level > 0 {
+            cur += section.level + section.heading.len();
+            if pos < cur {
+                return pos;
+            }
+        }
+
+        // generated text is displayed but isn't in the full text, so the two are counted apart
+        let mut full = cur;
+        if section.expanded {
+            for n in &section.children {
+                let display_len = self.len_of(*n, false);
+                if pos - cur < display_len {
+                    if self.is_generated(*n) {
+                        return full;
+                    }
+                    return self.translate_at(*n, pos - cur) + full;
+                }
+                cur += display_len;
+                full += self.len_of(*n, true);
+            }
+        }
+        return pos - cur + full;
+    }
+
+    fn inv_translate(&self, pos: usize) -> usize {
+        return self.inv_translate_at(self.root, pos);
+    }
+
+    fn inv_translate_at(&self, id: NodeId, pos: usize) -> usize {
+        let Some(section) = self.section(id) else {
+            return pos;
+        };
+        let mut cur = 0;
+        if section.level > 0 {
+            cur += section.level + section.heading.len();
+            if pos < cur {
+                return pos;
+            }
+        }
+
+        // just assume we're inside this section since the parent wouldn't have called if we weren't
+        if !section.expanded {
+            // heading text contains a newline, remove this if that changes
+            return cur - 1;
+        }
+
+        let mut full = cur;
+        for n in &section.children {
+            let full_len = self.len_of(*n, true);
+            if pos - full < full_len {
+                return self.inv_translate_at(*n, pos - full) + cur;
+            }
+            full += full_len;
+            cur += self.len_of(*n, false);
+        }
+
+        return cur;
+    }
+
+    fn is_generated(&self, id: NodeId) -> bool {
+        return matches!(&self.nodes[id], Node::Text(s) if s.mdtype == MarkdownType::Generated);
+    }
+
+    fn has_toc(&self) -> bool {
+        return self
+            .nodes
+            .values()
+            .any(|n| matches!(n, Node::Text(s) if s.mdtype == MarkdownType::Toc));
+    }
+
+    // put a fresh table of contents after every {{toc}}, dropping the ones made before
+    fn generate(&mut self) {
+        if self.nodes.values().any(|n| match n {
+            Node::Text(s) => s.mdtype == MarkdownType::Generated,
+            _ => false,
+        }) {
+            self.generate_at(self.root, None);
+        }
+        if self.has_toc() {
+            let toc = toc_text(&self.headings());
+            self.generate_at(self.root, Some(&toc));
+        }
+    }
+
+    // rebuild a section's children with the generated text taken out, or put in when there's some
+    fn generate_at(&mut self, id: NodeId, toc: Option<&str>) {
+        let Some(section) = self.section(id) else {
+            return;
+        };
+        let old = section.children.clone();
+        let mut children = Vec::with_capacity(old.len());
+        for n in old {
+            if self.is_generated(n) {
+                self.nodes.remove(n);
+                continue;
+            }
+            children.push(n);
+            self.generate_at(n, toc);
+            if let (Some(toc), Node::Text(s)) = (toc, &self.nodes[n])
+                && s.mdtype == MarkdownType::Toc
+            {
+                children.push(self.nodes.insert(Node::Text(MarkdownString {
+                    text: toc.to_string(),
+                    mdtype: MarkdownType::Generated,
+                    expanded: true,
+                })));
+            }
+        }
+        if let Node::Section(section) = &mut self.nodes[id] {
+            section.children = children;
+        }
+        self.update_len(id);
+    }
+
+    // the displayed byte ranges of generated text
+    fn generated_ranges(&self) -> Vec<std::ops::Range<usize>> {
+        let mut ranges = Vec::new();
+        self.generated_ranges_at(self.root, 0, &mut ranges);
+        return ranges;
+    }
+
+    fn generated_ranges_at(
+        &self,
+        id: NodeId,
+        pos: usize,
+        ranges: &mut Vec<std::ops::Range<usize>>,
+    ) {
+        let Some(section) = self.section(id) else {
+            if self.is_generated(id) {
+                ranges.push(pos..pos + self.len_of(id, false));
+            }
+            return;
+        };
+        if !section.expanded {
+            return;
+        }
+        let mut cur = pos + section.level + section.heading.len();
+        for n in &section.children {
+            self.generated_ranges_at(*n, cur, ranges);
+            cur += self.len_of(*n, false);
+        }
+    }
+
+    // change the folding of the section at a path, `change` gets its current state
+    fn fold(&mut self, path: &[usize], change: impl Fn(bool) -> bool) {
+        let mut ids = vec![self.root];
+        for i in path {
+            match self.section(*ids.last().unwrap()) {
+                Some(section) => ids.push(section.children[*i]),
+                None => panic!("cannot fold string"),
+            }
+        }
+        match &mut self.nodes[*ids.last().unwrap()] {
+            Node::Section(section) => section.expanded = change(section.expanded),
+            Node::Text(_) => panic!("cannot fold string"),
+        }
+        for id in ids.into_iter().rev() {
+            self.update_len(id);
+        }
+    }
+
+    // drop a node and everything under it from the arena
+    fn remove(&mut self, id: NodeId) {
+        if let Some(Node::Section(section)) = self.nodes.remove(id) {
+            for child in section.children {
+                self.remove(child);
+            }
+        }
+    }
+
+    // replace a range of the full text by parsing again just the body of the innermost section
+    //   holding it, the rest of the tree and its folds are left alone
+    //   false when nothing smaller than the whole note will do: the edit touches a heading, or it
+    //   would make a heading that ends the section or run the section's last line into the next
+    fn reparse_within(&mut self, range: std::ops::Range<usize>, text: &str) -> bool {
+        // the sections down to the edit, with where each one's body starts
+        let mut sections = Vec::new();
+        let mut id = self.root;
+        let mut start = 0;
+        'descend: while let Some(section) = self.section(id) {
+            let body = start + section.level + section.heading.len();
+            if range.start < body || range.end > start + section.full_len {
+                break;
+            }
+            sections.push((id, body));
+            let mut cur = body;
+            for child in &section.children {
+                let len = self.len_of(*child, true);
+                if self.section(*child).is_some() && range.start >= cur && range.end <= cur + len {
+                    id = *child;
+                    start = cur;
+                    continue 'descend;
+                }
+                cur += len;
+            }
+            break;
+        }
+
+        let ends_document = |end: usize| end == self.len(true);
+        let found = sections
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(depth, (id, body))| {
+                let section = self.section(*id)?;
+                if depth == 0 || !section.heading.ends_with('\n') {
+                    return None;
+                }
+                let end = body + section.full_len - section.level - section.heading.len();
+                let mut new_body = String::new();
+                for child in &section.children {
+                    self.write(*child, true, &mut new_body);
+                }
+                new_body.replace_range(range.start - body..range.end - body, text);
+                let nested = SECTION_HEADING
+                    .captures_iter(&new_body)
+                    .all(|caps| caps[1].len() > section.level);
+                let whole_lines =
+                    new_body.is_empty() || new_body.ends_with('\n') || ends_document(end);
+                return (nested && whole_lines).then_some((depth, new_body));
+            });
+        let Some((depth, new_body)) = found else {
+            return false;
+        };
+
+        let id = sections[depth].0;
+        // subsections that come through with the same heading stay folded as they were
+        let mut folds: Vec<(usize, String, bool)> = self
+            .sections_under(id)
+            .into_iter()
+            .filter_map(|n| self.section(n))
+            .map(|s| (s.level, s.heading.clone(), s.expanded))
+            .collect();
+        let old = match &mut self.nodes[id] {
+            Node::Section(section) => std::mem::take(&mut section.children),
+            Node::Text(_) => return false,
+        };
+        for child in old {
+            self.remove(child);
+        }
+        let children = self.parse(new_body);
+        if let Node::Section(section) = &mut self.nodes[id] {
+            section.children = children;
+        }
+        let new = self.sections_under(id);
+        for n in &new {
+            if let Node::Section(section) = &mut self.nodes[*n]
+                && let Some(i) = folds.iter().position(|(level, heading, _)| {
+                    *level == section.level && *heading == section.heading
+                })
+            {
+                section.expanded = folds.remove(i).2;
+            }
+        }
+        for n in new.into_iter().rev() {
+            self.update_len(n);
+        }
+        for (id, _) in sections[..=depth].iter().rev() {
+            self.update_len(*id);
+        }
+        return true;
+    }
+
+    // the sections below a node, each before the ones inside it
+    fn sections_under(&self, id: NodeId) -> Vec<NodeId> {
+        let mut found = Vec::new();
+        if let Some(section) = self.section(id) {
+            for child in &section.children {
+                if self.section(*child).is_some() {
+                    found.push(*child);
+                    found.extend(self.sections_under(*child));
+                }
+            }
+        }
+        return found;
+    }
+
+    fn toggle(&mut self, path: &[usize]) {
+        // the root has no heading to fold under
+        if path.is_empty() {
+            return;
+        }
+        self.fold(path, |expanded| !expanded);
+    }
+
+    fn collapse(&mut self, path: &[usize]) {
+        self.fold(path, |_| false);
+    }
+
+    #[cfg(test)]
+    fn expand(&mut self, path: &[usize]) {
+        self.fold(path, |_| true);
+    }
+
+    fn path(&self, pos: usize) -> Vec<usize> {
+        return self.path_at(self.root, pos);
+    }
+
+    fn path_at(&self, id: NodeId, pos: usize) -> Vec<usize> {
+        tracing::trace!("pathing {}", pos);
+        let Some(section) = self.section(id) else {
+            return Vec::<usize>::new();
+        };
+        let mut cur = section.level + section.heading.len();
+        if pos < cur {
+            return Vec::<usize>::new();
+        }
+
+        for (i, n) in section.children.iter().enumerate() {
+            let l = self.len_of(*n, false);
+            tracing::trace!("{} {} {:?}", i, cur, self.nodes[*n]);
+            if pos <= cur + l {
+                if self.section(*n).is_none() {
+                    return Vec::<usize>::new();
+                }
+                let mut tmp = self.path_at(*n, pos - cur);
+                tmp.insert(0, i);
+                return tmp;
+            }
+            cur += l;
+        }
+        return Vec::<usize>::new();
+    }
+
+    fn markdown(&self) -> Vec<MarkdownString> {
+        let mut md = Vec::new();
+        self.markdown_at(self.root, &mut md);
+        return md;
+    }
+
+    fn markdown_at(&self, id: NodeId, md: &mut Vec<MarkdownString>) {
+        let section = match &self.nodes[id] {
+            Node::Text(text) => {
+                md.push(text.clone());
+                return;
+            }
+            Node::Section(section) => section,
+        };
+
+        let mut hstring = "".to_string();
+        for _ in 0..section.level {
+            hstring += "#";
+        }
+        hstring += &section.heading;
+
+        md.push(MarkdownString {
+            text: hstring,
+            mdtype: match section.level {
+                1 => MarkdownType::Heading1,
+                2 => MarkdownType::Heading2,
+                _ => MarkdownType::Heading3,
+            },
+            expanded: section.expanded,
+        });
+
+        if section.expanded {
+            for c in &section.children {
+                self.markdown_at(*c, md);
+            }
+        }
+    }
+
+    fn get_node(&self, pos: usize) -> MarkdownString {
+        return self.get_node_at(self.root, pos);
+    }
+
+    fn get_node_at(&self, id: NodeId, pos: usize) -> MarkdownString {
+        let section = match &self.nodes[id] {
+            Node::Text(text) => return text.clone(),
+            Node::Section(section) => section,
+        };
+
+        let mut cur = section.level + section.heading.len();
+        if pos < cur {
+            let mut hstring = "".to_string();
+            for _ in 0..section.level {
+                hstring += "#";
+            }
+            hstring += &section.heading;
+
+            return MarkdownString {
+                text: hstring,
+                mdtype: section.mdtype.clone(),
+                expanded: true,
+            };
+        }
+
+        for n in &section.children {
+            let len = self.len_of(*n, false);
+            if pos < cur + len {
+                return self.get_node_at(*n, pos - cur);
+            }
+            cur += len;
+        }
+        return MarkdownString {
+            text: "".to_string(),
+            mdtype: MarkdownType::None,
+            expanded: true,
+        };
+    }
+
+    // the displayed ranges of every section around a position, outermost first
+    fn sections(&self, pos: usize, ranges: &mut Vec<(usize, usize)>) {
+        self.sections_at(self.root, pos, 0, ranges);
+    }
+
+    fn sections_at(&self, id: NodeId, pos: usize, offset: usize, ranges: &mut Vec<(usize, usize)>) {
+        let Some(section) = self.section(id) else {
+            return;
+        };
+        if section.level > 0 {
+            ranges.push((offset, offset + section.display_len));
+        }
+        if !section.expanded {
+            return;
+        }
+
+        let mut cur = offset + section.level + section.heading.len();
+        for n in &section.children {
+            let len = self.len_of(*n, false);
+            if pos >= cur && pos < cur + len {
+                self.sections_at(*n, pos, cur, ranges);
+                return;
+            }
+            cur += len;
+        }
+    }
+
+    fn headings(&self) -> Vec<Heading> {
+        let mut headings = Vec::new();
+        self.headings_at(self.root, &mut Vec::new(), 0, true, &mut headings);
+        return headings;
+    }
+
+    fn headings_at(
+        &self,
+        id: NodeId,
+        path: &mut Vec<usize>,
+        pos: usize,
+        visible: bool,
+        headings: &mut Vec<Heading>,
+    ) {
+        let Some(section) = self.section(id) else {
+            return;
+        };
+        if section.level > 0 {
+            let mut body = String::new();
+            for n in &section.children {
+                self.write(*n, true, &mut body);
+            }
+            headings.push(Heading {
+                title: section.heading.trim().to_string(),
+                level: section.level,
+                path: path.clone(),
+                pos,
+                visible,
+                expanded: section.expanded,
+                progress: task_progress(&body),
+            });
+        }
+
+        let mut cur = pos + section.level + section.heading.len();
+        for (i, n) in section.children.iter().enumerate() {
+            path.push(i);
+            self.headings_at(*n, path, cur, visible && section.expanded, headings);
+            path.pop();
+            cur += self.len_of(*n, true);
+        }
+    }
+
+    // expand every section between the root and a position in the full text
+    fn reveal(&mut self, pos: usize) {
+        self.reveal_at(self.root, pos);
+    }
+
+    fn reveal_at(&mut self, id: NodeId, pos: usize) {
+        let section = match &mut self.nodes[id] {
+            Node::Text(_) => return,
+            Node::Section(section) => section,
+        };
+        let mut cur = section.level + section.heading.len();
+        if pos < cur {
+            return;
+        }
+        section.expanded = true;
+
+        for n in section.children.clone() {
+            let len = self.len_of(n, true);
+            if pos < cur + len {
+                self.reveal_at(n, pos - cur);
+                break;
+            }
+            cur += len;
+        }
+        self.update_len(id);
+    }
+
+    fn validate(&self, problems: &mut Vec<String>) {
+        self.validate_at(self.root, &mut Vec::new(), problems);
+    }
+
+    // the cached lengths have to match what the children add up to
+    fn validate_at(&self, id: NodeId, path: &mut Vec<usize>, problems: &mut Vec<String>) {
+        let Some(section) = self.section(id) else {
+            return;
+        };
+        let mut length = 0;
+        if section.level > 0 {
+            length = section.level + section.heading.len();
+        }
+        let full = length
+            + section
+                .children
+                .iter()
+                .map(|n| self.len_of(*n, true))
+                .sum::<usize>();
+        let mut display = length;
+        if section.expanded {
+            display += section
+                .children
+                .iter()
+                .map(|n| self.len_of(*n, false))
+                .sum::<usize>();
+        }
+        if full != section.full_len {
+            problems.push(format!(
+                "section {:?} caches a full length of {} but its children add up to {}",
+                path, section.full_len, full
+            ));
+        }
+        if display != section.display_len {
+            problems.push(format!(
+                "section {:?} caches a displayed length of {} but its children add up to {}",
+                path, section.display_len, display
+            ));
+        }
+
+        for (i, n) in section.children.iter().enumerate() {
+            path.push(i);
+            self.validate_at(*n, path, problems);
+            path.pop();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Note {
+    // the full text, spliced on each edit rather than copied, and as one string once asked for
+    internal: Rope,
+    full: Option<String>,
+    tree: Tree,
+    // the displayed text, which egui wants as a &str, spliced in place when an edit only
+    //   changes what it touches
+    repr: String,
+    // the tree before each edit or fold, and the ones undone since
+    history: Vec<Tree>,
+    future: Vec<Tree>,
+    // where the last run of typing or deleting stopped, keys pressed there join the same undo step
+    typing: Option<Typing>,
+    // how many times the whole text has been parsed again after an edit, for spotting slow paths
+    reparses: usize,
+    // what the frontmatter says, only a full parse can change it
+    metadata: HashMap<String, Value>,
+    // the text changed since it was loaded or last saved
+    modified: bool,
+}
+
+const HISTORY_LEN: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Typing {
+    // the displayed character after the last one typed, and whether that was a space
+    Inserting { at: usize, space: bool },
+    Deleting { at: usize },
+}
+
+// the patterns are compiled once on first use, parsing runs on every edit and highlighting every frame
+static HEADINGS: LazyLock<[(Regex, MarkdownType); 3]> = LazyLock::new(|| {
+    [
+        (
+            Regex::new(r"(?m)^# [^\n]+$").unwrap(),
+            MarkdownType::Heading1,
+        ),
+        (
+            Regex::new(r"(?m)^## [^\n]+$").unwrap(),
+            MarkdownType::Heading2,
+        ),
+        (
+            Regex::new(r"(?m)^### [^\n]+$").unwrap(),
+            MarkdownType::Heading3,
+        ),
+    ]
+});
+
+static INLINE: LazyLock<[(Regex, MarkdownType); 16]> = LazyLock::new(|| {
+    [
+        (Regex::new(r"\*\*[^\*\n]*\*\*").unwrap(), MarkdownType::Bold),
+        (Regex::new(r"_[^_\n]*_").unwrap(), MarkdownType::Italic),
+        (
+            Regex::new(r"~~[^~\n]+~~").unwrap(),
+            MarkdownType::Strikethrough,
+        ),
+        (
+            Regex::new(r"@@([\\/A-Za-z0-9_-]+(?:#[\p{L}\p{N}_-]+)?)").unwrap(),
+            MarkdownType::Link,
+        ),
+        (Regex::new(r"\[\[[^\]\n]+\]\]").unwrap(), MarkdownType::Link),
+        (
+            Regex::new(r"\[[^\]\n]*\]\(https?://[^)\s]+\)").unwrap(),
+            MarkdownType::WebLink,
+        ),
+        // the same as links::URL, stopping before closing punctuation
+        (
+            Regex::new(r"https?://[^\s<>()\[\]]*[^\s<>()\[\].,;:!?'\x22]").unwrap(),
+            MarkdownType::WebLink,
+        ),
+        (Regex::new(r"`[^\n]*`").unwrap(), MarkdownType::Monospace),
+        (
+            Regex::new(r"(?ms)^```[^\n`]*\n.*?^```$").unwrap(),
+            MarkdownType::Code(String::new()),
+        ),
+        (
+            Regex::new(r"(?m)^[ \t]*[-*+] ").unwrap(),
+            MarkdownType::Bullet(0),
+        ),
+        (
+            Regex::new(r"(?m)^[ \t]*\d+[.)] ").unwrap(),
+            MarkdownType::Numbered(0),
+        ),
+        (
+            Regex::new(r"(?m)^[ \t]*[-*+] \[[ xX]\]").unwrap(),
+            MarkdownType::Task(false),
+        ),
+        // a lone $ before a number is money, so the math has to hug its dollar signs
+        (
+            Regex::new(r"\$[^\s$](?:[^$\n]*[^\s$])?\$").unwrap(),
+            MarkdownType::Math(false),
+        ),
+        (
+            Regex::new(r"(?s)\$\$[^$]+\$\$").unwrap(),
+            MarkdownType::Math(true),
+        ),
+        (
+            Regex::new(r"#[\p{L}_][\p{L}\p{N}_/-]*").unwrap(),
+            MarkdownType::Tag,
+        ),
+        (Regex::new(r"(?m)^\{\{toc\}\}$").unwrap(), MarkdownType::Toc),
+    ]
+});
+
+static TASK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^[ \t]*[-*+] \[([ xX])\]").unwrap());
+
+// the space after the #s is kept with the title, without one it's a #tag
+static SECTION_HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^(#+)( [^\n]*)$").unwrap());
+
+// the language of a fenced block, the word after the opening ```
+pub fn code_language(text: &str) -> &str {
+    let first = text.lines().next().unwrap_or("");
+    return first.trim_start_matches('`').trim();
+}
+
+// how deeply a list item is nested, a level for every two columns it's indented by
+pub fn list_depth(marker: &str) -> usize {
+    let columns: usize = marker
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum();
+    return columns / 2;
+}
+
+// the patterns only know what kind of thing matched, the details come from the text
+fn with_details(mdtype: MarkdownType, text: &str) -> MarkdownType {
+    return match mdtype {
+        MarkdownType::Code(_) => MarkdownType::Code(code_language(text).to_string()),
+        MarkdownType::Bullet(_) => MarkdownType::Bullet(list_depth(text)),
+        MarkdownType::Numbered(_) => MarkdownType::Numbered(list_depth(text)),
+        MarkdownType::Task(_) => MarkdownType::Task(!text.ends_with("[ ]")),
+        other => other,
+    };
+}
+
+// the first match of a pattern, the ones tied to the start of a line only count at the very
+//   start of what's left when that really is the start of a line
+//   tags have to stand apart from what's before them
+fn find_from(pattern: &(Regex, MarkdownType), t: &str, line_start: bool) -> Option<(usize, usize)> {
+    let anchored = matches!(
+        pattern.1,
+        MarkdownType::Heading1
+            | MarkdownType::Heading2
+            | MarkdownType::Heading3
+            | MarkdownType::Code(_)
+            | MarkdownType::Bullet(_)
+            | MarkdownType::Numbered(_)
+            | MarkdownType::Task(_)
+            | MarkdownType::Toc
+    );
+    // a # straight after a word or a url's slash isn't a tag
+    let tag_ok = |start: usize| {
+        return pattern.1 != MarkdownType::Tag
+            || !t[..start]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || "#&/:".contains(c));
+    };
+    return pattern
+        .0
+        .find_iter(t)
+        .map(|m| (m.start(), m.end()))
+        .find(|m| (m.0 > 0 || line_start || !anchored) && tag_ok(m.0));
+}
+
+impl Tree {
+    fn parse_strings(&mut self, text: String) -> Vec<NodeId> {
+        let mut output: Vec<NodeId> = vec![];
+        // TODO: handle the different types right
+
+        //let mut lines = text.split('\n').peekable();
+        let mut t = text;
+        let mut line_start = true;
+
+        //while let Some(line) = lines.next() {
+        while !t.is_empty() {
+            //let is_last = lines.peek().is_none();
+            //let mut t = String::from(line);
+            /*if !is_last {
+                t += "\n";
+            }*/
+
+            let mut rerun = true;
+            while rerun {
+                rerun = false;
+                let mut first_match: Option<((usize, usize), MarkdownType)> = None;
+                for r in INLINE.iter() {
+                    if let Some(range) = find_from(r, t.as_str(), line_start) {
+                        // give up early if there was a match before this
+                        if let Some(first) = &first_match
+                            && first.0.0 < range.0
+                        {
+                            continue;
+                        }
+
+                        first_match = Some((range, r.1.clone()));
+                    }
+                }
+
+                if let Some(first) = &first_match {
+                    if first.0.0 > 0 {
+                        output.push(self.nodes.insert(Node::Text(MarkdownString {
+                            text: t[..first.0.0].to_string(),
+                            mdtype: MarkdownType::Paragraph,
+                            expanded: true,
+                        })));
+                    }
+
+                    let text = &t[first.0.0..first.0.1];
+                    output.push(self.nodes.insert(Node::Text(MarkdownString {
+                        text: text.to_string(),
+                        mdtype: with_details(first.1.clone(), text),
+                        expanded: true,
+                    })));
+                    line_start = text.ends_with('\n');
+                    t = t[first.0.1..].to_string();
+                    rerun = true;
+                }
+            }
+
+            if !t.is_empty() {
+                output.push(self.nodes.insert(Node::Text(MarkdownString {
+                    text: t.clone(),
+                    mdtype: MarkdownType::Paragraph,
+                    expanded: true,
+                })));
+                break;
+            }
+        }
+        return output;
+    }
+}
+
+// TODO: This is also awful, need to make this parser that keeps showing up into a template with a callback
+pub fn highlight_parse(text: &str) -> Vec<MarkdownStr<'_>> {
+    let mut output: Vec<MarkdownStr> = vec![];
+
+    let mut t = text;
+    let mut line_start = true;
+    if let Some(len) = frontmatter::len(t) {
+        output.push(MarkdownStr {
+            text: &t[..len],
+            mdtype: MarkdownType::Frontmatter,
+        });
+        t = &t[len..];
+    }
+    while !t.is_empty() {
+        let mut rerun = true;
+        while rerun {
+            rerun = false;
+            let mut first_match: Option<((usize, usize), MarkdownType)> = None;
+            for r in HEADINGS.iter().chain(INLINE.iter()) {
+                if let Some(range) = find_from(r, t, line_start) {
+                    // give up early if there was a match before this
+                    if let Some(first) = &first_match
+                        && first.0.0 < range.0
+                    {
+                        continue;
+                    }
+
+                    first_match = Some((range, r.1.clone()));
+                }
+            }
+
+            if let Some(first) = &first_match {
+                if first.0.0 > 0 {
+                    output.push(MarkdownStr {
+                        text: &t[..first.0.0],
+                        mdtype: MarkdownType::Paragraph,
+                    });
+                }
+
+                let text = &t[first.0.0..first.0.1];
+                output.push(MarkdownStr {
+                    text,
+                    mdtype: with_details(first.1.clone(), text),
+                });
+                line_start = text.ends_with('\n');
+                t = &t[first.0.1..];
+                rerun = true;
+            }
+        }
+
+        if !t.is_empty() {
+            output.push(MarkdownStr {
+                text: t,
+                mdtype: MarkdownType::Paragraph,
+            });
+            break;
+        }
+    }
+    return output;
+}
+
+impl Tree {
+    fn parse(&mut self, text: String) -> Vec<NodeId> {
+        let mut nodes: Vec<NodeId> = Vec::new();
+
+        let mut level = 0;
+        let mut pos = 0;
+        let mut heading = "".to_string();
+
+        for caps in SECTION_HEADING.captures_iter(text.as_str()) {
+            // initialize the level if this is the first heading we've encountered
+            if level == 0 {
+                let cap = caps.get(1).unwrap();
+                let range = cap.range();
+
+                level = cap.len();
+                pos = caps.get(2).unwrap().range().end;
+                heading = caps.get(2).unwrap().as_str().to_string();
+                if text.len() > pos && &text[pos..pos + 1] == "\n" {
+                    heading += "\n";
+                    pos += 1;
+                }
+
+                if range.start > 0 {
+                    nodes.extend(self.parse_strings(text[..range.start].to_string()));
+                }
+
+                continue;
+            } else if caps.get(1).unwrap().len() > level {
+                continue;
+            }
+
+            let range = caps.get(0).unwrap().range();
+            let children = self.parse(text[pos..range.start].to_string());
+            nodes.push(self.add_section(heading, level, children));
+            heading = caps.get(2).unwrap().as_str().to_string();
+            pos = range.end;
+            level = caps.get(1).unwrap().len();
+            if text.len() > pos && &text[pos..pos + 1] == "\n" {
+                heading += "\n";
+                pos += 1;
+            }
+        }
+
+        if level == 0 {
+            nodes.extend(self.parse_strings(text));
+            //nodes.push(Box::new(MarkdownString::new(text)));
+            return nodes;
+        }
+
+        // parse the remainder of the file and stick the last heading on it
+        //   TODO: I don't like having a second copy of this here
+        let children = self.parse(text[pos..].to_string());
+        nodes.push(self.add_section(heading, level, children));
+
+        return nodes;
+    }
+}
+
+// start of the line containing pos
+fn line_start(text: &str, pos: usize) -> usize {
+    match text[..pos].rfind('\n') {
+        Some(i) => i + 1,
+        None => 0,
+    }
+}
+
+// end of the line containing pos, including the trailing newline
+fn line_end(text: &str, pos: usize) -> usize {
+    match text[pos..].find('\n') {
+        Some(i) => pos + i + 1,
+        None => text.len(),
+    }
+}
+
+// full lines covered by a selection, a selection ending at the start of a line doesn't include it
+fn line_range(text: &str, start: usize, end: usize) -> (usize, usize) {
+    let first = line_start(text, start);
+    if end > start && text[..end].ends_with('\n') {
+        return (first, end);
+    }
+    return (first, line_end(text, end));
+}
+
+// grow a range out to the word characters on either side of it
+fn word_range(text: &str, start: usize, end: usize) -> (usize, usize) {
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+    let before: usize = text[..start]
+        .chars()
+        .rev()
+        .take_while(is_word)
+        .map(char::len_utf8)
+        .sum();
+    let after: usize = text[end..]
+        .chars()
+        .take_while(is_word)
+        .map(char::len_utf8)
+        .sum();
+    return (start - before, end + after);
+}
+
+// swap two adjacent runs of lines, keeping the newline structure of the original text
+fn swap_lines(first: &str, second: &str) -> String {
+    let mut output = second.strip_suffix('\n').unwrap_or(second).to_string();
+    output += "\n";
+    output += first.strip_suffix('\n').unwrap_or(first);
+    if second.ends_with('\n') {
+        output += "\n";
+    }
+    return output;
+}
+
+impl Note {
+    pub fn new(content: String) -> Self {
+        let mut tmp = Self {
+            internal: Rope::from_str(&content),
+            full: None,
+            tree: Tree::default(),
+            repr: "".to_string(),
+            history: Vec::new(),
+            future: Vec::new(),
+            typing: None,
+            reparses: 0,
+            metadata: HashMap::new(),
+            modified: false,
+        };
+        tmp.tree.reset(&content);
+        tmp.refresh_metadata();
+        tmp.refresh();
+        return tmp;
+    }
+
+    fn reparse(&mut self) {
+        self.tree.reset(&self.internal.to_string());
+        self.refresh_metadata();
+        self.reparses += 1;
+    }
+
+    fn refresh_metadata(&mut self) {
+        self.metadata = self
+            .tree
+            .frontmatter()
+            .map(frontmatter::parse)
+            .unwrap_or_default();
+    }
+
+    // the keys and values in the note's frontmatter, empty when it hasn't got any
+    pub fn metadata(&self) -> &HashMap<String, Value> {
+        return &self.metadata;
+    }
+
+    // replace a range of the full text in the rope, positions are bytes
+    fn edit_rope(&mut self, range: std::ops::Range<usize>, text: &str) {
+        let start = self.internal.byte_to_char(range.start);
+        let end = self.internal.byte_to_char(range.end);
+        self.internal.remove(start..end);
+        self.internal.insert(start, text);
+        self.full = None;
+        self.modified = true;
+    }
+
+    // the tree was swapped for another, the rope has to follow it
+    fn sync_rope(&mut self) {
+        let text = self.tree.string(true);
+        // stepping back over a fold leaves the text as it was
+        if self.internal != text.as_str() {
+            self.modified = true;
+        }
+        self.internal = Rope::from_str(&text);
+        self.full = None;
+        self.refresh_metadata();
+    }
+
+    // make sorted, non-overlapping replacements in the full text, reparsing only the sections
+    //   they land in where that's enough and the whole note otherwise
+    //   true when it was enough, the folding is as it was and only the edited text moved
+    fn splice(&mut self, changes: Vec<(std::ops::Range<usize>, String)>) -> bool {
+        let mut incremental = true;
+        // back to front so the earlier ranges stay put
+        for (range, text) in changes.into_iter().rev() {
+            self.edit_rope(range.clone(), &text);
+            incremental = incremental && self.tree.reparse_within(range, &text);
+        }
+        if !incremental {
+            self.reparse();
+        }
+        return incremental;
+    }
+
+    pub fn reparses(&self) -> usize {
+        return self.reparses;
+    }
+
+    pub fn is_modified(&self) -> bool {
+        return self.modified;
+    }
+
+    // the text as it is now has been written out
+    pub fn mark_saved(&mut self) {
+        self.modified = false;
+    }
+
+    pub fn full(&mut self) -> &str {
+        return self.full.get_or_insert_with(|| self.internal.to_string());
+    }
+
+    pub fn refresh(&mut self) {
+        self.tree.generate();
+        self.repr = self.tree.string(false);
+    }
+
+    // the displayed byte ranges of text made from the rest of the note, like a table of contents
+    pub fn generated(&self) -> Vec<std::ops::Range<usize>> {
+        return self.tree.generated_ranges();
+    }
+
+    // whether a displayed byte range reaches into generated text, typing just before or after
+    //   it still goes into the note
+    fn in_generated(&self, range: std::ops::Range<usize>) -> bool {
+        return self.tree.generated_ranges().iter().any(|g| {
+            if range.is_empty() {
+                return g.start < range.start && range.start < g.end;
+            }
+            return range.start < g.end && g.start < range.end;
+        });
+    }
+
+    // where the heading a line of a table of contents lists is in the full text
+    pub fn toc_target(&self, char_index: usize) -> Option<usize> {
+        let pos = cluster_byte(&self.repr, char_index);
+        let range = self
+            .tree
+            .generated_ranges()
+            .into_iter()
+            .find(|g| g.start < pos && pos <= g.end)?;
+        // every line of the list starts with its newline
+        let line = self.repr[range.start..pos]
+            .matches('\n')
+            .count()
+            .checked_sub(1)?;
+        return self.tree.headings().get(line).map(|h| h.pos);
+    }
+
+    // the section holding a displayed character
+    pub fn path(&self, pos: usize) -> Vec<usize> {
+        tracing::trace!("{:?}", self.tree);
+        return self.tree.path(cluster_byte(&self.repr, pos));
+    }
+
+    // fold or unfold a section, which undo steps back through like an edit
+    pub fn toggle(&mut self, path: &[usize]) {
+        self.checkpoint();
+        self.tree.toggle(path);
+    }
+
+    // fold sections without an undo step, for putting a note back the way it was left
+    pub fn collapse(&mut self, paths: &[Vec<usize>]) {
+        for path in paths.iter().filter(|p| !p.is_empty()) {
+            self.tree.collapse(path);
+        }
+        self.refresh();
+    }
+
+    pub fn markdown(&self) -> Vec<MarkdownString> {
+        self.tree.markdown()
+    }
+
+    // the markdown under a displayed character
+    pub fn get_node(&self, pos: usize) -> MarkdownString {
+        self.tree.get_node(cluster_byte(&self.repr, pos))
+    }
+
+    pub fn translate(&self, pos: usize) -> usize {
+        self.tree.translate(pos)
+    }
+
+    pub fn inv_translate(&self, pos: usize) -> usize {
+        self.tree.inv_translate(pos)
+    }
+
+    pub fn headings(&self) -> Vec<Heading> {
+        return self.tree.headings();
+    }
+
+    // unfold whatever hides a position in the full text, returns where it ends up on screen
+    pub fn reveal(&mut self, pos: usize) -> usize {
+        self.tree.reveal(pos);
+        self.refresh();
+        return self.tree.inv_translate(pos);
+    }
+
+    // take the full text as another instance of the note has it now
+    //   the undo history is dropped, stepping back past someone else's edit would undo it for them too
+    pub fn merge_remote(&mut self, text: &str) {
+        self.internal = Rope::from_str(text);
+        self.full = None;
+        self.modified = true;
+        self.history.clear();
+        self.future.clear();
+        self.reparse();
+        self.refresh();
+    }
+
+    // swap in a new full text as one change that can be undone, for rewrites that reach into
+    //   folded sections too
+    pub fn rewrite(&mut self, text: &str) {
+        let len = self.full().len();
+        self.commit(vec![(0..len, text.to_string())]);
+    }
+
+    // replace a range of the full text and reparse
+    fn replace_full(&mut self, range: std::ops::Range<usize>, text: &str) {
+        self.commit(vec![(range, text.to_string())]);
+    }
+
+    // keep the tree as it is now as an undo step
+    fn checkpoint(&mut self) {
+        self.history.push(self.tree.clone());
+        if self.history.len() > HISTORY_LEN {
+            self.history.remove(0);
+        }
+        self.future.clear();
+        self.typing = None;
+    }
+
+    // make sorted, non-overlapping replacements in the full text with one reparse and one undo entry
+    fn commit(&mut self, changes: Vec<(std::ops::Range<usize>, String)>) {
+        self.checkpoint();
+        self.splice(changes);
+        self.refresh();
+    }
+
+    // apply an edit as a single change, nothing is touched if any part of it doesn't fit
+    pub fn apply(&mut self, edit: Edit) -> Result<(), EditError> {
+        let mut changes = Vec::new();
+        self.lower(edit, &mut changes)?;
+        let changes = sort_changes(changes)?;
+        self.commit(changes);
+        return Ok(());
+    }
+
+    // turn an edit into replacements in the full text
+    fn lower(
+        &self,
+        edit: Edit,
+        changes: &mut Vec<(std::ops::Range<usize>, String)>,
+    ) -> Result<(), EditError> {
+        // characters on screen to bytes in the full text
+        let check = |range: &std::ops::Range<usize>| {
+            if range.start > range.end || range.end > self.repr.chars().count() {
+                return Err(EditError::OutOfRange);
+            }
+            let range = cluster_range(&self.repr, range.clone());
+            return Ok(self.tree.translate(range.start)..self.tree.translate(range.end));
+        };
+
+        match edit {
+            Edit::Insert { pos, text } => changes.push((check(&(pos..pos))?, text)),
+            Edit::Delete(range) => changes.push((check(&range)?, String::new())),
+            Edit::Replace { range, text } => changes.push((check(&range)?, text)),
+            Edit::Wrap {
+                range,
+                before,
+                after,
+            } => {
+                let range = check(&range)?;
+                changes.push((range.start..range.start, before));
+                changes.push((range.end..range.end, after));
+            }
+            Edit::MoveLines { range, up } => {
+                check(&range)?;
+                let range = cluster_range(&self.repr, range);
+                let (range, text, _, _) = self
+                    .moved_lines(range.start, range.end, up)
+                    .ok_or(EditError::OutOfRange)?;
+                changes.push((range, text));
+            }
+            Edit::Batch(edits) => {
+                for edit in edits {
+                    self.lower(edit, changes)?;
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    // check the tree against the text it came from, returns a description of each broken invariant
+    //   `internal` is only brought up to date on demand, so the full text is rendered and reparsed instead
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        self.tree.validate(&mut problems);
+
+        let full = self.tree.string(true);
+        if full.len() != self.tree.len(true) {
+            problems.push(format!(
+                "full text is {} long but the tree says {}",
+                full.len(),
+                self.tree.len(true)
+            ));
+        }
+        let reparsed = Tree::new(&full);
+        if reparsed.string(true) != full {
+            problems.push("reparsing the full text changes it".to_string());
+        }
+        if self.internal != full.as_str() {
+            problems.push("the full text is out of date with the tree".to_string());
+        }
+        if self.repr != self.tree.string(false) {
+            problems.push("displayed text is out of date with the tree".to_string());
+        }
+
+        for pos in (0..=self.repr.len()).filter(|p| self.repr.is_char_boundary(*p)) {
+            let full_pos = self.tree.translate(pos);
+            let back = self.tree.inv_translate(full_pos);
+            if back != pos {
+                problems.push(format!(
+                    "position {} translates to {} but comes back as {}",
+                    pos, full_pos, back
+                ));
+                break;
+            }
+        }
+        return problems;
+    }
+
+    // go back to the note from before the last edit or fold
+    //   returns where the change was for the cursor to go, None when there's nothing to undo
+    pub fn undo(&mut self) -> Option<usize> {
+        let tree = self.history.pop()?;
+        self.future.push(std::mem::replace(&mut self.tree, tree));
+        return Some(self.restored());
+    }
+
+    pub fn redo(&mut self) -> Option<usize> {
+        let tree = self.future.pop()?;
+        self.history.push(std::mem::replace(&mut self.tree, tree));
+        return Some(self.restored());
+    }
+
+    // bring the text up to date with a tree from the history, returns the displayed character
+    //   just after the part that changed
+    fn restored(&mut self) -> usize {
+        let before = std::mem::take(&mut self.repr);
+        self.sync_rope();
+        self.refresh();
+        self.typing = None;
+        let prefix = before
+            .chars()
+            .zip(self.repr.chars())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let suffix = before
+            .chars()
+            .rev()
+            .zip(self.repr.chars().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let len = self.repr.chars().count();
+        let suffix = suffix
+            .min(len - prefix)
+            .min(before.chars().count() - prefix);
+        return len - suffix;
+    }
+
+    // move the lines covered by a selection past the neighbouring line,
+    //   a collapsed section is moved over (or moves) as a whole
+    //   positions are bytes of the displayed text, returns the selection ends in the new one
+    pub fn move_lines(&mut self, a: usize, b: usize, up: bool) -> Option<(usize, usize)> {
+        let a = floor_char(&self.repr, a);
+        let b = floor_char(&self.repr, b);
+        let (range, text, block_start, new_start) = self.moved_lines(a, b, up)?;
+
+        let a = self.tree.translate(a) - block_start + new_start;
+        let b = self.tree.translate(b) - block_start + new_start;
+        self.replace_full(range, &text);
+        return Some((self.tree.inv_translate(a), self.tree.inv_translate(b)));
+    }
+
+    // add a # to the heading of the section a position is in, or take one away, the sections
+    //   after it are sorted under or beside it again by the reparse, returns where the position
+    //   ends up, None outside any section or when the heading can't go further
+    pub fn shift_heading(&mut self, pos: usize, deeper: bool) -> Option<usize> {
+        let pos = self.tree.translate(pos.min(self.repr.len()));
+        let heading = self
+            .tree
+            .headings()
+            .into_iter()
+            .rev()
+            .find(|h| h.pos <= pos)?;
+        let level = match deeper {
+            true if heading.level < 3 => heading.level + 1,
+            false if heading.level > 1 => heading.level - 1,
+            _ => return None,
+        };
+
+        let hashes = heading.pos..heading.pos + heading.level;
+        let pos = if pos >= hashes.end {
+            pos + level - heading.level
+        } else {
+            pos.min(heading.pos + level)
+        };
+        self.replace_full(hashes, &"#".repeat(level));
+        return Some(self.tree.inv_translate(pos));
+    }
+
+    // the full text range and replacement for moving lines, plus where the moved block starts before and after
+    fn moved_lines(
+        &self,
+        a: usize,
+        b: usize,
+        up: bool,
+    ) -> Option<(std::ops::Range<usize>, String, usize, usize)> {
+        let (start, end) = line_range(&self.repr, a.min(b), a.max(b));
+
+        let full = self.tree.string(true);
+        let block_start = self.tree.translate(start);
+        let block_end = self.tree.translate(end);
+        let block = &full[block_start..block_end];
+
+        let (range, text, new_start) = if up {
+            if start == 0 {
+                return None;
+            }
+            let above_start = self.tree.translate(line_start(&self.repr, start - 1));
+            let above = &full[above_start..block_start];
+            (
+                above_start..block_end,
+                swap_lines(above, block),
+                above_start,
+            )
+        } else {
+            if end >= self.repr.len() {
+                return None;
+            }
+            let below_end = self.tree.translate(line_end(&self.repr, end));
+            let below = &full[block_end..below_end];
+            (
+                block_start..below_end,
+                swap_lines(block, below),
+                block_start + below.strip_suffix('\n').unwrap_or(below).len() + 1,
+            )
+        };
+
+        return Some((range, text, block_start, new_start));
+    }
+
+    // the displayed characters of the innermost section around a displayed character, heading included
+    pub fn section_range(&self, pos: usize) -> Option<std::ops::Range<usize>> {
+        let mut ranges = Vec::new();
+        self.tree
+            .sections(cluster_byte(&self.repr, pos), &mut ranges);
+        let (start, end) = ranges.pop()?;
+        return Some(char_index(&self.repr, start)..char_index(&self.repr, end));
+    }
+
+    // grow a selection to the next enclosing structure:
+    //   word -> inline node -> line -> paragraph -> section(s) -> whole note
+    //   positions are bytes of the displayed text
+    pub fn expand_selection(&self, a: usize, b: usize) -> (usize, usize) {
+        let text = self.repr.as_str();
+        let start = floor_char(text, a.min(b));
+        let end = floor_char(text, a.max(b));
+        let mut candidates = Vec::<(usize, usize)>::new();
+
+        candidates.push(word_range(text, start, end));
+
+        let mut cur = 0;
+        for item in self.tree.markdown() {
+            let len = item.text.len();
+            if cur <= start && start < cur + len && end <= cur + len {
+                candidates.push((cur, cur + len));
+                break;
+            }
+            cur += len;
+        }
+
+        let (line_first, line_last) = line_range(text, start, end);
+        let line_last = line_first.max(line_last - text[..line_last].ends_with('\n') as usize);
+        candidates.push((line_first, line_last));
+
+        // paragraphs are bounded by blank lines and headings
+        let is_break = |line: &str| line.trim().is_empty() || line.starts_with('#');
+        let mut para_first = line_first;
+        while para_first > 0 {
+            let prev = line_start(text, para_first - 1);
+            if is_break(&text[prev..para_first]) {
+                break;
+            }
+            para_first = prev;
+        }
+        let mut para_last = line_last;
+        while para_last < text.len() {
+            let next = line_end(text, para_last + 1);
+            if is_break(&text[para_last + 1..next]) {
+                break;
+            }
+            para_last = next - text[..next].ends_with('\n') as usize;
+        }
+        candidates.push((para_first, para_last));
+
+        self.tree.sections(start, &mut candidates);
+        candidates.push((0, text.len()));
+
+        return candidates
+            .into_iter()
+            .filter(|(s, e)| *s <= start && *e >= end && e - s > end - start)
+            .min_by_key(|(s, e)| e - s)
+            .unwrap_or((start, end));
+    }
+
+    // sort the lines covered by a selection, a folded section sorts by its heading and keeps its body
+    //   positions are bytes of the displayed text, returns the range of the sorted lines in it
+    pub fn sort_lines(&mut self, a: usize, b: usize, options: SortOptions) -> (usize, usize) {
+        let a = floor_char(&self.repr, a);
+        let b = floor_char(&self.repr, b);
+        let (start, end) = line_range(&self.repr, a.min(b), a.max(b));
+
+        let full = self.tree.string(true);
+        let block_start = self.tree.translate(start);
+        let block_end = self.tree.translate(end);
+
+        let mut lines = Vec::<(String, &str)>::new();
+        let mut pos = start;
+        while pos < end {
+            let next = line_end(&self.repr, pos);
+            let mut key = self.repr[pos..next].trim_end_matches('\n').to_string();
+            if options.case_insensitive {
+                key = key.to_lowercase();
+            }
+            let line = &full[self.tree.translate(pos)..self.tree.translate(next)];
+            lines.push((key, line.strip_suffix('\n').unwrap_or(line)));
+            pos = next;
+        }
+
+        lines.sort_by(|x, y| x.0.cmp(&y.0));
+        if options.reverse {
+            lines.reverse();
+        }
+        if options.unique {
+            lines.dedup_by(|x, y| x.0 == y.0);
+        }
+
+        let mut text = lines
+            .iter()
+            .map(|(_, line)| *line)
+            .collect::<Vec<&str>>()
+            .join("\n");
+        if full[..block_end].ends_with('\n') {
+            text += "\n";
+        }
+
+        self.replace_full(block_start..block_end, &text);
+        return (
+            self.tree.inv_translate(block_start),
+            self.tree.inv_translate(block_start + text.len()),
+        );
+    }
+
+    // change the case of a selection, or of the word under the cursor when nothing is selected
+    //   positions are bytes of the displayed text, returns the range of the changed text in it
+    pub fn transform_case(&mut self, a: usize, b: usize, case: TextCase) -> (usize, usize) {
+        let mut start = floor_char(&self.repr, a.min(b));
+        let mut end = floor_char(&self.repr, a.max(b));
+        if start == end {
+            (start, end) = word_range(&self.repr, start, end);
+        }
+
+        let full_start = self.tree.translate(start);
+        let full_end = self.tree.translate(end);
+        let text = case.apply(&self.tree.string(true)[full_start..full_end]);
+
+        self.replace_full(full_start..full_end, &text);
+        return (
+            self.tree.inv_translate(full_start),
+            self.tree.inv_translate(full_start + text.len()),
+        );
+    }
+
+    // copy the lines covered by a selection below themselves, returns the selection on the copy
+    //   positions are bytes of the displayed text
+    pub fn duplicate_lines(&mut self, a: usize, b: usize) -> (usize, usize) {
+        let a = floor_char(&self.repr, a);
+        let b = floor_char(&self.repr, b);
+        let (start, end) = line_range(&self.repr, a.min(b), a.max(b));
+
+        let full = self.tree.string(true);
+        let block_start = self.tree.translate(start);
+        let block_end = self.tree.translate(end);
+        let mut block = full[block_start..block_end].to_string();
+        if !block.ends_with('\n') {
+            block.insert(0, '\n');
+        }
+
+        let a = self.tree.translate(a) + block.len();
+        let b = self.tree.translate(b) + block.len();
+        self.replace_full(block_end..block_end, &block);
+        return (self.tree.inv_translate(a), self.tree.inv_translate(b));
+    }
+}
+
+impl Default for Note {
+    fn default() -> Self {
+        Self {
+            internal: Rope::new(),
+            full: None,
+            tree: Tree::default(),
+            repr: "".to_string(),
+            history: Vec::new(),
+            future: Vec::new(),
+            typing: None,
+            reparses: 0,
+            metadata: HashMap::new(),
+            modified: false,
+        }
+    }
+}
+
+// the editing primitives a GUI text widget needs, positions are in displayed characters
+impl Note {
+    // egui asks once for the whole buffer, generated text turns edits away itself
+    pub fn is_mutable(&self) -> bool {
+        return true;
+    }
+    pub fn as_str(&self) -> &str {
+        return self.repr.as_str();
+    }
+    // inserting inside a grapheme cluster would split it, so that goes in front of the cluster instead
+    pub fn insert_text(&mut self, text: &str, char_index: usize) -> usize {
+        let pos = cluster_byte(&self.repr, char_index);
+        if self.in_generated(pos..pos) {
+            return 0;
+        }
+        // a character typed where the last one went carries on its undo step, up to the next word
+        let mut chars = text.chars();
+        let single = chars.next().filter(|_| chars.next().is_none());
+        let carries_on = single.is_some_and(|c| match self.typing {
+            Some(Typing::Inserting { at, space }) => {
+                c != '\n' && at == char_index && (space || !c.is_whitespace())
+            }
+            _ => false,
+        });
+        if carries_on {
+            self.future.clear();
+        } else {
+            self.checkpoint();
+        }
+        self.typing = single.filter(|c| *c != '\n').map(|c| Typing::Inserting {
+            at: char_index + 1,
+            space: c.is_whitespace(),
+        });
+
+        let full = self.tree.translate(pos);
+        // try for a fast insert first, otherwise parse again as little as will do
+        let in_place = if self.tree.insert(text, pos) {
+            self.edit_rope(full..full, text);
+            true
+        } else {
+            self.splice(vec![(full..full, text.to_string())])
+        };
+        // a table of contents has to follow any change to the headings
+        if in_place && !self.tree.has_toc() && self.tree.len(false) == self.repr.len() + text.len()
+        {
+            self.repr.insert_str(pos, text);
+        } else {
+            self.refresh();
+        }
+        return text.chars().count();
+    }
+    // deletes whole clusters, a range that cuts into one takes all of it
+    pub fn delete_char_range(&mut self, char_range: std::ops::Range<usize>) {
+        // backspace or delete pressed again where the last one was joins its undo step
+        let single = char_range.len() == 1;
+        let carries_on = single
+            && match self.typing {
+                Some(Typing::Deleting { at }) => at == char_range.end || at == char_range.start,
+                _ => false,
+            };
+        let range = cluster_range(&self.repr, char_range.clone());
+        if self.in_generated(range.clone()) {
+            return;
+        }
+        if carries_on {
+            self.future.clear();
+        } else {
+            self.checkpoint();
+        }
+        self.typing = single.then_some(Typing::Deleting {
+            at: char_range.start,
+        });
+
+        let full = self.tree.translate(range.start)..self.tree.translate(range.end);
+        let in_place = self.splice(vec![(full, String::new())]);
+        if in_place && !self.tree.has_toc() && self.tree.len(false) == self.repr.len() - range.len()
+        {
+            self.repr.replace_range(range, "");
+        } else {
+            self.refresh();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::edit::{Edit, EditError};
+    use crate::note::{
+        MarkdownType, Node, Note, Progress, SortOptions, TextCase, Tree, highlight_parse,
+    };
+
+    #[test]
+    fn test_code_blocks() {
+        let text = "```rust\nlet a = **1**;\n```\nbetween\n```\nplain\n```\n`x`";
+        let parsed: Vec<(&str, MarkdownType)> = highlight_parse(text)
+            .into_iter()
+            .map(|s| (s.text, s.mdtype))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "```rust\nlet a = **1**;\n```",
+                    MarkdownType::Code("rust".to_string())
+                ),
+                ("\nbetween\n", MarkdownType::Paragraph),
+                ("```\nplain\n```", MarkdownType::Code(String::new())),
+                ("\n", MarkdownType::Paragraph),
+                ("`x`", MarkdownType::Monospace),
+            ],
+            parsed
+        );
+
+        let mut tree = Tree::default();
+        tree.reset("# Code\n```py\nx = 1\n```\n");
+        let code = tree.nodes.values().find_map(|n| match n {
+            Node::Text(s) => Some(s.mdtype.clone()).filter(|t| *t != MarkdownType::Paragraph),
+            _ => None,
+        });
+        assert_eq!(Some(MarkdownType::Code("py".to_string())), code);
+    }
+
+    #[test]
+    fn test_lists() {
+        let text = "- one **b**\n  * two - x\n\t1. three\n10) four\n**a** - not\n-not\n";
+        let parsed: Vec<(&str, MarkdownType)> = highlight_parse(text)
+            .into_iter()
+            .map(|s| (s.text, s.mdtype))
+            .collect();
+        assert_eq!(
+            vec![
+                ("- ", MarkdownType::Bullet(0)),
+                ("one ", MarkdownType::Paragraph),
+                ("**b**", MarkdownType::Bold),
+                ("\n", MarkdownType::Paragraph),
+                ("  * ", MarkdownType::Bullet(1)),
+                ("two - x\n", MarkdownType::Paragraph),
+                ("\t1. ", MarkdownType::Numbered(2)),
+                ("three\n", MarkdownType::Paragraph),
+                ("10) ", MarkdownType::Numbered(0)),
+                ("four\n", MarkdownType::Paragraph),
+                ("**a**", MarkdownType::Bold),
+                (" - not\n-not\n", MarkdownType::Paragraph),
+            ],
+            parsed
+        );
+    }
+
+    #[test]
+    fn test_tasks() {
+        let text = "- [ ] todo\n  * [X] done\n- [y] no\n";
+        let parsed: Vec<(&str, MarkdownType)> = highlight_parse(text)
+            .into_iter()
+            .map(|s| (s.text, s.mdtype))
+            .collect();
+        assert_eq!(
+            vec![
+                ("- [ ]", MarkdownType::Task(false)),
+                (" todo\n", MarkdownType::Paragraph),
+                ("  * [X]", MarkdownType::Task(true)),
+                (" done\n", MarkdownType::Paragraph),
+                ("- ", MarkdownType::Bullet(0)),
+                ("[y] no\n", MarkdownType::Paragraph),
+            ],
+            parsed
+        );
+    }
+
+    #[test]
+    fn test_strikethrough() {
+        let parsed: Vec<(&str, MarkdownType)> = highlight_parse("~~gone~~ ~~\n~~ ~x~")
+            .into_iter()
+            .map(|s| (s.text, s.mdtype))
+            .collect();
+        assert_eq!(
+            vec![
+                ("~~gone~~", MarkdownType::Strikethrough),
+                (" ~~\n~~ ~x~", MarkdownType::Paragraph),
+            ],
+            parsed
+        );
+    }
+
+    #[test]
+    fn test_toc() {
+        let mut note = Note::new("{{toc}}\n# A\ntext\n## B\nmore\n".to_string());
+        let shown = "{{toc}}\n• A\n  • B\n# A\ntext\n## B\nmore\n";
+        assert_eq!(shown, note.as_str());
+        assert_eq!("{{toc}}\n# A\ntext\n## B\nmore\n", note.full());
+        assert_eq!(vec![7..21], note.generated());
+        // positions are in displayed characters, the bullets are more than a byte
+        let at = |text: &str, find: &str| text[..text.find(find).unwrap()].chars().count();
+
+        // the list can't be typed into or deleted from
+        assert_eq!(0, note.insert_text("x", 10));
+        note.delete_char_range(10..11);
+        assert_eq!(shown, note.as_str());
+
+        // it follows the headings, and the text around it edits as usual
+        note.insert_text("Z", at(shown, "# A") + 2);
+        assert_eq!(
+            "{{toc}}\n• ZA\n  • B\n# ZA\ntext\n## B\nmore\n",
+            note.as_str()
+        );
+        assert_eq!("{{toc}}\n# ZA\ntext\n## B\nmore\n", note.full());
+        let text = at(note.as_str(), "text");
+        note.delete_char_range(text..text + 4);
+        assert_eq!("{{toc}}\n# ZA\n\n## B\nmore\n", note.full());
+
+        // a line of the list goes to its heading
+        let b = at(note.as_str(), "• B");
+        let target = note.full().find("## B");
+        assert_eq!(target, note.toc_target(b));
+        assert_eq!(None, note.toc_target(2));
+    }
+
+    #[test]
+    fn test_web_links() {
+        let parsed: Vec<(&str, MarkdownType)> =
+            highlight_parse("go to https://x.org/a_b_c. or [the site](https://y.org/#top)!")
+                .into_iter()
+                .map(|s| (s.text, s.mdtype))
+                .collect();
+        assert_eq!(
+            vec![
+                ("go to ", MarkdownType::Paragraph),
+                ("https://x.org/a_b_c", MarkdownType::WebLink),
+                (". or ", MarkdownType::Paragraph),
+                ("[the site](https://y.org/#top)", MarkdownType::WebLink),
+                ("!", MarkdownType::Paragraph),
+            ],
+            parsed
+        );
+    }
+
+    #[test]
+    fn test_math() {
+        let text = "$x^2$ costs $5 and $10\n$$\n\\sum x\n$$";
+        let parsed: Vec<(&str, MarkdownType)> = highlight_parse(text)
+            .into_iter()
+            .map(|s| (s.text, s.mdtype))
+            .collect();
+        assert_eq!(
+            vec![
+                ("$x^2$", MarkdownType::Math(false)),
+                (" costs $5 and $10\n", MarkdownType::Paragraph),
+                ("$$\n\\sum x\n$$", MarkdownType::Math(true)),
+            ],
+            parsed
+        );
+    }
+
+    #[test]
+    fn test_frontmatter() {
+        let text = "---\ntitle: Plans\n# not a heading\ntags: [a, b]\n---\n# Heading\nbody\n";
+        let mut note = Note::new(text.to_string());
+        assert_eq!(Some("Plans"), note.metadata()["title"].as_str());
+        assert_eq!(vec!["a", "b"], note.metadata()["tags"].strings());
+        assert_eq!(
+            vec!["Heading"],
+            note.headings()
+                .iter()
+                .map(|h| h.title.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(MarkdownType::Frontmatter, highlight_parse(text)[0].mdtype);
+        assert_eq!(text, note.full());
+
+        // editing the frontmatter reads it again, and undoing puts the old values back
+        let at = text.find("Plans").unwrap();
+        note.apply(Edit::Replace {
+            range: at..at + 5,
+            text: "Goals".to_string(),
+        })
+        .unwrap();
+        assert_eq!(Some("Goals"), note.metadata()["title"].as_str());
+        note.undo();
+        assert_eq!(Some("Plans"), note.metadata()["title"].as_str());
+        assert!(
+            Note::new("# no metadata\n".to_string())
+                .metadata()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_tags() {
+        let text =
+            "#todo at start\n# Heading\nsee #work/plans, x#no &#39; a.org/#top `#code` #2024\n";
+        let tags: Vec<&str> = highlight_parse(text)
+            .into_iter()
+            .filter(|s| s.mdtype == MarkdownType::Tag)
+            .map(|s| s.text)
+            .collect();
+        assert_eq!(vec!["#todo", "#work/plans"], tags);
+
+        let mut tree = Tree::default();
+        tree.reset(text);
+        assert_eq!(1, tree.headings().len());
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut sec = Tree::default();
+        let mut example = "# Big Head\n## Little Head\nSome body\nMore Body##Second Little Head\none body\n# Another Big One\nend";
+        sec.reset(example);
+        println!("{:?}", sec);
+        assert_eq!(example, sec.string(true));
+
+        example = "not starting with a heading\n# Now Heading\nasdfasdf\nasdf\n";
+        sec.reset(example);
+        assert_eq!(example, sec.string(true));
+
+        example = "# A\n## B\n### C";
+        sec.reset(example);
+        println!("{:?}", sec);
+        assert_eq!(example, sec.string(true));
+
+        example = "# A\n### B\n## C";
+        sec.reset(example);
+        println!("{:?}", sec);
+        assert_eq!(example, sec.string(true));
+
+        example = "# A";
+        sec.reset(example);
+        println!("{:?}", sec);
+        assert_eq!(example, sec.string(true));
+
+        example = "# A\n#\na\n";
+        sec.reset(example);
+        println!("{:?}", sec);
+        assert_eq!(example, sec.string(true));
+
+        example = "# A\n\n## B\n\na\n";
+        sec.reset(example);
+        println!("{:?}", sec);
+        assert_eq!(example, sec.string(true));
+    }
+
+    #[test]
+    fn test_expand() {
+        let example = "# A\n## B\nbbbbb\n## C\nccccc";
+        let mut sec = Tree::new(example);
+
+        sec.collapse(&[0usize]);
+        assert_eq!("# A\n", sec.string(false));
+        assert_eq!(4, sec.len(false));
+
+        sec.expand(&[0usize]);
+        sec.collapse(&[0usize, 1usize]);
+        assert_eq!("# A\n## B\nbbbbb\n## C\n", sec.string(false));
+        assert_eq!(20, sec.len(false));
+    }
+
+    #[test]
+    fn test_expand_2() {
+        let example = "# A\n# B\n## C\nccccc";
+        let mut sec = Tree::new(example);
+
+        assert_eq!([1usize, 0usize], sec.path(15).iter().as_slice());
+
+        println!("{:?}", sec);
+
+        sec.collapse(&[1usize, 0usize]);
+        println!("{}", sec.string(false));
+        assert_eq!("# A\n# B\n## C\n", sec.string(false));
+    }
+
+    #[test]
+    fn test_insert() {
+        let example = "# A\n## B\nbbbbb\n## C\nccccc";
+        let mut sec = Tree::new(example);
+        sec.collapse(&[0usize, 0usize]);
+        println!("{}", sec.string(false));
+        sec.insert("d", 15);
+        assert_eq!("# A\n## B\n## C\ncdcccc", sec.string(false));
+    }
+
+    #[test]
+    fn test_translate() {
+        let example = "# A\n## B\nbbbbb\n## C\nccccc";
+        let mut sec = Tree::new(example);
+        println!("{}", sec.string(false));
+
+        assert_eq!(1, sec.translate(1));
+        assert_eq!(2, sec.translate(2));
+        assert_eq!(4, sec.translate(4));
+        println!("testing 10");
+        assert_eq!(10, sec.translate(10));
+        assert_eq!(example.len(), sec.translate(example.len()));
+
+        sec.collapse(&[0usize, 0usize]);
+        println!("{}", sec.string(false));
+        println!("testing 10, folded");
+        assert_eq!(16, sec.translate(10));
+        assert_eq!(8, sec.inv_translate(11));
+        assert_eq!(11, sec.inv_translate(17));
+    }
+
+    #[test]
+    fn test_translate_heading_lengths() {
+        // the heading's own length covers its trailing newline, so every
+        //   position in an unfolded tree maps to itself
+        let example = "# A\n## Bee\nbbbbb\n### C\nccccc\n## D\nd";
+        let mut sec = Tree::new(example);
+        for pos in 0..=example.len() {
+            assert_eq!(pos, sec.translate(pos));
+            assert_eq!(pos, sec.inv_translate(pos));
+        }
+
+        // folding "## Bee" hides its body, the heading after it lands on "## D"
+        sec.collapse(&[0usize, 0usize]);
+        let shown = sec.string(false);
+        assert_eq!("# A\n## Bee\n## D\nd", shown);
+        for pos in 0..11 {
+            assert_eq!(pos, sec.translate(pos));
+        }
+        assert_eq!(example.find("## D").unwrap(), sec.translate(11));
+        assert_eq!(example.len(), sec.translate(shown.len()));
+        assert_eq!(11, sec.inv_translate(example.find("## D").unwrap()));
+    }
+
+    #[test]
+    fn test_cached_len() {
+        let example = "# A\n## B\nbbbbb\n## C\nccccc";
+        let mut sec = Tree::new(example);
+        let check = |sec: &Tree| {
+            assert_eq!(sec.string(true).len(), sec.len(true));
+            assert_eq!(sec.string(false).len(), sec.len(false));
+        };
+        check(&sec);
+
+        sec.collapse(&[0usize, 0usize]);
+        check(&sec);
+        sec.insert("xy", 15);
+        check(&sec);
+        sec.toggle(&[0usize]);
+        check(&sec);
+        sec.reveal(12);
+        check(&sec);
+        sec.expand(&[0usize, 1usize]);
+        check(&sec);
+    }
+
+    #[test]
+    fn test_path() {
+        let example = "# A\n## B\nbbbbb\n## C\nccccc";
+        let sec = Tree::new(example);
+
+        assert_eq!([0usize, 1usize], sec.path(20).iter().as_slice());
+    }
+
+    #[test]
+    fn test_note_insert() {
+        let mut note = Note::new("# A\n\na\n".to_string());
+        note.insert_text("#", 4);
+        assert_eq!("# A\n#\na\n", note.as_str());
+    }
+
+    #[test]
+    fn test_move_lines() {
+        let mut note = Note::new("a\nb\nc".to_string());
+        assert_eq!(Some((3, 3)), note.move_lines(1, 1, false));
+        assert_eq!("b\na\nc", note.as_str());
+        assert_eq!(Some((4, 5)), note.move_lines(2, 3, false));
+        assert_eq!("b\nc\na", note.as_str());
+        assert_eq!(None, note.move_lines(4, 5, false));
+        assert_eq!(Some((0, 3)), note.move_lines(2, 5, true));
+        assert_eq!("c\na\nb", note.as_str());
+        assert_eq!(None, note.move_lines(0, 3, true));
+
+        let mut note = Note::new("é\nab\n".to_string());
+        assert_eq!(Some((3, 3)), note.move_lines(1, 1, false));
+        assert_eq!("ab\né\n", note.as_str());
+        assert_eq!(Some((0, 0)), note.move_lines(3, 3, true));
+        assert_eq!("é\nab\n", note.as_str());
+    }
+
+    #[test]
+    fn test_shift_heading() {
+        let mut note = Note::new("# A\na\n# B\nb\n".to_string());
+        // B goes under A
+        assert_eq!(Some(10), note.shift_heading(9, true));
+        assert_eq!("# A\na\n## B\nb\n", note.as_str());
+        assert_eq!(
+            vec![vec![0], vec![0, 1]],
+            note.headings()
+                .into_iter()
+                .map(|h| h.path)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(7), note.shift_heading(7, false));
+        assert_eq!("# A\na\n# B\nb\n", note.as_str());
+        assert_eq!(None, note.shift_heading(1, false));
+        // the cursor in the hashes stays with them
+        assert_eq!(Some(0), note.shift_heading(0, true));
+        assert_eq!(Some(0), note.shift_heading(0, true));
+        assert_eq!(None, note.shift_heading(0, true));
+        assert_eq!("### A\na\n# B\nb\n", note.as_str());
+    }
+
+    #[test]
+    fn test_graphemes() {
+        // e + combining acute and a family emoji in front of a link
+        let mut note = Note::new("e\u{301} 👨\u{200d}👩 @@link".to_string());
+        assert_eq!(MarkdownType::Link, note.get_node(7).mdtype);
+        assert_eq!(MarkdownType::Paragraph, note.get_node(6).mdtype);
+        assert_eq!(MarkdownType::Paragraph, note.get_node(4).mdtype);
+
+        assert_eq!(1, note.insert_text("x", 1));
+        assert_eq!("xe\u{301} 👨\u{200d}👩 @@link", note.as_str());
+        note.delete_char_range(5..6);
+        assert_eq!("xe\u{301}  @@link", note.as_str());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut note = Note::new("x\n# A\n## B\nbbb\n## C\nccc\n# D\nddd".to_string());
+        assert_eq!(Vec::<String>::new(), note.validate());
+        note.toggle(&[1usize, 0usize]);
+        note.refresh();
+        assert_eq!(Vec::<String>::new(), note.validate());
+        note.insert_text("yy", 1);
+        assert_eq!(Vec::<String>::new(), note.validate());
+
+        let root = note.tree.root;
+        if let Node::Section(section) = &mut note.tree.nodes[root] {
+            section.full_len += 1;
+        }
+        let problems = note.validate();
+        assert_eq!(2, problems.len());
+        assert!(problems[0].starts_with("section [] caches a full length"));
+        assert!(problems[1].starts_with("full text is"));
+    }
+
+    // the tree after an edit is the one a fresh parse of the text would build
+    fn assert_parsed(note: &mut Note) {
+        let full = note.full().to_string();
+        let fresh = Note::new(full.clone());
+        let shape = |note: &Note| {
+            return note
+                .markdown()
+                .into_iter()
+                .map(|m| (m.text, m.mdtype))
+                .collect::<Vec<_>>();
+        };
+        assert_eq!(shape(&fresh), shape(note), "{:?}", full);
+        assert_eq!(Vec::<String>::new(), note.validate());
+    }
+
+    #[test]
+    fn test_reparse_within() {
+        let mut note = Note::new("x\n# A\n## B\nbbb\n## C\nccc\n# D\nddd\n".to_string());
+        let reparses = note.reparses();
+        // inside B's body, then a new subsection in A, then a deletion in C
+        note.apply(Edit::Insert {
+            pos: 13,
+            text: "**b**".to_string(),
+        })
+        .unwrap();
+        assert_parsed(&mut note);
+        note.apply(Edit::Insert {
+            pos: 6,
+            text: "### E\neee\n".to_string(),
+        })
+        .unwrap();
+        assert_parsed(&mut note);
+        assert_eq!(2, note.headings().iter().filter(|h| h.level == 2).count());
+        note.delete_char_range(35..37);
+        assert_parsed(&mut note);
+        assert_eq!(reparses, note.reparses());
+
+        // D stays folded through edits elsewhere
+        let d = note
+            .headings()
+            .into_iter()
+            .find(|h| h.title == "D")
+            .unwrap();
+        note.toggle(&d.path);
+        note.refresh();
+        note.delete_char_range(13..14);
+        assert!(note.as_str().ends_with("# D\n"));
+        assert_eq!(reparses, note.reparses());
+
+        // a heading that ends its section, editing a heading, and running into the next heading
+        //   all need the whole note
+        for (range, text) in [(11..11, "# F\n"), (3..3, "x"), (23..24, "")] {
+            let mut note = Note::new("x\n# A\n## B\nbbb\n## C\nccc\n# D\nddd\n".to_string());
+            let reparses = note.reparses();
+            let full = note.full().to_string();
+            note.replace_full(range, text);
+            assert_eq!(reparses + 1, note.reparses(), "{:?} in {:?}", text, full);
+            assert_parsed(&mut note);
+        }
+
+        // whatever gets typed or deleted wherever, the tree comes out as a full parse would have it
+        let text = "x\n# A\n## B\nbbb\n### C\nccc\n## D\n# E\neee";
+        for pos in 0..=text.len() {
+            for typed in ["#", "\n", "## x\n", "a"] {
+                let mut note = Note::new(text.to_string());
+                note.replace_full(pos..pos, typed);
+                assert_parsed(&mut note);
+            }
+            if pos < text.len() {
+                let mut note = Note::new(text.to_string());
+                note.replace_full(pos..pos + 1, "");
+                assert_parsed(&mut note);
+            }
+        }
+    }
+
+    #[test]
+    fn test_edits_keep_folds() {
+        let text = "# A\naaa\n## B\nbbb\n### C\nccc\n# D\nddd";
+        let mut note = Note::new(text.to_string());
+        let b = note
+            .headings()
+            .into_iter()
+            .find(|h| h.title == "B")
+            .unwrap();
+        note.toggle(&b.path);
+        note.refresh();
+        assert_eq!("# A\naaa\n## B\n# D\nddd", note.as_str());
+        note.delete_char_range(4..5);
+        note.insert_text("x", 4);
+        note.insert_text("\n", 8);
+        assert_eq!("# A\nxaa\n\n## B\n# D\nddd", note.as_str());
+        assert_eq!("# A\nxaa\n\n## B\nbbb\n### C\nccc\n# D\nddd", note.full());
+        assert_eq!(Vec::<String>::new(), note.validate());
+
+        // typing or deleting anywhere on screen keeps the full and displayed text in step
+        for pos in 0..=note.as_str().chars().count() {
+            for typed in ["#", "\n", "## x\n", "a"] {
+                let mut edited = Note::new(text.to_string());
+                edited.toggle(&b.path);
+                edited.refresh();
+                edited.insert_text(typed, pos);
+                assert_eq!(
+                    Vec::<String>::new(),
+                    edited.validate(),
+                    "{:?} at {}",
+                    typed,
+                    pos
+                );
+            }
+            let mut edited = Note::new(text.to_string());
+            edited.toggle(&b.path);
+            edited.refresh();
+            if pos < edited.as_str().len() {
+                edited.delete_char_range(pos..pos + 1);
+                assert_eq!(
+                    Vec::<String>::new(),
+                    edited.validate(),
+                    "deleting at {}",
+                    pos
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut note = Note::new("a\nb\nc".to_string());
+        let edit = Edit::Batch(vec![
+            Edit::Wrap {
+                range: 0..1,
+                before: "**".to_string(),
+                after: "**".to_string(),
+            },
+            Edit::Replace {
+                range: 4..5,
+                text: "C".to_string(),
+            },
+        ]);
+        assert_eq!(Ok(()), note.apply(edit));
+        assert_eq!("**a**\nb\nC", note.as_str());
+
+        let overlapping = Edit::Batch(vec![
+            Edit::Delete(0..3),
+            Edit::Insert {
+                pos: 1,
+                text: "x".to_string(),
+            },
+        ]);
+        assert_eq!(Err(EditError::Overlapping), note.apply(overlapping));
+        assert_eq!(Err(EditError::OutOfRange), note.apply(Edit::Delete(10..20)));
+        assert_eq!("**a**\nb\nC", note.as_str());
+
+        assert!(note.undo().is_some());
+        assert_eq!("a\nb\nc", note.as_str());
+        assert!(note.undo().is_none());
+        assert!(note.redo().is_some());
+        assert_eq!("**a**\nb\nC", note.as_str());
+        assert!(note.undo().is_some());
+
+        let up = Edit::MoveLines {
+            range: 0..0,
+            up: true,
+        };
+        assert_eq!(Err(EditError::OutOfRange), note.apply(up));
+        let down = Edit::MoveLines {
+            range: 0..0,
+            up: false,
+        };
+        assert_eq!(Ok(()), note.apply(down));
+        assert_eq!("b\na\nc", note.as_str());
+    }
+
+    #[test]
+    fn test_merge_remote() {
+        let mut note = Note::new("# A\nabc".to_string());
+        note.apply(Edit::Delete(0..2)).unwrap();
+        note.merge_remote("# A\nabc\n# B\nremote");
+        assert_eq!("# A\nabc\n# B\nremote", note.as_str());
+        assert_eq!(2, note.headings().len());
+        assert!(note.undo().is_none());
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let mut note = Note::new("x\n# A\n@@a\n".to_string());
+        note.toggle(&[1usize]);
+        note.refresh();
+        note.rewrite("x\n# A\n[[a]]\n");
+        assert_eq!("x\n# A\n[[a]]\n", note.full());
+        assert!(note.undo().is_some());
+        assert_eq!("x\n# A\n@@a\n", note.full());
+    }
+
+    #[test]
+    fn test_modified() {
+        let mut note = Note::new("# A\nbody\n".to_string());
+        assert!(!note.is_modified());
+
+        // folding and unfolding leaves the text alone
+        note.toggle(&note.path(0));
+        note.undo();
+        assert!(!note.is_modified());
+
+        note.insert_text("x", 0);
+        assert!(note.is_modified());
+        note.mark_saved();
+        assert!(!note.is_modified());
+
+        note.delete_char_range(0..1);
+        assert!(note.is_modified());
+        note.mark_saved();
+        note.undo();
+        assert!(note.is_modified());
+        note.mark_saved();
+        note.apply(Edit::Insert {
+            pos: 0,
+            text: "y".to_string(),
+        })
+        .unwrap();
+        assert!(note.is_modified());
+    }
+
+    #[test]
+    fn test_undo_typing() {
+        let mut note = Note::new("# A\nbody\n".to_string());
+        for (i, c) in "one two".chars().enumerate() {
+            note.insert_text(&c.to_string(), 9 + i);
+        }
+        assert_eq!("# A\nbody\none two", note.as_str());
+        // backspacing over part of the last word is a step of its own
+        note.delete_char_range(15..16);
+        note.delete_char_range(14..15);
+        assert_eq!("# A\nbody\none t", note.as_str());
+
+        // the cursor goes after whatever came back
+        assert_eq!(Some(16), note.undo());
+        assert_eq!("# A\nbody\none two", note.as_str());
+        // each word goes with the space in front of it
+        assert_eq!(Some(12), note.undo());
+        assert_eq!("# A\nbody\none", note.as_str());
+        assert_eq!(Some(9), note.undo());
+        assert_eq!("# A\nbody\n", note.as_str());
+        assert_eq!(Some(12), note.redo());
+        assert_eq!(Some(16), note.redo());
+        assert_eq!("# A\nbody\none two", note.as_str());
+
+        // a paste is one step however long, and a fold is one too
+        note.insert_text(" three", 16);
+        let path = note.path(2);
+        note.toggle(&path);
+        note.refresh();
+        assert_eq!("# A\n", note.as_str());
+        assert_eq!(Some(22), note.undo());
+        assert_eq!("# A\nbody\none two three", note.as_str());
+        assert_eq!(Some(16), note.undo());
+        assert_eq!("# A\nbody\none two", note.as_str());
+    }
+
+    #[test]
+    fn test_move_lines_folded() {
+        let mut note = Note::new("x\n# A\naaa\n# B\nbbb\n".to_string());
+        note.toggle(&[1usize]);
+        note.refresh();
+        assert_eq!("x\n# A\n# B\nbbb\n", note.as_str());
+        // moving the line below a folded section skips over its hidden body
+        note.move_lines(0, 0, false);
+        assert_eq!("# A\naaa\nx\n# B\nbbb\n", note.as_str());
+
+        // positions below a folded section are displayed ones
+        let mut note = Note::new("# Ä\nää\n# B\nbé\nc\n".to_string());
+        note.toggle(&[0usize]);
+        note.refresh();
+        assert_eq!("# Ä\n# B\nbé\nc\n", note.as_str());
+        assert_eq!(Some((9, 9)), note.move_lines(13, 13, true));
+        assert_eq!("# Ä\n# B\nc\nbé\n", note.as_str());
+    }
+
+    #[test]
+    fn test_duplicate_lines() {
+        let mut note = Note::new("a\nb".to_string());
+        assert_eq!((3, 3), note.duplicate_lines(1, 1));
+        assert_eq!("a\na\nb", note.as_str());
+        assert_eq!((6, 7), note.duplicate_lines(4, 5));
+        assert_eq!("a\na\nb\nb", note.as_str());
+
+        let mut note = Note::new("é\nab".to_string());
+        assert_eq!((7, 8), note.duplicate_lines(4, 5));
+        assert_eq!("é\nab\nab", note.as_str());
+
+        // a folded section above doesn't count towards the returned positions, and stays folded
+        let mut note = Note::new("# Ä\nää\n# B\nbé\n".to_string());
+        note.toggle(&[0usize]);
+        note.refresh();
+        assert_eq!((13, 17), note.duplicate_lines(9, 13));
+        assert_eq!("# Ä\n# B\nbé\nbé\n", note.as_str());
+    }
+
+    #[test]
+    fn test_expand_selection() {
+        let note = Note::new("# A\nsome **bold** text\nmore\n\nnext\n## B\nb".to_string());
+        let mut sel = note.expand_selection(12, 12);
+        assert_eq!((11, 15), sel);
+        sel = note.expand_selection(sel.0, sel.1);
+        assert_eq!((9, 17), sel);
+        sel = note.expand_selection(sel.0, sel.1);
+        assert_eq!((4, 22), sel);
+        sel = note.expand_selection(sel.0, sel.1);
+        assert_eq!((4, 27), sel);
+        sel = note.expand_selection(sel.0, sel.1);
+        assert_eq!((0, 40), sel);
+        assert_eq!((39, 40), note.expand_selection(39, 39));
+        assert_eq!((34, 40), note.expand_selection(39, 40));
+
+        let note = Note::new("un éclair glacé\n".to_string());
+        assert_eq!((3, 10), note.expand_selection(5, 5));
+        assert_eq!((11, 17), note.expand_selection(17, 17));
+        assert_eq!((0, 17), note.expand_selection(3, 10));
+    }
+
+    #[test]
+    fn test_sort_lines() {
+        let mut note = Note::new("x\nb\nA\na\nb\ny".to_string());
+        assert_eq!((2, 10), note.sort_lines(2, 9, SortOptions::default()));
+        assert_eq!("x\nA\na\nb\nb\ny", note.as_str());
+
+        let options = SortOptions {
+            case_insensitive: true,
+            reverse: true,
+            unique: true,
+        };
+        assert_eq!((2, 6), note.sort_lines(2, 10, options));
+        assert_eq!("x\nb\na\ny", note.as_str());
+
+        let mut note = Note::new("ö\nü\nä\n".to_string());
+        assert_eq!((0, 9), note.sort_lines(1, 8, SortOptions::default()));
+        assert_eq!("ä\nö\nü\n", note.as_str());
+
+        // a folded section above doesn't count towards the returned range
+        let mut note = Note::new("# Ä\nää\n# B\nc\nb\n".to_string());
+        note.toggle(&[0usize]);
+        note.refresh();
+        assert_eq!((9, 13), note.sort_lines(9, 13, SortOptions::default()));
+        assert_eq!("# Ä\n# B\nb\nc\n", note.as_str());
+    }
+
+    #[test]
+    fn test_transform_case() {
+        assert_eq!(
+            "Hello World's **Bold**",
+            TextCase::Title.apply("hello wORLD's **bold**")
+        );
+
+        let mut note = Note::new("some text here".to_string());
+        assert_eq!((5, 9), note.transform_case(6, 6, TextCase::Upper));
+        assert_eq!("some TEXT here", note.as_str());
+        assert_eq!((0, 14), note.transform_case(14, 0, TextCase::Title));
+        assert_eq!("Some Text Here", note.as_str());
+
+        // ß grows to SS, the returned range covers the longer text
+        let mut note = Note::new("# Ä\nää\n# B\nstraße ok".to_string());
+        note.toggle(&[0usize]);
+        note.refresh();
+        assert_eq!((9, 16), note.transform_case(13, 13, TextCase::Upper));
+        assert_eq!("# Ä\n# B\nSTRASSE ok", note.as_str());
+    }
+
+    #[test]
+    fn test_headings_reveal() {
+        let mut note = Note::new("# A\n## B\nbbb\n## C\nccc".to_string());
+        let headings = note.headings();
+        assert_eq!(3, headings.len());
+        assert_eq!("C", headings[2].title);
+        assert_eq!(vec![0usize, 1usize], headings[2].path);
+        assert_eq!(13, headings[2].pos);
+
+        note.toggle(&[0usize]);
+        note.refresh();
+        assert!(!note.headings()[2].visible);
+        assert!(!note.headings()[0].expanded);
+        assert_eq!("# A\n", note.as_str());
+        assert_eq!(14, note.reveal(14));
+        assert_eq!("# A\n## B\nbbb\n## C\nccc", note.as_str());
+    }
+
+    #[test]
+    fn test_section_range() {
+        let note = Note::new("intro\n# Ä\n## B\nbbb\n## C\nccc".to_string());
+        assert_eq!(Some(10..19), note.section_range(12));
+        assert_eq!(Some(6..27), note.section_range(7));
+        assert_eq!(None, note.section_range(2));
+    }
+
+    #[test]
+    fn test_task_progress() {
+        let note = Note::new(
+            "# Plan\n- [x] one\n## Later\n  * [ ] two **soon**\n- [X] three\nnot - [ ] this\n# Notes\ntext"
+                .to_string(),
+        );
+        let progress: Vec<Option<Progress>> = note.headings().iter().map(|h| h.progress).collect();
+        assert_eq!(
+            vec![
+                Some(Progress { done: 2, total: 3 }),
+                Some(Progress { done: 1, total: 2 }),
+                None
+            ],
+            progress
+        );
+    }
+
+    #[test]
+    fn test_markdown() {
+        let example = "# A\n## B\nbbbbb\n## C\nccccc";
+        let sec = Tree::new(example);
+
+        let md = sec.markdown();
+        assert_eq!(MarkdownType::Heading3, md[0].mdtype);
+        assert_eq!(MarkdownType::Heading1, md[1].mdtype);
+        assert_eq!(MarkdownType::Heading2, md[2].mdtype);
+        assert_eq!(MarkdownType::Paragraph, md[3].mdtype);
+        assert_eq!(MarkdownType::Heading2, md[4].mdtype);
+        assert_eq!(MarkdownType::Paragraph, md[5].mdtype);
+    }
+
+    #[test]
+    fn test_reparses() {
+        let mut note = Note::new("# A\nabc".to_string());
+        assert_eq!(0, note.reparses());
+        // inside a section only that section is parsed again
+        note.delete_char_range(4..5);
+        assert_eq!(0, note.reparses());
+        note.apply(Edit::Insert {
+            pos: 0,
+            text: "x".to_string(),
+        })
+        .unwrap();
+        assert_eq!(1, note.reparses());
+    }
+}
@@ -0,0 +1,90 @@
+// markdown delimiters typed in pairs: around a selection, or with the closing one put in ahead of
+//   the cursor and stepped over when it's typed
+
+use crate::typography::in_code;
+
+// what typing a delimiter does instead of going in on its own
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pairing {
+    // put these on either side of the selection, which stays selected
+    Wrap(char, char),
+    // insert the text with the cursor this many characters into it
+    Insert(String, usize),
+    // move past the delimiter after the cursor rather than typing another
+    Skip,
+}
+
+// text after the cursor that a closing delimiter can go in front of
+fn closable(after: &str) -> bool {
+    return after
+        .chars()
+        .next()
+        .is_none_or(|c| c.is_whitespace() || ")]}.,;:!?".contains(c));
+}
+
+// work out what typing `typed` between `before` and `after` does with `selected` in between
+pub fn pair(before: &str, selected: &str, after: &str, typed: char) -> Option<Pairing> {
+    if in_code(before) && typed != '`' {
+        return None;
+    }
+    let close = match typed {
+        '[' => ']',
+        '`' | '_' | '*' => typed,
+        ']' if selected.is_empty() && after.starts_with(']') => return Some(Pairing::Skip),
+        _ => return None,
+    };
+    if !selected.is_empty() {
+        return Some(Pairing::Wrap(typed, close));
+    }
+    if typed != '[' && after.starts_with(typed) {
+        return Some(Pairing::Skip);
+    }
+    if !closable(after) {
+        return None;
+    }
+    let prev = before.chars().next_back();
+    return match typed {
+        '[' => Some(Pairing::Insert("[]".to_string(), 1)),
+        // a third backtick starts a fence rather than another span
+        '`' if prev == Some('`') => None,
+        '`' => Some(Pairing::Insert("``".to_string(), 1)),
+        // underscores inside words are snake_case
+        '_' if prev.is_some_and(char::is_alphanumeric) => None,
+        '_' => Some(Pairing::Insert("__".to_string(), 1)),
+        // a lone star is a list marker or italics typed by hand, the second one makes it bold
+        '*' if before.ends_with('*') && !before.ends_with("**") => {
+            Some(Pairing::Insert("***".to_string(), 1))
+        }
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pairs::{Pairing, pair};
+
+    #[test]
+    fn test_pair() {
+        let insert = |text: &str| Some(Pairing::Insert(text.to_string(), 1));
+        assert_eq!(Some(Pairing::Wrap('*', '*')), pair("a ", "word", " b", '*'));
+        assert_eq!(Some(Pairing::Wrap('[', ']')), pair("", "link", "", '['));
+        assert_eq!(Some(Pairing::Wrap('`', '`')), pair("", "x", "", '`'));
+
+        assert_eq!(insert("[]"), pair("see ", "", "", '['));
+        assert_eq!(insert("[]"), pair("see [", "", "]", '['));
+        assert_eq!(insert("``"), pair("run ", "", " now", '`'));
+        assert_eq!(insert("__"), pair("so ", "", "", '_'));
+        assert_eq!(insert("***"), pair("so *", "", "", '*'));
+
+        assert_eq!(Some(Pairing::Skip), pair("[[note", "", "]]", ']'));
+        assert_eq!(Some(Pairing::Skip), pair("`code", "", "`", '`'));
+        assert_eq!(Some(Pairing::Skip), pair("**bold", "", "**", '*'));
+
+        assert_eq!(None, pair("", "", "", '*'));
+        assert_eq!(None, pair("snake", "", "", '_'));
+        assert_eq!(None, pair("``", "", "", '`'));
+        assert_eq!(None, pair("", "", "word", '['));
+        assert_eq!(None, pair("a ]", "", "", ']'));
+        assert_eq!(None, pair("```\n", "", "", '['));
+    }
+}
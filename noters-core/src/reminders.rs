@@ -0,0 +1,66 @@
+use chrono::NaiveDateTime;
+use regex::Regex;
+use std::sync::LazyLock;
+
+static REMINDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"⏰\s*(\d{4}-\d{2}-\d{2})[ T](\d{1,2}:\d{2})\s*(.*)").unwrap());
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reminder {
+    pub at: NaiveDateTime,
+    pub text: String,
+    // counted from 1, like the go to line dialog
+    pub line: usize,
+}
+
+// every `⏰ 2024-07-01 09:00 text` in a note, dates that don't exist are skipped
+pub fn parse(text: &str) -> Vec<Reminder> {
+    let mut reminders = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let Some(caps) = REMINDER.captures(line) else {
+            continue;
+        };
+        let stamp = format!("{} {}", &caps[1], &caps[2]);
+        match NaiveDateTime::parse_from_str(&stamp, "%Y-%m-%d %H:%M") {
+            Ok(at) => reminders.push(Reminder {
+                at,
+                text: caps[3].trim().to_string(),
+                line: i + 1,
+            }),
+            Err(e) => tracing::debug!("bad reminder time {}: {}", stamp, e),
+        }
+    }
+    return reminders;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reminders::{Reminder, parse};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_parse() {
+        let text = "# Todo\n- call bob ⏰ 2024-07-01 09:00 about the thing\n⏰2024-02-30 10:00 no such day\n⏰ 2024-12-24T7:30\n";
+        assert_eq!(
+            vec![
+                Reminder {
+                    at: NaiveDate::from_ymd_opt(2024, 7, 1)
+                        .unwrap()
+                        .and_hms_opt(9, 0, 0)
+                        .unwrap(),
+                    text: "about the thing".to_string(),
+                    line: 2,
+                },
+                Reminder {
+                    at: NaiveDate::from_ymd_opt(2024, 12, 24)
+                        .unwrap()
+                        .and_hms_opt(7, 30, 0)
+                        .unwrap(),
+                    text: String::new(),
+                    line: 4,
+                },
+            ],
+            parse(text)
+        );
+    }
+}
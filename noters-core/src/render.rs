@@ -0,0 +1,708 @@
+use crate::formula;
+use crate::links;
+use crate::note::{MarkdownType, highlight_parse};
+
+// where a renderer writes to, one call per piece of the note
+trait Target {
+    fn heading(&mut self, level: usize, title: &str);
+    fn code_block(&mut self, language: &str, body: &str);
+    // inline text, never contains a newline
+    fn inline(&mut self, mdtype: &MarkdownType, text: &str);
+    fn line_break(&mut self);
+}
+
+// the text between the markers, or all of it if they aren't there
+fn strip<'a>(text: &'a str, before: &str, after: &str) -> &'a str {
+    return text
+        .strip_prefix(before)
+        .and_then(|t| t.strip_suffix(after))
+        .unwrap_or(text);
+}
+
+// table formulas show their values rather than the formula
+fn walk(text: &str, target: &mut impl Target) {
+    let text = formula::bake(text);
+    for s in highlight_parse(&text) {
+        match s.mdtype {
+            MarkdownType::Heading1 | MarkdownType::Heading2 | MarkdownType::Heading3 => {
+                let level = s.text.len() - s.text.trim_start_matches('#').len();
+                target.heading(level, s.text[level..].trim());
+            }
+            // metadata is for NoteRs, not for whoever reads the export
+            MarkdownType::Frontmatter => {}
+            MarkdownType::Code(language) => {
+                let body = strip(s.text, "```", "```");
+                let body = body.split_once('\n').map_or("", |(_, body)| body);
+                target.code_block(&language, body.strip_suffix('\n').unwrap_or(body));
+            }
+            _ => {
+                for (i, line) in s.text.split('\n').enumerate() {
+                    if i > 0 {
+                        target.line_break();
+                    }
+                    if !line.is_empty() {
+                        target.inline(&s.mdtype, line);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// text made safe for html, both between tags and inside quoted attributes
+pub fn escape(text: &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;");
+}
+
+// paragraphs are runs of lines separated by a blank line
+#[derive(Default)]
+struct Html {
+    out: String,
+    in_paragraph: bool,
+    line_empty: bool,
+    pending_newline: bool,
+    // for a page shown on its own, there are no other notes' pages to link to
+    unlinked: bool,
+}
+
+impl Html {
+    fn close(&mut self) {
+        if self.in_paragraph {
+            self.out.push_str("</p>\n");
+            self.in_paragraph = false;
+        }
+        self.pending_newline = false;
+    }
+}
+
+impl Target for Html {
+    fn heading(&mut self, level: usize, title: &str) {
+        self.close();
+        self.out
+            .push_str(&format!("<h{0}>{1}</h{0}>\n", level, escape(title)));
+        self.line_empty = false;
+    }
+
+    fn code_block(&mut self, language: &str, body: &str) {
+        self.close();
+        if language.is_empty() {
+            self.out.push_str("<pre><code>");
+        } else {
+            self.out.push_str(&format!(
+                "<pre><code class=\"language-{}\">",
+                escape(language)
+            ));
+        }
+        self.out.push_str(&escape(body));
+        self.out.push_str("</code></pre>\n");
+        self.line_empty = false;
+    }
+
+    fn inline(&mut self, mdtype: &MarkdownType, text: &str) {
+        if !self.in_paragraph {
+            self.out.push_str("<p>");
+            self.in_paragraph = true;
+        } else if self.pending_newline {
+            self.out.push('\n');
+        }
+        self.pending_newline = false;
+        self.line_empty = false;
+
+        let html = match mdtype {
+            MarkdownType::Bold => format!("<strong>{}</strong>", escape(strip(text, "**", "**"))),
+            MarkdownType::Italic => format!("<em>{}</em>", escape(strip(text, "_", "_"))),
+            MarkdownType::Strikethrough => {
+                format!("<del>{}</del>", escape(strip(text, "~~", "~~")))
+            }
+            MarkdownType::Monospace => format!("<code>{}</code>", escape(strip(text, "`", "`"))),
+            MarkdownType::Link if self.unlinked => escape(links::link_text(text)),
+            MarkdownType::Link => {
+                format!(
+                    "<a href=\"{}.html\">{}</a>",
+                    escape(links::link_target(text)),
+                    escape(links::link_text(text))
+                )
+            }
+            MarkdownType::WebLink => {
+                let (url, label) = web_link(text);
+                format!("<a href=\"{}\">{}</a>", escape(url), escape(label))
+            }
+            _ => escape(text),
+        };
+        self.out.push_str(&html);
+    }
+
+    fn line_break(&mut self) {
+        if self.line_empty {
+            self.close();
+        } else if self.in_paragraph {
+            self.pending_newline = true;
+        }
+        self.line_empty = true;
+    }
+}
+
+// the note as an html fragment, links point at the linked note's .html file
+pub fn to_html(text: &str) -> String {
+    return html(text, false);
+}
+
+// the note as an html fragment that's served alone, links to other notes are just their text
+pub fn to_html_unlinked(text: &str) -> String {
+    return html(text, true);
+}
+
+fn html(text: &str, unlinked: bool) -> String {
+    let mut html = Html {
+        line_empty: true,
+        unlinked,
+        ..Default::default()
+    };
+    walk(text, &mut html);
+    html.close();
+    return html.out;
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+const STRIKE: &str = "\x1b[9m";
+const CYAN: &str = "\x1b[36m";
+const BLUE: &str = "\x1b[34m";
+
+// keeps the note's lines as they are, styling with ansi escapes instead of markup
+#[derive(Default)]
+struct Terminal {
+    out: String,
+}
+
+impl Target for Terminal {
+    fn heading(&mut self, level: usize, title: &str) {
+        let style = if level == 1 {
+            format!("{}{}", BOLD, UNDERLINE)
+        } else {
+            BOLD.to_string()
+        };
+        self.out.push_str(&format!("{}{}{}", style, title, RESET));
+    }
+
+    fn code_block(&mut self, _language: &str, body: &str) {
+        for (i, line) in body.split('\n').enumerate() {
+            if i > 0 {
+                self.out.push('\n');
+            }
+            self.out.push_str(&format!("{}  {}{}", CYAN, line, RESET));
+        }
+    }
+
+    fn inline(&mut self, mdtype: &MarkdownType, text: &str) {
+        let styled = match mdtype {
+            MarkdownType::Bold => format!("{}{}{}", BOLD, strip(text, "**", "**"), RESET),
+            MarkdownType::Italic => format!("{}{}{}", ITALIC, strip(text, "_", "_"), RESET),
+            MarkdownType::Strikethrough => {
+                format!("{}{}{}", STRIKE, strip(text, "~~", "~~"), RESET)
+            }
+            MarkdownType::Monospace => format!("{}{}{}", CYAN, strip(text, "`", "`"), RESET),
+            MarkdownType::Link => {
+                format!("{}{}{}{}", UNDERLINE, BLUE, links::link_text(text), RESET)
+            }
+            MarkdownType::WebLink => format!("{}{}{}{}", UNDERLINE, BLUE, web_link(text).1, RESET),
+            _ => text.to_string(),
+        };
+        self.out.push_str(&styled);
+    }
+
+    fn line_break(&mut self) {
+        self.out.push('\n');
+    }
+}
+
+// the note styled for a terminal with ansi escapes
+pub fn to_terminal(text: &str) -> String {
+    let mut terminal = Terminal::default();
+    walk(text, &mut terminal);
+    return terminal.out;
+}
+
+// the address and the text shown for a bare url or a [text](url) link
+fn web_link(text: &str) -> (&str, &str) {
+    return match links::file_links(text).into_iter().next() {
+        Some(link) => (link.target, link.label),
+        None => (text, text),
+    };
+}
+
+// markdown links and image embeds down to their text
+fn plain_line(line: &str) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+    let mut found: Vec<(std::ops::Range<usize>, &str)> = links::image_links(line)
+        .into_iter()
+        .chain(links::file_links(line))
+        .map(|link| (link.range, link.label))
+        .collect();
+    found.sort_by_key(|(range, _)| range.start);
+    for (range, label) in found {
+        if range.start < last {
+            continue;
+        }
+        out.push_str(&line[last..range.start]);
+        out.push_str(label);
+        last = range.end;
+    }
+    out.push_str(&line[last..]);
+    return out;
+}
+
+// just the words, for pasting where markdown isn't rendered
+#[derive(Default)]
+struct Plain {
+    out: String,
+    // quote markers only count at the start of a line
+    mid_line: bool,
+}
+
+impl Target for Plain {
+    fn heading(&mut self, _level: usize, title: &str) {
+        self.out.push_str(title);
+    }
+
+    fn code_block(&mut self, _language: &str, body: &str) {
+        self.out.push_str(body);
+    }
+
+    fn inline(&mut self, mdtype: &MarkdownType, text: &str) {
+        let plain = match mdtype {
+            MarkdownType::Bold => strip(text, "**", "**").to_string(),
+            MarkdownType::Italic => strip(text, "_", "_").to_string(),
+            MarkdownType::Strikethrough => strip(text, "~~", "~~").to_string(),
+            MarkdownType::Monospace => strip(text, "`", "`").to_string(),
+            MarkdownType::Link => links::link_text(text).to_string(),
+            _ if self.mid_line => plain_line(text),
+            _ => plain_line(text.trim_start_matches(['>', ' '])),
+        };
+        self.out.push_str(&plain);
+        self.mid_line = true;
+    }
+
+    fn line_break(&mut self) {
+        self.out.push('\n');
+        self.mid_line = false;
+    }
+}
+
+// the note with its markdown taken out
+pub fn to_plain(text: &str) -> String {
+    let mut plain = Plain::default();
+    walk(text, &mut plain);
+    return plain.out;
+}
+
+// the typefaces a printed page uses, one per inline style
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Face {
+    Regular,
+    Bold,
+    Italic,
+    Mono,
+}
+
+impl Face {
+    // roughly how wide a character is as a share of the font size, the standard pdf fonts
+    //   average a bit under these so lines come out a little short rather than overflowing
+    fn advance(self) -> f32 {
+        return match self {
+            Face::Regular | Face::Italic => 0.52,
+            Face::Bold => 0.57,
+            Face::Mono => 0.6,
+        };
+    }
+
+    pub fn width(self, text: &str, size: f32) -> f32 {
+        return text.chars().count() as f32 * self.advance() * size;
+    }
+}
+
+// something put on a printed page, positions are in points from the top left corner
+#[derive(Clone, Debug, PartialEq)]
+pub enum Placed {
+    // y is the baseline
+    Text {
+        x: f32,
+        y: f32,
+        size: f32,
+        face: Face,
+        text: String,
+    },
+    // y is the top edge, target is the image as written in the note
+    Image {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        target: String,
+    },
+}
+
+// page size and margin in points
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Paper {
+    pub width: f32,
+    pub height: f32,
+    pub margin: f32,
+}
+
+pub const A4: Paper = Paper {
+    width: 595.0,
+    height: 842.0,
+    margin: 56.0,
+};
+
+pub const LETTER: Paper = Paper {
+    width: 612.0,
+    height: 792.0,
+    margin: 54.0,
+};
+
+const BODY_SIZE: f32 = 11.0;
+const CODE_SIZE: f32 = 9.5;
+const HEADING_SIZES: [f32; 3] = [20.0, 16.0, 13.0];
+const LEADING: f32 = 1.35;
+
+// lays the note out in lines and pages, wrapping words at the right margin
+struct Pages<'a> {
+    paper: Paper,
+    // the size in points of an embedded image, None if it can't be read
+    image_size: &'a dyn Fn(&str) -> Option<(f32, f32)>,
+    pages: Vec<Vec<Placed>>,
+    // the top of the next line
+    y: f32,
+    // the line being filled, its items get their baseline when it's finished
+    line: Vec<Placed>,
+    x: f32,
+    line_size: f32,
+    line_empty: bool,
+}
+
+impl Pages<'_> {
+    fn text_width(&self) -> f32 {
+        return self.paper.width - 2.0 * self.paper.margin;
+    }
+
+    fn text_height(&self) -> f32 {
+        return self.paper.height - 2.0 * self.paper.margin;
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Vec::new());
+        self.y = self.paper.margin;
+    }
+
+    // start a new page if this much more doesn't fit on the current one
+    fn make_room(&mut self, height: f32) {
+        if self.y + height > self.paper.height - self.paper.margin && self.y > self.paper.margin {
+            self.new_page();
+        }
+    }
+
+    fn space(&mut self, height: f32) {
+        // space at the top of a page is wasted
+        if self.y > self.paper.margin {
+            self.y += height;
+        }
+    }
+
+    fn finish_line(&mut self) {
+        if self.line.is_empty() {
+            return;
+        }
+        let height = self.line_size * LEADING;
+        self.make_room(height);
+        let baseline = self.y + self.line_size;
+        for mut placed in self.line.drain(..) {
+            if let Placed::Text { y, .. } = &mut placed {
+                *y = baseline;
+            }
+            self.pages.last_mut().unwrap().push(placed);
+        }
+        self.y += height;
+        self.x = 0.0;
+        self.line_size = 0.0;
+    }
+
+    // one word or space, merged into the run before it when the style matches
+    fn piece(&mut self, text: &str, face: Face, size: f32) {
+        let width = face.width(text, size);
+        if self.x > 0.0 && self.x + face.width(text.trim_end(), size) > self.text_width() {
+            self.finish_line();
+            if text.trim().is_empty() {
+                return;
+            }
+        }
+        self.line_size = self.line_size.max(size);
+        if let Some(Placed::Text {
+            size: s,
+            face: f,
+            text: run,
+            ..
+        }) = self.line.last_mut()
+            && *s == size
+            && *f == face
+        {
+            run.push_str(text);
+        } else {
+            self.line.push(Placed::Text {
+                x: self.paper.margin + self.x,
+                y: 0.0,
+                size,
+                face,
+                text: text.to_string(),
+            });
+        }
+        self.x += width;
+    }
+
+    fn words(&mut self, text: &str, face: Face, size: f32) {
+        for word in text.split_inclusive(' ') {
+            // a word longer than the line is cut wherever it has to be
+            let mut word = word;
+            while face.width(word, size) > self.text_width() {
+                let fits = ((self.text_width() / (face.advance() * size)) as usize).max(1);
+                let cut = word.char_indices().nth(fits).map_or(word.len(), |(i, _)| i);
+                self.finish_line();
+                self.piece(&word[..cut], face, size);
+                word = &word[cut..];
+            }
+            self.piece(word, face, size);
+        }
+    }
+
+    fn image(&mut self, label: &str, target: &str) {
+        self.finish_line();
+        let Some((width, height)) = (self.image_size)(target).filter(|(w, h)| *w > 0.0 && *h > 0.0)
+        else {
+            self.words(&format!("[{}]", label), Face::Italic, BODY_SIZE);
+            return;
+        };
+        let scale = (self.text_width() / width)
+            .min(self.text_height() / height)
+            .min(1.0);
+        let (width, height) = (width * scale, height * scale);
+        self.make_room(height);
+        self.pages.last_mut().unwrap().push(Placed::Image {
+            x: self.paper.margin,
+            y: self.y,
+            width,
+            height,
+            target: target.to_string(),
+        });
+        self.y += height + BODY_SIZE * (LEADING - 1.0);
+    }
+}
+
+impl Target for Pages<'_> {
+    fn heading(&mut self, level: usize, title: &str) {
+        self.finish_line();
+        let size = HEADING_SIZES[level.clamp(1, 3) - 1];
+        self.space(size * 0.6);
+        // a heading shouldn't be left alone at the bottom of a page
+        self.make_room(size * LEADING + BODY_SIZE * LEADING * 2.0);
+        self.words(title, Face::Bold, size);
+        self.finish_line();
+        self.line_empty = false;
+    }
+
+    fn code_block(&mut self, _language: &str, body: &str) {
+        self.finish_line();
+        self.space(BODY_SIZE * 0.4);
+        for line in body.split('\n') {
+            if line.is_empty() {
+                self.y += CODE_SIZE * LEADING;
+                continue;
+            }
+            self.words(line, Face::Mono, CODE_SIZE);
+            self.finish_line();
+        }
+        self.space(BODY_SIZE * 0.4);
+        self.line_empty = false;
+    }
+
+    fn inline(&mut self, mdtype: &MarkdownType, text: &str) {
+        self.line_empty = false;
+        match mdtype {
+            MarkdownType::Bold => self.words(strip(text, "**", "**"), Face::Bold, BODY_SIZE),
+            MarkdownType::Italic => self.words(strip(text, "_", "_"), Face::Italic, BODY_SIZE),
+            MarkdownType::Strikethrough => {
+                self.words(strip(text, "~~", "~~"), Face::Regular, BODY_SIZE)
+            }
+            MarkdownType::Monospace => self.words(strip(text, "`", "`"), Face::Mono, BODY_SIZE),
+            MarkdownType::Link => self.words(links::link_text(text), Face::Regular, BODY_SIZE),
+            _ => {
+                let mut last = 0;
+                for embed in links::image_links(text) {
+                    self.words(
+                        &plain_line(&text[last..embed.range.start]),
+                        Face::Regular,
+                        BODY_SIZE,
+                    );
+                    self.image(embed.label, embed.target);
+                    last = embed.range.end;
+                }
+                self.words(&plain_line(&text[last..]), Face::Regular, BODY_SIZE);
+            }
+        }
+    }
+
+    fn line_break(&mut self) {
+        if self.line_empty {
+            // a blank line between paragraphs
+            self.space(BODY_SIZE * 0.6);
+        }
+        self.finish_line();
+        self.line_empty = true;
+    }
+}
+
+// the note laid out on pages for printing, image_size gives an embedded image's size in points
+pub fn to_pages(
+    text: &str,
+    paper: Paper,
+    image_size: &dyn Fn(&str) -> Option<(f32, f32)>,
+) -> Vec<Vec<Placed>> {
+    let mut pages = Pages {
+        paper,
+        image_size,
+        pages: Vec::new(),
+        y: paper.margin,
+        line: Vec::new(),
+        x: 0.0,
+        line_size: 0.0,
+        line_empty: true,
+    };
+    pages.new_page();
+    walk(text, &mut pages);
+    pages.finish_line();
+    return pages.pages;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::{
+        A4, Face, Placed, escape, to_html, to_html_unlinked, to_pages, to_plain, to_terminal,
+    };
+
+    #[test]
+    fn test_to_html() {
+        let text = "---\ntags: [x]\n---\n# Title\nSome **bold** and _it_ ~~not~~\nsame paragraph\n\nsee @@other <b>\n## Code\n```rust\nlet a = 1;\n```\n";
+        assert_eq!(
+            "<h1>Title</h1>\n\
+             <p>Some <strong>bold</strong> and <em>it</em> <del>not</del>\nsame paragraph</p>\n\
+             <p>see <a href=\"other.html\">other</a> &lt;b&gt;</p>\n\
+             <h2>Code</h2>\n\
+             <pre><code class=\"language-rust\">let a = 1;</code></pre>\n",
+            to_html(text)
+        );
+        assert_eq!("", to_html(""));
+        assert_eq!(
+            "<p>see <a href=\"b.html\">Bee</a></p>\n",
+            to_html("see [[b|Bee]]")
+        );
+        assert_eq!(
+            "<p><a href=\"https://x.org\">site</a> and <a href=\"https://y.org\">https://y.org</a></p>\n",
+            to_html("[site](https://x.org) and https://y.org")
+        );
+        assert_eq!(
+            "<p>see Bee and <a href=\"https://x.org\">site</a></p>\n",
+            to_html_unlinked("see [[b|Bee]] and [site](https://x.org)")
+        );
+        assert_eq!(
+            "&lt;a title=&quot;x&quot;&gt;&#39;y&#39; &amp; z",
+            escape("<a title=\"x\">'y' & z")
+        );
+    }
+
+    #[test]
+    fn test_to_terminal() {
+        assert_eq!(
+            "\x1b[1m\x1b[4mTitle\x1b[0m\nSome \x1b[1mbold\x1b[0m `",
+            to_terminal("# Title\nSome **bold** `")
+        );
+        assert_eq!(
+            "| a | b |\n|---|---|\n| 2 | 4 |",
+            to_terminal("| a | b |\n|---|---|\n| 2 | =A2*2 |")
+        );
+    }
+
+    #[test]
+    fn test_to_plain() {
+        assert_eq!(
+            "Title\nSome bold and it, see other\nquoted [x] a link and pic > 1\n\nlet a = 1;",
+            to_plain(
+                "# Title\nSome **bold** and _it_, see @@other\n> quoted [x] [a link](https://x.org) and ![pic](p.png) > 1\n\n```rust\nlet a = 1;\n```"
+            )
+        );
+    }
+
+    fn texts(page: &[Placed]) -> Vec<(f32, Face, &str)> {
+        return page
+            .iter()
+            .filter_map(|p| match p {
+                Placed::Text { y, face, text, .. } => Some((*y, *face, text.as_str())),
+                Placed::Image { .. } => None,
+            })
+            .collect();
+    }
+
+    #[test]
+    fn test_to_pages() {
+        let no_images = |_: &str| None;
+        let pages = to_pages(
+            "# Title\nSome **bold** text\n\n![pic](p.png)",
+            A4,
+            &no_images,
+        );
+        assert_eq!(1, pages.len());
+        let first = texts(&pages[0]);
+        assert_eq!(Face::Bold, first[0].1);
+        assert_eq!("Title", first[0].2);
+        assert_eq!(
+            vec!["Some ", "bold", " text"],
+            first[1..4].iter().map(|t| t.2).collect::<Vec<_>>()
+        );
+        // the words of a line share its baseline, below the heading's
+        assert!(first[1].0 > first[0].0 && first[1].0 == first[3].0);
+        assert_eq!("[pic]", first[4].2);
+
+        // long paragraphs wrap inside the margins and run onto more pages
+        let long = "word ".repeat(3000);
+        let pages = to_pages(&long, A4, &no_images);
+        assert!(pages.len() > 1);
+        for page in &pages {
+            for (y, _, text) in texts(page) {
+                assert!(y <= A4.height - A4.margin);
+                assert!(Face::Regular.width(text.trim_end(), 11.0) <= A4.width - 2.0 * A4.margin);
+            }
+        }
+    }
+
+    #[test]
+    fn test_to_pages_images() {
+        let size = |target: &str| (target == "big.png").then_some((2000.0, 1000.0));
+        let pages = to_pages("before\n![a](big.png)\nafter", A4, &size);
+        let Placed::Image {
+            x, width, height, ..
+        } = &pages[0][1]
+        else {
+            panic!("expected the image");
+        };
+        assert_eq!(A4.margin, *x);
+        assert_eq!(A4.width - 2.0 * A4.margin, *width);
+        assert_eq!(*width / 2.0, *height);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::collab::splice;
+use automerge::transaction::{CommitOptions, Transactable};
+use automerge::{ActorId, AutoCommit, LoadOptions, ObjId, ObjType, ROOT, ReadDoc, TextEncoding};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// the vault as a set of automerge documents, one per note, so two machines can swap them and
+//   end up with the same notes: each document is the note's whole edit history, merging two of
+//   them keeps everything either side wrote
+
+const EXTENSION: &str = "automerge";
+
+// every replica starts a note from the same change, so two machines that both had the note
+//   before they first synced still agree on which text object holds it
+fn fresh() -> AutoCommit {
+    let mut doc = AutoCommit::new_with_encoding(TextEncoding::UnicodeCodePoint)
+        .with_actor(ActorId::from(vec![0; 16]));
+    doc.put_object(ROOT, "text", ObjType::Text)
+        .expect("a new document takes a text object");
+    doc.commit_with(CommitOptions::default().with_time(0));
+    doc.set_actor(ActorId::random());
+    return doc;
+}
+
+fn text_obj(doc: &AutoCommit) -> Option<ObjId> {
+    return match doc.get(ROOT, "text") {
+        Ok(Some((_, obj))) => Some(obj),
+        _ => None,
+    };
+}
+
+fn text_of(doc: &AutoCommit) -> String {
+    return text_obj(doc)
+        .and_then(|obj| doc.text(&obj).ok())
+        .unwrap_or_default();
+}
+
+// note names have folders in them, each document is one flat file
+fn file_name(name: &str) -> String {
+    return format!(
+        "{}.{}",
+        name.replace('%', "%25").replace('/', "%2F"),
+        EXTENSION
+    );
+}
+
+fn note_name(file: &str) -> Option<String> {
+    let stem = file.strip_suffix(&format!(".{}", EXTENSION))?;
+    return Some(stem.replace("%2F", "/").replace("%25", "%"));
+}
+
+// both machines edited a note since they last synced
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub name: String,
+    pub mine: String,
+    pub theirs: String,
+    // what the crdt made of the two, this is what the note holds until someone picks
+    pub merged: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Imported {
+    // nothing new in what came over
+    Unchanged,
+    // the note has new text from the other side
+    Updated(String),
+    Conflict(Conflict),
+}
+
+#[derive(Default)]
+pub struct Replica {
+    notes: BTreeMap<String, AutoCommit>,
+    // documents that need writing out
+    dirty: BTreeSet<String>,
+}
+
+impl Replica {
+    // read back the documents saved in a folder, ones that don't load are started over
+    pub fn load(dir: &Path) -> Self {
+        let mut replica = Self::default();
+        let Ok(entries) = fs::read_dir(dir) else {
+            return replica;
+        };
+        for entry in entries.flatten() {
+            let file = entry.file_name().to_string_lossy().to_string();
+            let Some(name) = note_name(&file) else {
+                continue;
+            };
+            let options = LoadOptions::new().text_encoding(TextEncoding::UnicodeCodePoint);
+            match fs::read(entry.path())
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| {
+                    AutoCommit::load_with_options(&bytes, options).map_err(|e| e.to_string())
+                }) {
+                Ok(doc) => {
+                    replica.notes.insert(name, doc);
+                }
+                Err(e) => tracing::warn!("couldn't load the sync history of {}: {}", name, e),
+            }
+        }
+        return replica;
+    }
+
+    // write out the documents that changed since the last save
+    pub fn save(&mut self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for name in std::mem::take(&mut self.dirty) {
+            if let Some(doc) = self.notes.get_mut(&name) {
+                fs::write(dir.join(file_name(&name)), doc.save())?;
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn text(&self, name: &str) -> Option<String> {
+        return self.notes.get(name).map(text_of);
+    }
+
+    // bring a note's history up to date with its text on disk, returns true if it had changed
+    pub fn record(&mut self, name: &str, text: &str) -> bool {
+        let doc = self.notes.entry(name.to_string()).or_insert_with(fresh);
+        let Some(obj) = text_obj(doc) else {
+            return false;
+        };
+        let Some((range, inserted)) = splice(&text_of(doc), text) else {
+            return false;
+        };
+        if let Err(e) = doc.splice_text(&obj, range.start, range.len() as isize, &inserted) {
+            tracing::warn!("couldn't record a change to {}: {}", name, e);
+            return false;
+        }
+        doc.commit();
+        self.dirty.insert(name.to_string());
+        return true;
+    }
+
+    // the notes with a fingerprint of their histories, to work out which ones differ
+    pub fn manifest(&mut self) -> Vec<(String, Vec<u8>)> {
+        return self
+            .notes
+            .iter_mut()
+            .map(|(name, doc)| {
+                let mut heads = doc.get_heads();
+                heads.sort();
+                let bytes = heads.iter().flat_map(|h| h.0).collect();
+                return (name.clone(), bytes);
+            })
+            .collect();
+    }
+
+    // a note's whole history, to send
+    pub fn export(&mut self, name: &str) -> Option<Vec<u8>> {
+        return self.notes.get_mut(name).map(AutoCommit::save);
+    }
+
+    // merge in a note's history from the other side
+    pub fn import(&mut self, name: &str, bytes: &[u8]) -> Result<Imported, String> {
+        let options = LoadOptions::new().text_encoding(TextEncoding::UnicodeCodePoint);
+        let mut theirs = AutoCommit::load_with_options(bytes, options)
+            .map_err(|e| format!("{} came over damaged: {}", name, e))?;
+        let doc = self.notes.entry(name.to_string()).or_insert_with(fresh);
+
+        let mine = text_of(doc);
+        let mut before = doc.get_heads();
+        let mut remote = theirs.get_heads();
+        doc.merge(&mut theirs)
+            .map_err(|e| format!("couldn't merge {}: {}", name, e))?;
+        let mut after = doc.get_heads();
+        before.sort();
+        remote.sort();
+        after.sort();
+        if after == before {
+            return Ok(Imported::Unchanged);
+        }
+        self.dirty.insert(name.to_string());
+        let merged = text_of(doc);
+        // only their side moved on, or this side had nothing of the note yet
+        if after == remote || mine.is_empty() {
+            return Ok(Imported::Updated(merged));
+        }
+        let theirs = text_of(&theirs);
+        if theirs == mine {
+            // both had the same text from different histories, the merge holds it twice
+            self.record(name, &mine);
+            return Ok(Imported::Unchanged);
+        }
+        return Ok(Imported::Conflict(Conflict {
+            name: name.to_string(),
+            mine,
+            theirs,
+            merged,
+        }));
+    }
+}
+
+// where a vault keeps its sync state, hidden so it isn't listed as notes
+pub fn folder(root: &Path) -> PathBuf {
+    return root.join(".sync");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::replica::{Imported, Replica, file_name, note_name};
+    use automerge::{ROOT, ReadDoc};
+
+    fn sync(a: &mut Replica, b: &mut Replica, name: &str) -> Imported {
+        let bytes = a.export(name).unwrap();
+        return b.import(name, &bytes).unwrap();
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!("work%2F100%25.automerge", file_name("work/100%"));
+        assert_eq!(
+            Some("work/100%".to_string()),
+            note_name("work%2F100%25.automerge")
+        );
+        assert_eq!(None, note_name("identity.der"));
+    }
+
+    #[test]
+    fn test_sync_and_conflict() {
+        let mut laptop = Replica::default();
+        let mut desktop = Replica::default();
+        laptop.record("todo", "# Todo\n- milk\n");
+        assert_eq!(
+            Imported::Updated("# Todo\n- milk\n".to_string()),
+            sync(&mut laptop, &mut desktop, "todo")
+        );
+        assert_eq!(Imported::Unchanged, sync(&mut laptop, &mut desktop, "todo"));
+        assert_eq!(laptop.manifest(), desktop.manifest());
+
+        // one side edits, the other just takes it
+        desktop.record("todo", "# Todo\n- milk\n- eggs\n");
+        assert_eq!(
+            Imported::Updated("# Todo\n- milk\n- eggs\n".to_string()),
+            sync(&mut desktop, &mut laptop, "todo")
+        );
+
+        // both edit while apart
+        laptop.record("todo", "# Todo\n- oat milk\n- eggs\n");
+        desktop.record("todo", "# Todo\n- milk\n- eggs\n- bread\n");
+        let Imported::Conflict(conflict) = sync(&mut desktop, &mut laptop, "todo") else {
+            panic!("expected a conflict");
+        };
+        assert_eq!("# Todo\n- oat milk\n- eggs\n", conflict.mine);
+        assert_eq!("# Todo\n- milk\n- eggs\n- bread\n", conflict.theirs);
+        assert_eq!("# Todo\n- oat milk\n- eggs\n- bread\n", conflict.merged);
+        assert_eq!(Some(conflict.merged), laptop.text("todo"));
+    }
+
+    #[test]
+    fn test_same_note_on_both() {
+        let mut laptop = Replica::default();
+        let mut desktop = Replica::default();
+        laptop.record("index", "hello\n");
+        desktop.record("index", "hello\n");
+        assert_eq!(
+            Imported::Unchanged,
+            sync(&mut laptop, &mut desktop, "index")
+        );
+        assert_eq!(Some("hello\n".to_string()), desktop.text("index"));
+        sync(&mut desktop, &mut laptop, "index");
+        assert_eq!(Some("hello\n".to_string()), laptop.text("index"));
+        // one text object between them, not two fighting over the key
+        let doc = &laptop.notes["index"];
+        assert_eq!(1, doc.get_all(ROOT, "text").unwrap().len());
+    }
+}
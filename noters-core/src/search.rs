@@ -0,0 +1,230 @@
+use regex::Regex;
+use std::ops::Range;
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// byte ranges of whole-word occurrences of `word` in `text`
+pub fn find_word(text: &str, word: &str) -> Vec<Range<usize>> {
+    let mut output = Vec::new();
+    if word.is_empty() {
+        return output;
+    }
+
+    for (start, _) in text.match_indices(word) {
+        let end = start + word.len();
+        let before = text[..start].chars().next_back().is_some_and(is_word);
+        let after = text[end..].chars().next().is_some_and(is_word);
+        if !before && !after {
+            output.push(start..end);
+        }
+    }
+    return output;
+}
+
+// is this selection a single word worth looking for elsewhere
+pub fn is_single_word(text: &str) -> bool {
+    return !text.is_empty() && text.chars().all(is_word);
+}
+
+// the text as a regex that matches just it
+pub fn escape(text: &str) -> String {
+    return regex::escape(text);
+}
+
+// what a replace across notes looks for: the text as typed, or a regex whose groups the
+//   replacement can use as $1 or ${name}
+pub enum Pattern {
+    Literal(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn new(text: &str, regex: bool) -> Result<Self, regex::Error> {
+        if regex {
+            return Ok(Self::Regex(Regex::new(text)?));
+        }
+        return Ok(Self::Literal(text.to_string()));
+    }
+
+    // the text as typed, in any case
+    pub fn ignoring_case(text: &str) -> Self {
+        if text.is_empty() {
+            return Self::Literal(String::new());
+        }
+        let regex = Regex::new(&format!("(?i){}", regex::escape(text)));
+        return regex.map_or_else(|_| Self::Literal(text.to_string()), Self::Regex);
+    }
+}
+
+// an occurrence in a note, with its line (counted from 1) to show around it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    pub range: Range<usize>,
+    pub line: usize,
+    pub context: String,
+}
+
+impl Match {
+    fn new(text: &str, range: Range<usize>) -> Self {
+        let line_start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[range.end..]
+            .find('\n')
+            .map_or(text.len(), |i| range.end + i);
+        return Self {
+            line: text[..range.start].matches('\n').count() + 1,
+            context: text[line_start..line_end].trim().to_string(),
+            range,
+        };
+    }
+}
+
+// everything the pattern matches in the text, empty matches are left out
+pub fn find(text: &str, pattern: &Pattern) -> Vec<Match> {
+    return find_replacements(text, pattern, "")
+        .into_iter()
+        .map(|r| Match {
+            range: r.range,
+            line: r.line,
+            context: r.context,
+        })
+        .collect();
+}
+
+// one occurrence and what it turns into, with its line (counted from 1) to show in a preview
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replacement {
+    pub range: Range<usize>,
+    pub found: String,
+    pub with: String,
+    pub line: usize,
+    pub context: String,
+}
+
+// everything the pattern matches in the text, empty matches are left out
+pub fn find_replacements(text: &str, pattern: &Pattern, replace: &str) -> Vec<Replacement> {
+    let found: Vec<(Range<usize>, String)> = match pattern {
+        Pattern::Literal(literal) if literal.is_empty() => Vec::new(),
+        Pattern::Literal(literal) => text
+            .match_indices(literal.as_str())
+            .map(|(i, _)| (i..i + literal.len(), replace.to_string()))
+            .collect(),
+        Pattern::Regex(regex) => regex
+            .captures_iter(text)
+            .filter_map(|captures| {
+                let whole = captures.get(0)?;
+                if whole.is_empty() {
+                    return None;
+                }
+                let mut with = String::new();
+                captures.expand(replace, &mut with);
+                return Some((whole.range(), with));
+            })
+            .collect(),
+    };
+    return found
+        .into_iter()
+        .map(|(range, with)| {
+            let found = Match::new(text, range);
+            return Replacement {
+                found: text[found.range.clone()].to_string(),
+                with,
+                line: found.line,
+                context: found.context,
+                range: found.range,
+            };
+        })
+        .collect();
+}
+
+// the text with the replacements made, None if it's changed and they no longer line up
+pub fn apply_replacements(text: &str, replacements: &[Replacement]) -> Option<String> {
+    let mut out = text.to_string();
+    let mut sorted: Vec<&Replacement> = replacements.iter().collect();
+    sorted.sort_by_key(|r| std::cmp::Reverse(r.range.start));
+    for replacement in sorted {
+        if out.get(replacement.range.clone()) != Some(replacement.found.as_str()) {
+            return None;
+        }
+        out.replace_range(replacement.range.clone(), &replacement.with);
+    }
+    return Some(out);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::{
+        Pattern, apply_replacements, escape, find, find_replacements, find_word, is_single_word,
+    };
+
+    #[test]
+    fn test_find_word() {
+        assert_eq!(vec![0..3, 17..20], find_word("cat catalog cat_ cat", "cat"));
+        assert_eq!(3, find_word("cat catalog cat cat", "cat").len());
+        assert!(find_word("anything", "").is_empty());
+        assert!(is_single_word("snake_case2"));
+        assert!(!is_single_word("two words"));
+    }
+
+    #[test]
+    fn test_replacements() {
+        let text = "a cat\nthe cat sat\n";
+        let literal = Pattern::new("cat", false).unwrap();
+        let found = find_replacements(text, &literal, "dog");
+        assert_eq!(2, found.len());
+        assert_eq!(
+            (2, 1, "a cat"),
+            (
+                found[0].range.start,
+                found[0].line,
+                found[0].context.as_str()
+            )
+        );
+        assert_eq!(
+            (2, "the cat sat"),
+            (found[1].line, found[1].context.as_str())
+        );
+        assert_eq!(
+            Some("a cat\nthe dog sat\n".to_string()),
+            apply_replacements(text, &found[1..])
+        );
+        assert_eq!(
+            Some("a dog\nthe dog sat\n".to_string()),
+            apply_replacements(text, &found)
+        );
+        // the text moved on since the search
+        assert_eq!(None, apply_replacements("a bat\n", &found[..1]));
+
+        let regex = Pattern::new(r"(\w+) (sat)", true).unwrap();
+        let found = find_replacements(text, &regex, "$2 $1");
+        assert_eq!(
+            vec!["sat cat".to_string()],
+            found.iter().map(|r| r.with.clone()).collect::<Vec<_>>()
+        );
+        assert!(find_replacements(text, &Pattern::new("x*", true).unwrap(), "y").is_empty());
+        assert!(find_replacements(text, &Pattern::new("", false).unwrap(), "y").is_empty());
+        assert!(Pattern::new("(", true).is_err());
+
+        // a selection taken into a regex search matches just itself
+        let escaped = Pattern::new(&escape("cat (1)"), true).unwrap();
+        assert_eq!(1, find("cat (1) cat 1", &escaped).len());
+    }
+
+    #[test]
+    fn test_find() {
+        let text = "Rust\nsome rust code\nnothing\n";
+        let found = find(text, &Pattern::ignoring_case("RUST"));
+        assert_eq!(
+            vec![0..4, 10..14],
+            found.iter().map(|m| m.range.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            (2, "some rust code"),
+            (found[1].line, found[1].context.as_str())
+        );
+        // regex characters are taken as they are
+        assert_eq!(1, find("a.b axb", &Pattern::ignoring_case("a.b")).len());
+        assert!(find(text, &Pattern::ignoring_case("")).is_empty());
+    }
+}
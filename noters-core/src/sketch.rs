@@ -0,0 +1,117 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+// a sketch embedded in a note is an image link on a line of its own, pointing at an svg attachment
+static SKETCH_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^!\[sketch\]\(([^)\n]+\.svg)\)$").unwrap());
+
+static POLYLINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<polyline[^>]*\spoints="([^"]*)""#).unwrap());
+
+static SIZE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<svg[^>]*\swidth="([\d.]+)"[^>]*\sheight="([\d.]+)""#).unwrap());
+
+pub const WIDTH: f32 = 600.0;
+pub const HEIGHT: f32 = 300.0;
+
+// freehand strokes, each a run of points in the sketch's own coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sketch {
+    pub width: f32,
+    pub height: f32,
+    pub strokes: Vec<Vec<(f32, f32)>>,
+}
+
+impl Default for Sketch {
+    fn default() -> Self {
+        Self {
+            width: WIDTH,
+            height: HEIGHT,
+            strokes: Vec::new(),
+        }
+    }
+}
+
+impl Sketch {
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+            self.width, self.height, self.width, self.height
+        );
+        for stroke in &self.strokes {
+            let points: Vec<String> = stroke
+                .iter()
+                .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+                .collect();
+            svg.push_str(&format!(
+                "  <polyline fill=\"none\" stroke=\"currentColor\" stroke-width=\"2\" points=\"{}\"/>\n",
+                points.join(" ")
+            ));
+        }
+        svg.push_str("</svg>\n");
+        return svg;
+    }
+
+    // read back what to_svg wrote, anything other than polylines is ignored
+    pub fn from_svg(svg: &str) -> Self {
+        let mut sketch = Sketch::default();
+        if let Some(caps) = SIZE.captures(svg) {
+            sketch.width = caps[1].parse().unwrap_or(WIDTH);
+            sketch.height = caps[2].parse().unwrap_or(HEIGHT);
+        }
+        for caps in POLYLINE.captures_iter(svg) {
+            let stroke: Vec<(f32, f32)> = caps[1]
+                .split_whitespace()
+                .filter_map(|point| {
+                    let (x, y) = point.split_once(',')?;
+                    return Some((x.parse().ok()?, y.parse().ok()?));
+                })
+                .collect();
+            if !stroke.is_empty() {
+                sketch.strokes.push(stroke);
+            }
+        }
+        return sketch;
+    }
+}
+
+pub fn link(path: &str) -> String {
+    return format!("![sketch]({})", path);
+}
+
+// every sketch link in the text: the byte range of its line and the attachment path
+pub fn links(text: &str) -> Vec<(std::ops::Range<usize>, &str)> {
+    return SKETCH_LINK
+        .captures_iter(text)
+        .map(|caps| (caps.get(0).unwrap().range(), caps.get(1).unwrap().as_str()))
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sketch::{Sketch, link, links};
+
+    #[test]
+    fn test_svg_round_trip() {
+        let sketch = Sketch {
+            width: 200.0,
+            height: 100.0,
+            strokes: vec![vec![(1.0, 2.0), (3.5, 4.0)], vec![(10.0, 10.0)]],
+        };
+        assert_eq!(sketch, Sketch::from_svg(&sketch.to_svg()));
+        assert_eq!(Sketch::default(), Sketch::from_svg("not svg"));
+    }
+
+    #[test]
+    fn test_links() {
+        let text = format!(
+            "# A\n{}\ntext ![sketch](b.svg)\n{}",
+            link("attachments/a.svg"),
+            link("c.svg")
+        );
+        assert_eq!(
+            vec![(4..32, "attachments/a.svg"), (55..71, "c.svg")],
+            links(&text)
+        );
+    }
+}
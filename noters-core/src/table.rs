@@ -0,0 +1,148 @@
+// markdown table helpers
+
+// an empty table with a header row and `rows` body rows
+pub fn empty_table(rows: usize, columns: usize) -> String {
+    let columns = columns.max(1);
+    let blank = format!("{}|\n", "|   ".repeat(columns));
+    let mut output = blank.clone();
+    output += &format!("{}|\n", "|---".repeat(columns));
+    for _ in 0..rows {
+        output += &blank;
+    }
+    return output;
+}
+
+// split one line of delimited text into fields, double quotes can wrap a field holding the delimiter
+fn split_fields(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c == '"' && chars.peek() == Some(&'"') {
+                field.push('"');
+                chars.next();
+            } else if c == '"' {
+                quoted = false;
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' && field.trim().is_empty() {
+            field.clear();
+            quoted = true;
+        } else if c == delimiter {
+            fields.push(field.trim().to_string());
+            field.clear();
+        } else {
+            field.push(c);
+        }
+    }
+    fields.push(field.trim().to_string());
+    return fields;
+}
+
+// the cells of pasted csv or tsv, None unless every line has the same number of columns (at least two)
+pub fn parse_delimited(text: &str) -> Option<Vec<Vec<String>>> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if lines.len() < 2 || lines.iter().any(|line| line.trim_start().starts_with('|')) {
+        return None;
+    }
+    // spreadsheets copy as tabs, so they win over commas
+    for delimiter in ['\t', ','] {
+        let rows: Vec<Vec<String>> = lines
+            .iter()
+            .map(|line| split_fields(line, delimiter))
+            .collect();
+        let columns = rows[0].len();
+        if columns >= 2 && rows.iter().all(|row| row.len() == columns) {
+            return Some(rows);
+        }
+    }
+    return None;
+}
+
+// a markdown table with the first row as the header, columns padded to line up
+pub fn from_rows(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0).max(1);
+    let cell = |row: &Vec<String>, i: usize| -> String {
+        return row.get(i).map_or(String::new(), |c| c.replace('|', "\\|"));
+    };
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            return rows
+                .iter()
+                .map(|row| cell(row, i).chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(3);
+        })
+        .collect();
+    let line = |row: &Vec<String>| -> String {
+        let mut output = String::new();
+        for (i, width) in widths.iter().enumerate() {
+            output += &format!("| {:<width$} ", cell(row, i), width = width);
+        }
+        return output + "|\n";
+    };
+
+    let mut output = String::new();
+    let empty = Vec::new();
+    output += &line(rows.first().unwrap_or(&empty));
+    for width in &widths {
+        output += &format!("|{}", "-".repeat(width + 2));
+    }
+    output += "|\n";
+    for row in rows.iter().skip(1) {
+        output += &line(row);
+    }
+    return output;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::table::{empty_table, from_rows, parse_delimited};
+
+    #[test]
+    fn test_empty_table() {
+        assert_eq!("|   |   |\n|---|---|\n|   |   |\n", empty_table(1, 2));
+        assert_eq!("|   |\n|---|\n", empty_table(0, 0));
+    }
+
+    #[test]
+    fn test_parse_delimited() {
+        let rows = |r: &[&[&str]]| -> Vec<Vec<String>> {
+            return r
+                .iter()
+                .map(|row| row.iter().map(|c| c.to_string()).collect())
+                .collect();
+        };
+        assert_eq!(
+            Some(rows(&[&["name", "qty"], &["apple, red", "3"]])),
+            parse_delimited("name\tqty\r\napple, red\t3\r\n")
+        );
+        assert_eq!(
+            Some(rows(&[&["a", "b"], &["x, y", "say \"hi\""]])),
+            parse_delimited("a,b\n\"x, y\",\"say \"\"hi\"\"\"\n")
+        );
+        assert_eq!(None, parse_delimited("just, one line"));
+        assert_eq!(None, parse_delimited("a, b\nsome prose, with, commas"));
+        assert_eq!(None, parse_delimited("| a | b |\n|---|---|"));
+    }
+
+    #[test]
+    fn test_from_rows() {
+        let rows = vec![
+            vec!["name".to_string(), "q".to_string()],
+            vec!["a|b".to_string(), "12345".to_string()],
+        ];
+        assert_eq!(
+            "| name | q     |\n|------|-------|\n| a\\|b | 12345 |\n",
+            from_rows(&rows)
+        );
+    }
+}
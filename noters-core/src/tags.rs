@@ -0,0 +1,42 @@
+use crate::frontmatter;
+use crate::note::{MarkdownType, highlight_parse};
+
+// the #tags in a note's text along with any listed under `tags:` in its frontmatter,
+//   without their #s, sorted and each only once
+pub fn tags(text: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    for s in highlight_parse(text) {
+        match s.mdtype {
+            MarkdownType::Tag => tags.push(s.text[1..].to_string()),
+            MarkdownType::Frontmatter => {
+                if let Some(listed) = frontmatter::parse(s.text).get("tags") {
+                    tags.extend(
+                        listed
+                            .strings()
+                            .into_iter()
+                            .map(|t| t.trim_start_matches('#').to_string())
+                            .filter(|t| !t.is_empty()),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+    tags.sort();
+    tags.dedup();
+    return tags;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tags::tags;
+
+    #[test]
+    fn test_tags() {
+        assert_eq!(
+            vec!["ideas", "todo", "work"],
+            tags("---\ntags: [work, '#ideas']\n---\n#todo and #work\n```\n#code\n```\n")
+        );
+        assert!(tags("# Heading\nno tags here\n").is_empty());
+    }
+}
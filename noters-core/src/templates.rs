@@ -0,0 +1,59 @@
+use crate::timestamp::{Stamp, StampFormats};
+use crate::vault;
+use chrono::{DateTime, Local};
+use std::path::Path;
+
+// new notes can start from a template, which is any note in the vault's templates folder
+//   {{title}}, {{date}}, {{time}} and {{now}} in it are filled in when the note is made
+
+pub const FOLDER: &str = "templates";
+
+// the templates in a vault, as note names
+pub fn list(root: &Path) -> Vec<String> {
+    let prefix = format!("{}/", FOLDER);
+    return vault::list_notes(root)
+        .into_iter()
+        .filter(|name| name.starts_with(&prefix))
+        .collect();
+}
+
+// the template's text for a note called `title`
+pub fn fill(template: &str, title: &str, formats: &StampFormats, now: &DateTime<Local>) -> String {
+    return template
+        .replace("{{title}}", title)
+        .replace("{{date}}", &formats.format(Stamp::Date, now))
+        .replace("{{time}}", &formats.format(Stamp::Time, now))
+        .replace("{{now}}", &formats.format(Stamp::Timestamp, now));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::templates::{fill, list};
+    use crate::testing::TempDir;
+    use crate::timestamp::StampFormats;
+    use chrono::{Local, TimeZone};
+    use std::fs;
+
+    #[test]
+    fn test_fill() {
+        let now = Local.with_ymd_and_hms(2024, 3, 9, 14, 5, 0).unwrap();
+        assert_eq!(
+            "# Standup\n2024-03-09 14:05\n{{other}}",
+            fill(
+                "# {{title}}\n{{date}} {{time}}\n{{other}}",
+                "Standup",
+                &StampFormats::default(),
+                &now
+            )
+        );
+    }
+
+    #[test]
+    fn test_list() {
+        let root = TempDir::new("templates");
+        fs::create_dir_all(root.join("templates")).unwrap();
+        fs::write(root.join("index.md"), "").unwrap();
+        fs::write(root.join("templates/meeting.md"), "").unwrap();
+        assert_eq!(vec!["templates/meeting"], list(&root));
+    }
+}
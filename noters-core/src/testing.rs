@@ -0,0 +1,61 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// a fresh directory for one test, so tests running side by side (or another checkout's)
+//   never share one, it's removed again when the test is done with it
+pub struct TempDir {
+    path: PathBuf,
+}
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "noters-test-{}-{}-{}",
+            name,
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("the temp directory is writable");
+        return Self { path };
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        return &self.path;
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        return &self.path;
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_temp_dir() {
+        let a = TempDir::new("a");
+        let b = TempDir::new("a");
+        assert_ne!(a.to_path_buf(), b.to_path_buf());
+        assert!(a.is_dir());
+        let path = a.to_path_buf();
+        drop(a);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stamp {
+    Date,
+    Time,
+    Timestamp,
+}
+
+// strftime style formats used when inserting the current date/time
+#[derive(Clone, Debug)]
+pub struct StampFormats {
+    pub date: String,
+    pub time: String,
+    pub timestamp: String,
+}
+
+impl Default for StampFormats {
+    fn default() -> Self {
+        Self {
+            date: "%Y-%m-%d".to_string(),
+            time: "%H:%M".to_string(),
+            timestamp: "%Y-%m-%dT%H:%M:%S%:z".to_string(),
+        }
+    }
+}
+
+// abbreviations typed into a note that expand into a stamp
+const ABBREVIATIONS: [(&str, Stamp); 3] = [
+    (";;date", Stamp::Date),
+    (";;time", Stamp::Time),
+    (";;now", Stamp::Timestamp),
+];
+
+// whether chrono understands every specifier in a format, formatting with one it doesn't panics
+pub fn is_valid(format: &str) -> bool {
+    return !StrftimeItems::new(format).any(|item| matches!(item, Item::Error));
+}
+
+impl StampFormats {
+    pub fn format(&self, stamp: Stamp, now: &DateTime<Local>) -> String {
+        let format = match stamp {
+            Stamp::Date => &self.date,
+            Stamp::Time => &self.time,
+            Stamp::Timestamp => &self.timestamp,
+        };
+        return now.format(format).to_string();
+    }
+
+    // if the text before the cursor ends in an abbreviation,
+    //   returns how many characters to replace and the expansion
+    pub fn expand(&self, before: &str, now: &DateTime<Local>) -> Option<(usize, String)> {
+        for (abbreviation, stamp) in ABBREVIATIONS {
+            if before.ends_with(abbreviation) {
+                return Some((abbreviation.len(), self.format(stamp, now)));
+            }
+        }
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timestamp::{Stamp, StampFormats, is_valid};
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_expand() {
+        let formats = StampFormats::default();
+        let now = Local.with_ymd_and_hms(2024, 7, 1, 9, 5, 0).unwrap();
+        assert_eq!("2024-07-01", formats.format(Stamp::Date, &now));
+        assert_eq!(
+            Some((6, "09:05".to_string())),
+            formats.expand("at ;;time", &now)
+        );
+        assert_eq!(None, formats.expand(";;date ", &now));
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(is_valid("%d.%m.%Y %H:%M"));
+        assert!(is_valid("%%"));
+        assert!(!is_valid("%Q"));
+        assert!(!is_valid("%Y-%"));
+    }
+}
@@ -0,0 +1,71 @@
+// as-you-type replacement of plain punctuation with typographic characters
+
+// is the end of this text inside a code span or fenced block
+pub(crate) fn in_code(before: &str) -> bool {
+    let mut fenced = false;
+    for line in before.lines() {
+        if line.trim_start().starts_with("```") {
+            fenced = !fenced;
+        }
+    }
+    if fenced {
+        return true;
+    }
+
+    let line = match before.rfind('\n') {
+        Some(i) => &before[i + 1..],
+        None => before,
+    };
+    return line.matches('`').count() % 2 == 1;
+}
+
+// work out what typing `typed` after `before` should turn into
+//   returns how many characters before the cursor to remove and the text to insert instead
+pub fn substitute(before: &str, typed: char) -> Option<(usize, String)> {
+    if in_code(before) {
+        return None;
+    }
+
+    let prev = before.chars().next_back();
+    let opening = match prev {
+        None => true,
+        Some(c) => c.is_whitespace() || "([{“‘-–—".contains(c),
+    };
+    let line = match before.rfind('\n') {
+        Some(i) => &before[i + 1..],
+        None => before,
+    };
+
+    match typed {
+        '"' => Some((0, if opening { "“" } else { "”" }.to_string())),
+        '\'' => Some((0, if opening { "‘" } else { "’" }.to_string())),
+        // leave rules and list markers made of dashes at the start of a line alone
+        '-' if line.chars().all(|c| c == '-') => None,
+        '-' if prev == Some('-') => Some((1, "–".to_string())),
+        '-' if prev == Some('–') => Some((1, "—".to_string())),
+        '.' if before.ends_with("..") => Some((2, "…".to_string())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::typography::substitute;
+
+    #[test]
+    fn test_substitute() {
+        assert_eq!(Some((0, "“".to_string())), substitute("say ", '"'));
+        assert_eq!(Some((0, "”".to_string())), substitute("say “hi", '"'));
+        assert_eq!(Some((0, "’".to_string())), substitute("don", '\''));
+        assert_eq!(Some((1, "–".to_string())), substitute("1-", '-'));
+        assert_eq!(Some((1, "—".to_string())), substitute("wait–", '-'));
+        assert_eq!(Some((2, "…".to_string())), substitute("so..", '.'));
+        assert_eq!(None, substitute("a\n--", '-'));
+        assert_eq!(None, substitute("a `code ", '"'));
+        assert_eq!(None, substitute("```rust\nlet x = ", '"'));
+        assert_eq!(
+            Some((0, "“".to_string())),
+            substitute("```\ncode\n```\n", '"')
+        );
+    }
+}
@@ -0,0 +1,414 @@
+use crate::links::{self, LinkSyntax};
+use crate::search::{self, Match, Pattern, Replacement};
+use crate::tags;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// every note under the vault root as a link path (relative, `/` separated, no extension)
+pub fn list_notes(root: &Path) -> Vec<String> {
+    let mut notes = Vec::new();
+    collect_notes(root, root, &mut notes);
+    notes.sort();
+    return notes;
+}
+
+fn collect_notes(root: &Path, dir: &Path, notes: &mut Vec<String>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::warn!("Failed to read {}: {}", dir.display(), e);
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // skip hidden files and folders like .git
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            collect_notes(root, &path, notes);
+        } else if path.extension().is_some_and(|e| e == "md")
+            && let Ok(relative) = path.with_extension("").strip_prefix(root)
+        {
+            let parts: Vec<String> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect();
+            notes.push(parts.join("/"));
+        }
+    }
+}
+
+// every folder under the vault root, relative and `/` separated, hidden ones left out
+pub fn list_folders(root: &Path) -> Vec<String> {
+    let mut folders = Vec::new();
+    collect_folders(root, root, &mut folders);
+    folders.sort();
+    return folders;
+}
+
+fn collect_folders(root: &Path, dir: &Path, folders: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') || !path.is_dir() {
+            continue;
+        }
+        if let Ok(relative) = path.strip_prefix(root) {
+            folders.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+        collect_folders(root, &path, folders);
+    }
+}
+
+// whether a name is only folders and a file under the vault, no .., root or drive in it
+pub fn is_note_name(name: &str) -> bool {
+    let inside = Path::new(name)
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)));
+    return !name.trim().is_empty() && inside;
+}
+
+// where a note goes, as long as its name keeps it inside the vault
+fn note_path(root: &Path, name: &str) -> io::Result<PathBuf> {
+    if !is_note_name(name) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?} isn't a note name", name),
+        ));
+    }
+    return Ok(root.join(format!("{}.md", name)));
+}
+
+// make a new note with some text in it, and any folders it's in
+//   an existing note is never overwritten, nor is anything outside the vault written
+pub fn create_note(root: &Path, name: &str, text: &str) -> io::Result<PathBuf> {
+    let path = note_path(root, name)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    io::Write::write_all(&mut file, text.as_bytes())?;
+    return Ok(path);
+}
+
+// settings that belong to the vault rather than the machine, `key = value` lines
+fn settings_file(root: &Path) -> PathBuf {
+    return root.join(".settings");
+}
+
+pub fn setting(root: &Path, key: &str) -> Option<String> {
+    let text = fs::read_to_string(settings_file(root)).ok()?;
+    return text.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        return (k.trim() == key).then(|| v.trim().to_string());
+    });
+}
+
+// change one setting, keeping the others and any comments as they were
+pub fn set_setting(root: &Path, key: &str, value: &str) -> io::Result<()> {
+    let path = settings_file(root);
+    let text = fs::read_to_string(&path).unwrap_or_default();
+    let mut lines: Vec<String> = Vec::new();
+    let mut found = false;
+    for line in text.lines() {
+        if line.split_once('=').is_some_and(|(k, _)| k.trim() == key) {
+            if !found {
+                lines.push(format!("{} = {}", key, value));
+            }
+            found = true;
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    if !found {
+        lines.push(format!("{} = {}", key, value));
+    }
+    return fs::write(path, lines.join("\n") + "\n");
+}
+
+// the syntax new links are written in
+pub fn link_syntax(root: &Path) -> LinkSyntax {
+    return setting(root, "link-syntax")
+        .and_then(|key| LinkSyntax::from_key(&key))
+        .unwrap_or_default();
+}
+
+// rewrite the links in every note but the one left out, returns the notes that changed
+pub fn convert_links(
+    root: &Path,
+    from: Option<LinkSyntax>,
+    to: LinkSyntax,
+    except: &str,
+) -> io::Result<Vec<String>> {
+    let mut changed = Vec::new();
+    for name in list_notes(root).into_iter().filter(|name| name != except) {
+        let path = root.join(format!("{}.md", name));
+        let text = fs::read_to_string(&path)?;
+        let (converted, count) = links::convert(&text, from, to);
+        if count > 0 {
+            fs::write(&path, converted)?;
+            changed.push(name);
+        }
+    }
+    return Ok(changed);
+}
+
+// move a note to a new name, which can be in another folder, and point the links to it there
+//   the note left out has its links rewritten by whoever has it open
+//   returns where the note is now and how many links changed
+pub fn rename_note(
+    root: &Path,
+    from: &str,
+    to: &str,
+    except: &str,
+) -> io::Result<(PathBuf, usize)> {
+    let old = note_path(root, from)?;
+    let new = note_path(root, to)?;
+    if new.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to),
+        ));
+    }
+    if let Some(parent) = new.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&old, &new)?;
+    let except = if except == from { to } else { except };
+    let mut changed = 0;
+    for name in list_notes(root).into_iter().filter(|name| name != except) {
+        let path = root.join(format!("{}.md", name));
+        let text = fs::read_to_string(&path)?;
+        let (retargeted, count) = links::retarget(&text, from, to);
+        if count > 0 {
+            write_atomic(&path, &retargeted)?;
+            changed += count;
+        }
+    }
+    return Ok((new, changed));
+}
+
+// every match in every note, by note, a note that can't be read is passed over
+pub fn search(root: &Path, pattern: &Pattern) -> Vec<(String, Vec<Match>)> {
+    let mut found = Vec::new();
+    for name in list_notes(root) {
+        let path = root.join(format!("{}.md", name));
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                tracing::warn!("Couldn't search {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let matches = search::find(&text, pattern);
+        if !matches.is_empty() {
+            found.push((name, matches));
+        }
+    }
+    return found;
+}
+
+// every tag in the vault and the notes that have it, both in order
+pub fn tags(root: &Path) -> BTreeMap<String, Vec<String>> {
+    let mut tagged: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for name in list_notes(root) {
+        let path = root.join(format!("{}.md", name));
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                tracing::warn!("Couldn't read the tags of {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        for tag in tags::tags(&text) {
+            tagged.entry(tag).or_default().push(name.clone());
+        }
+    }
+    return tagged;
+}
+
+// what a replace would change in every note but the one left out, by note
+pub fn find_replacements(
+    root: &Path,
+    pattern: &Pattern,
+    replace: &str,
+    except: &str,
+) -> io::Result<Vec<(String, Vec<Replacement>)>> {
+    let mut found = Vec::new();
+    for name in list_notes(root).into_iter().filter(|name| name != except) {
+        let text = fs::read_to_string(root.join(format!("{}.md", name)))?;
+        let replacements = search::find_replacements(&text, pattern, replace);
+        if !replacements.is_empty() {
+            found.push((name, replacements));
+        }
+    }
+    return Ok(found);
+}
+
+// write to a temporary file beside the real one and move it over, so nothing is ever half written
+fn write_atomic(path: &Path, text: &str) -> io::Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = path.with_file_name(format!(".{}.tmp", name));
+    fs::write(&temporary, text)?;
+    return fs::rename(&temporary, path);
+}
+
+// make the chosen replacements in a note, unless it's changed since they were found
+pub fn apply_replacements(root: &Path, name: &str, replacements: &[Replacement]) -> io::Result<()> {
+    let path = root.join(format!("{}.md", name));
+    let text = fs::read_to_string(&path)?;
+    let Some(replaced) = search::apply_replacements(&text, replacements) else {
+        return Err(io::Error::other(format!(
+            "{} changed since the search",
+            name
+        )));
+    };
+    return write_atomic(&path, &replaced);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::links::LinkSyntax;
+    use crate::search::Pattern;
+    use crate::testing::TempDir;
+    use crate::vault::{
+        apply_replacements, convert_links, create_note, find_replacements, is_note_name,
+        link_syntax, list_folders, list_notes, rename_note, search, set_setting, setting, tags,
+    };
+    use std::fs;
+
+    #[test]
+    fn test_list_notes() {
+        let root = TempDir::new("list-notes");
+        fs::create_dir_all(root.join("work/.hidden")).unwrap();
+        fs::write(root.join("index.md"), "").unwrap();
+        fs::write(root.join("work/meetings.md"), "").unwrap();
+        fs::write(root.join("work/notes.txt"), "").unwrap();
+        fs::write(root.join("work/.hidden/secret.md"), "").unwrap();
+
+        assert_eq!(vec!["index", "work/meetings"], list_notes(&root));
+    }
+
+    #[test]
+    fn test_settings_and_conversion() {
+        let root = TempDir::new("link-syntax");
+        assert_eq!(LinkSyntax::At, link_syntax(&root));
+        fs::write(root.join(".settings"), "# vault\nother = 1\n").unwrap();
+        set_setting(&root, "link-syntax", "wiki").unwrap();
+        set_setting(&root, "link-syntax", "markdown").unwrap();
+        assert_eq!(LinkSyntax::Markdown, link_syntax(&root));
+        assert_eq!(Some("1".to_string()), setting(&root, "other"));
+        assert_eq!(
+            "# vault\nother = 1\nlink-syntax = markdown\n",
+            fs::read_to_string(root.join(".settings")).unwrap()
+        );
+
+        fs::write(root.join("a.md"), "see @@b\n").unwrap();
+        fs::write(root.join("b.md"), "no links\n").unwrap();
+        fs::write(root.join("open.md"), "@@a\n").unwrap();
+        assert_eq!(
+            vec!["a".to_string()],
+            convert_links(&root, None, LinkSyntax::Wiki, "open").unwrap()
+        );
+        assert_eq!(
+            "see [[b]]\n",
+            fs::read_to_string(root.join("a.md")).unwrap()
+        );
+        assert_eq!("@@a\n", fs::read_to_string(root.join("open.md")).unwrap());
+    }
+
+    #[test]
+    fn test_replacements() {
+        let root = TempDir::new("vault-replace");
+        fs::create_dir_all(root.join("work")).unwrap();
+        fs::write(root.join("a.md"), "old name, old name\n").unwrap();
+        fs::write(root.join("work/b.md"), "nothing\n").unwrap();
+        fs::write(root.join("open.md"), "old name\n").unwrap();
+
+        let pattern = Pattern::new("old name", false).unwrap();
+        let matches = search(&root, &Pattern::ignoring_case("OLD"));
+        assert_eq!(
+            vec!["a", "open"],
+            matches.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(2, matches[0].1.len());
+        let found = find_replacements(&root, &pattern, "new name", "open").unwrap();
+        assert_eq!(1, found.len());
+        assert_eq!(("a", 2), (found[0].0.as_str(), found[0].1.len()));
+        apply_replacements(&root, "a", &found[0].1[1..]).unwrap();
+        assert_eq!(
+            "old name, new name\n",
+            fs::read_to_string(root.join("a.md")).unwrap()
+        );
+        // the file moved on, so the old matches don't line up any more
+        assert!(apply_replacements(&root, "a", &found[0].1[1..]).is_err());
+        assert_eq!(vec!["a", "open", "work/b"], list_notes(&root));
+    }
+
+    #[test]
+    fn test_create_note() {
+        let root = TempDir::new("create-note");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        let path = create_note(&root, "work/2024/plan", "# Plan\n").unwrap();
+        assert_eq!(root.join("work/2024/plan.md"), path);
+        assert_eq!("# Plan\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(vec!["work", "work/2024"], list_folders(&root));
+
+        assert!(create_note(&root, "work/2024/plan", "again").is_err());
+        assert_eq!("# Plan\n", fs::read_to_string(&path).unwrap());
+        assert!(create_note(&root, "../outside", "").is_err());
+        assert!(create_note(&root, "/etc/nope", "").is_err());
+        assert!(create_note(&root, " ", "").is_err());
+        assert!(is_note_name("work/2024/plan"));
+        assert!(!is_note_name("work/../../plan"));
+        assert!(!is_note_name("./plan"));
+    }
+
+    #[test]
+    fn test_tags() {
+        let root = TempDir::new("tags");
+        fs::create_dir_all(root.join("work")).unwrap();
+        fs::write(root.join("a.md"), "#todo #idea\n").unwrap();
+        fs::write(root.join("work/b.md"), "---\ntags: [todo]\n---\n").unwrap();
+        fs::write(root.join("c.md"), "nothing\n").unwrap();
+
+        let found = tags(&root);
+        assert_eq!(vec!["idea", "todo"], found.keys().collect::<Vec<_>>());
+        assert_eq!(vec!["a", "work/b"], found["todo"]);
+    }
+
+    #[test]
+    fn test_rename_note() {
+        let root = TempDir::new("rename-note");
+        fs::write(root.join("plan.md"), "see @@plan").unwrap();
+        fs::write(root.join("index.md"), "@@plan and [[plan|the plan]]").unwrap();
+        fs::write(root.join("open.md"), "@@plan").unwrap();
+        fs::write(root.join("taken.md"), "").unwrap();
+
+        let (path, changed) = rename_note(&root, "plan", "work/plan", "open").unwrap();
+        assert_eq!(root.join("work/plan.md"), path);
+        assert_eq!(3, changed);
+        assert!(!root.join("plan.md").exists());
+        assert_eq!("see @@work/plan", fs::read_to_string(&path).unwrap());
+        assert_eq!(
+            "@@work/plan and [[work/plan|the plan]]",
+            fs::read_to_string(root.join("index.md")).unwrap()
+        );
+        // the open note is left to the editor
+        assert_eq!("@@plan", fs::read_to_string(root.join("open.md")).unwrap());
+
+        assert!(rename_note(&root, "index", "taken", "").is_err());
+        assert!(rename_note(&root, "index", "../outside", "").is_err());
+        assert!(root.join("index.md").exists());
+    }
+}
@@ -0,0 +1,378 @@
+use crate::files;
+use crate::i18n::trf;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::fs;
+use std::path::Path;
+
+// readable copies of web pages saved as notes under archive/ in the vault
+
+// never part of the readable text
+const SKIPPED: &[&str] = &[
+    "script", "style", "nav", "header", "footer", "aside", "form", "noscript", "iframe", "svg",
+    "button", "template",
+];
+
+// a saved page: the note it went to and what the page called itself
+#[derive(Debug, PartialEq)]
+pub struct Archived {
+    pub url: String,
+    pub name: String,
+    pub title: String,
+}
+
+// an href made absolute against the page's own url
+fn absolute(base: &str, href: &str) -> String {
+    if href.contains("://") || href.starts_with("mailto:") || href.starts_with('#') {
+        return href.to_string();
+    }
+    let scheme_end = base.find("://").map_or(0, |i| i + 3);
+    let scheme = &base[..scheme_end.saturating_sub(3)];
+    let origin_end = base[scheme_end..]
+        .find('/')
+        .map_or(base.len(), |i| scheme_end + i);
+    if let Some(rest) = href.strip_prefix("//") {
+        return format!("{}://{}", scheme, rest);
+    }
+    if href.starts_with('/') {
+        return format!("{}{}", &base[..origin_end], href);
+    }
+    let dir_end = base[origin_end..]
+        .rfind('/')
+        .map_or(base.len(), |i| origin_end + i);
+    return format!("{}/{}", &base[..dir_end], href);
+}
+
+// note names only take letters, numbers and dashes, see the link pattern
+fn slug(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.len() >= 60 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        return "page".to_string();
+    }
+    return slug.to_string();
+}
+
+fn is_skipped(element: &ElementRef) -> bool {
+    return SKIPPED.contains(&element.value().name());
+}
+
+// how much paragraph text sits directly in an element, the biggest is taken as the article
+fn score(element: &ElementRef) -> usize {
+    return element
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|child| child.value().name() == "p")
+        .map(|p| p.text().map(str::len).sum::<usize>())
+        .sum();
+}
+
+// the part of the page worth keeping: an <article> or <main>, else the block with the most paragraphs
+fn content(document: &Html) -> Option<ElementRef<'_>> {
+    for tag in ["article", "main"] {
+        let selector = Selector::parse(tag).unwrap();
+        if let Some(element) = document.select(&selector).next() {
+            return Some(element);
+        }
+    }
+    let blocks = Selector::parse("div, section, td").unwrap();
+    let best = document.select(&blocks).max_by_key(score);
+    if let Some(best) = best.filter(|b| score(b) > 0) {
+        return Some(best);
+    }
+    return document.select(&Selector::parse("body").unwrap()).next();
+}
+
+fn title(document: &Html) -> String {
+    for query in ["title", "h1"] {
+        let selector = Selector::parse(query).unwrap();
+        if let Some(element) = document.select(&selector).next() {
+            let text = element.text().collect::<String>();
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !text.is_empty() {
+                return text;
+            }
+        }
+    }
+    return "Untitled page".to_string();
+}
+
+// markdown written as the html is walked, blocks are kept a blank line apart
+struct Markdown<'a> {
+    base: &'a str,
+    out: String,
+    // nested lists, with the next number for ordered ones
+    lists: Vec<Option<usize>>,
+    quote: usize,
+}
+
+impl Markdown<'_> {
+    fn line_start(&self) -> String {
+        return "> ".repeat(self.quote);
+    }
+
+    fn block(&mut self) {
+        let out = self.out.trim_end_matches([' ', '>']);
+        self.out.truncate(out.len());
+        if self.out.is_empty() {
+            return;
+        }
+        while !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+        self.out += &self.line_start();
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        self.out += &self.line_start();
+    }
+
+    fn text(&mut self, text: &str) {
+        let mut words = text.split_whitespace().peekable();
+        if words.peek().is_none() {
+            if !text.is_empty() && !self.out.ends_with([' ', '\n']) && !self.out.is_empty() {
+                self.out.push(' ');
+            }
+            return;
+        }
+        if text.starts_with(char::is_whitespace) && !self.out.ends_with([' ', '\n']) {
+            self.out.push(' ');
+        }
+        self.out += &words.collect::<Vec<_>>().join(" ");
+        if text.ends_with(char::is_whitespace) {
+            self.out.push(' ');
+        }
+    }
+
+    fn children(&mut self, element: ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.text(text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // the children as markdown on their own, for markers that go around them
+    fn inner(&mut self, element: ElementRef) -> String {
+        let mut inner = Markdown {
+            base: self.base,
+            out: String::new(),
+            lists: Vec::new(),
+            quote: 0,
+        };
+        inner.children(element);
+        return inner.out.trim().to_string();
+    }
+
+    fn wrapped(&mut self, element: ElementRef, marker: &str) {
+        let inner = self.inner(element);
+        if !inner.is_empty() {
+            self.text(" ");
+            self.out += &format!("{}{}{}", marker, inner, marker);
+        }
+    }
+
+    fn element(&mut self, element: ElementRef) {
+        if is_skipped(&element) {
+            return;
+        }
+        let name = element.value().name();
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                // the note's own title is the only level 1 heading
+                let level = if name <= "h2" { 2 } else { 3 };
+                let text = self.inner(element);
+                self.block();
+                self.out += &format!("{} {}", "#".repeat(level), text);
+                self.block();
+            }
+            "p" | "div" | "section" | "article" | "main" | "figure" | "table" | "tr" => {
+                self.block();
+                self.children(element);
+                self.block();
+            }
+            "br" => self.newline(),
+            "hr" => {
+                self.block();
+                self.out += "---";
+                self.block();
+            }
+            "strong" | "b" => self.wrapped(element, "**"),
+            "em" | "i" => self.wrapped(element, "_"),
+            "code" => self.wrapped(element, "`"),
+            "pre" => {
+                let code = element.text().collect::<String>();
+                self.block();
+                self.out += &format!("```\n{}\n```", code.trim_end());
+                self.block();
+            }
+            "a" => {
+                let text = self.inner(element);
+                match element.value().attr("href") {
+                    Some(href) if !text.is_empty() && !href.starts_with("javascript:") => {
+                        self.text(" ");
+                        self.out += &format!("[{}]({})", text, absolute(self.base, href));
+                    }
+                    _ => self.text(&text),
+                }
+            }
+            "img" => {
+                if let Some(src) = element.value().attr("src") {
+                    let alt = element.value().attr("alt").unwrap_or("");
+                    self.out += &format!("![{}]({})", alt.trim(), absolute(self.base, src));
+                }
+            }
+            "ul" | "ol" => {
+                self.block();
+                self.lists.push((name == "ol").then_some(1));
+                self.children(element);
+                self.lists.pop();
+                self.block();
+            }
+            "li" => {
+                if !self.out.is_empty() && !self.out.ends_with('\n') {
+                    self.newline();
+                }
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}.", *n - 1)
+                    }
+                    _ => "-".to_string(),
+                };
+                self.out += &format!("{}{} ", "  ".repeat(depth), marker);
+                self.children(element);
+            }
+            "blockquote" => {
+                self.quote += 1;
+                self.block();
+                self.children(element);
+                self.quote -= 1;
+                self.block();
+            }
+            _ => self.children(element),
+        }
+    }
+}
+
+// the page's title and its readable part as markdown
+pub fn readable(html: &str, url: &str) -> (String, String) {
+    let document = Html::parse_document(html);
+    let title = title(&document);
+    let mut markdown = Markdown {
+        base: url,
+        out: String::new(),
+        lists: Vec::new(),
+        quote: 0,
+    };
+    if let Some(content) = content(&document) {
+        markdown.children(content);
+    }
+    let lines: Vec<&str> = markdown.out.lines().map(str::trim_end).collect();
+    let mut body = lines.join("\n");
+    while body.contains("\n\n\n") {
+        body = body.replace("\n\n\n", "\n\n");
+    }
+    return (title, body.trim().to_string());
+}
+
+fn fetch(url: &str) -> Result<String, String> {
+    let mut response = ureq::get(url)
+        .header("User-Agent", "NoteRs")
+        .call()
+        .map_err(|e| trf("Couldn't download {}: {}", &[&url, &e]))?;
+    return response
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| trf("Couldn't read {}: {}", &[&url, &e]));
+}
+
+// download a page and write its readable copy to a new note, the original url goes in the frontmatter
+pub fn save(root: &Path, url: &str) -> Result<Archived, String> {
+    let html = fetch(url)?;
+    let (title, body) = readable(&html, url);
+
+    let base = format!("archive/{}", slug(&title));
+    let mut name = base.clone();
+    let mut n = 2;
+    while root.join(&name).with_extension("md").exists() {
+        name = format!("{}-{}", base, n);
+        n += 1;
+    }
+    let path = files::resolve(root, &name).map_err(|e| e.to_string())?;
+    let archived = chrono::Local::now().format("%Y-%m-%d %H:%M");
+    let text = format!(
+        "---\nsource: {}\narchived: {}\n---\n# {}\n\n{}\n",
+        url, archived, title, body
+    );
+    fs::write(&path, text).map_err(|e| trf("Couldn't save {}: {}", &[&path.display(), &e]))?;
+    return Ok(Archived {
+        url: url.to_string(),
+        name,
+        title,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::{absolute, readable, slug};
+
+    #[test]
+    fn test_absolute() {
+        let base = "https://example.com/blog/post.html";
+        assert_eq!(
+            "https://example.com/img/a.png",
+            absolute(base, "/img/a.png")
+        );
+        assert_eq!("https://example.com/blog/b.html", absolute(base, "b.html"));
+        assert_eq!(
+            "https://cdn.example.com/x",
+            absolute(base, "//cdn.example.com/x")
+        );
+        assert_eq!("http://other.org/", absolute(base, "http://other.org/"));
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(
+            "why-rust-s-borrow-checker",
+            slug("Why Rust's Borrow Checker?")
+        );
+        assert_eq!("page", slug("¿¡!"));
+    }
+
+    #[test]
+    fn test_readable() {
+        let html = r#"<html><head><title>A Post | Blog</title><script>x()</script></head>
+            <body><nav><a href="/">Home</a></nav>
+            <article><h1>A Post</h1><p>Some <b>bold</b> and a
+              <a href="/more">link</a>.</p>
+            <ul><li>one</li><li>two <em>it</em></li></ul>
+            <pre>let a = 1;</pre>
+            <blockquote><p>quoted</p></blockquote></article>
+            <footer>© me</footer></body></html>"#;
+        let (title, body) = readable(html, "https://blog.example/posts/1");
+        assert_eq!("A Post | Blog", title);
+        assert_eq!(
+            "## A Post\n\nSome **bold** and a [link](https://blog.example/more).\n\n- one\n- two _it_\n\n```\nlet a = 1;\n```\n\n> quoted",
+            body
+        );
+    }
+}
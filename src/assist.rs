@@ -0,0 +1,156 @@
+use crate::i18n::{tr, trf};
+use serde_json::{Value, json};
+use std::time::Duration;
+
+// commands that hand text to a language model behind an openai compatible endpoint
+//   nothing is sent anywhere unless NOTERS_LLM_URL is set, e.g. to http://localhost:11434/v1
+//   NOTERS_LLM_KEY and NOTERS_LLM_MODEL are optional
+
+const DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub url: String,
+    pub key: Option<String>,
+    pub model: String,
+}
+
+impl Config {
+    pub fn from_env() -> Option<Self> {
+        let var = |name| {
+            std::env::var(name)
+                .ok()
+                .filter(|v: &String| !v.trim().is_empty())
+        };
+        return Some(Self {
+            url: var("NOTERS_LLM_URL")?,
+            key: var("NOTERS_LLM_KEY"),
+            model: var("NOTERS_LLM_MODEL").unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+        });
+    }
+
+    fn endpoint(&self) -> String {
+        return format!("{}/chat/completions", self.url.trim_end_matches('/'));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    Summarize,
+    Rewrite,
+    Outline,
+}
+
+impl Command {
+    pub const ALL: [Command; 3] = [Command::Summarize, Command::Rewrite, Command::Outline];
+
+    pub fn label(&self) -> &'static str {
+        return match self {
+            Command::Summarize => tr("Summarize section"),
+            Command::Rewrite => tr("Rewrite selection"),
+            Command::Outline => tr("Generate outline"),
+        };
+    }
+
+    fn instructions(&self) -> &'static str {
+        return match self {
+            Command::Summarize => {
+                "Summarize the following markdown note section in a short paragraph. Reply with the summary only."
+            }
+            Command::Rewrite => {
+                "Rewrite the following text to be clearer and more concise, keeping its meaning and any markdown. Reply with the rewritten text only."
+            }
+            Command::Outline => {
+                "Write an outline of the following notes as a nested markdown bullet list. Reply with the list only."
+            }
+        };
+    }
+}
+
+fn request(config: &Config, command: Command, text: &str) -> Value {
+    return json!({
+        "model": config.model,
+        "messages": [
+            {"role": "system", "content": command.instructions()},
+            {"role": "user", "content": text},
+        ],
+    });
+}
+
+// the text of the first choice in a chat completion
+fn reply(response: &Value) -> Result<String, String> {
+    if let Some(message) = response["error"]["message"].as_str() {
+        return Err(trf("The model endpoint refused: {}", &[&message]));
+    }
+    return response["choices"][0]["message"]["content"]
+        .as_str()
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+        .ok_or_else(|| tr("The model endpoint sent back no text").to_string());
+}
+
+// send the text off and wait for the answer, this blocks so it belongs on a Task
+pub fn run(config: &Config, command: Command, text: &str) -> Result<String, String> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(Duration::from_secs(120)))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let mut call = agent
+        .post(&config.endpoint())
+        .header("Content-Type", "application/json");
+    if let Some(key) = &config.key {
+        call = call.header("Authorization", &format!("Bearer {}", key));
+    }
+    let mut response = call
+        .send(request(config, command, text).to_string())
+        .map_err(|e| trf("Couldn't reach {}: {}", &[&config.url, &e]))?;
+    let status = response.status();
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| trf("Couldn't read the reply from {}: {}", &[&config.url, &e]))?;
+    let value: Value = serde_json::from_str(&body).map_err(|_| {
+        trf(
+            "{} replied with {} and no usable answer",
+            &[&config.url, &status],
+        )
+    })?;
+    return reply(&value);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assist::{Command, Config, reply, request};
+    use serde_json::json;
+
+    #[test]
+    fn test_request() {
+        let config = Config {
+            url: "http://localhost:11434/v1/".to_string(),
+            key: None,
+            model: "llama3".to_string(),
+        };
+        assert_eq!(
+            "http://localhost:11434/v1/chat/completions",
+            config.endpoint()
+        );
+        let body = request(&config, Command::Rewrite, "some text");
+        assert_eq!("llama3", body["model"]);
+        assert_eq!("user", body["messages"][1]["role"]);
+        assert_eq!("some text", body["messages"][1]["content"]);
+    }
+
+    #[test]
+    fn test_reply() {
+        let ok =
+            json!({"choices": [{"message": {"role": "assistant", "content": " A summary.\n"}}]});
+        assert_eq!(Ok("A summary.".to_string()), reply(&ok));
+        let refused = json!({"error": {"message": "bad key"}});
+        assert_eq!(
+            Err("The model endpoint refused: bad key".to_string()),
+            reply(&refused)
+        );
+        assert!(reply(&json!({"choices": []})).is_err());
+    }
+}
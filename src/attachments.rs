@@ -0,0 +1,83 @@
+use chrono::{DateTime, Local};
+use std::fs;
+use std::path::Path;
+
+// images pasted from the clipboard, saved as png files in the vault's attachments folder
+//   egui only passes text pastes along, so the image is read from the clipboard directly
+
+pub const FOLDER: &str = "attachments";
+
+// where a pasted image goes, relative to the vault root, counted up past the first one in a second
+pub fn pasted_name(now: &DateTime<Local>, count: usize) -> String {
+    let stamp = now.format("%Y%m%d-%H%M%S");
+    if count > 1 {
+        return format!("{}/pasted-{}-{}.png", FOLDER, stamp, count);
+    }
+    return format!("{}/pasted-{}.png", FOLDER, stamp);
+}
+
+pub fn image_link(name: &str) -> String {
+    return format!("![]({})", name);
+}
+
+// write rgba pixels out as a png, making the folder if it isn't there yet
+pub fn save_png(path: &Path, width: usize, height: usize, rgba: Vec<u8>) -> Result<(), String> {
+    let image = image::RgbaImage::from_raw(width as u32, height as u32, rgba)
+        .ok_or("the image data doesn't match its size")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    return image.save(path).map_err(|e| e.to_string());
+}
+
+// save whatever image is on the clipboard into the vault, returns its name there
+pub fn paste_image(root: &Path, now: &DateTime<Local>) -> Result<String, String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    let image = clipboard.get_image().map_err(|e| e.to_string())?;
+    let name = (1..)
+        .map(|count| pasted_name(now, count))
+        .find(|name| !root.join(name).exists())
+        .unwrap();
+    save_png(
+        &root.join(&name),
+        image.width,
+        image.height,
+        image.bytes.into_owned(),
+    )?;
+    return Ok(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attachments::{image_link, pasted_name, save_png};
+    use chrono::{Local, TimeZone};
+    use noters_core::testing::TempDir;
+
+    #[test]
+    fn test_save_png() {
+        let root = TempDir::new("paste");
+        let now = Local.with_ymd_and_hms(2024, 3, 5, 14, 7, 9).unwrap();
+        let name = pasted_name(&now, 1);
+        assert_eq!("attachments/pasted-20240305-140709.png", name);
+        assert_eq!(
+            "attachments/pasted-20240305-140709-2.png",
+            pasted_name(&now, 2)
+        );
+        assert_eq!(
+            "![](attachments/pasted-20240305-140709.png)",
+            image_link(&name)
+        );
+
+        save_png(
+            &root.join(&name),
+            2,
+            1,
+            vec![255, 0, 0, 255, 0, 0, 255, 128],
+        )
+        .unwrap();
+        let saved = image::open(root.join(&name)).unwrap().to_rgba8();
+        assert_eq!((2, 1), saved.dimensions());
+        assert_eq!([0, 0, 255, 128], saved.get_pixel(1, 0).0);
+        assert!(save_png(&root.join("short.png"), 2, 2, vec![0; 4]).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+// saving the note by itself once the typing stops
+//   turned on and off, and timed, by `autosave` and `autosave-delay` in config.toml
+
+pub struct Autosave {
+    pub enabled: bool,
+    // how long the note has to sit unchanged before it's written
+    pub delay: Duration,
+    // the first edit since the last save, and the latest one
+    edited: Option<(Instant, Instant)>,
+}
+
+impl Default for Autosave {
+    fn default() -> Self {
+        return Autosave {
+            enabled: true,
+            delay: Duration::from_secs(2),
+            edited: None,
+        };
+    }
+}
+
+impl Autosave {
+    pub fn edited(&mut self, now: Instant) {
+        let first = self.edited.map_or(now, |(first, _)| first);
+        self.edited = Some((first, now));
+    }
+
+    pub fn saved(&mut self) {
+        self.edited = None;
+    }
+
+    // the delay has passed since the last edit, or typing has gone on for a long while without a pause
+    pub fn is_due(&self, now: Instant) -> bool {
+        let Some((first, last)) = self.edited.filter(|_| self.enabled) else {
+            return false;
+        };
+        return now >= last + self.delay || now >= first + self.delay * 10;
+    }
+
+    // how long until the next save is due, for waking up to make it
+    pub fn wait(&self, now: Instant) -> Option<Duration> {
+        let (first, last) = self.edited.filter(|_| self.enabled)?;
+        let due = (last + self.delay).min(first + self.delay * 10);
+        return Some(due.saturating_duration_since(now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::autosave::Autosave;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_autosave() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut autosave = Autosave::default();
+        assert!(!autosave.is_due(at(10_000)));
+
+        autosave.edited(at(0));
+        autosave.edited(at(1_500));
+        assert!(!autosave.is_due(at(3_000)));
+        assert_eq!(Some(Duration::from_millis(500)), autosave.wait(at(3_000)));
+        assert!(autosave.is_due(at(3_500)));
+
+        // typing without a break still gets saved now and then
+        for ms in (0..=20_000).step_by(500) {
+            autosave.edited(at(ms));
+        }
+        assert!(autosave.is_due(at(20_000)));
+
+        autosave.saved();
+        assert!(!autosave.is_due(at(30_000)));
+        assert_eq!(None, autosave.wait(at(30_000)));
+
+        autosave.enabled = false;
+        autosave.edited(at(0));
+        assert!(!autosave.is_due(at(30_000)));
+        assert_eq!(None, autosave.wait(at(0)));
+    }
+}
@@ -0,0 +1,41 @@
+use eframe::egui::TextBuffer;
+use noters_core::note::Note;
+use std::any::TypeId;
+use std::ops::{Deref, DerefMut};
+
+// lets the TextEdit work on a note directly, the editing itself lives in the core crate
+#[derive(Default)]
+pub struct NoteBuffer(pub Note);
+
+impl Deref for NoteBuffer {
+    type Target = Note;
+
+    fn deref(&self) -> &Note {
+        return &self.0;
+    }
+}
+
+impl DerefMut for NoteBuffer {
+    fn deref_mut(&mut self) -> &mut Note {
+        return &mut self.0;
+    }
+}
+
+impl TextBuffer for NoteBuffer {
+    fn is_mutable(&self) -> bool {
+        return self.0.is_mutable();
+    }
+    fn as_str(&self) -> &str {
+        return self.0.as_str();
+    }
+    fn insert_text(&mut self, text: &str, char_index: usize) -> usize {
+        return self.0.insert_text(text, char_index);
+    }
+    fn delete_char_range(&mut self, char_range: std::ops::Range<usize>) {
+        self.0.delete_char_range(char_range);
+    }
+
+    fn type_id(&self) -> TypeId {
+        TypeId::of::<Self>()
+    }
+}
@@ -0,0 +1,202 @@
+use eframe::egui::TextBuffer;
+use std::ops::Range;
+
+// several selections edited at once, as character ranges sorted by position
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Carets {
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl Carets {
+    pub fn single(range: Range<usize>) -> Self {
+        return Self {
+            ranges: vec![range],
+        };
+    }
+
+    // one range per line between two positions, covering the same columns on each
+    pub fn block(text: &str, anchor: usize, head: usize) -> Self {
+        let mut lines = Vec::<(usize, usize)>::new();
+        let mut start = 0;
+        for line in text.split('\n') {
+            let len = line.chars().count();
+            lines.push((start, len));
+            start += len + 1;
+        }
+
+        let locate = |pos: usize| {
+            for (i, (start, len)) in lines.iter().enumerate() {
+                if pos <= start + len {
+                    return (i, pos.saturating_sub(*start));
+                }
+            }
+            return (lines.len() - 1, lines[lines.len() - 1].1);
+        };
+        let (anchor_line, anchor_col) = locate(anchor);
+        let (head_line, head_col) = locate(head);
+        let first_col = anchor_col.min(head_col);
+        let last_col = anchor_col.max(head_col);
+
+        let ranges = lines[anchor_line.min(head_line)..=anchor_line.max(head_line)]
+            .iter()
+            .map(|(start, len)| start + first_col.min(*len)..start + last_col.min(*len))
+            .collect();
+        return Self { ranges };
+    }
+
+    // another caret or selection, taking the place of any it overlaps
+    pub fn add(&mut self, range: Range<usize>) {
+        self.ranges
+            .retain(|r| r.end < range.start || r.start > range.end);
+        let at = self.ranges.partition_point(|r| r.start < range.start);
+        self.ranges.insert(at, range);
+    }
+
+    // the next place the first selection's text shows up after the last one, wrapping around to
+    //   the start of the text, None when it's empty or every place already has a caret
+    pub fn next_occurrence(&self, text: &str) -> Option<Range<usize>> {
+        let byte = |pos: usize| text.char_indices().nth(pos).map_or(text.len(), |(i, _)| i);
+        let first = self.ranges.first()?;
+        let needle = &text[byte(first.start)..byte(first.end)];
+        if needle.is_empty() {
+            return None;
+        }
+        let after = byte(self.ranges.last()?.end);
+        let (before, rest): (Vec<usize>, Vec<usize>) = text
+            .match_indices(needle)
+            .map(|(i, _)| i)
+            .partition(|i| *i < after);
+        let len = needle.chars().count();
+        return rest
+            .into_iter()
+            .chain(before)
+            .map(|i| {
+                let start = text[..i].chars().count();
+                return start..start + len;
+            })
+            .find(|found| !self.ranges.contains(found));
+    }
+
+    // apply an edit at every range, last first so earlier positions stay valid,
+    //   `edit` returns the range it removed and the number of characters it inserted
+    fn edit(
+        &mut self,
+        buffer: &mut dyn TextBuffer,
+        edit: impl Fn(&mut dyn TextBuffer, Range<usize>) -> (Range<usize>, usize),
+    ) {
+        let mut changes = Vec::with_capacity(self.ranges.len());
+        for range in self.ranges.iter().rev() {
+            changes.push(edit(buffer, range.clone()));
+        }
+        changes.reverse();
+
+        let mut shift = 0isize;
+        for (range, (removed, inserted)) in self.ranges.iter_mut().zip(changes) {
+            let caret = (removed.start as isize + shift) as usize + inserted;
+            *range = caret..caret;
+            shift += inserted as isize - removed.len() as isize;
+        }
+    }
+
+    pub fn insert(&mut self, buffer: &mut dyn TextBuffer, text: &str) {
+        let count = text.chars().count();
+        self.edit(buffer, |buffer, range| {
+            if !range.is_empty() {
+                buffer.delete_char_range(range.clone());
+            }
+            buffer.insert_text(text, range.start);
+            (range, count)
+        });
+    }
+
+    // remove each selection, or the character before each caret
+    pub fn backspace(&mut self, buffer: &mut dyn TextBuffer) {
+        self.edit(buffer, |buffer, range| {
+            let range = if range.is_empty() {
+                range.start.saturating_sub(1)..range.end
+            } else {
+                range
+            };
+            if !range.is_empty() {
+                buffer.delete_char_range(range.clone());
+            }
+            (range, 0)
+        });
+    }
+
+    // remove each selection, or the character after each caret
+    pub fn delete(&mut self, buffer: &mut dyn TextBuffer) {
+        let len = buffer.as_str().chars().count();
+        self.edit(buffer, |buffer, range| {
+            let range = if range.is_empty() {
+                range.start..(range.end + 1).min(len)
+            } else {
+                range
+            };
+            if !range.is_empty() {
+                buffer.delete_char_range(range.clone());
+            }
+            (range, 0)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::carets::Carets;
+
+    #[test]
+    fn test_block() {
+        let text = "abcd\nab\nabcdef";
+        assert_eq!(vec![1..3, 6..7, 9..11], Carets::block(text, 1, 11).ranges);
+        assert_eq!(vec![2..3, 7..7], Carets::block(text, 7, 3).ranges);
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut text = "abcd\nab\nabcdef".to_string();
+        let mut carets = Carets::block(&text, 1, 11);
+        carets.insert(&mut text, "XY");
+        assert_eq!("aXYd\naXY\naXYdef", text);
+        assert_eq!(vec![3..3, 8..8, 12..12], carets.ranges);
+
+        carets.backspace(&mut text);
+        assert_eq!("aXd\naX\naXdef", text);
+        assert_eq!(vec![2..2, 6..6, 9..9], carets.ranges);
+
+        let mut text = "abc\nabc".to_string();
+        let mut carets = Carets::block(&text, 0, 4);
+        carets.delete(&mut text);
+        assert_eq!("bc\nbc", text);
+        assert_eq!(vec![0..0, 3..3], carets.ranges);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut carets = Carets {
+            ranges: vec![2..2, 8..10],
+        };
+        carets.add(5..5);
+        assert_eq!(vec![2..2, 5..5, 8..10], carets.ranges);
+        carets.add(9..9);
+        assert_eq!(vec![2..2, 5..5, 9..9], carets.ranges);
+
+        let mut text = "ab ab\nab".to_string();
+        carets.ranges = vec![0..0, 3..3, 6..6];
+        carets.insert(&mut text, "x");
+        assert_eq!("xab xab\nxab", text);
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        let text = "cat dog cat cät cat";
+        let mut carets = Carets::single(8..11);
+        assert_eq!(Some(16..19), carets.next_occurrence(text));
+        carets.add(16..19);
+        // wraps around to the start
+        assert_eq!(Some(0..3), carets.next_occurrence(text));
+        carets.add(0..3);
+        assert_eq!(None, carets.next_occurrence(text));
+        assert_eq!(None, Carets::single(2..2).next_occurrence(text));
+    }
+}
@@ -0,0 +1,54 @@
+use crate::files;
+use noters_core::{encoding, render};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+const CAT_USAGE: &str = "usage: noters cat [--html] <note or file>...";
+
+// a note name under the vault, or a path to any markdown file
+fn find(name: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(name);
+    if path.is_file() {
+        return Some(path);
+    }
+    let path = files::vault_root()?.join(name).with_extension("md");
+    return Some(path).filter(|p| p.is_file());
+}
+
+// `noters cat`, print notes rendered for the terminal or as html, returns the exit code
+//   escapes are left out when the output isn't a terminal
+pub fn cat(args: &[String]) -> i32 {
+    let html = args.iter().any(|a| a == "--html");
+    let names: Vec<&String> = args.iter().filter(|a| !a.starts_with("--")).collect();
+    if names.is_empty() {
+        eprintln!("{}", CAT_USAGE);
+        return 2;
+    }
+
+    let mut code = 0;
+    for name in names {
+        let Some(path) = find(name) else {
+            eprintln!("noters: no note called {}", name);
+            code = 1;
+            continue;
+        };
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("noters: couldn't read {}: {}", path.display(), e);
+                code = 1;
+                continue;
+            }
+        };
+        let (text, _) = encoding::decode(&bytes);
+        if html {
+            print!("{}", render::to_html(&text));
+        } else if io::stdout().is_terminal() {
+            println!("{}", render::to_terminal(&text));
+        } else {
+            println!("{}", text);
+        }
+    }
+    return code;
+}
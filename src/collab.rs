@@ -0,0 +1,344 @@
+use crate::i18n::trf;
+use crate::share::{same_token, token};
+use noters_core::collab::{Message, Session};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::time::Duration;
+
+// the sync provider for collaborative editing: instances talk over tcp, one hosts and the
+//   others join it, and the host passes along whatever one guest sends to the rest
+//   each frame is a 4 byte big endian length and then an encoded message
+//   a guest's first frame is the session token the host made up, anyone without it is dropped
+//   nothing is encrypted, the token and the note can be read by anyone on the network
+
+pub const DEFAULT_PORT: u16 = 7878;
+
+// frames bigger than this are taken as garbage rather than allocated
+const MAX_FRAME: usize = 64 * 1024 * 1024;
+
+// a peer that takes longer than this to take a frame is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn write_frame(stream: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    stream.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    return stream.write_all(bytes);
+}
+
+pub fn read_frame(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("a {} byte frame is too big", len),
+        ));
+    }
+    let mut bytes = vec![0; len];
+    stream.read_exact(&mut bytes)?;
+    return Ok(bytes);
+}
+
+struct Peer {
+    id: u64,
+    // frames for the writer thread, so a stalled peer never holds up the editor
+    outgoing: Sender<Vec<u8>>,
+    // frames from the reader thread, None once the connection is gone
+    incoming: Receiver<Option<Vec<u8>>>,
+}
+
+impl Peer {
+    fn new(id: u64, stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let mut reader = stream.try_clone()?;
+        let (send, incoming) = mpsc::channel();
+        std::thread::spawn(move || {
+            loop {
+                match read_frame(&mut reader) {
+                    Ok(frame) => {
+                        if send.send(Some(frame)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::info!("collaboration peer {} disconnected: {}", id, e);
+                        let _ = send.send(None);
+                        return;
+                    }
+                }
+            }
+        });
+        let mut writer = stream;
+        let (outgoing, frames) = mpsc::channel::<Vec<u8>>();
+        std::thread::spawn(move || {
+            for frame in frames {
+                if let Err(e) = write_frame(&mut writer, &frame) {
+                    tracing::info!("couldn't send to collaboration peer {}: {}", id, e);
+                    // the reader fails too and the peer is dropped with the next sync
+                    let _ = writer.shutdown(Shutdown::Both);
+                    return;
+                }
+            }
+        });
+        return Ok(Self {
+            id,
+            outgoing,
+            incoming,
+        });
+    }
+}
+
+pub struct Collab {
+    name: String,
+    session: Session,
+    peers: Vec<Peer>,
+    // new guests, only while hosting
+    accepting: Option<Receiver<TcpStream>>,
+    next_id: u64,
+    // the caret position last sent out, in full text chars
+    sent_cursor: Option<usize>,
+    // what the status bar says about the session
+    pub description: String,
+    // what guests have to send before they're let in
+    pub token: String,
+}
+
+// read a new guest's token, they get a few seconds to send it
+fn admit(stream: &TcpStream, secret: &str) -> io::Result<bool> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = stream;
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len != secret.len() {
+        return Ok(false);
+    }
+    let mut sent = vec![0; len];
+    reader.read_exact(&mut sent)?;
+    stream.set_read_timeout(None)?;
+    return Ok(same_token(&sent, secret.as_bytes()));
+}
+
+impl Collab {
+    // share the open note with whoever connects to the port and knows the session's token
+    pub fn host(port: u16, text: &str, name: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let secret = token();
+        let (send, accepting) = mpsc::channel();
+        {
+            let secret = secret.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            let send = send.clone();
+                            let secret = secret.clone();
+                            // a slow guest doesn't hold up the others
+                            std::thread::spawn(move || match admit(&stream, &secret) {
+                                Ok(true) => {
+                                    let _ = send.send(stream);
+                                }
+                                Ok(false) => tracing::warn!(
+                                    "dropped a collaborator from {:?} with the wrong token",
+                                    stream.peer_addr()
+                                ),
+                                Err(e) => tracing::info!("couldn't admit a collaborator: {}", e),
+                            });
+                        }
+                        Err(e) => tracing::warn!("couldn't accept a collaborator: {}", e),
+                    }
+                }
+            });
+        }
+        tracing::info!("hosting a collaboration session on port {}", port);
+        return Ok(Self {
+            name: name.to_string(),
+            session: Session::start(text),
+            peers: Vec::new(),
+            accepting: Some(accepting),
+            next_id: 1,
+            sent_cursor: None,
+            description: trf(
+                "Hosting on port {} with token {}, unencrypted",
+                &[&port, &secret],
+            ),
+            token: secret,
+        });
+    }
+
+    // connect to someone's session, the note arrives with the first sync
+    pub fn join(address: &str, secret: &str, name: &str) -> io::Result<Self> {
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such address"))?;
+        let mut stream = TcpStream::connect_timeout(&address, Duration::from_secs(5))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        write_frame(&mut stream, secret.as_bytes())?;
+        let mut session = Session::join();
+        session.add_peer(0);
+        return Ok(Self {
+            name: name.to_string(),
+            session,
+            peers: vec![Peer::new(0, stream)?],
+            accepting: None,
+            next_id: 1,
+            sent_cursor: None,
+            description: trf("Joined {}, unencrypted", &[&address]),
+            token: secret.to_string(),
+        });
+    }
+
+    // true once a guest has the host's note
+    pub fn has_note(&self) -> bool {
+        return self.session.text().is_some();
+    }
+
+    pub fn peer_count(&self) -> usize {
+        return self.peers.len();
+    }
+
+    // the guest lost the host, there's nobody left to sync with
+    pub fn is_closed(&self) -> bool {
+        return self.accepting.is_none() && self.peers.is_empty();
+    }
+
+    // other people's carets as chars in the full text
+    pub fn remote_cursors(&self) -> Vec<(String, usize)> {
+        return self.session.remote_cursors();
+    }
+
+    // queue a message for a peer's writer thread, one that's gone is dropped with the next sync
+    fn send(&mut self, peer: u64, message: &Message) {
+        if let Some(p) = self.peers.iter().find(|p| p.id == peer)
+            && p.outgoing.send(message.encode()).is_err()
+        {
+            tracing::info!("collaboration peer {} stopped taking messages", peer);
+        }
+    }
+
+    // swap changes with everyone, called each frame with the note's full text and caret
+    //   returns the merged text when someone else's edits changed it
+    pub fn sync(&mut self, text: &str, cursor: usize) -> Option<String> {
+        let mut joined = false;
+        while let Some(stream) = self.accepting.as_ref().and_then(|a| a.try_recv().ok()) {
+            let id = self.next_id;
+            self.next_id += 1;
+            match Peer::new(id, stream) {
+                Ok(peer) => {
+                    tracing::info!("collaborator {} joined", id);
+                    self.peers.push(peer);
+                    self.session.add_peer(id);
+                    joined = true;
+                }
+                Err(e) => tracing::warn!("couldn't set up a collaborator: {}", e),
+            }
+        }
+
+        // the caret goes out against the text it was in, before anything remote is merged
+        self.session.local_change(text);
+        if self.has_note() && (joined || self.sent_cursor != Some(cursor)) {
+            self.sent_cursor = Some(cursor);
+            if let Some(message) = self.session.cursor(&self.name, cursor) {
+                let ids: Vec<u64> = self.peers.iter().map(|p| p.id).collect();
+                for id in ids {
+                    self.send(id, &message);
+                }
+            }
+        }
+
+        let mut merged = None;
+        let mut gone = Vec::new();
+        let mut received = Vec::new();
+        for peer in &self.peers {
+            loop {
+                match peer.incoming.try_recv() {
+                    Ok(Some(frame)) => received.push((peer.id, frame)),
+                    Ok(None) | Err(TryRecvError::Disconnected) => {
+                        gone.push(peer.id);
+                        break;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                }
+            }
+        }
+        for (from, frame) in received {
+            let Some(message) = Message::decode(&frame) else {
+                tracing::warn!("unreadable message from collaboration peer {}", from);
+                continue;
+            };
+            if matches!(message, Message::Cursor { .. }) {
+                let others: Vec<u64> = self
+                    .peers
+                    .iter()
+                    .map(|p| p.id)
+                    .filter(|id| *id != from)
+                    .collect();
+                for id in others {
+                    self.send(id, &message);
+                }
+            }
+            merged = self.session.receive(from, message).or(merged);
+        }
+        for id in gone {
+            self.peers.retain(|p| p.id != id);
+            self.session.remove_peer(id);
+        }
+
+        for (peer, message) in self.session.outgoing() {
+            self.send(peer, &message);
+        }
+        return merged;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collab::Collab;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_host_and_join() {
+        // a port that was free a moment ago
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut host = Collab::host(port, "shared text", "host").unwrap();
+        let address = format!("127.0.0.1:{}", port);
+        let mut stranger = Collab::join(&address, "not the token", "stranger").unwrap();
+        let mut guest = Collab::join(&address, &host.token, "guest").unwrap();
+
+        let start = Instant::now();
+        let mut guest_text = None;
+        while guest_text.is_none() && start.elapsed() < Duration::from_secs(5) {
+            host.sync("shared text", 0);
+            guest_text = guest.sync("", 0);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(Some("shared text".to_string()), guest_text);
+        assert_eq!(1, host.peer_count());
+
+        // the host hung up on the one with the wrong token
+        let start = Instant::now();
+        while !stranger.is_closed() && start.elapsed() < Duration::from_secs(5) {
+            assert_eq!(None, stranger.sync("", 0));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(stranger.is_closed());
+        assert!(!stranger.has_note());
+
+        let mut host_text = None;
+        let start = Instant::now();
+        while host_text.is_none() && start.elapsed() < Duration::from_secs(5) {
+            guest.sync("shared text!", 12);
+            host_text = host.sync("shared text", 0);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(Some("shared text!".to_string()), host_text);
+        assert_eq!(vec![("guest".to_string(), 12)], host.remote_cursors());
+    }
+}
@@ -0,0 +1,323 @@
+use crate::i18n::tr;
+use crate::picker::Picker;
+use eframe::egui::{self, KeyboardShortcut};
+
+// everything that can be done from a shortcut or the command palette
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Palette,
+    Save,
+    ToggleFold,
+    NavBack,
+    NavForward,
+    NewNote,
+    RenameNote,
+    OpenNote,
+    InsertLink,
+    Bold,
+    Italic,
+    Code,
+    GoTo,
+    FindInNote,
+    SearchNotes,
+    ReplaceInNotes,
+    Tags,
+    Outline,
+    Settings,
+    Split,
+    Window,
+    Undo,
+    Redo,
+    DemoteHeading,
+    PromoteHeading,
+    ExpandSelection,
+    MoveLinesUp,
+    MoveLinesDown,
+    DuplicateLines,
+    SortLines,
+    UpperCase,
+    LowerCase,
+    TitleCase,
+    PasteImage,
+    InsertDate,
+    InsertTime,
+    InsertTimestamp,
+    SelectNextOccurrence,
+    NextOccurrence,
+    PreviousOccurrence,
+}
+
+struct Registered {
+    command: Command,
+    // an untranslated msgid, the palette translates it when it's shown
+    name: &'static str,
+    shortcuts: Vec<KeyboardShortcut>,
+}
+
+// the commands features have registered, with their names and the keys that run them
+#[derive(Default)]
+pub struct Commands {
+    registered: Vec<Registered>,
+    palette: Option<Picker>,
+}
+
+impl Commands {
+    pub fn register(
+        &mut self,
+        command: Command,
+        name: &'static str,
+        shortcuts: &[KeyboardShortcut],
+    ) {
+        self.registered.push(Registered {
+            command,
+            name,
+            shortcuts: shortcuts.to_vec(),
+        });
+    }
+
+    // the commands whose shortcuts were pressed this frame, their keys are taken so nothing else sees them
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<Command> {
+        // consume_shortcut ignores extra shift/alt, so the longer combinations go first
+        let mut shortcuts: Vec<(Command, KeyboardShortcut)> = self
+            .registered
+            .iter()
+            .flat_map(|r| r.shortcuts.iter().map(|s| (r.command, *s)))
+            .collect();
+        shortcuts.sort_by_key(|(_, s)| std::cmp::Reverse(held(s.modifiers)));
+
+        let mut pressed = Vec::new();
+        for (command, shortcut) in shortcuts {
+            if ctx.input_mut(|i| i.consume_shortcut(&shortcut)) && !pressed.contains(&command) {
+                pressed.push(command);
+            }
+        }
+        return pressed;
+    }
+
+    // put the shortcuts from the config in place of the ones the commands came with, returns what
+    //   couldn't be understood
+    pub fn bind(&mut self, keys: &[(String, Vec<String>)]) -> Vec<String> {
+        let mut errors = Vec::new();
+        for (name, shortcuts) in keys {
+            let Some(registered) = self
+                .registered
+                .iter_mut()
+                .find(|r| config_name(r.command) == *name)
+            else {
+                errors.push(format!("config.toml: there's no command called {}", name));
+                continue;
+            };
+            let mut parsed = Vec::new();
+            for shortcut in shortcuts {
+                match parse_shortcut(shortcut) {
+                    Some(shortcut) => parsed.push(shortcut),
+                    None => errors.push(format!(
+                        "config.toml: {} for {} isn't a shortcut",
+                        shortcut, name
+                    )),
+                }
+            }
+            registered.shortcuts = parsed;
+        }
+        return errors;
+    }
+
+    pub fn open_palette(&mut self) {
+        self.palette = Some(Picker::default());
+    }
+
+    // the palette, returns the command picked from it
+    pub fn palette(&mut self, ctx: &egui::Context) -> Option<Command> {
+        let mut picker = self.palette.take()?;
+        let listed: Vec<&Registered> = self
+            .registered
+            .iter()
+            .filter(|r| r.command != Command::Palette)
+            .collect();
+        let items: Vec<String> = listed
+            .iter()
+            .map(|r| match r.shortcuts.first() {
+                Some(shortcut) => format!("{}  ({})", tr(r.name), ctx.format_shortcut(shortcut)),
+                None => tr(r.name).to_string(),
+            })
+            .collect();
+
+        let mut open = true;
+        let mut chosen = None;
+        egui::Window::new(tr("Command palette"))
+            .open(&mut open)
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 40.0))
+            .show(ctx, |ui| {
+                picker.query_box(ui, tr("Type a command"));
+                chosen = picker.list(ui, &items).map(|i| listed[i].command);
+            });
+
+        if chosen.is_none() && open && !ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.palette = Some(picker);
+        }
+        return chosen;
+    }
+}
+
+// what a command is called in the config's [keys], ToggleFold is toggle-fold
+pub fn config_name(command: Command) -> String {
+    let mut name = String::new();
+    for c in format!("{:?}", command).chars() {
+        if c.is_uppercase() && !name.is_empty() {
+            name.push('-');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    return name;
+}
+
+// a shortcut written like Ctrl+Shift+S or Alt+Left, Ctrl is Cmd on a mac
+pub fn parse_shortcut(text: &str) -> Option<KeyboardShortcut> {
+    let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    // Ctrl++ is the plus key
+    if text.ends_with("++") {
+        parts.truncate(parts.len() - 2);
+        parts.push("+");
+    }
+    let key = egui::Key::from_name(parts.pop()?)?;
+    let mut modifiers = egui::Modifiers::NONE;
+    for part in parts {
+        modifiers |= match part.to_lowercase().as_str() {
+            "ctrl" | "cmd" | "command" => egui::Modifiers::COMMAND,
+            "shift" => egui::Modifiers::SHIFT,
+            "alt" | "option" => egui::Modifiers::ALT,
+            _ => return None,
+        };
+    }
+    return Some(KeyboardShortcut::new(modifiers, key));
+}
+
+fn held(modifiers: egui::Modifiers) -> usize {
+    return [
+        modifiers.alt,
+        modifiers.ctrl,
+        modifiers.shift,
+        modifiers.mac_cmd,
+        modifiers.command,
+    ]
+    .iter()
+    .filter(|m| **m)
+    .count();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::{Command, Commands, config_name, parse_shortcut};
+    use eframe::egui::{self, Key, KeyboardShortcut, Modifiers};
+
+    fn press(commands: &Commands, modifiers: Modifiers, key: Key) -> Vec<Command> {
+        let ctx = egui::Context::default();
+        let input = egui::RawInput {
+            modifiers,
+            events: vec![egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers,
+            }],
+            ..Default::default()
+        };
+        let mut pressed = Vec::new();
+        let _ = ctx.run(input, |ctx| pressed = commands.pressed(ctx));
+        return pressed;
+    }
+
+    #[test]
+    fn test_pressed() {
+        let mut commands = Commands::default();
+        commands.register(
+            Command::NavForward,
+            "Go forward",
+            &[KeyboardShortcut::new(Modifiers::ALT, Key::ArrowRight)],
+        );
+        commands.register(
+            Command::ExpandSelection,
+            "Expand selection",
+            &[KeyboardShortcut::new(
+                Modifiers::ALT | Modifiers::SHIFT,
+                Key::ArrowRight,
+            )],
+        );
+        commands.register(
+            Command::Redo,
+            "Redo",
+            &[
+                KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z),
+                KeyboardShortcut::new(Modifiers::COMMAND, Key::Y),
+            ],
+        );
+        commands.register(Command::Tags, "Browse tags", &[]);
+
+        assert_eq!(
+            press(&commands, Modifiers::ALT, Key::ArrowRight),
+            vec![Command::NavForward]
+        );
+        // the longer combination wins even though it was registered second
+        assert_eq!(
+            press(
+                &commands,
+                Modifiers::ALT | Modifiers::SHIFT,
+                Key::ArrowRight
+            ),
+            vec![Command::ExpandSelection]
+        );
+        assert_eq!(
+            press(&commands, Modifiers::COMMAND, Key::Y),
+            vec![Command::Redo]
+        );
+        assert_eq!(press(&commands, Modifiers::NONE, Key::F3), vec![]);
+    }
+
+    #[test]
+    fn test_bind() {
+        assert_eq!("toggle-fold", config_name(Command::ToggleFold));
+        assert_eq!(
+            Some(KeyboardShortcut::new(
+                Modifiers::COMMAND | Modifiers::SHIFT,
+                Key::S
+            )),
+            parse_shortcut("Ctrl+Shift+S")
+        );
+        assert_eq!(
+            Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::Plus)),
+            parse_shortcut("ctrl++")
+        );
+        assert_eq!(None, parse_shortcut("Hyper+S"));
+        assert_eq!(None, parse_shortcut("Ctrl+"));
+
+        let mut commands = Commands::default();
+        commands.register(
+            Command::Save,
+            "Save",
+            &[KeyboardShortcut::new(Modifiers::COMMAND, Key::S)],
+        );
+        commands.register(Command::Tags, "Browse tags", &[]);
+        let errors = commands.bind(&[
+            ("save".to_string(), vec![]),
+            (
+                "tags".to_string(),
+                vec!["Alt+T".to_string(), "Meh".to_string()],
+            ),
+            ("launch".to_string(), vec!["F1".to_string()]),
+        ]);
+        assert_eq!(
+            vec![
+                "config.toml: Meh for tags isn't a shortcut",
+                "config.toml: there's no command called launch",
+            ],
+            errors
+        );
+        assert_eq!(press(&commands, Modifiers::COMMAND, Key::S), vec![]);
+        assert_eq!(
+            press(&commands, Modifiers::ALT, Key::T),
+            vec![Command::Tags]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use eframe::egui;
+use noters_core::{fuzzy, links, vault};
+use std::ops::Range;
+use std::path::Path;
+
+// the list of notes that pops up under the cursor while an @@ link is typed
+//   the vault is read when the list first shows and again each time it comes back
+
+#[derive(Default)]
+pub struct LinkCompletion {
+    notes: Option<Vec<String>>,
+    // the notes matching what's typed, best first
+    matches: Vec<usize>,
+    selected: usize,
+    // the chars from the @@ up to the cursor while the list is up
+    typed: Option<Range<usize>>,
+    // an @@ the list was closed on, it stays closed until another is typed
+    dismissed: Option<usize>,
+    // where the list was last drawn, clicking it takes focus from the editor
+    rect: Option<egui::Rect>,
+}
+
+impl LinkCompletion {
+    pub fn is_open(&self) -> bool {
+        return self.typed.is_some() && !self.matches.is_empty();
+    }
+
+    // the keys that move through the list, taken before the editor sees them
+    //   returns the note picked with enter or tab
+    pub fn keys(&mut self, ctx: &egui::Context) -> Option<(Range<usize>, String)> {
+        if !self.is_open() {
+            return None;
+        }
+        let pressed = |key| ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key));
+        if pressed(egui::Key::ArrowDown) {
+            self.selected = (self.selected + 1).min(self.matches.len() - 1);
+        }
+        if pressed(egui::Key::ArrowUp) {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        if pressed(egui::Key::Escape) {
+            self.dismissed = self.typed.take().map(|typed| typed.start);
+            return None;
+        }
+        if pressed(egui::Key::Enter) || pressed(egui::Key::Tab) {
+            return self.chosen(self.matches[self.selected]);
+        }
+        return None;
+    }
+
+    // follow the text before the cursor, opening the list on an @@ and filtering it as the path is typed
+    pub fn update(&mut self, root: &Path, text: &str, cursor: usize) {
+        let before: String = text.chars().take(cursor).collect();
+        let Some((start, query)) = links::typed_link(&before) else {
+            self.close();
+            return;
+        };
+        let start = before[..start].chars().count();
+        if self.dismissed == Some(start) {
+            return;
+        }
+        self.dismissed = None;
+        let notes = self.notes.get_or_insert_with(|| vault::list_notes(root));
+        self.matches = fuzzy::rank(query, notes.iter().map(String::as_str))
+            .into_iter()
+            .take(10)
+            .collect();
+        if self.typed.as_ref().is_none_or(|typed| typed.start != start) {
+            self.selected = 0;
+        }
+        self.selected = self.selected.min(self.matches.len().saturating_sub(1));
+        self.typed = Some(start..cursor);
+    }
+
+    pub fn hovered(&self, ctx: &egui::Context) -> bool {
+        let pointer = ctx.input(|i| i.pointer.hover_pos());
+        return self
+            .rect
+            .zip(pointer)
+            .is_some_and(|(rect, pos)| rect.contains(pos));
+    }
+
+    pub fn close(&mut self) {
+        self.typed = None;
+        self.rect = None;
+        self.notes = None;
+        self.dismissed = None;
+    }
+
+    // the note picked, and the @@ and the path typed after it which the link replaces
+    fn chosen(&mut self, index: usize) -> Option<(Range<usize>, String)> {
+        let note = self.notes.as_ref()?.get(index).cloned()?;
+        let typed = self.typed.clone()?;
+        self.close();
+        // a finished @@path still ends at the cursor, the list isn't wanted back for it
+        self.dismissed = Some(typed.start);
+        return Some((typed, note));
+    }
+
+    // the list, just under the caret, returns a note that was clicked
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        caret: egui::Rect,
+    ) -> Option<(Range<usize>, String)> {
+        if !self.is_open() {
+            return None;
+        }
+        let Some(notes) = &self.notes else {
+            return None;
+        };
+        let mut clicked = None;
+        let shown = egui::Area::new(egui::Id::new("link completion"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(caret.left_bottom())
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for (i, m) in self.matches.iter().enumerate() {
+                        if ui
+                            .selectable_label(i == self.selected, &notes[*m])
+                            .clicked()
+                        {
+                            clicked = Some(*m);
+                        }
+                    }
+                });
+            });
+        self.rect = Some(shown.response.rect);
+        return clicked.and_then(|m| self.chosen(m));
+    }
+}
@@ -0,0 +1,428 @@
+use crate::fonts::Fonts;
+use crate::make_color32;
+use crate::theme::Theme;
+use cssparser::{Parser, ParserInput};
+use cssparser_color::Color;
+use eframe::egui::Color32;
+use noters_core::timestamp::{self, StampFormats};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Value};
+
+// how NoteRs is set up, read from ~/.config/noters/config.toml at start
+//   the settings window writes it back, keeping comments and anything it doesn't know about
+//
+//   vault = "~/NoteRs"
+//   font = "~/.fonts/Inter.ttf"
+//   font-size = 14.0
+//   monospace-font-size = 12.0
+//   autosave = true
+//   autosave-delay = 2.0
+//   # or "light", "system" to follow the desktop, or one from [themes]
+//   theme = "dark"
+//   # strftime formats for inserted dates and times
+//   date-format = "%d.%m.%Y"
+//   time-format = "%H:%M"
+//   timestamp-format = "%Y-%m-%d %H:%M"
+//
+//   # in place of the theme's own
+//   [colors]
+//   background = "#1e2030"
+//   foreground = "rgb(202, 211, 248)"
+//
+//   [themes.paper]
+//   base = "light"
+//   link = "teal"
+//
+//   [keys]
+//   save = "Ctrl+S"
+//   redo = ["Ctrl+Shift+Z", "Ctrl+Y"]
+//   toggle-fold = []
+
+#[derive(Clone)]
+pub struct Config {
+    // where the notes are, the default is ~/NoteRs
+    pub vault: Option<PathBuf>,
+    // the name of the theme, built in, from [themes] or "system"
+    pub theme: String,
+    pub themes: Vec<Theme>,
+    // colors from [colors] taking the place of the theme's
+    pub background: Option<Color32>,
+    pub foreground: Option<Color32>,
+    pub fonts: Fonts,
+    pub autosave: bool,
+    // seconds the note has to sit unchanged before autosave writes it
+    pub autosave_delay: f32,
+    pub stamps: StampFormats,
+    // shortcuts for commands, by the command's name, in place of the ones they come with
+    pub keys: Vec<(String, Vec<String>)>,
+    // the file as it was read, the values above are put back into it when it's saved
+    document: DocumentMut,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        return Config {
+            vault: None,
+            theme: "dark".to_string(),
+            themes: Theme::builtin(),
+            background: None,
+            foreground: None,
+            fonts: Fonts::default(),
+            autosave: true,
+            autosave_delay: 2.0,
+            stamps: StampFormats::default(),
+            keys: Vec::new(),
+            document: DocumentMut::new(),
+        };
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::home_dir().map(|home| home.join(".config")))?;
+    return Some(dir.join("noters").join("config.toml"));
+}
+
+// a css color, like #1e2030, rgb(30, 32, 48) or a name
+pub fn parse_color(text: &str) -> Option<Color32> {
+    let mut input = ParserInput::new(text);
+    let color = Color::parse(&mut Parser::new(&mut input)).ok()?;
+    return Some(make_color32(&color)).filter(|c| *c != Color32::TRANSPARENT);
+}
+
+fn color_text(color: Color32) -> String {
+    return format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b());
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/")
+        && let Some(home) = std::env::home_dir()
+    {
+        return home.join(rest);
+    }
+    return PathBuf::from(path);
+}
+
+// change a value while keeping the comment after it
+fn set(item: &mut Item, value: impl Into<Value>) {
+    let mut value = value.into();
+    if let Some(old) = item.as_value() {
+        *value.decor_mut() = old.decor().clone();
+    }
+    *item = Item::Value(value);
+}
+
+impl Config {
+    // the settings in a config file's text, with a message for each one that couldn't be used
+    pub fn parse(text: &str) -> (Self, Vec<String>) {
+        let mut config = Config::default();
+        let mut errors = Vec::new();
+        let document: DocumentMut = match text.parse() {
+            Ok(document) => document,
+            Err(e) => {
+                errors.push(format!("config.toml: {}", e));
+                return (config, errors);
+            }
+        };
+        let mut wrong = |key: &str| errors.push(format!("config.toml: {} isn't valid", key));
+
+        if let Some(item) = document.get("vault") {
+            match item.as_str() {
+                Some(vault) => config.vault = Some(expand_home(vault)),
+                None => wrong("vault"),
+            }
+        }
+        for (key, path) in [
+            ("font", &mut config.fonts.proportional),
+            ("monospace-font", &mut config.fonts.monospace),
+            ("bold-font", &mut config.fonts.bold),
+            ("bold-italic-font", &mut config.fonts.bold_italic),
+        ] {
+            if let Some(item) = document.get(key) {
+                match item.as_str() {
+                    Some(font) => *path = Some(expand_home(font)),
+                    None => wrong(key),
+                }
+            }
+        }
+        for (key, size) in [
+            ("font-size", &mut config.fonts.size),
+            ("monospace-font-size", &mut config.fonts.monospace_size),
+        ] {
+            if let Some(item) = document.get(key) {
+                match item.as_float().or(item.as_integer().map(|i| i as f64)) {
+                    Some(value) if value > 0.0 => *size = value as f32,
+                    _ => wrong(key),
+                }
+            }
+        }
+        if let Some(item) = document.get("autosave") {
+            match item.as_bool() {
+                Some(autosave) => config.autosave = autosave,
+                None => wrong("autosave"),
+            }
+        }
+        if let Some(item) = document.get("autosave-delay") {
+            match item.as_float().or(item.as_integer().map(|i| i as f64)) {
+                Some(delay) if delay >= 0.0 => config.autosave_delay = delay as f32,
+                _ => wrong("autosave-delay"),
+            }
+        }
+        for (key, format) in [
+            ("date-format", &mut config.stamps.date),
+            ("time-format", &mut config.stamps.time),
+            ("timestamp-format", &mut config.stamps.timestamp),
+        ] {
+            if let Some(item) = document.get(key) {
+                match item.as_str().filter(|f| timestamp::is_valid(f)) {
+                    Some(value) => *format = value.to_string(),
+                    None => wrong(key),
+                }
+            }
+        }
+        let colors = document.get("colors");
+        for (key, color) in [
+            ("background", &mut config.background),
+            ("foreground", &mut config.foreground),
+        ] {
+            let Some(item) = colors.and_then(|c| c.get(key)) else {
+                continue;
+            };
+            match item.as_str().and_then(parse_color) {
+                Some(parsed) => *color = Some(parsed),
+                None => wrong(&format!("colors.{}", key)),
+            }
+        }
+        if let Some(item) = document.get("theme") {
+            match item.as_str() {
+                Some(theme) => config.theme = theme.to_string(),
+                None => wrong("theme"),
+            }
+        }
+        let themes = document.get("themes").and_then(Item::as_table_like);
+        for (name, item) in themes.iter().flat_map(|t| t.iter()) {
+            let Some(table) = item.as_table_like() else {
+                wrong(&format!("themes.{}", name));
+                continue;
+            };
+            let base = table.get("base").and_then(Item::as_str).unwrap_or("dark");
+            let Some(mut theme) = config.themes.iter().find(|t| t.name == base).cloned() else {
+                wrong(&format!("themes.{}.base", name));
+                continue;
+            };
+            theme.name = name.to_string();
+            for (key, item) in table.iter().filter(|(key, _)| *key != "base") {
+                let color = item.as_str().and_then(parse_color);
+                if !color.is_some_and(|color| theme.set(key, color)) {
+                    wrong(&format!("themes.{}.{}", name, key));
+                }
+            }
+            config.themes.retain(|t| t.name != name);
+            config.themes.push(theme);
+        }
+        if config.theme != "system" && !config.themes.iter().any(|t| t.name == config.theme) {
+            wrong("theme");
+            config.theme = "dark".to_string();
+        }
+        if let Some(keys) = document.get("keys").and_then(Item::as_table_like) {
+            for (command, item) in keys.iter() {
+                let shortcuts = match (item.as_str(), item.as_array()) {
+                    (Some(shortcut), _) => Some(vec![shortcut.to_string()]),
+                    (_, Some(array)) => array
+                        .iter()
+                        .map(|s| s.as_str().map(str::to_string))
+                        .collect(),
+                    _ => None,
+                };
+                match shortcuts {
+                    Some(shortcuts) => config.keys.push((command.to_string(), shortcuts)),
+                    None => wrong(&format!("keys.{}", command)),
+                }
+            }
+        }
+        config.document = document;
+        return (config, errors);
+    }
+
+    pub fn load(path: &Path) -> (Self, Vec<String>) {
+        return match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (Config::default(), Vec::new()),
+            Err(e) => (
+                Config::default(),
+                vec![format!("{}: {}", path.display(), e)],
+            ),
+        };
+    }
+
+    // the theme picked, with any colors from [colors] in place, `system` is the desktop's if it's known
+    pub fn theme(&self, system: Option<&Theme>) -> Theme {
+        let picked = match self.theme.as_str() {
+            "system" => system,
+            name => self.themes.iter().find(|t| t.name == name),
+        };
+        let mut theme = picked.cloned().unwrap_or_default();
+        if let Some(background) = self.background {
+            theme.background = background;
+        }
+        if let Some(foreground) = self.foreground {
+            theme.text = foreground;
+        }
+        return theme;
+    }
+
+    pub fn write_text(&mut self) -> String {
+        let document = &mut self.document;
+        for (key, path) in [
+            ("vault", &self.vault),
+            ("font", &self.fonts.proportional),
+            ("monospace-font", &self.fonts.monospace),
+            ("bold-font", &self.fonts.bold),
+            ("bold-italic-font", &self.fonts.bold_italic),
+        ] {
+            match path {
+                Some(path) => set(&mut document[key], path.to_string_lossy().to_string()),
+                None => {
+                    document.remove(key);
+                }
+            }
+        }
+        set(&mut document["font-size"], self.fonts.size as f64);
+        set(
+            &mut document["monospace-font-size"],
+            self.fonts.monospace_size as f64,
+        );
+        set(&mut document["autosave"], self.autosave);
+        set(&mut document["autosave-delay"], self.autosave_delay as f64);
+        set(&mut document["theme"], self.theme.clone());
+        if !document.contains_table("colors") {
+            document["colors"] = toml_edit::table();
+        }
+        let colors = document["colors"].as_table_like_mut().unwrap();
+        for (key, color) in [
+            ("background", self.background),
+            ("foreground", self.foreground),
+        ] {
+            match color {
+                Some(color) => set(colors.entry(key).or_insert(Item::None), color_text(color)),
+                None => {
+                    colors.remove(key);
+                }
+            }
+        }
+        if colors.is_empty() {
+            document.remove("colors");
+        }
+        return document.to_string();
+    }
+
+    pub fn save(&mut self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        return fs::write(path, self.write_text());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{Config, parse_color};
+    use crate::theme::Theme;
+    use eframe::egui::Color32;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(Some(Color32::from_rgb(30, 32, 48)), parse_color("#1e2030"));
+        assert_eq!(
+            Some(Color32::from_rgb(202, 211, 248)),
+            parse_color("rgb(202, 211, 248)")
+        );
+        assert_eq!(Some(Color32::from_rgb(255, 0, 0)), parse_color("red"));
+        assert_eq!(None, parse_color("reddish"));
+    }
+
+    #[test]
+    fn test_config() {
+        let text = "# mine\nvault = \"/notes\"\nfont-size = 16\nmonospace-font = \"/fonts/mono.ttf\"\nautosave = \"yes\"\n\n[colors]\nbackground = \"white\" # light\n";
+        let (mut config, errors) = Config::parse(text);
+        assert_eq!(Some(PathBuf::from("/notes")), config.vault);
+        assert_eq!(16.0, config.fonts.size);
+        assert_eq!(12.0, config.fonts.monospace_size);
+        assert_eq!(
+            Some(PathBuf::from("/fonts/mono.ttf")),
+            config.fonts.monospace
+        );
+        assert!(config.autosave);
+        assert_eq!(Some(Color32::WHITE), config.background);
+        assert_eq!(None, config.foreground);
+        assert_eq!(vec!["config.toml: autosave isn't valid"], errors);
+
+        // written back with the comments kept
+        config.autosave = false;
+        config.foreground = Some(Color32::BLACK);
+        let written = config.write_text();
+        assert!(written.starts_with("# mine\n"));
+        assert!(written.contains("# light"));
+        let (config, errors) = Config::parse(&written);
+        assert!(errors.is_empty());
+        assert!(!config.autosave);
+        assert_eq!(
+            Some(PathBuf::from("/fonts/mono.ttf")),
+            config.fonts.monospace
+        );
+        assert_eq!(Some(Color32::BLACK), config.foreground);
+        assert_eq!(Color32::WHITE, config.theme(None).background);
+        assert_eq!(Theme::dark().link, config.theme(None).link);
+
+        let (config, errors) =
+            Config::parse("[keys]\nsave = \"Ctrl+S\"\nredo = [\"Ctrl+Y\", \"F4\"]\nundo = 1\n");
+        assert_eq!(
+            vec![
+                ("save".to_string(), vec!["Ctrl+S".to_string()]),
+                (
+                    "redo".to_string(),
+                    vec!["Ctrl+Y".to_string(), "F4".to_string()]
+                ),
+            ],
+            config.keys
+        );
+        assert_eq!(vec!["config.toml: keys.undo isn't valid"], errors);
+
+        let text = "theme = \"paper\"\n[themes.paper]\nbase = \"light\"\nlink = \"teal\"\nglow = \"red\"\n[themes.ink]\nbase = \"sepia\"\n";
+        let (config, errors) = Config::parse(text);
+        let theme = config.theme(None);
+        assert_eq!("paper", theme.name);
+        assert_eq!(Color32::from_rgb(0, 128, 128), theme.link);
+        assert_eq!(Theme::light().background, theme.background);
+        assert!(!theme.dark);
+        assert_eq!(
+            vec![
+                "config.toml: themes.paper.glow isn't valid",
+                "config.toml: themes.ink.base isn't valid",
+            ],
+            errors
+        );
+        let (config, errors) =
+            Config::parse("date-format = \"%d.%m.%Y\"\ntime-format = \"%H:%Q\"\n");
+        assert_eq!("%d.%m.%Y", config.stamps.date);
+        assert_eq!("%H:%M", config.stamps.time);
+        assert_eq!(vec!["config.toml: time-format isn't valid"], errors);
+
+        let (config, errors) = Config::parse("theme = \"neon\"");
+        assert_eq!(Theme::dark(), config.theme(None));
+        assert_eq!(vec!["config.toml: theme isn't valid"], errors);
+        let (config, errors) = Config::parse("theme = \"system\"");
+        assert!(errors.is_empty());
+        assert_eq!(Theme::dark(), config.theme(None));
+        assert_eq!(Theme::light(), config.theme(Some(&Theme::light())));
+
+        let (config, errors) = Config::parse("vault = [");
+        assert_eq!(None, config.vault);
+        assert_eq!(1, errors.len());
+    }
+}
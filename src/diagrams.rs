@@ -0,0 +1,156 @@
+use crate::files::Task;
+use crate::i18n::trf;
+use eframe::egui;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// fenced ```dot and ```mermaid blocks drawn as pictures under their opening fence
+//   graphviz and mermaid-cli do the drawing, each block is rendered once in the background
+//   and kept as a texture until its source changes
+
+// how tall the row holding a rendered diagram is
+pub const ROW_HEIGHT: f32 = 260.0;
+
+// the languages that can be drawn and the sh command that turns {input} into the png {output}
+const COMMANDS: &[(&str, &str)] = &[
+    ("dot", "dot -Tpng {input} -o {output}"),
+    ("mermaid", "mmdc -q -i {input} -o {output}"),
+];
+
+pub fn can_draw(language: &str) -> bool {
+    return COMMANDS.iter().any(|(name, _)| *name == language);
+}
+
+// identifies a block by what it says, so an edit to it draws it again
+pub fn key(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    return hasher.finish();
+}
+
+// the lines between the fences
+pub fn body(text: &str) -> &str {
+    let open = text.find('\n').map_or(text.len(), |i| i + 1);
+    let close = text.rfind('\n').map_or(text.len(), |i| i + 1).max(open);
+    return &text[open..close];
+}
+
+// single quoted for sh
+fn quote(path: &Path) -> String {
+    return format!("'{}'", path.display().to_string().replace('\'', r"'\''"));
+}
+
+fn render(language: &str, source: &str, key: u64) -> Result<egui::ColorImage, String> {
+    let Some((_, command)) = COMMANDS.iter().find(|(name, _)| *name == language) else {
+        return Err(trf("Can't draw {} diagrams", &[&language]));
+    };
+    let file = |extension: &str| -> PathBuf {
+        let name = format!(
+            "noters-diagram-{}-{}.{}",
+            std::process::id(),
+            key,
+            extension
+        );
+        return std::env::temp_dir().join(name);
+    };
+    let (input, output) = (file(language), file("png"));
+    std::fs::write(&input, source).map_err(|e| e.to_string())?;
+    let command = command
+        .replace("{input}", &quote(&input))
+        .replace("{output}", &quote(&output));
+    tracing::info!("drawing a diagram with `{}`", command);
+    let ran = Command::new("sh").arg("-c").arg(&command).output();
+    let _ = std::fs::remove_file(&input);
+    let ran = ran.map_err(|e| trf("Couldn't run `{}`: {}", &[&command, &e]))?;
+    if !ran.status.success() {
+        let stderr = String::from_utf8_lossy(&ran.stderr);
+        return Err(trf(
+            "Drawing the diagram failed ({}): {}",
+            &[&ran.status, &stderr.trim()],
+        ));
+    }
+    let image = image::open(&output).map_err(|e| e.to_string());
+    let _ = std::fs::remove_file(&output);
+    let image = image?.to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    return Ok(egui::ColorImage::from_rgba_unmultiplied(
+        size,
+        image.as_raw(),
+    ));
+}
+
+enum Drawing {
+    Rendering(Task<Result<egui::ColorImage, String>>),
+    Done(egui::TextureHandle),
+    Failed(String),
+}
+
+pub enum Shown<'a> {
+    Rendering,
+    Done(&'a egui::TextureHandle),
+    Failed(&'a str),
+}
+
+#[derive(Default)]
+pub struct Diagrams {
+    drawings: HashMap<u64, Drawing>,
+    // blocks switched to showing their source instead
+    pub sources: HashSet<u64>,
+}
+
+impl Diagrams {
+    pub fn toggle(&mut self, key: u64) {
+        if !self.sources.remove(&key) {
+            self.sources.insert(key);
+        }
+    }
+
+    // the picture for a block, starting it off the first time it's asked for
+    pub fn get(&mut self, ctx: &egui::Context, language: &str, text: &str) -> Shown<'_> {
+        let key = key(text);
+        let drawing = self.drawings.entry(key).or_insert_with(|| {
+            let (language, source) = (language.to_string(), body(text).to_string());
+            return Drawing::Rendering(Task::spawn(move || render(&language, &source, key)));
+        });
+        if let Drawing::Rendering(task) = drawing {
+            match task.poll() {
+                Some(Ok(image)) => {
+                    let texture = ctx.load_texture("diagram", image, egui::TextureOptions::LINEAR);
+                    *drawing = Drawing::Done(texture);
+                }
+                Some(Err(e)) => {
+                    tracing::warn!("{}", e);
+                    *drawing = Drawing::Failed(e);
+                }
+                None => ctx.request_repaint_after(std::time::Duration::from_millis(100)),
+            }
+        }
+        return match drawing {
+            Drawing::Rendering(_) => Shown::Rendering,
+            Drawing::Done(texture) => Shown::Done(texture),
+            Drawing::Failed(e) => Shown::Failed(e),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagrams::{body, can_draw, key};
+
+    #[test]
+    fn test_blocks() {
+        assert!(can_draw("dot"));
+        assert!(can_draw("mermaid"));
+        assert!(!can_draw("rust"));
+        assert_eq!(
+            "digraph { a -> b }\n",
+            body("```dot\ndigraph { a -> b }\n```")
+        );
+        assert_eq!("", body("```dot\n```"));
+        assert_eq!(key("```dot\na\n```"), key("```dot\na\n```"));
+        assert_ne!(key("```dot\na\n```"), key("```dot\nb\n```"));
+    }
+}
@@ -0,0 +1,30 @@
+use crate::i18n::trf;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+// what went wrong opening or saving a note, along with the path involved
+#[derive(Debug)]
+pub enum FileError {
+    CreateDir(PathBuf, io::Error),
+    Read(PathBuf, io::Error),
+    Write(PathBuf, io::Error),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::CreateDir(path, e) => {
+                f.write_str(&trf("Couldn't create {}: {}", &[&path.display(), e]))
+            }
+            FileError::Read(path, e) => {
+                f.write_str(&trf("Couldn't open {}: {}", &[&path.display(), e]))
+            }
+            FileError::Write(path, e) => {
+                f.write_str(&trf("Couldn't save {}: {}", &[&path.display(), e]))
+            }
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+// things that happen to notes, published as they happen and handed out once per frame
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    NoteOpened(PathBuf),
+    // the first save of a note that wasn't on disk yet, followed by its NoteSaved
+    NoteCreated(PathBuf),
+    NoteSaved(PathBuf),
+    NoteModified,
+    LinkFollowed(String),
+    // the path of the section in the note's tree
+    SectionToggled(Vec<usize>),
+}
+
+// anything that wants to hear about events, like hooks and plugins
+pub trait Subscriber {
+    fn notify(&mut self, event: &Event);
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    queue: Vec<Event>,
+}
+
+impl EventBus {
+    // several edits in a frame only need to be heard about once
+    pub fn publish(&mut self, event: Event) {
+        if event == Event::NoteModified && self.queue.last() == Some(&event) {
+            return;
+        }
+        tracing::debug!("event {:?}", event);
+        self.queue.push(event);
+    }
+
+    // everything published since last time, in order
+    pub fn take(&mut self) -> Vec<Event> {
+        return std::mem::take(&mut self.queue);
+    }
+
+    // hand out everything published since last time to each subscriber, in order
+    pub fn dispatch(&mut self, subscribers: &mut [&mut dyn Subscriber]) -> Vec<Event> {
+        let events = self.take();
+        for event in &events {
+            for subscriber in subscribers.iter_mut() {
+                subscriber.notify(event);
+            }
+        }
+        return events;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{Event, EventBus, Subscriber};
+    use std::path::PathBuf;
+
+    #[derive(Default)]
+    struct Recorder(Vec<Event>);
+
+    impl Subscriber for Recorder {
+        fn notify(&mut self, event: &Event) {
+            self.0.push(event.clone());
+        }
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let mut bus = EventBus::default();
+        bus.publish(Event::NoteOpened(PathBuf::from("a.md")));
+        bus.publish(Event::NoteModified);
+        bus.publish(Event::NoteModified);
+        bus.publish(Event::SectionToggled(vec![0]));
+        bus.publish(Event::NoteModified);
+
+        let mut first = Recorder::default();
+        let mut second = Recorder::default();
+        let events = bus.dispatch(&mut [&mut first, &mut second]);
+        let expected = vec![
+            Event::NoteOpened(PathBuf::from("a.md")),
+            Event::NoteModified,
+            Event::SectionToggled(vec![0]),
+            Event::NoteModified,
+        ];
+        assert_eq!(expected, first.0);
+        assert_eq!(expected, second.0);
+        assert_eq!(expected, events);
+        assert!(bus.take().is_empty());
+    }
+}
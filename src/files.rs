@@ -0,0 +1,365 @@
+use crate::error::FileError;
+use noters_core::encoding::{self, Encoding};
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::SystemTime;
+
+// a note as it was on disk: its text, how it was encoded and enough to tell if it changes later
+#[derive(Clone, Debug, PartialEq)]
+pub struct Disk {
+    pub text: String,
+    pub encoding: Encoding,
+    pub fingerprint: Fingerprint,
+}
+
+// a note read off the UI thread: where it resolved to, and what was there if it exists yet
+pub type Loaded = Result<(PathBuf, Option<Disk>), FileError>;
+
+// what a finished save did
+#[derive(Debug, PartialEq)]
+pub enum Saved {
+    Written(PathBuf),
+    // someone else changed the file since it was loaded, nothing was written
+    Conflict(PathBuf, Disk),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl Fingerprint {
+    fn of(path: &Path, bytes: &[u8]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        return Self {
+            modified: fs::metadata(path).and_then(|m| m.modified()).ok(),
+            len: bytes.len() as u64,
+            hash: hasher.finish(),
+        };
+    }
+}
+
+fn read(path: &Path) -> Result<Disk, FileError> {
+    let bytes = fs::read(path).map_err(|e| FileError::Read(path.to_path_buf(), e))?;
+    let (text, encoding) = encoding::decode(&bytes);
+    if encoding.lossy {
+        tracing::warn!("{} isn't valid utf-8, decoded lossily", path.display());
+    }
+    tracing::debug!("{:?}", encoding);
+    return Ok(Disk {
+        text,
+        encoding,
+        fingerprint: Fingerprint::of(path, &bytes),
+    });
+}
+
+// what's on disk now if it isn't what was last loaded or written
+//   the modified time and size are checked first so an untouched file isn't read again
+//   a file that's been deleted since doesn't count, saving just puts it back
+fn changed(path: &Path, known: Option<Fingerprint>) -> Result<Option<Disk>, FileError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    if let Some(known) = known {
+        let metadata = fs::metadata(path).ok();
+        let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+        let len = metadata.as_ref().map(|m| m.len());
+        if modified.is_some() && modified == known.modified && len == Some(known.len) {
+            return Ok(None);
+        }
+    }
+    let disk = read(path)?;
+    if Some(disk.fingerprint.hash) == known.map(|k| k.hash) {
+        return Ok(None);
+    }
+    return Ok(Some(disk));
+}
+
+// work running on its own thread, checked on each frame until it's done
+pub struct Task<T> {
+    result: Receiver<T>,
+}
+
+impl<T: Send + 'static> Task<T> {
+    pub fn spawn(work: impl FnOnce() -> T + Send + 'static) -> Self {
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(work());
+        });
+        return Self { result };
+    }
+
+    pub fn poll(&self) -> Option<T> {
+        return self.result.try_recv().ok();
+    }
+}
+
+// where notes live, NoteRs in the home directory
+pub fn vault_root() -> Option<PathBuf> {
+    return std::env::home_dir().map(|home| home.join("NoteRs"));
+}
+
+// turn a note name into a file under the vault, making any folders on the way
+//   an existing folder opens its index.md, anything else gets a .md extension
+pub fn resolve(root: &Path, name: &str) -> Result<PathBuf, FileError> {
+    let mut path = root.to_path_buf();
+
+    let binding = PathBuf::from(name);
+    let mut iter = binding.components().peekable();
+    while let Some(item) = iter.next() {
+        let is_last = iter.peek().is_none();
+        path.push(item);
+
+        if is_last {
+            if path.exists() {
+                if path.is_dir() {
+                    tracing::debug!("exists already, add index.md");
+                    path.push("index.md");
+                } else {
+                    tracing::debug!("path is a file");
+                }
+            } else {
+                tracing::debug!("not a folder, add .md");
+                path.set_extension("md");
+            }
+        } else {
+            fs::create_dir_all(path.as_path())
+                .map_err(|e| FileError::CreateDir(path.clone(), e))?;
+        }
+    }
+    return Ok(path);
+}
+
+pub fn load(root: &Path, name: &str) -> Loaded {
+    let path = resolve(root, name)?;
+    tracing::info!("opening {}", path.display());
+    if !path.exists() {
+        return Ok((path, None));
+    }
+    let disk = read(&path)?;
+    tracing::trace!("`\n{}\n`", disk.text);
+    return Ok((path, Some(disk)));
+}
+
+enum Job {
+    // what the file held when it was opened, saves check against it
+    Track(PathBuf, Option<Fingerprint>),
+    // force writes even if the file changed underneath
+    Save {
+        path: PathBuf,
+        text: String,
+        encoding: Encoding,
+        force: bool,
+    },
+}
+
+// writes notes one after another on a single thread, so a later save can't land before an earlier one
+//   the thread remembers what each tracked file looked like, and won't overwrite someone else's changes
+#[derive(Default)]
+pub struct Saver {
+    jobs: Option<Sender<Job>>,
+    done: Option<Receiver<Result<Saved, FileError>>>,
+    pending: usize,
+}
+
+fn write(
+    known: &mut HashMap<PathBuf, Option<Fingerprint>>,
+    path: PathBuf,
+    text: &str,
+    encoding: &Encoding,
+    force: bool,
+) -> Result<Saved, FileError> {
+    if !force
+        && let Some(fingerprint) = known.get(&path)
+        && let Some(disk) = changed(&path, *fingerprint)?
+    {
+        tracing::warn!("{} changed on disk, not saving", path.display());
+        return Ok(Saved::Conflict(path, disk));
+    }
+    tracing::info!("writing {}", path.display());
+    tracing::trace!("`\n{}\n`", text);
+    let bytes = encoding::encode(text, encoding);
+    fs::write(path.as_path(), &bytes).map_err(|e| FileError::Write(path.clone(), e))?;
+    known.insert(path.clone(), Some(Fingerprint::of(&path, &bytes)));
+    return Ok(Saved::Written(path));
+}
+
+fn run(queue: Receiver<Job>, finished: Sender<Result<Saved, FileError>>) {
+    let mut known: HashMap<PathBuf, Option<Fingerprint>> = HashMap::new();
+    for job in queue {
+        let (path, text, encoding, force) = match job {
+            Job::Track(path, fingerprint) => {
+                known.insert(path, fingerprint);
+                continue;
+            }
+            Job::Save {
+                path,
+                text,
+                encoding,
+                force,
+            } => (path, text, encoding, force),
+        };
+        let result = write(&mut known, path, &text, &encoding, force);
+        if finished.send(result).is_err() {
+            return;
+        }
+    }
+}
+
+impl Saver {
+    fn send(&mut self, job: Job) -> bool {
+        let jobs = self.jobs.get_or_insert_with(|| {
+            let (jobs, queue) = mpsc::channel();
+            let (finished, done) = mpsc::channel();
+            thread::spawn(move || run(queue, finished));
+            self.done = Some(done);
+            return jobs;
+        });
+        return jobs.send(job).is_ok();
+    }
+
+    pub fn track(&mut self, path: PathBuf, fingerprint: Option<Fingerprint>) {
+        self.send(Job::Track(path, fingerprint));
+    }
+
+    pub fn save(&mut self, path: PathBuf, text: String, encoding: Encoding, force: bool) {
+        let job = Job::Save {
+            path,
+            text,
+            encoding,
+            force,
+        };
+        if self.send(job) {
+            self.pending += 1;
+        }
+    }
+
+    pub fn is_saving(&self) -> bool {
+        return self.pending > 0;
+    }
+
+    // block until every save asked for so far is done, for when the app is closing
+    pub fn wait(&mut self) -> Vec<Result<Saved, FileError>> {
+        let Some(done) = &self.done else {
+            return Vec::new();
+        };
+        let mut finished = Vec::new();
+        while self.pending > 0
+            && let Ok(result) = done.recv()
+        {
+            self.pending -= 1;
+            finished.push(result);
+        }
+        return finished;
+    }
+
+    // the saves that finished since last time
+    pub fn poll(&mut self) -> Vec<Result<Saved, FileError>> {
+        let Some(done) = &self.done else {
+            return Vec::new();
+        };
+        let finished: Vec<_> = done.try_iter().collect();
+        self.pending -= finished.len();
+        return finished;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::files::{Saved, Saver, load, resolve};
+    use noters_core::encoding::{Encoding, LineEnding};
+    use noters_core::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_resolve() {
+        let root = TempDir::new("resolve");
+        fs::create_dir_all(root.join("folder")).unwrap();
+
+        assert_eq!(root.join("note.md"), resolve(&root, "note").unwrap());
+        assert_eq!(
+            root.join("folder/index.md"),
+            resolve(&root, "folder").unwrap()
+        );
+        assert_eq!(
+            root.join("new/deep.md"),
+            resolve(&root, "new/deep").unwrap()
+        );
+        assert!(root.join("new").is_dir());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let root = TempDir::new("save-and-load");
+
+        assert_eq!((root.join("a.md"), None), load(&root, "a").unwrap());
+
+        let crlf = Encoding {
+            line_ending: LineEnding::Crlf,
+            ..Default::default()
+        };
+        let mut saver = Saver::default();
+        saver.track(root.join("a.md"), None);
+        saver.save(
+            root.join("a.md"),
+            "first".to_string(),
+            Encoding::default(),
+            false,
+        );
+        saver.save(root.join("a.md"), "second\nline".to_string(), crlf, false);
+        assert_eq!(
+            vec![
+                Saved::Written(root.join("a.md")),
+                Saved::Written(root.join("a.md"))
+            ],
+            finish(&mut saver)
+        );
+        assert_eq!(
+            b"second\r\nline",
+            fs::read(root.join("a.md")).unwrap().as_slice()
+        );
+        let (path, disk) = load(&root, "a").unwrap();
+        let disk = disk.unwrap();
+        assert_eq!(root.join("a.md"), path);
+        assert_eq!(("second\nline", crlf), (disk.text.as_str(), disk.encoding));
+    }
+
+    #[test]
+    fn test_conflict() {
+        let root = TempDir::new("conflict");
+        fs::write(root.join("a.md"), "loaded").unwrap();
+
+        let (path, disk) = load(&root, "a").unwrap();
+        let mut saver = Saver::default();
+        saver.track(path.clone(), disk.map(|d| d.fingerprint));
+
+        fs::write(&path, "changed elsewhere").unwrap();
+        saver.save(path.clone(), "mine".to_string(), Encoding::default(), false);
+        let saved = finish(&mut saver);
+        let [Saved::Conflict(conflict, disk)] = saved.as_slice() else {
+            panic!("expected a conflict, got {:?}", saved);
+        };
+        assert_eq!(&path, conflict);
+        assert_eq!("changed elsewhere", disk.text);
+        assert_eq!("changed elsewhere", fs::read_to_string(&path).unwrap());
+
+        saver.save(path.clone(), "mine".to_string(), Encoding::default(), true);
+        assert_eq!(vec![Saved::Written(path.clone())], finish(&mut saver));
+        assert_eq!("mine", fs::read_to_string(&path).unwrap());
+    }
+
+    fn finish(saver: &mut Saver) -> Vec<Saved> {
+        let mut saved = Vec::new();
+        while saver.is_saving() {
+            saved.extend(saver.poll().into_iter().map(Result::unwrap));
+        }
+        return saved;
+    }
+}
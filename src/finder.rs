@@ -0,0 +1,142 @@
+use crate::files::Task;
+use crate::i18n::{tr, trf};
+use eframe::egui;
+use noters_core::search::{Match, Pattern};
+use noters_core::vault;
+use std::path::{Path, PathBuf};
+
+// searching the text of every note in the vault, in a panel down the side
+//   each search runs on its own thread, a query typed while one runs waits for it to finish
+
+// past this many matches the rest aren't listed
+const MAX_MATCHES: usize = 500;
+
+type Found = (String, Vec<(String, Vec<Match>)>);
+
+#[derive(Default)]
+pub struct Finder {
+    pub open: bool,
+    focus: bool,
+    query: String,
+    // the query the results are for
+    searched: String,
+    results: Vec<(String, Vec<Match>)>,
+    searching: Option<Task<Found>>,
+}
+
+fn search(root: PathBuf, query: String) -> Found {
+    let mut found = vault::search(&root, &Pattern::ignoring_case(&query));
+    let mut left = MAX_MATCHES;
+    found.retain_mut(|(_, matches)| {
+        matches.truncate(left);
+        left -= matches.len();
+        return !matches.is_empty();
+    });
+    return (query, found);
+}
+
+impl Finder {
+    pub fn show_panel(&mut self) {
+        self.open = true;
+        self.focus = true;
+    }
+
+    fn poll(&mut self, ctx: &egui::Context, root: &Path) {
+        if let Some(searching) = &self.searching {
+            match searching.poll() {
+                Some((query, results)) => {
+                    self.searched = query;
+                    self.results = results;
+                    self.searching = None;
+                }
+                None => ctx.request_repaint_after(std::time::Duration::from_millis(50)),
+            }
+        }
+        if self.searching.is_none() && self.query != self.searched {
+            if self.query.trim().is_empty() {
+                self.searched = self.query.clone();
+                self.results.clear();
+                return;
+            }
+            let (root, query) = (root.to_path_buf(), self.query.clone());
+            self.searching = Some(Task::spawn(move || search(root, query)));
+        }
+    }
+
+    // the panel, returns the note and byte offset of a match that was clicked
+    pub fn show(&mut self, ctx: &egui::Context, root: &Path) -> Option<(String, usize)> {
+        if !self.open {
+            return None;
+        }
+        self.poll(ctx, root);
+        let mut chosen = None;
+        egui::SidePanel::left("search")
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(tr("Search"));
+                    if ui.small_button("✕").on_hover_text(tr("Close")).clicked() {
+                        self.open = false;
+                    }
+                });
+                let query = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text(tr("Text in any note"))
+                        .desired_width(f32::INFINITY),
+                );
+                if std::mem::take(&mut self.focus) {
+                    query.request_focus();
+                }
+                if self.searching.is_some() {
+                    ui.spinner();
+                } else if !self.searched.trim().is_empty() {
+                    let count: usize = self.results.iter().map(|(_, m)| m.len()).sum();
+                    ui.weak(if count >= MAX_MATCHES {
+                        trf("The first {} matches", &[&count])
+                    } else {
+                        trf("{} matches in {} notes", &[&count, &self.results.len()])
+                    });
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (note, matches) in &self.results {
+                        egui::CollapsingHeader::new(note.as_str())
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                for found in matches {
+                                    let label = trf("{}: {}", &[&found.line, &found.context]);
+                                    if ui.selectable_label(false, label).clicked() {
+                                        chosen = Some((note.clone(), found.range.start));
+                                    }
+                                }
+                            });
+                    }
+                });
+            });
+        return chosen;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::finder::{MAX_MATCHES, search};
+    use noters_core::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_search() {
+        let root = TempDir::new("finder");
+        fs::write(root.join("a.md"), "x\n".repeat(MAX_MATCHES - 1)).unwrap();
+        fs::write(root.join("b.md"), "x x x\n").unwrap();
+        fs::write(root.join("c.md"), "x\n").unwrap();
+
+        let (query, found) = search(root.to_path_buf(), "X".to_string());
+        assert_eq!("X", query);
+        // the limit cuts into b and leaves c out
+        assert_eq!(
+            vec!["a", "b"],
+            found.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(1, found[1].1.len());
+    }
+}
@@ -0,0 +1,144 @@
+use noters_core::note::{Heading, Note};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// which sections of each note were folded, so they stay that way across switching notes and restarts
+//   kept in .folds at the vault root, a line per folded section of `path<TAB>title<TAB>title…`
+//   naming the headings down to it, which outlasts edits that would shift a position or index
+
+#[derive(Default)]
+pub struct Folds {
+    root: PathBuf,
+    folds: HashMap<String, Vec<Vec<String>>>,
+}
+
+fn file(root: &Path) -> PathBuf {
+    return root.join(".folds");
+}
+
+// each heading's title along with those of the headings it's under
+fn chains(headings: &[Heading]) -> Vec<Vec<String>> {
+    let mut above: Vec<&Heading> = Vec::new();
+    let mut chains = Vec::new();
+    for heading in headings {
+        while above.last().is_some_and(|h| h.level >= heading.level) {
+            above.pop();
+        }
+        above.push(heading);
+        chains.push(above.iter().map(|h| h.title.clone()).collect());
+    }
+    return chains;
+}
+
+impl Folds {
+    pub fn load(root: &Path) -> Self {
+        let text = fs::read_to_string(file(root)).unwrap_or_default();
+        let mut folds: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+        for line in text.lines() {
+            let mut parts = line.split('\t');
+            let Some(path) = parts.next().filter(|p| !p.is_empty()) else {
+                continue;
+            };
+            let chain: Vec<String> = parts.map(str::to_string).collect();
+            if !chain.is_empty() {
+                folds.entry(path.to_string()).or_default().push(chain);
+            }
+        }
+        return Self {
+            root: root.to_path_buf(),
+            folds,
+        };
+    }
+
+    fn key(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        return relative.to_string_lossy().to_string();
+    }
+
+    // note which of a note's sections are folded and write it all out
+    pub fn remember(&mut self, path: &Path, note: &Note) {
+        let headings = note.headings();
+        let folded: Vec<Vec<String>> = chains(&headings)
+            .into_iter()
+            .zip(&headings)
+            .filter(|(_, h)| !h.expanded)
+            .map(|(chain, _)| chain)
+            .collect();
+        let key = self.key(path);
+        if self.folds.get(&key).map_or(&[][..], Vec::as_slice) == folded.as_slice() {
+            return;
+        }
+        if folded.is_empty() {
+            self.folds.remove(&key);
+        } else {
+            self.folds.insert(key, folded);
+        }
+
+        let mut paths: Vec<&String> = self.folds.keys().collect();
+        paths.sort();
+        let mut lines = Vec::new();
+        for path in paths {
+            for chain in &self.folds[path] {
+                lines.push(format!("{}\t{}", path, chain.join("\t")));
+            }
+        }
+        if let Err(e) = fs::write(file(&self.root), lines.join("\n") + "\n") {
+            tracing::warn!("Couldn't save which sections are folded: {}", e);
+        }
+    }
+
+    // fold a freshly opened note's sections the way they were left
+    pub fn restore(&self, path: &Path, note: &mut Note) {
+        let Some(folded) = self.folds.get(&self.key(path)) else {
+            return;
+        };
+        let headings = note.headings();
+        let paths: Vec<Vec<usize>> = chains(&headings)
+            .into_iter()
+            .zip(headings)
+            .filter(|(chain, _)| folded.contains(chain))
+            .map(|(_, h)| h.path)
+            .collect();
+        note.collapse(&paths);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::folds::Folds;
+    use noters_core::note::Note;
+    use noters_core::testing::TempDir;
+
+    fn folded(note: &Note) -> Vec<String> {
+        return note
+            .headings()
+            .into_iter()
+            .filter(|h| !h.expanded)
+            .map(|h| h.title)
+            .collect();
+    }
+
+    #[test]
+    fn test_folds() {
+        let root = TempDir::new("folds");
+        let text = "# A\n## Notes\na\n# B\n## Notes\nb\n";
+        let mut note = Note::new(text.to_string());
+        let second = note.headings()[3].path.clone();
+        note.toggle(&second);
+        let mut folds = Folds::load(&root);
+        folds.remember(&root.join("a.md"), &note);
+
+        // the same title under another heading stays open
+        let mut note = Note::new(text.to_string());
+        Folds::load(&root).restore(&root.join("a.md"), &mut note);
+        assert_eq!(vec!["Notes"], folded(&note));
+        assert!(note.headings()[1].expanded);
+
+        // unfolding everything forgets the note
+        let mut note = Note::new(text.to_string());
+        folds.remember(&root.join("a.md"), &note);
+        Folds::load(&root).restore(&root.join("a.md"), &mut note);
+        assert!(folded(&note).is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+use eframe::egui::{self, FontData, FontDefinitions, FontFamily, FontId};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+// the fonts notes are written in, set in config.toml
+//
+//   font = "~/.fonts/Inter.ttf"
+//   font-size = 14.0
+//   monospace-font = "~/.fonts/JetBrainsMono.ttf"
+//   monospace-font-size = 12.0
+//   bold-font = "~/.fonts/Inter-Bold.ttf"
+//   bold-italic-font = "~/.fonts/Inter-BoldItalic.ttf"
+//
+//   a font file goes in front of egui's own, which still cover whatever it's missing, bold text
+//   takes a bold font from the system when none is set, and bold italic is bold slanted without one
+
+// the families bold and bold italic text are drawn in
+const BOLD: &str = "bold";
+const BOLD_ITALIC: &str = "bold italic";
+
+// where the usual systems keep a bold and a bold italic sans
+const SYSTEM_BOLD: [&str; 4] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans-Bold.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Bold.ttf",
+    "C:\\Windows\\Fonts\\arialbd.ttf",
+];
+const SYSTEM_BOLD_ITALIC: [&str; 4] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans-BoldOblique.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans-BoldOblique.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Bold Italic.ttf",
+    "C:\\Windows\\Fonts\\arialbi.ttf",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fonts {
+    pub proportional: Option<PathBuf>,
+    pub monospace: Option<PathBuf>,
+    pub size: f32,
+    pub monospace_size: f32,
+    pub bold: Option<PathBuf>,
+    pub bold_italic: Option<PathBuf>,
+    // a bold italic font was loaded, otherwise the bold one gets slanted
+    has_bold_italic: bool,
+}
+
+impl Default for Fonts {
+    fn default() -> Self {
+        return Fonts {
+            proportional: None,
+            monospace: None,
+            size: 14.0,
+            monospace_size: 12.0,
+            bold: None,
+            bold_italic: None,
+            has_bold_italic: false,
+        };
+    }
+}
+
+fn put_first(
+    families: &mut BTreeMap<FontFamily, Vec<String>>,
+    family: FontFamily,
+    name: Option<String>,
+) {
+    if let Some(name) = name {
+        families.entry(family).or_default().insert(0, name);
+    }
+}
+
+impl Fonts {
+    pub fn body(&self) -> FontId {
+        return FontId::proportional(self.size);
+    }
+
+    pub fn monospace(&self) -> FontId {
+        return FontId::monospace(self.monospace_size);
+    }
+
+    pub fn bold(&self) -> FontId {
+        return FontId::new(self.size, FontFamily::Name(BOLD.into()));
+    }
+
+    // the font for bold italic text, and whether it needs slanting to look it
+    pub fn bold_italic(&self) -> (FontId, bool) {
+        let font_id = FontId::new(self.size, FontFamily::Name(BOLD_ITALIC.into()));
+        return (font_id, !self.has_bold_italic);
+    }
+
+    // a heading's size and the height of its line, in proportion to the body text
+    pub fn heading(&self, level: usize) -> (FontId, f32) {
+        let scale = match level {
+            1 => 32.0 / 14.0,
+            2 => 24.0 / 14.0,
+            _ => 16.0 / 14.0,
+        };
+        let size = self.size * scale;
+        return (FontId::proportional(size), size + 4.0);
+    }
+
+    // put the font files in place, returns the ones that couldn't be read
+    pub fn load(&mut self, ctx: &egui::Context) -> Vec<String> {
+        let mut definitions = FontDefinitions::default();
+        let mut errors = Vec::new();
+        let mut read = |path: Option<&PathBuf>| {
+            let path = path?;
+            match std::fs::read(path) {
+                Ok(data) => {
+                    let name = path.display().to_string();
+                    let data = Arc::new(FontData::from_owned(data));
+                    definitions.font_data.insert(name.clone(), data);
+                    return Some(name);
+                }
+                Err(e) => {
+                    errors.push(format!("{}: {}", path.display(), e));
+                    return None;
+                }
+            }
+        };
+        let system = |paths: [&str; 4]| {
+            return paths.iter().map(PathBuf::from).find(|p| p.exists());
+        };
+        let proportional = read(self.proportional.as_ref());
+        let monospace = read(self.monospace.as_ref());
+        let bold = read(self.bold.clone().or_else(|| system(SYSTEM_BOLD)).as_ref());
+        let bold_italic = read(
+            self.bold_italic
+                .clone()
+                .or_else(|| system(SYSTEM_BOLD_ITALIC))
+                .as_ref(),
+        );
+        self.has_bold_italic = bold_italic.is_some();
+
+        let families = &mut definitions.families;
+        put_first(families, FontFamily::Proportional, proportional);
+        put_first(families, FontFamily::Monospace, monospace);
+        // bold and bold italic fall back on the regular fonts for what they don't have
+        let regular = families[&FontFamily::Proportional].clone();
+        for name in [BOLD, BOLD_ITALIC] {
+            families.insert(FontFamily::Name(name.into()), regular.clone());
+        }
+        put_first(families, FontFamily::Name(BOLD.into()), bold.clone());
+        // slanted bold is the next best thing to bold italic
+        put_first(
+            families,
+            FontFamily::Name(BOLD_ITALIC.into()),
+            bold_italic.or(bold),
+        );
+        ctx.set_fonts(definitions);
+        return errors;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fonts::Fonts;
+    use eframe::egui::{self, FontFamily};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_fonts() {
+        let fonts = Fonts {
+            size: 28.0,
+            ..Default::default()
+        };
+        assert_eq!(64.0, fonts.heading(1).0.size);
+        assert_eq!(36.0, fonts.heading(3).1);
+        assert_eq!(12.0, fonts.monospace().size);
+        assert_eq!(FontFamily::Name("bold".into()), fonts.bold().family);
+        assert_eq!(28.0, fonts.bold().size);
+
+        let mut fonts = Fonts {
+            monospace: Some(PathBuf::from("/no/such/font.ttf")),
+            ..Default::default()
+        };
+        let errors = fonts.load(&egui::Context::default());
+        assert_eq!(1, errors.len());
+        assert!(errors[0].starts_with("/no/such/font.ttf: "));
+
+        // without a bold italic font the bold one is slanted
+        let mut fonts = Fonts {
+            bold_italic: Some(PathBuf::from("/no/such/bold-italic.ttf")),
+            ..Default::default()
+        };
+        fonts.load(&egui::Context::default());
+        assert!(fonts.bold_italic().1);
+    }
+}
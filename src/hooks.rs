@@ -0,0 +1,294 @@
+use crate::config;
+use crate::events::{Event, Subscriber};
+use crate::files::Task;
+use crate::i18n::trf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hook {
+    Open,
+    Save,
+    Create,
+}
+
+impl Hook {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Hook::Open => "on-open",
+            Hook::Save => "on-save",
+            Hook::Create => "on-create",
+        };
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        return [Hook::Open, Hook::Save, Hook::Create]
+            .into_iter()
+            .find(|hook| hook.name() == name);
+    }
+}
+
+// read `event = command` lines, blank lines and # comments are skipped
+//   returns the hooks along with a message for each line that couldn't be used
+pub fn parse(text: &str) -> (Vec<(Hook, String)>, Vec<String>) {
+    let mut hooks = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed = line
+            .split_once('=')
+            .map(|(event, command)| (Hook::from_name(event.trim()), command.trim()));
+        match parsed {
+            Some((Some(hook), command)) if !command.is_empty() => {
+                hooks.push((hook, command.to_string()))
+            }
+            _ => errors.push(format!(
+                "line {}: expected `on-open|on-save|on-create = command`",
+                i + 1
+            )),
+        }
+    }
+    return (hooks, errors);
+}
+
+// the sha256 of a .hooks file, what the user trusted is remembered by it
+fn digest(text: &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, text.as_bytes());
+    return digest
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+}
+
+// shell commands run when notes are opened, saved or created, set up in the vault's .hooks file
+//   a vault can come from anyone, so nothing runs until the user has trusted the file as it is,
+//   any change to it has to be trusted again
+#[derive(Default)]
+pub struct Hooks {
+    root: PathBuf,
+    hooks: Vec<(Hook, String)>,
+    // hooks read from a file the user hasn't trusted yet, with its digest
+    untrusted: Option<(String, Vec<(Hook, String)>)>,
+    // the digests of the trusted files, one a line, beside config.toml
+    trust_file: Option<PathBuf>,
+    running: Vec<Task<Result<(), String>>>,
+}
+
+impl Subscriber for Hooks {
+    fn notify(&mut self, event: &Event) {
+        match event {
+            Event::NoteOpened(path) => self.run(Hook::Open, path),
+            Event::NoteCreated(path) => self.run(Hook::Create, path),
+            Event::NoteSaved(path) => self.run(Hook::Save, path),
+            _ => {}
+        }
+    }
+}
+
+impl Hooks {
+    pub fn file(root: &Path) -> PathBuf {
+        return root.join(".hooks");
+    }
+
+    pub fn load(&mut self, root: &Path) -> Vec<String> {
+        let trust_file = config::path().map(|p| p.with_file_name("trusted-hooks"));
+        return self.load_trusting(root, trust_file);
+    }
+
+    fn load_trusting(&mut self, root: &Path, trust_file: Option<PathBuf>) -> Vec<String> {
+        self.root = root.to_path_buf();
+        self.trust_file = trust_file;
+        self.hooks.clear();
+        self.untrusted = None;
+        let path = Self::file(root);
+        let Ok(text) = fs::read_to_string(&path) else {
+            return Vec::new();
+        };
+        let (hooks, errors) = parse(&text);
+        let digest = digest(&text);
+        if hooks.is_empty() || self.trusted().contains(&digest) {
+            tracing::info!("loaded {} hooks from {}", hooks.len(), path.display());
+            self.hooks = hooks;
+        } else {
+            tracing::info!(
+                "{} hooks in {} wait to be trusted",
+                hooks.len(),
+                path.display()
+            );
+            self.untrusted = Some((digest, hooks));
+        }
+        return errors
+            .into_iter()
+            .map(|e| format!("{} {}", path.display(), e))
+            .collect();
+    }
+
+    fn trusted(&self) -> Vec<String> {
+        let text = self
+            .trust_file
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        return text
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .map(str::to_string)
+            .collect();
+    }
+
+    // the commands waiting on the user before they can run
+    pub fn untrusted(&self) -> Option<&[(Hook, String)]> {
+        return self.untrusted.as_ref().map(|(_, hooks)| hooks.as_slice());
+    }
+
+    // run the waiting hooks from now on, and whenever this vault is opened with the file unchanged
+    pub fn trust(&mut self) -> Result<(), String> {
+        let Some((digest, hooks)) = self.untrusted.take() else {
+            return Ok(());
+        };
+        self.hooks = hooks;
+        let path = self
+            .trust_file
+            .clone()
+            .ok_or_else(|| "there's no config directory to remember the hooks in".to_string())?;
+        let mut text = fs::read_to_string(&path).unwrap_or_default();
+        text += &format!("{} {}\n", digest, self.root.display());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| trf("Couldn't write {}: {}", &[&dir.display(), &e]))?;
+        }
+        return fs::write(&path, text)
+            .map_err(|e| trf("Couldn't write {}: {}", &[&path.display(), &e]));
+    }
+
+    // leave the waiting hooks off until the vault is opened again
+    pub fn dismiss(&mut self) {
+        self.untrusted = None;
+    }
+
+    // start every command for an event in the background
+    //   the note and vault are passed in NOTERS_NOTE and NOTERS_VAULT
+    pub fn run(&mut self, hook: Hook, note: &Path) {
+        let root = &self.root;
+        for (_, command) in self.hooks.iter().filter(|(h, _)| *h == hook) {
+            tracing::info!("running {} hook `{}`", hook.name(), command);
+            let mut process = Command::new("sh");
+            process
+                .arg("-c")
+                .arg(command)
+                .current_dir(root)
+                .env("NOTERS_EVENT", hook.name())
+                .env("NOTERS_NOTE", note)
+                .env("NOTERS_VAULT", root);
+            let command = command.clone();
+            self.running.push(Task::spawn(move || {
+                let output = process
+                    .output()
+                    .map_err(|e| trf("Couldn't run `{}`: {}", &[&command, &e]))?;
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                tracing::debug!("`{}` finished with {}", command, output.status);
+                if !output.status.success() {
+                    return Err(format!("`{}` failed: {}", command, stderr.trim()));
+                }
+                return Ok(());
+            }));
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        return !self.running.is_empty();
+    }
+
+    // failures of the hooks that finished since last time
+    pub fn poll(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        self.running.retain(|task| match task.poll() {
+            Some(result) => {
+                errors.extend(result.err());
+                return false;
+            }
+            None => return true,
+        });
+        return errors;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{Event, Subscriber};
+    use crate::hooks::{Hook, Hooks, parse};
+    use noters_core::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_parse() {
+        let (hooks, errors) = parse(
+            "# format on save\non-save = fmt \"$NOTERS_NOTE\"\n\non-open=echo a=b\non-close = x\non-create =",
+        );
+        assert_eq!(
+            vec![
+                (Hook::Save, "fmt \"$NOTERS_NOTE\"".to_string()),
+                (Hook::Open, "echo a=b".to_string()),
+            ],
+            hooks
+        );
+        assert_eq!(2, errors.len());
+        assert!(errors[0].starts_with("line 5"));
+    }
+
+    #[test]
+    fn test_run() {
+        let root = TempDir::new("hooks");
+        fs::write(
+            Hooks::file(&root),
+            "on-save = echo \"$NOTERS_EVENT $NOTERS_NOTE\" > out.txt\non-save = exit 3\n",
+        )
+        .unwrap();
+
+        let trust_file = root.join("config/trusted-hooks");
+        let mut hooks = Hooks::default();
+        assert!(
+            hooks
+                .load_trusting(&root, Some(trust_file.clone()))
+                .is_empty()
+        );
+        // nothing runs from a vault's hooks until they're trusted
+        assert_eq!(2, hooks.untrusted().unwrap().len());
+        hooks.notify(&Event::NoteSaved(root.join("a.md")));
+        assert!(!hooks.is_running());
+        hooks.trust().unwrap();
+        assert!(hooks.untrusted().is_none());
+
+        // the same file is trusted next time, a changed one has to be trusted again
+        let mut hooks = Hooks::default();
+        hooks.load_trusting(&root, Some(trust_file.clone()));
+        assert!(hooks.untrusted().is_none());
+        hooks.run(Hook::Open, &root.join("a.md"));
+        assert!(!hooks.is_running());
+
+        hooks.notify(&Event::NoteSaved(root.join("a.md")));
+        let mut errors = Vec::new();
+        while hooks.is_running() {
+            errors.extend(hooks.poll());
+        }
+        assert_eq!(1, errors.len());
+        assert!(errors[0].starts_with("`exit 3` failed"));
+        assert_eq!(
+            format!("on-save {}\n", root.join("a.md").display()),
+            fs::read_to_string(root.join("out.txt")).unwrap()
+        );
+
+        fs::write(Hooks::file(&root), "on-open = touch opened.txt\n").unwrap();
+        let mut changed = Hooks::default();
+        changed.load_trusting(&root, Some(trust_file));
+        assert_eq!(1, changed.untrusted().unwrap().len());
+        changed.notify(&Event::NoteOpened(root.join("a.md")));
+        assert!(!changed.is_running());
+        assert!(!root.join("opened.txt").exists());
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+// translations of the interface, gettext style: the english text is the key, each language
+//   is a .po file of msgid / msgstr pairs built into the binary, and anything missing from it
+//   stays english. the locale comes from NOTERS_LANG, or the usual LANGUAGE / LC_* / LANG
+//   placeholders are {} and filled in order, a translation has to keep as many as the english
+
+const CATALOGS: &[(&str, &str)] = &[("de", include_str!("../locales/de.po"))];
+
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+// a quoted po string with its escapes undone, None if the line isn't one
+fn unquote(line: &str) -> Option<String> {
+    let inner = line.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    return Some(out);
+}
+
+#[derive(PartialEq)]
+enum Field {
+    None,
+    Id,
+    Str,
+}
+
+// the msgid -> msgstr pairs in a po file, untranslated and header entries are left out
+pub fn parse(po: &str) -> HashMap<String, String> {
+    let mut catalog = HashMap::new();
+    let mut id = String::new();
+    let mut translated = String::new();
+    let mut field = Field::None;
+    let mut finish = |id: &mut String, translated: &mut String| {
+        if !id.is_empty() && !translated.is_empty() {
+            catalog.insert(std::mem::take(id), std::mem::take(translated));
+        }
+        id.clear();
+        translated.clear();
+    };
+    for line in po.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("msgid ") {
+            finish(&mut id, &mut translated);
+            id = unquote(rest).unwrap_or_default();
+            field = Field::Id;
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            translated = unquote(rest).unwrap_or_default();
+            field = Field::Str;
+        } else if let Some(more) = unquote(line) {
+            // a long string carried on over several lines
+            match field {
+                Field::Id => id.push_str(&more),
+                Field::Str => translated.push_str(&more),
+                Field::None => {}
+            }
+        } else if line.is_empty() || line.starts_with('#') {
+            finish(&mut id, &mut translated);
+            field = Field::None;
+        }
+    }
+    finish(&mut id, &mut translated);
+    return catalog;
+}
+
+// a locale setting like de_AT.UTF-8@euro down to de_AT, None for the C locale or nothing set
+fn normalize(value: &str) -> Option<String> {
+    let value = value.split(['.', '@']).next()?.trim();
+    if value.is_empty() || value == "C" || value == "POSIX" {
+        return None;
+    }
+    return Some(value.replace('-', "_"));
+}
+
+// the locale to use, first of what's set in the order gettext looks
+pub fn detect(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    for name in ["NOTERS_LANG", "LANGUAGE", "LC_ALL", "LC_MESSAGES", "LANG"] {
+        let Some(value) = var(name) else {
+            continue;
+        };
+        // LANGUAGE is a list in order of preference
+        if let Some(locale) = value.split(':').find_map(normalize) {
+            return Some(locale);
+        }
+    }
+    return None;
+}
+
+// the catalog for a locale, trying the whole thing and then just the language
+fn catalog_for(locale: &str) -> Option<&'static str> {
+    let language = locale.split('_').next().unwrap_or(locale);
+    return [locale, language].into_iter().find_map(|wanted| {
+        CATALOGS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+            .map(|(_, po)| *po)
+    });
+}
+
+// pick the interface language once at startup
+pub fn init() {
+    let locale = detect(|name| std::env::var(name).ok());
+    let catalog = locale
+        .as_deref()
+        .and_then(catalog_for)
+        .map(parse)
+        .unwrap_or_default();
+    tracing::info!(
+        "interface language {} with {} translations",
+        locale.as_deref().unwrap_or("C"),
+        catalog.len()
+    );
+    let _ = CATALOG.set(catalog);
+}
+
+// the text in the interface language
+pub fn tr(msgid: &'static str) -> &'static str {
+    return CATALOG
+        .get()
+        .and_then(|catalog| catalog.get(msgid))
+        .map_or(msgid, String::as_str);
+}
+
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(i) = rest.find("{}") {
+        out.push_str(&rest[..i]);
+        if let Some(arg) = args.next() {
+            out.push_str(&arg.to_string());
+        }
+        rest = &rest[i + 2..];
+    }
+    out.push_str(rest);
+    return out;
+}
+
+// translate and fill in the {} placeholders, like format! for translated text
+pub fn trf(msgid: &'static str, args: &[&dyn Display]) -> String {
+    return fill(tr(msgid), args);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::i18n::{CATALOGS, catalog_for, detect, fill, parse};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse() {
+        let po = "# header\nmsgid \"\"\nmsgstr \"\"\n\"Language: de\\n\"\n\nmsgid \"Save\"\nmsgstr \"Speichern\"\n\nmsgid \"Saved \\\"{}\\\"\"\nmsgstr \"\"\n\"{} \"\n\"gespeichert\"\n\nmsgid \"Untranslated\"\nmsgstr \"\"\n";
+        let catalog = parse(po);
+        assert_eq!(2, catalog.len());
+        assert_eq!("Speichern", catalog["Save"]);
+        assert_eq!("{} gespeichert", catalog["Saved \"{}\""]);
+    }
+
+    #[test]
+    fn test_detect() {
+        let env = |vars: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            return detect(|name| vars.get(name).cloned());
+        };
+        assert_eq!(Some("de_AT".to_string()), env(&[("LANG", "de_AT.UTF-8")]));
+        assert_eq!(
+            Some("fr".to_string()),
+            env(&[("LANGUAGE", "fr:de"), ("LANG", "de_DE.UTF-8")])
+        );
+        assert_eq!(
+            Some("de_DE".to_string()),
+            env(&[("LC_ALL", "C"), ("LANG", "de_DE@euro")])
+        );
+        assert_eq!(
+            Some("en".to_string()),
+            env(&[("NOTERS_LANG", "en"), ("LANG", "de_DE")])
+        );
+        assert_eq!(None, env(&[("LANG", "C.UTF-8")]));
+
+        let german = parse(catalog_for("de_CH").unwrap());
+        assert_eq!("Speichern", german["Save"]);
+        assert_eq!(None, catalog_for("fr_FR"));
+    }
+
+    #[test]
+    fn test_fill() {
+        assert_eq!("3 of 4", fill("{} of {}", &[&3, &4]));
+        assert_eq!("a  b", fill("a {} b", &[]));
+    }
+
+    // every string the interface asks for is in every catalog, with its placeholders
+    #[test]
+    fn test_catalogs_complete() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut wanted = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let source = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            let bytes = source.as_bytes();
+            for call in ["tr(", "trf("] {
+                for (i, _) in source.match_indices(call) {
+                    // the call on its own, not the end of some other name
+                    if i > 0
+                        && (bytes[i - 1].is_ascii_alphanumeric() || b"_.\"".contains(&bytes[i - 1]))
+                    {
+                        continue;
+                    }
+                    let Some(literal) = source[i + call.len()..].trim_start().strip_prefix('"')
+                    else {
+                        continue;
+                    };
+                    let mut end = 0;
+                    while literal.as_bytes()[end] != b'"' || literal.as_bytes()[end - 1] == b'\\' {
+                        end += 1;
+                    }
+                    wanted.push(literal[..end].replace("\\\"", "\""));
+                }
+            }
+        }
+        assert!(wanted.len() > 100);
+        for (name, po) in CATALOGS {
+            let catalog = parse(po);
+            for msgid in &wanted {
+                let Some(translated) = catalog.get(msgid) else {
+                    panic!("{} has no translation of {:?}", name, msgid);
+                };
+                assert_eq!(
+                    msgid.matches("{}").count(),
+                    translated.matches("{}").count(),
+                    "{} placeholders in {:?}",
+                    name,
+                    msgid
+                );
+            }
+        }
+    }
+}
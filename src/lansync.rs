@@ -0,0 +1,1020 @@
+use crate::collab::{read_frame, write_frame};
+use crate::config;
+use crate::files;
+use crate::i18n::{tr, trf};
+use noters_core::mdns::{self, Service};
+use noters_core::replica::{self, Conflict, Imported, Replica};
+use noters_core::vault;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::{DigitallySignedStruct, DistinguishedName, SignatureScheme};
+use serde_json::{Value, json};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// keeping two machines' vaults the same over the local network, with nothing to set up:
+//   instances find each other with multicast dns, talk over tls with a certificate each one makes
+//   for itself, and swap the automerge history of every note that differs
+//   a device only syncs once it's been trusted on this side, by comparing fingerprints
+
+const SERVICE: &str = "_noters-sync._tcp.local";
+const PORT: u16 = 7879;
+// how often to look for devices and to sync with the ones that are trusted
+const DISCOVER_EVERY: Duration = Duration::from_secs(10);
+const SYNC_EVERY: Duration = Duration::from_secs(30);
+// a device that hasn't answered for this long is taken to be gone
+const FORGET_AFTER: Duration = Duration::from_secs(60);
+
+// this machine's certificate and key, made once and kept beside config.toml, out of the vault so
+//   they aren't synced or shared along with the notes
+pub struct Identity {
+    cert: CertificateDer<'static>,
+    key: PrivatePkcs8KeyDer<'static>,
+    pub fingerprint: String,
+    pub name: String,
+}
+
+// the sha256 of a certificate, it's what devices are trusted by
+pub fn fingerprint(cert: &[u8]) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, cert);
+    return digest
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":");
+}
+
+// the first 8 bytes of a fingerprint, enough for people to compare between screens
+pub fn short_fingerprint(fingerprint: &str) -> &str {
+    return fingerprint.get(..23).unwrap_or(fingerprint);
+}
+
+fn device_name() -> String {
+    let name = fs::read_to_string("/etc/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_default();
+    let name: String = name
+        .trim()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect();
+    if name.is_empty() {
+        return "noters".to_string();
+    }
+    return name;
+}
+
+// a file only this user can read, for the private key
+fn write_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    return options.open(path)?.write_all(bytes);
+}
+
+impl Identity {
+    // the folder beside config.toml the identity lives in
+    pub fn folder() -> Result<PathBuf, String> {
+        return config::path()
+            .map(|path| path.with_file_name("sync"))
+            .ok_or_else(|| tr("Couldn't find a folder for the sync certificate").to_string());
+    }
+
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let (cert_path, key_path) = (dir.join("identity.der"), dir.join("identity.key"));
+        let (cert, key) = match (fs::read(&cert_path), fs::read(&key_path)) {
+            (Ok(cert), Ok(key)) => (cert, key),
+            _ => {
+                let made = rcgen::generate_simple_self_signed(vec!["noters".to_string()])
+                    .map_err(|e| trf("Couldn't make a sync certificate: {}", &[&e]))?;
+                let (cert, key) = (made.cert.der().to_vec(), made.signing_key.serialize_der());
+                fs::create_dir_all(dir)
+                    .and_then(|_| fs::write(&cert_path, &cert))
+                    .and_then(|_| write_private(&key_path, &key))
+                    .map_err(|e| trf("Couldn't save the sync certificate: {}", &[&e]))?;
+                (cert, key)
+            }
+        };
+        return Ok(Self {
+            fingerprint: fingerprint(&cert),
+            cert: CertificateDer::from(cert),
+            key: PrivatePkcs8KeyDer::from(key),
+            name: device_name(),
+        });
+    }
+
+    fn key(&self) -> PrivateKeyDer<'static> {
+        return PrivateKeyDer::Pkcs8(self.key.clone_key());
+    }
+}
+
+// the devices this vault syncs with, by fingerprint, saved one per line with a name after it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trusted(BTreeMap<String, String>);
+
+impl Trusted {
+    fn path(dir: &Path) -> PathBuf {
+        return dir.join("trusted");
+    }
+
+    pub fn load(dir: &Path) -> Self {
+        let text = fs::read_to_string(Self::path(dir)).unwrap_or_default();
+        return Self::parse(&text);
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut trusted = BTreeMap::new();
+        for line in text.lines() {
+            if let Some((fingerprint, name)) = line.trim().split_once(' ') {
+                trusted.insert(fingerprint.to_string(), name.trim().to_string());
+            }
+        }
+        return Self(trusted);
+    }
+
+    fn save(&self, dir: &Path) -> io::Result<()> {
+        let text: String = self
+            .0
+            .iter()
+            .map(|(fingerprint, name)| format!("{} {}\n", fingerprint, name))
+            .collect();
+        fs::create_dir_all(dir)?;
+        return fs::write(Self::path(dir), text);
+    }
+
+    pub fn contains(&self, fingerprint: &str) -> bool {
+        return self.0.contains_key(fingerprint);
+    }
+}
+
+// certificates are checked against the trusted list once the handshake is done, so the tls layer
+//   takes any that signs properly
+#[derive(Debug)]
+struct AnyCert(Arc<CryptoProvider>);
+
+impl AnyCert {
+    fn tls12(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        return rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        );
+    }
+
+    fn tls13(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        return rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        );
+    }
+}
+
+impl ServerCertVerifier for AnyCert {
+    fn verify_server_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: &ServerName<'_>,
+        _: &[u8],
+        _: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        return Ok(ServerCertVerified::assertion());
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        return self.tls12(message, cert, dss);
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        return self.tls13(message, cert, dss);
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        return self.0.signature_verification_algorithms.supported_schemes();
+    }
+}
+
+impl ClientCertVerifier for AnyCert {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        return &[];
+    }
+
+    fn verify_client_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        return Ok(ClientCertVerified::assertion());
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        return self.tls12(message, cert, dss);
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        return self.tls13(message, cert, dss);
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        return self.0.signature_verification_algorithms.supported_schemes();
+    }
+}
+
+fn server_config(identity: &Identity) -> Result<rustls::ServerConfig, rustls::Error> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    return rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?
+        .with_client_cert_verifier(Arc::new(AnyCert(provider)))
+        .with_single_cert(vec![identity.cert.clone()], identity.key());
+}
+
+fn client_config(identity: &Identity) -> Result<rustls::ClientConfig, rustls::Error> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    return rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AnyCert(provider)))
+        .with_client_auth_cert(vec![identity.cert.clone()], identity.key());
+}
+
+// what the threads tell the window about
+pub enum SyncEvent {
+    Found(Device),
+    // a device tried to sync and isn't trusted yet
+    Untrusted(Device),
+    // a note changed on disk, with what it held before
+    Updated { path: PathBuf, before: String },
+    Conflict(Conflict),
+    Finished(Result<String, String>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Device {
+    pub name: String,
+    pub fingerprint: String,
+    pub addr: Option<SocketAddr>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Status {
+    Idle,
+    Syncing,
+    // when and with whom
+    Synced(String),
+    Failed(String),
+}
+
+// everything a sync round on either end works with
+struct Shared {
+    root: PathBuf,
+    identity: Identity,
+    replica: Mutex<Replica>,
+    trusted: Mutex<Trusted>,
+    events: Sender<SyncEvent>,
+}
+
+impl Shared {
+    fn dir(&self) -> PathBuf {
+        return replica::folder(&self.root);
+    }
+
+    fn is_trusted(&self, fingerprint: &str) -> bool {
+        return self.trusted.lock().unwrap().contains(fingerprint);
+    }
+
+    // the vault as it is on disk now goes into the history before anything is compared
+    fn scan(&self, replica: &mut Replica) {
+        for name in vault::list_notes(&self.root) {
+            let path = self.root.join(&name).with_extension("md");
+            match fs::read_to_string(&path) {
+                Ok(text) => {
+                    replica.record(&name, &text);
+                }
+                Err(e) => tracing::warn!("couldn't read {} to sync it: {}", path.display(), e),
+            }
+        }
+    }
+
+    // write out what came over, conflicts keep the merged text until someone picks
+    fn take(&self, replica: &mut Replica, name: &str, bytes: &[u8]) -> Result<(), String> {
+        // the name is the peer's word, it mustn't reach outside the vault
+        if !vault::is_note_name(name) {
+            return Err(trf("{} isn't a note name", &[&name]));
+        }
+        let text = match replica.import(name, bytes)? {
+            Imported::Unchanged => return Ok(()),
+            Imported::Updated(text) => text,
+            Imported::Conflict(conflict) => {
+                let merged = conflict.merged.clone();
+                let _ = self.events.send(SyncEvent::Conflict(conflict));
+                merged
+            }
+        };
+        let path = files::resolve(&self.root, name).map_err(|e| e.to_string())?;
+        let before = fs::read_to_string(&path).unwrap_or_default();
+        fs::write(&path, &text)
+            .map_err(|e| trf("Couldn't write {}: {}", &[&path.display(), &e]))?;
+        let _ = self.events.send(SyncEvent::Updated { path, before });
+        return Ok(());
+    }
+
+    fn manifest(&self, replica: &mut Replica) -> Value {
+        let notes: serde_json::Map<String, Value> = replica
+            .manifest()
+            .into_iter()
+            .map(|(name, heads)| (name, Value::String(hex(&heads))))
+            .collect();
+        return json!({"device": self.identity.name, "notes": notes});
+    }
+
+    // every note the other side doesn't have as we do, then the end marker
+    fn send_notes(
+        &self,
+        stream: &mut impl Write,
+        replica: &mut Replica,
+        theirs: &Value,
+    ) -> Result<usize, String> {
+        let mut sent = 0;
+        for (name, heads) in replica.manifest() {
+            if theirs["notes"][&name].as_str() == Some(hex(&heads).as_str()) {
+                continue;
+            }
+            let bytes = replica.export(&name).unwrap_or_default();
+            send_json(stream, &json!({ "note": name }))?;
+            write_frame(stream, &bytes).map_err(|e| e.to_string())?;
+            sent += 1;
+        }
+        send_json(stream, &json!({"done": true}))?;
+        return Ok(sent);
+    }
+
+    fn receive_notes(&self, stream: &mut impl Read, replica: &mut Replica) -> Result<(), String> {
+        loop {
+            let header = receive_json(stream)?;
+            if header["done"].as_bool() == Some(true) {
+                return Ok(());
+            }
+            let name = header["note"]
+                .as_str()
+                .ok_or("the other side sent something that isn't a note")?
+                .to_string();
+            let bytes = read_frame(stream).map_err(|e| e.to_string())?;
+            if let Err(e) = self.take(replica, &name, &bytes) {
+                tracing::warn!("{}", e);
+            }
+        }
+    }
+
+    // a round started here: our notes go first, then theirs come back with ours merged in
+    fn client_round(&self, stream: &mut impl ReadWrite) -> Result<(), String> {
+        let mut replica = self.replica.lock().unwrap();
+        self.scan(&mut replica);
+        send_json(stream, &self.manifest(&mut replica))?;
+        let theirs = receive_json(stream)?;
+        self.send_notes(stream, &mut replica, &theirs)?;
+        self.receive_notes(stream, &mut replica)?;
+        return replica
+            .save(&self.dir())
+            .map_err(|e| trf("Couldn't save the sync history: {}", &[&e]));
+    }
+
+    fn server_round(&self, stream: &mut impl ReadWrite) -> Result<(), String> {
+        let mut replica = self.replica.lock().unwrap();
+        self.scan(&mut replica);
+        let theirs = receive_json(stream)?;
+        send_json(stream, &self.manifest(&mut replica))?;
+        self.receive_notes(stream, &mut replica)?;
+        self.send_notes(stream, &mut replica, &theirs)?;
+        return replica
+            .save(&self.dir())
+            .map_err(|e| trf("Couldn't save the sync history: {}", &[&e]));
+    }
+}
+
+trait ReadWrite: Read + Write {}
+impl<T: Read + Write> ReadWrite for T {}
+
+fn hex(bytes: &[u8]) -> String {
+    return bytes.iter().map(|b| format!("{:02x}", b)).collect();
+}
+
+fn send_json(stream: &mut impl Write, value: &Value) -> Result<(), String> {
+    return write_frame(stream, value.to_string().as_bytes()).map_err(|e| e.to_string());
+}
+
+fn receive_json(stream: &mut impl Read) -> Result<Value, String> {
+    let frame = read_frame(stream).map_err(|e| trf("The connection dropped: {}", &[&e]))?;
+    return serde_json::from_slice(&frame).map_err(|e| trf("Garbled sync message: {}", &[&e]));
+}
+
+fn peer_fingerprint(certs: Option<&[CertificateDer<'_>]>) -> Option<String> {
+    return certs.and_then(|c| c.first()).map(|c| fingerprint(c));
+}
+
+// answer the rounds other devices start, each connection on a thread of its own
+fn serve(shared: Arc<Shared>, listener: TcpListener, stop: Arc<AtomicBool>) {
+    let config = match server_config(&shared.identity) {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            tracing::error!("couldn't set up tls for sync: {}", e);
+            return;
+        }
+    };
+    // strangers are brought up once, however often they knock
+    let announced = Arc::new(Mutex::new(HashSet::new()));
+    while !stop.load(Ordering::Relaxed) {
+        let (tcp, addr) = match listener.accept() {
+            Ok(accepted) => accepted,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                std::thread::sleep(Duration::from_millis(200));
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("sync listener: {}", e);
+                continue;
+            }
+        };
+        let (shared, config, announced) = (shared.clone(), config.clone(), announced.clone());
+        std::thread::spawn(move || answer(&shared, config, tcp, addr, &announced));
+    }
+}
+
+// one round with whoever connected, turned away before the replica is touched if they aren't trusted
+fn answer(
+    shared: &Shared,
+    config: Arc<rustls::ServerConfig>,
+    mut tcp: TcpStream,
+    addr: SocketAddr,
+    announced: &Mutex<HashSet<String>>,
+) {
+    let _ = tcp.set_nonblocking(false);
+    let _ = tcp.set_read_timeout(Some(Duration::from_secs(30)));
+    let handshake = (|| {
+        let mut conn = rustls::ServerConnection::new(config).map_err(|e| e.to_string())?;
+        while conn.is_handshaking() {
+            conn.complete_io(&mut tcp).map_err(|e| e.to_string())?;
+        }
+        return Ok::<_, String>(conn);
+    })();
+    // anything on the network can knock, that's not worth a failed sync on screen
+    let conn = match handshake {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::info!("sync handshake with {} failed: {}", addr.ip(), e);
+            return;
+        }
+    };
+    let fingerprint = peer_fingerprint(conn.peer_certificates()).unwrap_or_default();
+    if !shared.is_trusted(&fingerprint) {
+        tracing::info!("turned away {}, it isn't trusted yet", addr.ip());
+        if announced.lock().unwrap().insert(fingerprint.clone()) {
+            let _ = shared.events.send(SyncEvent::Untrusted(Device {
+                name: addr.ip().to_string(),
+                fingerprint,
+                addr: None,
+            }));
+        }
+        return;
+    }
+    let mut stream = rustls::StreamOwned::new(conn, tcp);
+    let result = shared.server_round(&mut stream).map(|_| {
+        stream.conn.send_close_notify();
+        let _ = stream.flush();
+        return addr.ip().to_string();
+    });
+    let _ = shared.events.send(SyncEvent::Finished(result));
+}
+
+// start a round with a trusted device
+fn sync_with(shared: &Shared, device: &Device) -> Result<String, String> {
+    let addr = device.addr.ok_or("the device has no address yet")?;
+    let config = client_config(&shared.identity).map_err(|e| e.to_string())?;
+    let mut tcp = TcpStream::connect_timeout(&addr, Duration::from_secs(5))
+        .map_err(|e| trf("Couldn't reach {}: {}", &[&device.name, &e]))?;
+    tcp.set_read_timeout(Some(Duration::from_secs(30)))
+        .map_err(|e| e.to_string())?;
+    let server_name = ServerName::try_from("noters").map_err(|e| e.to_string())?;
+    let mut conn =
+        rustls::ClientConnection::new(Arc::new(config), server_name).map_err(|e| e.to_string())?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut tcp).map_err(|e| {
+            trf(
+                "Couldn't set up a secure connection to {}: {}",
+                &[&device.name, &e],
+            )
+        })?;
+    }
+    // the certificate has to be the one that was trusted, not just any device with its name
+    if peer_fingerprint(conn.peer_certificates()).as_deref() != Some(device.fingerprint.as_str()) {
+        return Err(format!("{} presented a different certificate", device.name));
+    }
+    let mut stream = rustls::StreamOwned::new(conn, tcp);
+    shared.client_round(&mut stream)?;
+    return Ok(device.name.clone());
+}
+
+pub fn local_addr() -> Option<Ipv4Addr> {
+    // connecting a udp socket sends nothing, it only picks the interface
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect((mdns::GROUP, mdns::PORT)).ok()?;
+    return match socket.local_addr().ok()?.ip() {
+        std::net::IpAddr::V4(ip) => Some(ip),
+        _ => None,
+    };
+}
+
+fn mdns_socket() -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    // avahi and friends already have the port, share it with them
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, mdns::PORT).into())?;
+    socket.join_multicast_v4(&mdns::GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_read_timeout(Some(Duration::from_secs(1)))?;
+    return Ok(socket.into());
+}
+
+// announce ourselves and listen for the others
+fn discover(shared: Arc<Shared>, port: u16, stop: Arc<AtomicBool>) {
+    let socket = match mdns_socket() {
+        Ok(socket) => socket,
+        Err(e) => {
+            tracing::warn!("can't look for sync devices: {}", e);
+            let _ = shared.events.send(SyncEvent::Finished(Err(trf(
+                "Can't look for devices on the network: {}",
+                &[&e],
+            ))));
+            return;
+        }
+    };
+    let group = SocketAddr::from((mdns::GROUP, mdns::PORT));
+    let me = Service {
+        instance: format!(
+            "{}-{}",
+            shared.identity.name,
+            &shared.identity.fingerprint[..5]
+        ),
+        host: format!("{}.local", shared.identity.name),
+        port,
+        addrs: local_addr().into_iter().collect(),
+        txt: vec![format!("fp={}", shared.identity.fingerprint)],
+    };
+    let announcement = mdns::announce(SERVICE, &me);
+    let mut last_query: Option<Instant> = None;
+    let mut buf = [0; 9000];
+    while !stop.load(Ordering::Relaxed) {
+        if last_query.is_none_or(|t| t.elapsed() > DISCOVER_EVERY) {
+            last_query = Some(Instant::now());
+            let _ = socket.send_to(&mdns::query(SERVICE), group);
+            let _ = socket.send_to(&announcement, group);
+        }
+        let Ok((len, from)) = socket.recv_from(&mut buf) else {
+            continue;
+        };
+        let packet = &buf[..len];
+        if mdns::asks_for(packet, SERVICE) {
+            let _ = socket.send_to(&announcement, group);
+            continue;
+        }
+        for service in mdns::services(packet, SERVICE) {
+            let Some(fingerprint) = service.txt.iter().find_map(|t| t.strip_prefix("fp=")) else {
+                continue;
+            };
+            if fingerprint == shared.identity.fingerprint {
+                continue;
+            }
+            let ip = service.addrs.first().copied().map_or(from.ip(), Into::into);
+            let name = service.host.trim_end_matches(".local").to_string();
+            let _ = shared.events.send(SyncEvent::Found(Device {
+                name,
+                fingerprint: fingerprint.to_string(),
+                addr: Some(SocketAddr::new(ip, service.port)),
+            }));
+        }
+    }
+}
+
+// a choice made about a conflicting note
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    Merged,
+    Mine,
+    Theirs,
+    // both versions with markers around what differs, to sort out by hand
+    Markers,
+}
+
+pub struct LanSync {
+    shared: Arc<Shared>,
+    stop: Arc<AtomicBool>,
+    events: Receiver<SyncEvent>,
+    pub port: u16,
+    pub status: Status,
+    // devices seen on the network and when they were last heard from
+    pub devices: Vec<(Device, Instant)>,
+    // devices that tried to sync with us and aren't trusted
+    pub strangers: Vec<Device>,
+    pub conflicts: Vec<Conflict>,
+    round: Option<files::Task<Vec<Result<String, String>>>>,
+    last_round: Option<Instant>,
+}
+
+impl LanSync {
+    pub fn start(root: &Path) -> Result<Self, String> {
+        let dir = replica::folder(root);
+        let identity = Identity::load(&Identity::folder()?)?;
+        // older versions kept the key in the vault, where it no longer belongs
+        for old in ["identity.der", "identity.key"] {
+            if fs::remove_file(dir.join(old)).is_ok() {
+                tracing::info!("removed the old sync {} from the vault", old);
+            }
+        }
+        let listener = TcpListener::bind(("0.0.0.0", PORT))
+            .or_else(|_| TcpListener::bind(("0.0.0.0", 0)))
+            .map_err(|e| trf("Couldn't listen for sync connections: {}", &[&e]))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| trf("Couldn't listen for sync connections: {}", &[&e]))?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+
+        let (send, events) = mpsc::channel();
+        let shared = Arc::new(Shared {
+            root: root.to_path_buf(),
+            identity,
+            replica: Mutex::new(Replica::load(&dir)),
+            trusted: Mutex::new(Trusted::load(&dir)),
+            events: send,
+        });
+        let stop = Arc::new(AtomicBool::new(false));
+        {
+            let (shared, stop) = (shared.clone(), stop.clone());
+            std::thread::spawn(move || serve(shared, listener, stop));
+        }
+        {
+            let (shared, stop) = (shared.clone(), stop.clone());
+            std::thread::spawn(move || discover(shared, port, stop));
+        }
+        tracing::info!("lan sync listening on port {}", port);
+        return Ok(Self {
+            shared,
+            stop,
+            events,
+            port,
+            status: Status::Idle,
+            devices: Vec::new(),
+            strangers: Vec::new(),
+            conflicts: Vec::new(),
+            round: None,
+            last_round: None,
+        });
+    }
+
+    pub fn identity(&self) -> &Identity {
+        return &self.shared.identity;
+    }
+
+    pub fn is_trusted(&self, fingerprint: &str) -> bool {
+        return self.shared.is_trusted(fingerprint);
+    }
+
+    pub fn trust(&mut self, device: &Device, trusted: bool) {
+        let mut list = self.shared.trusted.lock().unwrap();
+        if trusted {
+            list.0
+                .insert(device.fingerprint.clone(), device.name.clone());
+            self.strangers
+                .retain(|d| d.fingerprint != device.fingerprint);
+        } else {
+            list.0.remove(&device.fingerprint);
+        }
+        if let Err(e) = list.save(&self.shared.dir()) {
+            tracing::warn!("couldn't save the trusted devices: {}", e);
+        }
+        drop(list);
+        if trusted {
+            self.last_round = None;
+        }
+    }
+
+    // sync with every trusted device that's around, on a thread
+    pub fn sync_now(&mut self) {
+        if self.round.is_some() {
+            return;
+        }
+        let devices: Vec<Device> = self
+            .devices
+            .iter()
+            .map(|(device, _)| device.clone())
+            .filter(|device| self.is_trusted(&device.fingerprint))
+            .collect();
+        self.last_round = Some(Instant::now());
+        if devices.is_empty() {
+            return;
+        }
+        self.status = Status::Syncing;
+        let shared = self.shared.clone();
+        self.round = Some(files::Task::spawn(move || {
+            return devices.iter().map(|d| sync_with(&shared, d)).collect();
+        }));
+    }
+
+    // what the sync threads found out, the events that concern the open note are handed back
+    pub fn poll(&mut self, ctx: &eframe::egui::Context) -> Vec<(PathBuf, String)> {
+        let mut updated = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                SyncEvent::Found(device) => {
+                    self.devices
+                        .retain(|(d, _)| d.fingerprint != device.fingerprint);
+                    self.devices.push((device, Instant::now()));
+                }
+                SyncEvent::Untrusted(device) => {
+                    if !self
+                        .strangers
+                        .iter()
+                        .any(|d| d.fingerprint == device.fingerprint)
+                    {
+                        self.strangers.push(device);
+                    }
+                }
+                SyncEvent::Updated { path, before } => updated.push((path, before)),
+                SyncEvent::Conflict(conflict) => {
+                    self.conflicts.retain(|c| c.name != conflict.name);
+                    self.conflicts.push(conflict);
+                }
+                SyncEvent::Finished(result) => self.finished(result),
+            }
+        }
+        if let Some(results) = self.round.as_ref().and_then(files::Task::poll) {
+            self.round = None;
+            for result in results {
+                self.finished(result);
+            }
+        }
+        self.devices
+            .retain(|(_, seen)| seen.elapsed() < FORGET_AFTER);
+        if self.last_round.is_none_or(|t| t.elapsed() > SYNC_EVERY) {
+            self.sync_now();
+        }
+        ctx.request_repaint_after(Duration::from_millis(500));
+        return updated;
+    }
+
+    fn finished(&mut self, result: Result<String, String>) {
+        match result {
+            Ok(device) => {
+                let when = chrono::Local::now().format("%H:%M");
+                self.status = Status::Synced(format!("{} with {}", when, device));
+            }
+            Err(e) => {
+                tracing::warn!("sync failed: {}", e);
+                self.status = Status::Failed(e);
+            }
+        }
+    }
+
+    // settle a conflicting note, the text is written over what the merge left and goes out next round
+    pub fn resolve(&mut self, name: &str, resolution: Resolution) -> Result<PathBuf, String> {
+        let Some(i) = self.conflicts.iter().position(|c| c.name == name) else {
+            return Err(format!("{} has no conflict", name));
+        };
+        if !vault::is_note_name(name) {
+            return Err(trf("{} isn't a note name", &[&name]));
+        }
+        let conflict = self.conflicts.remove(i);
+        let text = match resolution {
+            Resolution::Merged => conflict.merged,
+            Resolution::Mine => conflict.mine,
+            Resolution::Theirs => conflict.theirs,
+            Resolution::Markers => {
+                noters_core::merge::conflict_markers(&conflict.mine, &conflict.theirs)
+            }
+        };
+        let path = files::resolve(&self.shared.root, name).map_err(|e| e.to_string())?;
+        fs::write(&path, text).map_err(|e| trf("Couldn't write {}: {}", &[&path.display(), &e]))?;
+        return Ok(path);
+    }
+
+    pub fn label(&self) -> String {
+        return match &self.status {
+            Status::Idle if self.devices.is_empty() => tr("⇅ Looking for devices").to_string(),
+            Status::Idle => trf("⇅ {} devices nearby", &[&self.devices.len()]),
+            Status::Syncing => tr("⟳ Syncing…").to_string(),
+            Status::Synced(when) if self.conflicts.is_empty() => trf("✔ Synced {}", &[when]),
+            Status::Synced(_) => trf("⚠ {} sync conflicts", &[&self.conflicts.len()]),
+            Status::Failed(_) => tr("⚠ Sync failed").to_string(),
+        };
+    }
+}
+
+impl Drop for LanSync {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lansync::{
+        Device, Identity, Shared, SyncEvent, Trusted, fingerprint, serve, short_fingerprint,
+        sync_with,
+    };
+    use noters_core::replica::Replica;
+    use noters_core::testing::TempDir;
+    use std::fs;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::mpsc::{self, Receiver};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_trusted() {
+        let trusted = Trusted::parse("AB:CD laptop\nbroken\n12:34 desk top\n");
+        assert!(trusted.contains("AB:CD"));
+        assert_eq!(Some(&"desk top".to_string()), trusted.0.get("12:34"));
+        assert_eq!(2, trusted.0.len());
+        let full = fingerprint(b"cert");
+        assert_eq!(32 * 3 - 1, full.len());
+        assert!(full.starts_with("06:29:84:32:E8:06:6B:29:"));
+        assert_eq!("06:29:84:32:E8:06:6B:29", short_fingerprint(&full));
+        assert_eq!("AB:CD", short_fingerprint("AB:CD"));
+    }
+
+    #[test]
+    fn test_identity() {
+        let dir = TempDir::new("sync-identity");
+        let made = Identity::load(&dir).unwrap();
+        assert_eq!(made.fingerprint, Identity::load(&dir).unwrap().fingerprint);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join("identity.key"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+    }
+
+    fn shared(root: &std::path::Path) -> (Arc<Shared>, Receiver<SyncEvent>) {
+        let (events, received) = mpsc::channel();
+        let identity = Identity::load(&root.with_extension("identity")).unwrap();
+        let shared = Arc::new(Shared {
+            root: root.to_path_buf(),
+            identity,
+            replica: Mutex::new(Replica::default()),
+            trusted: Mutex::new(Trusted::default()),
+            events,
+        });
+        return (shared, received);
+    }
+
+    #[test]
+    fn test_take_outside_vault() {
+        let base = TempDir::new("lansync-take");
+        let root = base.join("vault");
+        fs::create_dir_all(&root).unwrap();
+        let (shared, _) = shared(&root);
+
+        let mut theirs = Replica::default();
+        for name in ["../escape", "/tmp/escape", "work/../../escape"] {
+            theirs.record(name, "owned\n");
+            let bytes = theirs.export(name).unwrap();
+            let mut replica = shared.replica.lock().unwrap();
+            assert!(shared.take(&mut replica, name, &bytes).is_err());
+            assert_eq!(None, replica.text(name));
+        }
+        assert!(!base.join("escape.md").exists());
+        assert!(!base.join("escape").exists());
+
+        theirs.record("work/plan", "inside\n");
+        let bytes = theirs.export("work/plan").unwrap();
+        let mut replica = shared.replica.lock().unwrap();
+        shared.take(&mut replica, "work/plan", &bytes).unwrap();
+        assert_eq!(
+            "inside\n",
+            fs::read_to_string(root.join("work/plan.md")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sync_round() {
+        let base = TempDir::new("lansync");
+        let (a_root, b_root) = (base.join("a"), base.join("b"));
+        fs::create_dir_all(&a_root).unwrap();
+        fs::create_dir_all(b_root.join("work")).unwrap();
+        fs::write(a_root.join("index.md"), "# Index\n").unwrap();
+        fs::write(b_root.join("work/plan.md"), "- ship it\n").unwrap();
+
+        let ((a, _), (b, b_events)) = (shared(&a_root), shared(&b_root));
+        let (stranger, _) = shared(&base.join("c"));
+        a.trusted
+            .lock()
+            .unwrap()
+            .0
+            .insert(b.identity.fingerprint.clone(), "b".to_string());
+        b.trusted
+            .lock()
+            .unwrap()
+            .0
+            .insert(a.identity.fingerprint.clone(), "a".to_string());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let server = {
+            let (b, stop) = (b.clone(), stop.clone());
+            std::thread::spawn(move || serve(b, listener, stop))
+        };
+        let device = Device {
+            name: "b".to_string(),
+            fingerprint: b.identity.fingerprint.clone(),
+            addr: Some(addr),
+        };
+        // a connection that never says anything doesn't hold up the round
+        let _idle = TcpStream::connect(addr).unwrap();
+        let start = Instant::now();
+        assert_eq!(Ok("b".to_string()), sync_with(&a, &device));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        // b doesn't know the stranger, however often it tries
+        assert!(sync_with(&stranger, &device).is_err());
+        assert!(sync_with(&stranger, &device).is_err());
+        // a device that isn't the one that was trusted is turned away
+        let wrong = Device {
+            fingerprint: "00:00".to_string(),
+            ..device
+        };
+        assert!(sync_with(&a, &wrong).is_err());
+        stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        server.join().unwrap();
+
+        let untrusted: Vec<String> = b_events
+            .try_iter()
+            .filter_map(|event| match event {
+                SyncEvent::Untrusted(device) => Some(device.fingerprint),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![stranger.identity.fingerprint.clone()], untrusted);
+
+        assert_eq!(
+            "- ship it\n",
+            fs::read_to_string(a_root.join("work/plan.md")).unwrap()
+        );
+        assert_eq!(
+            "# Index\n",
+            fs::read_to_string(b_root.join("index.md")).unwrap()
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, fmt};
+
+const KEPT_LINES: usize = 1000;
+const KEPT_FILES: usize = 7;
+
+// the most recent log lines, kept in memory for the logs window
+#[derive(Clone, Default)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl LogBuffer {
+    pub fn lines(&self) -> Vec<String> {
+        return self.lines.lock().unwrap().iter().cloned().collect();
+    }
+}
+
+// the fmt layer writes each event in one go, so every write is one entry
+impl io::Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == KEPT_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(String::from_utf8_lossy(buf).trim_end().to_string());
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+// $XDG_CONFIG_HOME/noters/logs, falling back to ~/.config
+pub fn log_dir() -> Option<PathBuf> {
+    let config = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => env::home_dir()?.join(".config"),
+    };
+    return Some(config.join("noters").join("logs"));
+}
+
+// log to a daily rotated file, the in-app buffer, and stderr for warnings (everything when verbose)
+//   RUST_LOG overrides the level, the returned guard has to live until exit so the file gets flushed
+pub fn init(verbose: bool) -> (LogBuffer, Option<WorkerGuard>) {
+    let buffer = LogBuffer::default();
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(if verbose {
+            "noters=debug,noters_core=debug"
+        } else {
+            "noters=info,noters_core=info"
+        })
+    });
+
+    let mut file_error = None;
+    let file = log_dir().and_then(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("noters")
+            .filename_suffix("log")
+            .max_log_files(KEPT_FILES)
+            .build(&dir)
+            .map_err(|e| file_error = Some(format!("{}: {}", dir.display(), e)))
+            .ok()
+    });
+    let (file_layer, guard) = match file.map(tracing_appender::non_blocking) {
+        Some((writer, guard)) => (
+            Some(fmt::layer().with_writer(writer).with_ansi(false)),
+            Some(guard),
+        ),
+        None => (None, None),
+    };
+
+    let memory = buffer.clone();
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .with(
+            fmt::layer()
+                .with_writer(move || memory.clone())
+                .with_ansi(false),
+        )
+        .with(
+            fmt::layer()
+                .with_writer(io::stderr)
+                .with_filter(if verbose {
+                    LevelFilter::TRACE
+                } else {
+                    LevelFilter::WARN
+                }),
+        )
+        .init();
+
+    if let Some(e) = file_error {
+        tracing::warn!("not logging to a file, couldn't set up {}", e);
+    }
+    return (buffer, guard);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logging::{KEPT_LINES, LogBuffer};
+    use std::io::Write;
+
+    #[test]
+    fn test_buffer() {
+        let mut buffer = LogBuffer::default();
+        for i in 0..KEPT_LINES + 2 {
+            buffer
+                .write_all(format!("line {}\n", i).as_bytes())
+                .unwrap();
+        }
+        let lines = buffer.lines();
+        assert_eq!(KEPT_LINES, lines.len());
+        assert_eq!("line 2", lines[0]);
+        assert_eq!(format!("line {}", KEPT_LINES + 1), lines[KEPT_LINES - 1]);
+    }
+}
@@ -1,387 +1,5267 @@
+use chrono::{DateTime, Local};
 use cssparser_color::Color;
-use eframe::egui::text::{CCursor, CCursorRange, LayoutJob};
+use eframe::egui::text::{CCursor, CCursorRange, Galley, LayoutJob};
 use eframe::egui::text_edit::TextEditState;
 use eframe::egui::{self, TextBuffer};
-use eframe::egui::{Color32, CursorIcon, FontFamily, FontId, Stroke, TextFormat, Visuals};
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::{env, fs};
+use eframe::egui::{Color32, CursorIcon, FontId, Stroke, TextFormat};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-mod note;
-use crate::note::{MarkdownStr, MarkdownType, Note, highlight_parse};
+mod archive;
+mod assist;
+mod attachments;
+mod autosave;
+mod buffer;
+mod carets;
+mod cli;
+mod collab;
+mod commands;
+mod completion;
+mod config;
+mod diagrams;
+mod error;
+mod events;
+mod files;
+mod finder;
+mod folds;
+mod fonts;
+mod hooks;
+mod i18n;
+mod lansync;
+mod logging;
+mod ocr;
+mod outline;
+mod pane;
+mod pdf;
+mod perf;
+mod picker;
+mod platform_theme;
+mod plugins;
+mod print;
+mod reminders;
+mod script_plugin;
+mod scripts;
+mod session;
+mod share;
+mod sketches;
+mod stylesheet;
+mod syntax;
+mod tag_browser;
+mod theme;
+mod toast;
+mod touch;
+mod viewport;
+mod views;
+use crate::archive::Archived;
+use crate::autosave::Autosave;
+use crate::buffer::NoteBuffer;
+use crate::carets::Carets;
+use crate::collab::Collab;
+use crate::commands::{Command, Commands};
+use crate::completion::LinkCompletion;
+use crate::config::Config;
+use crate::diagrams::{Diagrams, Shown};
+use crate::events::{Event, EventBus};
+use crate::files::{Disk, Saved, Saver, Task};
+use crate::finder::Finder;
+use crate::folds::Folds;
+use crate::fonts::Fonts;
+use crate::hooks::Hooks;
+use crate::i18n::{tr, trf};
+use crate::lansync::{LanSync, Resolution};
+use crate::logging::LogBuffer;
+use crate::outline::{Outline, Picked};
+use crate::pane::{Followed, Look, Pane};
+use crate::pdf::PdfPreview;
+use crate::perf::Perf;
+use crate::picker::Picker;
+use crate::platform_theme::PlatformTheme;
+use crate::plugins::{BlockRenderer, Plugins};
+use crate::reminders::Scheduler;
+use crate::scripts::{Action, Scripts};
+use crate::session::Session;
+use crate::share::Share;
+use crate::sketches::{SketchCache, SketchEditor};
+use crate::stylesheet::StyleSheet;
+use crate::tag_browser::TagBrowser;
+use crate::theme::Theme;
+use crate::toast::Toasts;
+use crate::touch::Touch;
+use crate::views::{View, Views};
+use noters_core::delimiters::{Format, Toggled};
+use noters_core::edit::Edit;
+use noters_core::encoding::Encoding;
+use noters_core::graphemes::{byte_index, char_index};
+use noters_core::links::LinkSyntax;
+use noters_core::note::{
+    Heading, MarkdownStr, MarkdownType, Note, SortOptions, TextCase, highlight_parse,
+};
+use noters_core::pairs::Pairing;
+use noters_core::search::{Pattern, Replacement};
+use noters_core::timestamp::{Stamp, StampFormats};
+use noters_core::{
+    delimiters, formula, links, lists, math, merge, pairs, render, search, sketch, table,
+    templates, typography, vault,
+};
 
 fn main() {
-    println!("{:?}", linux_theme::gtk::current::current());
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "cat") {
+        std::process::exit(cli::cat(&args[1..]));
+    }
+
+    let verbose = env::args()
+        .skip(1)
+        .any(|arg| arg == "--verbose" || arg == "-v");
+    let (logs, _guard) = logging::init(verbose);
+    i18n::init();
     let native_options = eframe::NativeOptions::default();
-    eframe::run_native(
+    if let Err(e) = eframe::run_native(
         "NoteRs",
         native_options,
-        Box::new(|cc| Ok(Box::new(NoteRs::new(cc)))),
-    );
+        Box::new(|cc| Ok(Box::new(NoteRs::new(cc, logs)))),
+    ) {
+        tracing::error!("Failed to start: {}", e);
+    }
 }
 
 #[derive(Default)]
 struct NoteRs {
     root: PathBuf,
     path: PathBuf,
+    // bom and line endings of the open note, put back when it's saved
+    encoding: Encoding,
     cursor_range: CCursorRange,
-    note: Note,
+    note: NoteBuffer,
     nav_history: Vec<String>,
     nav_forward: Vec<String>,
-    bg_color: Color32,
-    fg_color: Color32,
+    sort_options: SortOptions,
+    smart_typography: bool,
+    hide_markup: bool,
+    highlight_line: bool,
+    // wrap at this many columns with the text centered, or at the window edge when None
+    wrap_column: Option<usize>,
+    stamp_formats: StampFormats,
+    table_dialog: Option<(usize, usize)>,
+    // pasted text that looks like csv or tsv, waiting to be pasted as a table or as it is
+    table_paste: Option<String>,
+    link_dialog: Option<LinkDialog>,
+    goto_dialog: Option<GotoDialog>,
+    // bring the cursor into view after the next layout
+    scroll_to_cursor: bool,
+    text_drag: Option<Range<usize>>,
+    // several selections edited together, the TextEdit steps aside while these exist
+    carets: Option<Carets>,
+    // where an alt+drag block selection started, while it's still being dragged
+    block_anchor: Option<usize>,
+    // last frame's editor layout, used to hit test before the TextEdit runs
+    editor_galley: Option<(Arc<Galley>, egui::Pos2)>,
+    // characters on screen last frame, full highlighting only covers the lines around them
+    visible: Option<Range<usize>>,
+    toasts: Toasts,
+    // a note being read in the background, edits wait until it's in
+    loading: Option<Task<files::Loaded>>,
+    saver: Saver,
+    // what another program saved over the open note, while we ask what to do about it
+    conflict: Option<Disk>,
+    logs: LogBuffer,
+    // broken tree invariants that have already been reported
+    known_problems: Vec<String>,
+    show_logs: bool,
+    plugins: Plugins,
+    show_plugins: bool,
+    scripts: Scripts,
+    hooks: Hooks,
+    events: EventBus,
+    perf: Perf,
+    reminders: Scheduler,
+    // a line to jump to once the note being opened arrives
+    pending_line: Option<usize>,
+    // a byte offset in the full text of the note being opened, from a search
+    pending_position: Option<usize>,
+    // the heading a link to the note being opened went to
+    pending_anchor: Option<String>,
+    outline: Outline,
+    commands: Commands,
+    // the second editor of a split view
+    pane: Pane,
+    autosave: Autosave,
+    // which sections of each note were left folded
+    folds: Folds,
+    // asking whether to save before the window closes, and closing without saving once told to
+    close_prompt: bool,
+    discard_on_close: bool,
+    // when the open note was last written, for the status bar
+    last_saved: Option<DateTime<Local>>,
+    // notes detached into windows of their own
+    windows: Vec<(egui::ViewportId, Pane)>,
+    opened_windows: u64,
+    // a section the outline asked to fold, by its heading's position in the full text
+    pending_fold: Option<usize>,
+    finder: Finder,
+    tag_browser: TagBrowser,
+    // where each note was left, and the view to put back once the note being opened arrives
+    views: Views,
+    pending_view: Option<View>,
+    scroll_offset: f32,
+    restore_scroll: Option<f32>,
+    sketches: SketchCache,
+    sketch_editor: Option<SketchEditor>,
+    diagrams: Diagrams,
+    pdf_preview: Option<PdfPreview>,
+    pdf_width: f32,
+    // a web page being saved as a readable note
+    archiving: Option<Task<Result<Archived, String>>>,
+    // text being read out of an embedded image, goes in under the embed
+    extracting: Option<Task<(String, Result<String, String>)>>,
+    // the language model endpoint, only there when it's been configured
+    assist: Option<assist::Config>,
+    // a model answer on its way, goes in as a new block after where its text ended
+    assisting: Option<Task<(usize, Result<String, String>)>>,
+    touch: Touch,
+    // the open note shared with other instances, edits go both ways while this is up
+    collab: Option<Collab>,
+    // the address typed into the join dialog while it's open
+    join_dialog: Option<(String, String)>,
+    // keeping the vault in step with other machines on the network, while it's switched on
+    lan_sync: Option<LanSync>,
+    show_sync: bool,
+    // the open note readable at a link for a while
+    share: Option<Share>,
+    show_share: bool,
+    // the print dialog while it's open, and a pdf being written or printed
+    print_dialog: Option<print::Options>,
+    // how this vault writes links, and the links window with the syntax to convert from
+    link_syntax: LinkSyntax,
+    link_completion: LinkCompletion,
+    // the notes in the vault, for telling links to missing notes apart, None until first read
+    known_notes: Option<Arc<HashSet<String>>>,
+    reading_notes: Option<Task<HashSet<String>>>,
+    links_window: Option<Option<LinkSyntax>>,
+    replace_dialog: Option<ReplaceDialog>,
+    find_bar: Option<FindBar>,
+    new_note_dialog: Option<NewNoteDialog>,
+    rename_dialog: Option<RenameDialog>,
+    switch_dialog: Option<SwitchDialog>,
+    printing: Option<Task<Result<String, String>>>,
+    // the open note hasn't been written to disk yet, its first save creates it
+    unsaved_new: bool,
+    // the app's settings from config.toml, and the settings window's changes before they're saved
+    config: Config,
+    settings: Option<Config>,
+    // the config's theme, with its [colors] in place
+    theme: Theme,
+    platform_theme: PlatformTheme,
+    // style.css beside the config, shared with the layouter
+    stylesheet: Arc<StyleSheet>,
+}
+
+#[derive(Default)]
+struct GotoDialog {
+    picker: Picker,
+    headings: Vec<Heading>,
+    titles: Vec<String>,
+}
+
+// find and replace across the vault, previewed before anything changes
+#[derive(Default)]
+struct ReplaceDialog {
+    find: String,
+    replace: String,
+    regex: bool,
+    // each note's matches and whether to make them, the open note first
+    found: Vec<(String, Vec<(Replacement, bool)>)>,
+    searched: bool,
+    error: Option<String>,
+}
+
+// what the bar under the note looks for in it and what it puts in place of a match
+#[derive(Default)]
+struct FindBar {
+    find: String,
+    replace: String,
+    regex: bool,
+    focus: bool,
+    // a step asked for from the bar, taken when the editor is drawn
+    pending: Option<FindStep>,
+}
+
+#[derive(Clone, Copy)]
+enum FindStep {
+    Next,
+    Previous,
+    Replace,
+}
+
+// naming a new note, choosing where it goes and what it starts out as
+#[derive(Default)]
+struct NewNoteDialog {
+    name: String,
+    // the folder it goes in, empty for the top of the vault, and a new one to make inside it
+    folder: String,
+    new_folder: String,
+    folders: Vec<String>,
+    template: Option<String>,
+    templates: Vec<String>,
+    // link to the new note from the cursor in the one that's open
+    link: bool,
+    error: Option<String>,
+}
+
+// moving the open note to another name or folder
+#[derive(Default)]
+struct RenameDialog {
+    name: String,
+    error: Option<String>,
+}
+
+// jumping to another note by typing part of its name
+#[derive(Default)]
+struct SwitchDialog {
+    picker: Picker,
+    notes: Vec<String>,
+}
+
+#[derive(Default)]
+struct LinkDialog {
+    picker: Picker,
+    notes: Vec<String>,
+}
+
+// how notes are drawn, the theme's colors in the configured fonts, restyled by the stylesheet
+#[derive(Clone, Copy)]
+struct Style<'a> {
+    theme: &'a Theme,
+    fonts: &'a Fonts,
+    sheet: &'a StyleSheet,
+}
+
+fn draw_normal(job: &mut LayoutJob, style: &Style, text: &str) {
+    job.append(
+        text,
+        0.0,
+        TextFormat {
+            font_id: style.fonts.body(),
+            color: style.theme.text,
+            ..Default::default()
+        },
+    );
 }
 
-fn draw_normal(job: &mut LayoutJob, text: &str) {
+fn draw_bold(job: &mut LayoutJob, style: &Style, text: &str) {
     job.append(
         text,
         0.0,
         TextFormat {
-            color: Color32::from_rgb(180, 180, 180),
+            font_id: style.fonts.bold(),
+            color: style.theme.bold,
             ..Default::default()
         },
     );
 }
 
-fn draw_bold(job: &mut LayoutJob, text: &str) {
+fn draw_bold_italic(job: &mut LayoutJob, style: &Style, text: &str) {
+    let (font_id, slanted) = style.fonts.bold_italic();
     job.append(
         text,
         0.0,
         TextFormat {
-            color: Color32::from_rgb(255, 255, 255),
-            ..Default::default() // todo: bold
+            font_id,
+            color: style.theme.bold,
+            italics: slanted,
+            ..Default::default()
         },
     );
 }
 
-fn draw_italic(job: &mut LayoutJob, text: &str) {
+// **_bold italic_** or _**bold italic**_, the parser only sees the outer markers
+fn is_bold_italic(text: &str, outer: &str, inner: &str) -> bool {
+    let Some(inside) = text.strip_prefix(outer).and_then(|t| t.strip_suffix(outer)) else {
+        return false;
+    };
+    return inside.len() >= inner.len() * 2 && inside.starts_with(inner) && inside.ends_with(inner);
+}
+
+fn draw_italic(job: &mut LayoutJob, style: &Style, text: &str) {
     job.append(
         text,
         0.0,
         TextFormat {
-            color: Color32::from_rgb(200, 200, 200),
+            font_id: style.fonts.body(),
+            color: style.theme.italic,
             italics: true,
             ..Default::default()
         },
     );
 }
 
-fn draw_monospace(job: &mut LayoutJob, text: &str) {
+fn draw_strikethrough(job: &mut LayoutJob, style: &Style, text: &str) {
     job.append(
         text,
         0.0,
         TextFormat {
-            font_id: FontId {
-                size: 12.0,
-                family: FontFamily::Monospace,
-            },
-            color: Color32::from_rgb(200, 200, 200),
+            font_id: style.fonts.body(),
+            color: style.theme.faint,
+            strikethrough: Stroke::new(1.0, style.theme.faint),
             ..Default::default()
         },
     );
 }
 
-fn draw_heading(job: &mut LayoutJob, text: &str, level: usize) {
+fn draw_monospace(job: &mut LayoutJob, style: &Style, text: &str) {
+    draw_monospace_in(job, style, text, style.theme.code);
+}
+
+fn draw_monospace_in(job: &mut LayoutJob, style: &Style, text: &str, color: Color32) {
     job.append(
         text,
         0.0,
         TextFormat {
-            font_id: FontId {
-                size: match level {
-                    1 => 32.0,
-                    2 => 24.0,
-                    _ => 16.0,
-                },
-                family: FontFamily::Proportional,
-            },
-            color: Color32::from_rgb(255, 255, 255),
-            line_height: Some(match level {
-                1 => 36.0,
-                2 => 28.0,
-                _ => 20.0,
-            }),
+            font_id: style.fonts.monospace(),
+            color,
+            ..Default::default()
+        },
+    );
+}
+
+// a fenced block with its body coloured by language, the fences stay plain
+fn draw_code(job: &mut LayoutJob, style: &Style, text: &str, language: &str) {
+    let open = text.find('\n').map_or(text.len(), |i| i + 1);
+    let close = text.rfind('\n').map_or(text.len(), |i| i + 1).max(open);
+    let Some(pieces) = syntax::highlight(language, &text[open..close], style.theme.dark) else {
+        draw_monospace(job, style, text);
+        return;
+    };
+    draw_monospace(job, style, &text[..open]);
+    for (color, piece) in pieces {
+        draw_monospace_in(job, style, piece, color);
+    }
+    draw_monospace(job, style, &text[close..]);
+}
+
+fn draw_heading(job: &mut LayoutJob, style: &Style, text: &str, level: usize) {
+    let (font_id, line_height) = style.fonts.heading(level);
+    job.append(
+        text,
+        0.0,
+        TextFormat {
+            font_id,
+            color: style.theme.bold,
+            line_height: Some(line_height),
+            ..Default::default()
+        },
+    );
+}
+
+fn draw_link(job: &mut LayoutJob, style: &Style, text: &str) {
+    job.append(
+        text,
+        0.0,
+        TextFormat {
+            font_id: style.fonts.body(),
+            color: style.theme.link,
+            underline: Stroke::new(1.0, style.theme.link),
+            ..Default::default()
+        },
+    );
+}
+
+// a link to a note that isn't there yet
+fn draw_broken_link(job: &mut LayoutJob, style: &Style, text: &str) {
+    job.append(
+        text,
+        0.0,
+        TextFormat {
+            font_id: style.fonts.body(),
+            color: style.theme.broken_link,
+            underline: Stroke::new(1.0, style.theme.broken_link.gamma_multiply(0.5)),
+            ..Default::default()
+        },
+    );
+}
+
+// a link target names a note, or a folder with an index note in it
+//   one that's only a #heading is in the note it's written in
+fn note_exists(known: &HashSet<String>, target: &str) -> bool {
+    let target = links::split_anchor(target).0.trim_matches('/');
+    if target.is_empty() {
+        return true;
+    }
+    return known.contains(target) || known.contains(&format!("{}/index", target));
+}
+
+// the column left of the editor the fold chevrons sit in
+const FOLD_GUTTER: f32 = 18.0;
+
+fn draw_tag(job: &mut LayoutJob, style: &Style, text: &str) {
+    job.append(
+        text,
+        0.0,
+        TextFormat {
+            font_id: style.fonts.body(),
+            color: style.theme.tag,
+            background: style.theme.tag.gamma_multiply(0.12),
             ..Default::default()
         },
     );
 }
 
-fn draw_link(job: &mut LayoutJob, text: &str) {
+// the marker of a bullet or task is left invisible, paint_list_markers draws over it
+fn draw_list_marker(job: &mut LayoutJob, style: &Style, text: &str, ordered: bool) {
+    let marker = text.trim_start();
+    draw_normal(job, style, &text[..text.len() - marker.len()]);
     job.append(
-        &text,
+        marker,
         0.0,
         TextFormat {
-            color: Color32::from_rgb(80, 140, 255),
-            underline: Stroke::new(1.0, Color32::from_rgb(80, 140, 255)),
+            font_id: style.fonts.body(),
+            color: if ordered {
+                style.theme.list_marker
+            } else {
+                Color32::TRANSPARENT
+            },
             ..Default::default()
         },
     );
 }
 
-fn render_markdown(strings: Vec<MarkdownStr<'_>>) -> LayoutJob {
-    let mut job = LayoutJob::default();
+// change the format of a byte range of an already built job, splitting sections where needed
+fn restyle_range(job: &mut LayoutJob, range: Range<usize>, style: impl Fn(&mut TextFormat)) {
+    let mut sections = Vec::with_capacity(job.sections.len() + 2);
+    for section in job.sections.drain(..) {
+        let r = section.byte_range.clone();
+        let start = range.start.clamp(r.start, r.end);
+        let end = range.end.clamp(r.start, r.end);
+        if start == end {
+            sections.push(section);
+            continue;
+        }
+        for (part, inside) in [
+            (r.start..start, false),
+            (start..end, true),
+            (end..r.end, false),
+        ] {
+            if part.is_empty() {
+                continue;
+            }
+            let mut piece = section.clone();
+            piece.byte_range = part;
+            if inside {
+                style(&mut piece.format);
+            }
+            sections.push(piece);
+        }
+    }
+    job.sections = sections;
+}
 
+// fenced blocks in a language a plugin has claimed are drawn by that plugin, the rest are coloured
+//   links are checked against the vault's notes once they're known
+fn render_markdown(
+    job: &mut LayoutJob,
+    style: &Style,
+    strings: Vec<MarkdownStr<'_>>,
+    blocks: &HashMap<String, BlockRenderer>,
+    known: Option<&HashSet<String>>,
+) {
     for s in strings {
+        let broken = s.mdtype == MarkdownType::Link
+            && known.is_some_and(|known| !note_exists(known, links::link_target(s.text)));
+        let element = match &s.mdtype {
+            MarkdownType::Heading1 => "heading1",
+            MarkdownType::Heading2 => "heading2",
+            MarkdownType::Heading3 => "heading3",
+            MarkdownType::Paragraph => "paragraph",
+            MarkdownType::Bold => "bold",
+            MarkdownType::Italic => "italic",
+            MarkdownType::Strikethrough => "strikethrough",
+            MarkdownType::Tag => "tag",
+            MarkdownType::WebLink => "web-link",
+            MarkdownType::Link if broken => "broken-link",
+            MarkdownType::Link => "link",
+            MarkdownType::Monospace => "code",
+            MarkdownType::Math(_) => "math",
+            MarkdownType::Frontmatter => "frontmatter",
+            MarkdownType::Toc => "toc",
+            MarkdownType::Code(_) => "code-block",
+            _ => "",
+        };
+        let first = job.sections.len();
         match s.mdtype {
             MarkdownType::Heading1 => {
-                draw_heading(&mut job, &s.text, 1);
+                draw_heading(job, style, s.text, 1);
             }
             MarkdownType::Heading2 => {
-                draw_heading(&mut job, &s.text, 2);
+                draw_heading(job, style, s.text, 2);
             }
             MarkdownType::Heading3 => {
-                draw_heading(&mut job, &s.text, 3);
+                draw_heading(job, style, s.text, 3);
             }
             MarkdownType::Paragraph => {
-                draw_normal(&mut job, &s.text);
+                draw_normal(job, style, s.text);
+            }
+            MarkdownType::Bold if is_bold_italic(s.text, "**", "_") => {
+                draw_bold_italic(job, style, s.text);
+            }
+            MarkdownType::Italic if is_bold_italic(s.text, "_", "**") => {
+                draw_bold_italic(job, style, s.text);
             }
             MarkdownType::Bold => {
-                draw_bold(&mut job, &s.text);
+                draw_bold(job, style, s.text);
             }
             MarkdownType::Italic => {
-                draw_italic(&mut job, &s.text);
+                draw_italic(job, style, s.text);
+            }
+            MarkdownType::Strikethrough => {
+                draw_strikethrough(job, style, s.text);
+            }
+            MarkdownType::Tag => {
+                draw_tag(job, style, s.text);
+            }
+            MarkdownType::WebLink => {
+                draw_link(job, style, s.text);
             }
             MarkdownType::Link => {
-                draw_link(&mut job, &s.text);
+                if broken {
+                    draw_broken_link(job, style, s.text);
+                } else {
+                    draw_link(job, style, s.text);
+                }
             }
             MarkdownType::Monospace => {
-                draw_monospace(&mut job, &s.text);
+                draw_monospace(job, style, s.text);
             }
-            MarkdownType::Code => {
-                draw_monospace(&mut job, &s.text);
+            MarkdownType::Bullet(_) => {
+                draw_list_marker(job, style, s.text, false);
             }
+            MarkdownType::Numbered(_) => {
+                draw_list_marker(job, style, s.text, true);
+            }
+            MarkdownType::Task(_) => {
+                draw_list_marker(job, style, s.text, false);
+            }
+            MarkdownType::Math(_) => {
+                draw_monospace_in(job, style, s.text, style.theme.math);
+            }
+            MarkdownType::Frontmatter | MarkdownType::Toc => {
+                draw_monospace_in(job, style, s.text, style.theme.faint);
+            }
+            MarkdownType::Code(language) => match blocks.get(language.as_str()) {
+                Some(render) => render(job, s.text),
+                None => draw_code(job, style, s.text, &language),
+            },
             _ => {}
         }
+        if let Some(rule) = style.sheet.rule(element) {
+            for section in &mut job.sections[first..] {
+                rule.apply(&mut section.format);
+            }
+        }
+    }
+}
+
+// the byte ranges of the math among parsed pieces
+fn math_spans(strings: &[MarkdownStr<'_>]) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    for s in strings {
+        if let MarkdownType::Math(_) = s.mdtype {
+            spans.push(pos..pos + s.text.len());
+        }
+        pos += s.text.len();
+    }
+    return spans;
+}
+
+// the byte ranges and languages of the fenced blocks among parsed pieces that can be drawn
+fn diagram_spans(strings: &[MarkdownStr<'_>]) -> Vec<(Range<usize>, String)> {
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    for s in strings {
+        if let MarkdownType::Code(language) = &s.mdtype
+            && diagrams::can_draw(language)
+        {
+            spans.push((pos..pos + s.text.len(), language.clone()));
+        }
+        pos += s.text.len();
+    }
+    return spans;
+}
+
+// a cheap stand in for text off screen: only headings are picked out, so lines keep their height
+fn render_outline(job: &mut LayoutJob, style: &Style, text: &str) {
+    let mut plain = 0;
+    let mut pos = 0;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches('\n');
+        let level = content.len() - content.trim_start_matches('#').len();
+        if (1..=3).contains(&level) && content[level..].starts_with(' ') {
+            if plain < pos {
+                draw_normal(job, style, &text[plain..pos]);
+            }
+            draw_heading(job, style, content, level);
+            plain = pos + content.len();
+        }
+        pos += line.len();
+    }
+    if plain < text.len() {
+        draw_normal(job, style, &text[plain..]);
+    }
+}
+
+// a path typed into a settings field, nothing when it's left empty
+fn path_edit(ui: &mut egui::Ui, path: &mut Option<PathBuf>) -> egui::Response {
+    let mut text = path
+        .as_ref()
+        .map_or(String::new(), |p| p.display().to_string());
+    let response = ui.text_edit_singleline(&mut text);
+    if response.changed() {
+        *path = Some(PathBuf::from(text)).filter(|p| !p.as_os_str().is_empty());
     }
-    return job;
+    return response;
 }
 
 fn make_color32(inp: &Color) -> Color32 {
     match inp {
-        Color::Rgba(rgba) => Color32::from_rgb(rgba.red, rgba.green, rgba.blue),
+        Color::Rgba(rgba) => Color32::from_rgba_unmultiplied(
+            rgba.red,
+            rgba.green,
+            rgba.blue,
+            (rgba.alpha * 255.0).round() as u8,
+        ),
         _ => Color32::TRANSPARENT,
     }
 }
 
 impl NoteRs {
-    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    fn new(cc: &eframe::CreationContext<'_>, logs: LogBuffer) -> Self {
         // Customize egui here with cc.egui_ctx.set_fonts and cc.egui_ctx.set_visuals.
-        // Restore app state using cc.storage (requires the "persistence" feature).
         // Use the cc.gl (a glow::Context) to create graphics shaders and buffers that you can use
         // for e.g. egui::PaintCallback.
-        let mut new_one = Self::default();
-        match env::home_dir() {
-            Some(path) => {
-                new_one.root = path;
-                new_one.root.push("NoteRs");
+        let mut new_one = Self {
+            logs,
+            plugins: Plugins::builtin(),
+            assist: assist::Config::from_env(),
+            ..Default::default()
+        };
+        if let Some(path) = config::path() {
+            let (config, errors) = Config::load(&path);
+            for error in errors {
+                new_one.toasts.error(error);
+            }
+            new_one.config = config;
+            let (sheet, errors) = StyleSheet::load(&path.with_file_name("style.css"));
+            for error in errors {
+                new_one.toasts.error(error);
+            }
+            new_one.stylesheet = Arc::new(sheet);
+            for error in new_one.plugins.load(&path.with_file_name("plugins")) {
+                new_one.toasts.error(error);
             }
-            None => println!("Impossible to get your home dir!"),
+        }
+        match new_one.config.vault.clone().or_else(files::vault_root) {
+            Some(path) => new_one.root = path,
+            None => new_one.toasts.error(tr(
+                "Couldn't find your home directory, notes won't be saved",
+            )),
         }
 
-        // TODO: figure out a qt way to do this too
-        let colors = linux_theme::gtk::current::current().0;
-        //new_one.bg_color = make_color32(colors.get("window_bg_color").unwrap());
-        // TODO: pull these in using a qt lib/detect GTK and use other lib
-        new_one.bg_color = Color32::from_rgb(30, 32, 48);
-        new_one.fg_color = Color32::from_rgb(202, 211, 248);
-        new_one.open_file("index.md".to_string());
-
-        let mut visuals = Visuals::dark();
-        visuals.window_fill = new_one.bg_color;
-        visuals.panel_fill = new_one.bg_color;
-        cc.egui_ctx.set_visuals(visuals);
+        for error in new_one.scripts.load(&new_one.root) {
+            new_one.toasts.error(error);
+        }
+        for error in new_one.hooks.load(&new_one.root) {
+            new_one.toasts.error(error);
+        }
+        new_one.register_commands();
+        for error in new_one.commands.bind(&new_one.config.keys) {
+            new_one.toasts.error(error);
+        }
+        new_one.views = Views::load(&new_one.root);
+        new_one.folds = Folds::load(&new_one.root);
+        new_one.reminders.rescan(&new_one.root);
+        new_one.link_syntax = vault::link_syntax(&new_one.root);
+        if env::var("NOTERS_LAN_SYNC").is_ok_and(|v| v == "1") {
+            new_one.set_lan_sync(true);
+        }
 
-        println!("{:?}", new_one.bg_color);
+        new_one.load_fonts(&cc.egui_ctx);
+        new_one.apply_config(&cc.egui_ctx);
+        new_one.highlight_line = true;
+        // pick up where the last session left off
+        let session = Session::load(cc.storage, &new_one.root);
+        if let Some((note, view)) = session.note.as_ref().zip(session.view) {
+            new_one.views.remember(Path::new(note), view);
+        }
+        new_one.nav_history = session.back;
+        new_one.nav_forward = session.forward;
+        new_one.show_logs = session.show_logs;
+        new_one.show_plugins = session.show_plugins;
+        new_one.outline.open = session.show_outline;
+        new_one.pdf_width = session.pdf_width.unwrap_or(260.0);
+        new_one.open_file(session.note.unwrap_or_else(|| "index.md".to_string()));
 
         return new_one;
     }
 
+    // put the theme, autosave and stamp formats from the config in place, the vault only changes on a restart
+    fn apply_config(&mut self, ctx: &egui::Context) {
+        self.apply_theme(ctx);
+        self.autosave.enabled = self.config.autosave;
+        self.autosave.delay = Duration::from_secs_f32(self.config.autosave_delay);
+        self.stamp_formats = self.config.stamps.clone();
+    }
+
+    fn load_fonts(&mut self, ctx: &egui::Context) {
+        for error in self.config.fonts.load(ctx) {
+            self.toasts
+                .error(trf("Couldn't load the font {}", &[&error]));
+        }
+    }
+
+    fn apply_theme(&mut self, ctx: &egui::Context) {
+        self.theme = self.config.theme(self.platform_theme.current());
+        self.theme.apply(ctx);
+    }
+
+    // follow the desktop's colors as they change, when the config says to
+    fn poll_platform_theme(&mut self, ctx: &egui::Context) {
+        if self.config.theme == "system" && self.platform_theme.poll(ctx) {
+            self.apply_theme(ctx);
+        }
+    }
+
+    // start reading a note in the background, the current one stays up until it arrives
     fn open_file(&mut self, path: String) {
-        self.path = self.root.clone();
-
-        let binding = PathBuf::from(path);
-        let mut iter = binding.components().peekable();
-        while let Some(item) = iter.next() {
-            let is_last = iter.peek().is_none();
-            self.path.push(item);
-
-            if is_last {
-                if self.path.exists() {
-                    if self.path.is_dir() {
-                        println!("exists already, add index.md");
-                        self.path.push("index.md");
-                    } else {
-                        println!("path is a file");
-                    }
-                } else {
-                    println!("not a folder, add .md");
-                    self.path.set_extension("md");
+        // edits to the note being left are kept rather than dropped with it
+        if self.note.is_modified() {
+            self.save_file(false);
+        }
+        let root = self.root.clone();
+        self.loading = Some(Task::spawn(move || files::load(&root, &path)));
+    }
+
+    // force writes over whatever is on disk, otherwise a file changed elsewhere is left alone
+    fn save_file(&mut self, force: bool) {
+        self.autosave.saved();
+        // nothing has been opened yet
+        if self.path.as_os_str().is_empty() {
+            return;
+        }
+        self.note.mark_saved();
+        self.saver.save(
+            self.path.clone(),
+            self.note.full().to_string(),
+            self.encoding,
+            force,
+        );
+    }
+
+    // replace the open note with what's on disk, or both versions marked up for merging by hand
+    fn take_disk(&mut self, disk: Disk, merge: bool) {
+        let text = if merge {
+            merge::conflict_markers(self.note.full(), &disk.text)
+        } else {
+            disk.text
+        };
+        self.saver.track(self.path.clone(), Some(disk.fingerprint));
+        self.encoding = disk.encoding;
+        self.note = NoteBuffer(Note::new(text));
+        self.cursor_range = CCursorRange::default();
+    }
+
+    // closing with edits that autosave isn't going to keep asks what to do with them first
+    fn catch_close(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.viewport().close_requested())
+            && self.note.is_modified()
+            && !self.autosave.enabled
+            && !self.discard_on_close
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.close_prompt = true;
+        }
+    }
+
+    fn close_prompt(&mut self, ctx: &egui::Context) {
+        if !self.close_prompt {
+            return;
+        }
+        let mut choice = None;
+        egui::Modal::new(egui::Id::new("close prompt")).show(ctx, |ui| {
+            ui.label(trf(
+                "Save the changes to {} before closing?",
+                &[&self.path.display()],
+            ));
+            ui.horizontal(|ui| {
+                if ui.button(tr("Save")).clicked() {
+                    choice = Some("save");
                 }
-            } else {
-                if let Err(e) = fs::create_dir_all(self.path.as_path()) {
-                    eprintln!("Failed to create directory: {}", e);
+                if ui.button(tr("Don't save")).clicked() {
+                    choice = Some("discard");
+                }
+                if ui.button(tr("Cancel")).clicked() {
+                    choice = Some("cancel");
                 }
+            });
+        });
+        match choice {
+            // on_exit waits for the write to finish
+            Some("save") => {
+                self.close_prompt = false;
+                self.save_file(false);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
             }
+            Some("discard") => {
+                self.close_prompt = false;
+                self.discard_on_close = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+            Some(_) => self.close_prompt = false,
+            None => {}
         }
+    }
 
-        println!("opening {}", self.path.display());
-        if self.path.exists() {
-            match fs::read_to_string(self.path.as_path()) {
-                Ok(text) => {
-                    println!("`\n{}\n`", text);
-                    self.note = Note::new(text)
+    // a vault's hooks are shell commands, they only run once the user has seen and trusted them
+    fn hooks_prompt(&mut self, ctx: &egui::Context) {
+        let Some(hooks) = self.hooks.untrusted() else {
+            return;
+        };
+        let listed: Vec<String> = hooks
+            .iter()
+            .map(|(hook, command)| format!("{} = {}", hook.name(), command))
+            .collect();
+        let mut trust = None;
+        egui::Modal::new(egui::Id::new("hooks prompt")).show(ctx, |ui| {
+            ui.label(trf(
+                "{} has hooks that run these shell commands, only allow them if you trust whoever wrote the vault:",
+                &[&Hooks::file(&self.root).display()],
+            ));
+            for command in &listed {
+                ui.monospace(command);
+            }
+            ui.horizontal(|ui| {
+                if ui.button(tr("Allow")).clicked() {
+                    trust = Some(true);
+                }
+                if ui.button(tr("Not now")).clicked() {
+                    trust = Some(false);
+                }
+            });
+        });
+        match trust {
+            Some(true) => {
+                if let Err(e) = self.hooks.trust() {
+                    self.toasts.error(e);
                 }
-                Err(e) => println!("error opening file: {e:?}"),
             }
-        } else {
-            self.note = Note::default();
+            Some(false) => self.hooks.dismiss(),
+            None => {}
         }
     }
 
-    fn save_file(&mut self) {
-        let text = self.note.full();
-        println!("Writing {}: {}", self.path.display(), text);
-        fs::write(self.path.as_path(), text.as_bytes());
-    }
-}
-
-impl eframe::App for NoteRs {
-    fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let text_edit_id = ui.make_persistent_id("editor");
-            ui.heading(self.path.display().to_string());
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                let mut layouter = |ui: &egui::Ui, buf: &dyn TextBuffer, _wrap_width: f32| {
-                    // TODO: consider how to make this faster than just reparsing the whole thing
-                    //let new_note = Note::new(buf.as_str().to_string());
-                    //let job = render_markdown(new_note.markdown());
-                    let job = render_markdown(highlight_parse(buf.as_str()));
-
-                    ui.fonts_mut(|f| f.layout_job(job))
-                };
-                let editor = egui::TextEdit::multiline(&mut self.note)
-                    .desired_width(f32::INFINITY)
-                    .desired_rows((ctx.content_rect().height() / 16f32) as usize)
-                    .layouter(&mut layouter)
-                    .id(text_edit_id)
-                    .show(ui);
-                let response = editor.response;
-                let galley = editor.galley;
-                let painter = ui.painter();
-
-                if let Some(cursor_range) = editor.cursor_range {
-                    if self.cursor_range.primary.index != cursor_range.primary.index
-                        || self.cursor_range.secondary.index != cursor_range.primary.index
+    fn conflict_window(&mut self, ctx: &egui::Context) {
+        let Some(disk) = self.conflict.take() else {
+            return;
+        };
+        let mut choice = None;
+        egui::Window::new(tr("Changed on disk"))
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(trf(
+                    "{} was changed by another program since it was opened.",
+                    &[&self.path.display()],
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Overwrite")).clicked() {
+                        choice = Some("overwrite");
+                    }
+                    if ui.button(tr("Reload")).clicked() {
+                        choice = Some("reload");
+                    }
+                    if ui
+                        .button(tr("Merge"))
+                        .on_hover_text(tr("Mark up both versions to resolve by hand"))
+                        .clicked()
                     {
-                        println!("cursor moved: {:?}", cursor_range);
+                        choice = Some("merge");
                     }
-                    self.cursor_range = cursor_range;
-                }
+                    if ui.button(tr("Cancel")).clicked() {
+                        choice = Some("cancel");
+                    }
+                });
+            });
+        match choice {
+            Some("overwrite") => self.save_file(true),
+            Some("reload") => self.take_disk(disk, false),
+            Some("merge") => {
+                self.take_disk(disk, true);
+                self.toasts
+                    .info(tr("Resolve the marked conflicts, then save again"));
+            }
+            Some(_) => {}
+            None => self.conflict = Some(disk),
+        }
+    }
 
-                if response.clicked() {
-                    if let Some(pos) = response.interact_pointer_pos() {
-                        let local_pos = pos - response.rect.min;
-                        let cursor = galley.cursor_from_pos(local_pos);
-                        let idx = cursor.index;
+    // list the vault's notes again in the background, for finding broken links
+    fn read_known_notes(&mut self) {
+        let root = self.root.clone();
+        self.reading_notes = Some(Task::spawn(move || {
+            return vault::list_notes(&root).into_iter().collect();
+        }));
+    }
 
-                        let node = self.note.get_node(idx);
-                        match node.mdtype {
-                            MarkdownType::Link => {
-                                self.nav_history
-                                    .push(self.path.to_str().unwrap().to_string());
-                                self.nav_forward.clear();
-                                self.open_file(node.text[2..].to_string());
-                            }
-                            _ => {}
-                        }
-                    }
-                } else {
-                    // change the cursor icon when moving the mouse
-                    if let Some(p) = ctx.input_mut(|i| i.pointer.hover_pos()) {
-                        let local_pos = p - response.rect.min;
-                        let cursor = galley.cursor_from_pos(local_pos);
-                        let idx = cursor.index;
-                        let node = self.note.get_node(idx);
-                        match node.mdtype {
-                            MarkdownType::Link => {
-                                ctx.output_mut(|out| out.cursor_icon = CursorIcon::PointingHand)
-                            }
-                            _ => {}
-                        }
+    // pick up reads and writes that finished on their threads
+    fn poll_files(&mut self, ctx: &egui::Context) {
+        if let Some(notes) = self.reading_notes.as_ref().and_then(Task::poll) {
+            self.reading_notes = None;
+            self.known_notes = Some(Arc::new(notes));
+        }
+        if let Some(loaded) = self.loading.as_ref().and_then(Task::poll) {
+            self.loading = None;
+            match loaded {
+                Ok((path, disk)) => {
+                    self.saver
+                        .track(path.clone(), disk.as_ref().map(|d| d.fingerprint));
+                    let (text, encoding) = disk.map(|d| (d.text, d.encoding)).unwrap_or_default();
+                    if encoding.lossy {
+                        self.toasts.warning(trf(
+                            "{} isn't valid UTF-8, some characters were replaced",
+                            &[&path.display()],
+                        ));
                     }
-
-                    let mut index = 0;
-                    for item in self.note.markdown() {
-                        index += item.text.len();
-                        match item.mdtype {
-                            MarkdownType::Heading1
-                            | MarkdownType::Heading2
-                            | MarkdownType::Heading3 => {
-                                painter.text(
-                                    galley.pos_from_cursor(CCursor::new(index)).min,
-                                    egui::Align2::LEFT_TOP,
-                                    if item.expanded { "V" } else { ">" },
-                                    egui::FontId::default(),
-                                    ui.visuals().text_color(),
-                                );
-                            }
-                            _ => {}
-                        }
+                    if self.collab.take().is_some() {
+                        self.toasts.info(tr("Left the collaboration session"));
                     }
+                    self.events.publish(Event::NoteOpened(path.clone()));
+                    self.read_known_notes();
+                    self.remember_view();
+                    self.unsaved_new = !path.exists();
+                    self.pending_view = self.views.get(&path);
+                    self.path = path;
+                    self.encoding = encoding;
+                    self.note = NoteBuffer(Note::new(text));
+                    self.folds.restore(&self.path, &mut self.note);
+                    self.cursor_range = CCursorRange::default();
+                    self.scroll_offset = 0.0;
+                    self.autosave.saved();
+                    self.last_saved = None;
                 }
-
-                if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::S)) {
-                    self.save_file();
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                    self.toasts.error(e.to_string());
                 }
-                if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::T)) {
-                    // TODO: translate and toggle
-                    let path = self.note.path(self.cursor_range.primary.index);
-                    let mut global_cursor = (
-                        self.note.translate(self.cursor_range.primary.index),
-                        self.note.translate(self.cursor_range.secondary.index),
-                    );
-                    self.note.toggle(path.as_slice());
-                    self.note.refresh();
-                    global_cursor.0 = self.note.inv_translate(global_cursor.0);
-                    global_cursor.1 = self.note.inv_translate(global_cursor.1);
-
-                    println!("updating cursor to: {:?}", editor.cursor_range);
-
-                    if let Some(mut state) = TextEditState::load(ui.ctx(), text_edit_id) {
-                        // Move cursor to position 10
-                        //let cursor = editor.cursor_range; //CCursorRange::one(egui::text::CCursor::new(10));
-                        println!("really updating");
-                        state.cursor.set_char_range(Some(CCursorRange::two(
-                            egui::text::CCursor::new(global_cursor.0),
-                            egui::text::CCursor::new(global_cursor.1),
-                        )));
-                        state.store(ui.ctx(), text_edit_id);
+            }
+        }
+        for saved in self.saver.poll() {
+            match saved {
+                Ok(Saved::Written(path)) => {
+                    if path == self.path && self.unsaved_new {
+                        self.unsaved_new = false;
+                        self.read_known_notes();
+                        self.events.publish(Event::NoteCreated(path.clone()));
                     }
+                    self.events.publish(Event::NoteSaved(path.clone()));
+                    self.toasts.info(trf("Saved {}", &[&path.display()]));
                 }
-                if ctx.input_mut(|i| i.consume_key(egui::Modifiers::ALT, egui::Key::ArrowLeft)) {
-                    println!("Nav back");
-
-                    match self.nav_history.pop() {
-                        Some::<String>(s) => {
-                            self.nav_forward
-                                .push(self.path.to_str().unwrap().to_string());
-                            self.open_file(s);
-                        }
-                        _ => {}
+                Ok(Saved::Conflict(path, disk)) => {
+                    if path == self.path {
+                        self.conflict = Some(disk);
+                    } else {
+                        self.toasts.warning(trf(
+                            "{} changed on disk and wasn't saved",
+                            &[&path.display()],
+                        ));
                     }
                 }
-                if ctx.input_mut(|i| i.consume_key(egui::Modifiers::ALT, egui::Key::ArrowRight)) {
-                    println!("Nav forward {:?} {:?}", self.nav_history, self.nav_forward);
-
-                    match self.nav_forward.pop() {
-                        Some::<String>(s) => {
-                            self.nav_history
-                                .push(self.path.to_str().unwrap().to_string());
-                            self.open_file(s);
-                        }
-                        _ => {}
-                    }
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                    self.toasts.error(e.to_string());
                 }
-            });
-        });
+            }
+        }
+        let panes =
+            std::iter::once(&mut self.pane).chain(self.windows.iter_mut().map(|w| &mut w.1));
+        for pane in panes {
+            if let Some(e) = pane.poll(ctx, &mut self.saver) {
+                tracing::warn!("{}", e);
+                self.toasts.error(e);
+            }
+        }
+        for error in self.hooks.poll() {
+            tracing::warn!("{}", error);
+            self.toasts.error(error);
+        }
+        if self.loading.is_some()
+            || self.reading_notes.is_some()
+            || self.saver.is_saving()
+            || self.hooks.is_running()
+        {
+            ctx.request_repaint_after(Duration::from_millis(50));
+        }
+    }
+
+    fn set_cursor(&mut self, ctx: &egui::Context, id: egui::Id, primary: usize, secondary: usize) {
+        self.cursor_range = CCursorRange {
+            primary: CCursor::new(primary),
+            secondary: CCursor::new(secondary),
+            h_pos: None,
+        };
+        if let Some(mut state) = TextEditState::load(ctx, id) {
+            state.cursor.set_char_range(Some(self.cursor_range));
+            state.store(ctx, id);
+        }
+        self.scroll_to_cursor = true;
+    }
+
+    // note where the open note is scrolled to and where its cursor is, for when it comes back
+    fn remember_view(&mut self) {
+        if self.path.as_os_str().is_empty() {
+            return;
+        }
+        let view = View {
+            scroll: self.scroll_offset,
+            cursor: self.full_char(self.cursor_range.primary.index),
+            anchor: self.full_char(self.cursor_range.secondary.index),
+        };
+        self.views.remember(&self.path.clone(), view);
+    }
+
+    // put a note back the way it was left, without scrolling over to the cursor
+    fn restore_view(&mut self, ctx: &egui::Context, id: egui::Id, view: View) {
+        let len = self.note.full().chars().count();
+        let cursor = self.display_char(view.cursor.min(len));
+        let anchor = self.display_char(view.anchor.min(len));
+        self.set_cursor(ctx, id, cursor, anchor);
+        self.scroll_to_cursor = false;
+        self.restore_scroll = Some(view.scroll);
+    }
+
+    // put the cursor at a position in the full text, unfolding sections to get to it
+    fn goto_position(&mut self, ctx: &egui::Context, id: egui::Id, pos: usize) {
+        let pos = self.note.reveal(pos);
+        self.set_cursor(ctx, id, pos, pos);
+        ctx.memory_mut(|m| m.request_focus(id));
+    }
+
+    fn open_goto_dialog(&mut self) {
+        let headings = self.note.headings();
+        let titles = headings
+            .iter()
+            .map(|h| format!("{}{}", "  ".repeat(h.level - 1), h.title))
+            .collect();
+        self.goto_dialog = Some(GotoDialog {
+            picker: Picker::default(),
+            headings,
+            titles,
+        });
+    }
+
+    // where a line (counted from 1) starts in the full text
+    fn line_position(&mut self, line: usize) -> usize {
+        return self
+            .note
+            .full()
+            .split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(str::len)
+            .sum();
+    }
+
+    // notify about reminders as they come due, and open the note of any that get clicked
+    fn poll_reminders(&mut self, ctx: &egui::Context) {
+        for due in self.reminders.due(Local::now().naive_local()) {
+            self.reminders.fire(due);
+        }
+        for ((note, reminder), clicked) in self.reminders.poll() {
+            match clicked {
+                Ok(true) => {
+                    self.open_file(note);
+                    self.pending_line = Some(reminder.line);
+                }
+                Ok(false) => {}
+                // no desktop notifications, so make do with a toast
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                    self.toasts
+                        .warning(trf("Reminder: {} ({})", &[&reminder.text, &note]));
+                }
+            }
+        }
+        ctx.request_repaint_after(Duration::from_secs(10));
+    }
+
+    // fold or unfold the section holding a displayed position, the cursor stays on the same text
+    fn toggle_section(&mut self, ctx: &egui::Context, id: egui::Id, pos: usize) {
+        // TODO: translate and toggle
+        let path = self.note.path(pos);
+        let mut global_cursor = (
+            self.note.translate(self.cursor_range.primary.index),
+            self.note.translate(self.cursor_range.secondary.index),
+        );
+        self.note.toggle(path.as_slice());
+        self.note.refresh();
+        self.events.publish(Event::SectionToggled(path));
+        global_cursor.0 = self.note.inv_translate(global_cursor.0);
+        global_cursor.1 = self.note.inv_translate(global_cursor.1);
+
+        tracing::debug!("updating cursor to: {:?}", global_cursor);
+
+        if let Some(mut state) = TextEditState::load(ctx, id) {
+            state.cursor.set_char_range(Some(CCursorRange::two(
+                egui::text::CCursor::new(global_cursor.0),
+                egui::text::CCursor::new(global_cursor.1),
+            )));
+            state.store(ctx, id);
+        }
+    }
+
+    // what each feature can be asked to do, and the keys that ask for it
+    fn register_commands(&mut self) {
+        use egui::{Key, KeyboardShortcut as Keys, Modifiers};
+        const COMMAND: Modifiers = Modifiers::COMMAND;
+        const SHIFT: Modifiers = Modifiers::SHIFT;
+        const ALT: Modifiers = Modifiers::ALT;
+        let commands = &mut self.commands;
+        commands.register(
+            Command::Palette,
+            "Command palette",
+            &[Keys::new(COMMAND | SHIFT, Key::P)],
+        );
+        commands.register(Command::Save, "Save", &[Keys::new(COMMAND, Key::S)]);
+        commands.register(
+            Command::ToggleFold,
+            "Fold or unfold",
+            &[Keys::new(COMMAND, Key::T)],
+        );
+        commands.register(
+            Command::NavBack,
+            "Go back",
+            &[Keys::new(ALT, Key::ArrowLeft)],
+        );
+        commands.register(
+            Command::NavForward,
+            "Go forward",
+            &[Keys::new(ALT, Key::ArrowRight)],
+        );
+        commands.register(Command::NewNote, "New note", &[Keys::new(COMMAND, Key::N)]);
+        commands.register(Command::RenameNote, "Rename note", &[]);
+        commands.register(
+            Command::OpenNote,
+            "Open note",
+            &[Keys::new(COMMAND, Key::P)],
+        );
+        commands.register(
+            Command::InsertLink,
+            "Insert link",
+            &[Keys::new(COMMAND, Key::K)],
+        );
+        commands.register(Command::Bold, "Bold", &[Keys::new(COMMAND, Key::B)]);
+        commands.register(Command::Italic, "Italic", &[Keys::new(COMMAND, Key::I)]);
+        commands.register(Command::Code, "Inline code", &[Keys::new(COMMAND, Key::E)]);
+        commands.register(Command::GoTo, "Go to", &[Keys::new(COMMAND, Key::G)]);
+        commands.register(
+            Command::SearchNotes,
+            "Search all notes",
+            &[Keys::new(COMMAND | SHIFT, Key::F)],
+        );
+        commands.register(
+            Command::FindInNote,
+            "Find in note",
+            &[Keys::new(COMMAND, Key::F)],
+        );
+        commands.register(Command::ReplaceInNotes, "Replace in all notes", &[]);
+        commands.register(Command::Tags, "Browse tags", &[]);
+        commands.register(Command::Outline, "Show or hide the outline", &[]);
+        commands.register(Command::Settings, "Settings", &[]);
+        commands.register(
+            Command::Window,
+            "Open in new window",
+            &[Keys::new(COMMAND | SHIFT, Key::N)],
+        );
+        commands.register(
+            Command::Split,
+            "Split view",
+            &[Keys::new(COMMAND, Key::Backslash)],
+        );
+        commands.register(Command::Undo, "Undo", &[Keys::new(COMMAND, Key::Z)]);
+        commands.register(
+            Command::Redo,
+            "Redo",
+            &[
+                Keys::new(COMMAND | SHIFT, Key::Z),
+                Keys::new(COMMAND, Key::Y),
+            ],
+        );
+        commands.register(
+            Command::DemoteHeading,
+            "Demote heading",
+            &[Keys::new(COMMAND, Key::Period)],
+        );
+        commands.register(
+            Command::PromoteHeading,
+            "Promote heading",
+            &[Keys::new(COMMAND, Key::Comma)],
+        );
+        commands.register(
+            Command::ExpandSelection,
+            "Expand selection",
+            &[Keys::new(ALT | SHIFT, Key::ArrowRight)],
+        );
+        commands.register(
+            Command::MoveLinesUp,
+            "Move lines up",
+            &[Keys::new(ALT, Key::ArrowUp)],
+        );
+        commands.register(
+            Command::MoveLinesDown,
+            "Move lines down",
+            &[Keys::new(ALT, Key::ArrowDown)],
+        );
+        commands.register(
+            Command::DuplicateLines,
+            "Duplicate lines",
+            &[Keys::new(COMMAND | SHIFT, Key::D)],
+        );
+        commands.register(
+            Command::SortLines,
+            "Sort lines",
+            &[Keys::new(Modifiers::NONE, Key::F9)],
+        );
+        commands.register(Command::UpperCase, "Change to uppercase", &[]);
+        commands.register(Command::LowerCase, "Change to lowercase", &[]);
+        commands.register(Command::TitleCase, "Change to title case", &[]);
+        commands.register(
+            Command::PasteImage,
+            "Paste image",
+            &[Keys::new(COMMAND | SHIFT, Key::I)],
+        );
+        commands.register(
+            Command::InsertDate,
+            "Insert date",
+            &[Keys::new(COMMAND, Key::Semicolon)],
+        );
+        commands.register(
+            Command::InsertTime,
+            "Insert time",
+            &[Keys::new(COMMAND | SHIFT, Key::Semicolon)],
+        );
+        commands.register(Command::InsertTimestamp, "Insert timestamp", &[]);
+        commands.register(
+            Command::SelectNextOccurrence,
+            "Select next occurrence",
+            &[Keys::new(COMMAND, Key::D)],
+        );
+        commands.register(
+            Command::NextOccurrence,
+            "Next occurrence",
+            &[Keys::new(Modifiers::NONE, Key::F3)],
+        );
+        commands.register(
+            Command::PreviousOccurrence,
+            "Previous occurrence",
+            &[Keys::new(SHIFT, Key::F3)],
+        );
+    }
+
+    fn run_command(&mut self, ctx: &egui::Context, id: egui::Id, command: Command) {
+        tracing::debug!("run {:?}", command);
+        match command {
+            Command::Palette => self.commands.open_palette(),
+            Command::Save => {
+                self.save_file(false);
+                self.pane.save(&mut self.saver);
+                for (_, pane) in &mut self.windows {
+                    pane.save(&mut self.saver);
+                }
+            }
+            Command::ToggleFold => self.toggle_section(ctx, id, self.cursor_range.primary.index),
+            Command::NavBack => {
+                tracing::debug!("nav back {:?}", self.nav_history);
+                if let Some(s) = self.nav_history.pop() {
+                    self.nav_forward
+                        .push(self.path.to_str().unwrap().to_string());
+                    self.open_file(s);
+                }
+            }
+            Command::NavForward => {
+                tracing::debug!("nav forward {:?}", self.nav_forward);
+                if let Some(s) = self.nav_forward.pop() {
+                    self.nav_history
+                        .push(self.path.to_str().unwrap().to_string());
+                    self.open_file(s);
+                }
+            }
+            Command::NewNote => self.open_new_note_dialog(),
+            Command::RenameNote => {
+                if !self.path.as_os_str().is_empty() {
+                    self.rename_dialog = Some(RenameDialog {
+                        name: self.note_name(),
+                        ..Default::default()
+                    });
+                }
+            }
+            Command::InsertLink => self.toggle_link(ctx, id),
+            Command::Bold => self.toggle_format(ctx, id, Format::Bold),
+            Command::Italic => self.toggle_format(ctx, id, Format::Italic),
+            Command::Code => self.toggle_format(ctx, id, Format::Code),
+            Command::OpenNote => {
+                self.switch_dialog = Some(SwitchDialog {
+                    notes: vault::list_notes(&self.root),
+                    ..Default::default()
+                });
+            }
+            Command::GoTo => self.open_goto_dialog(),
+            Command::SearchNotes => self.finder.show_panel(),
+            Command::ReplaceInNotes => self.replace_dialog = Some(ReplaceDialog::default()),
+            Command::FindInNote => {
+                let find = self.selected_text();
+                let bar = self.find_bar.get_or_insert_default();
+                if !find.is_empty() && !find.contains('\n') {
+                    bar.find = if bar.regex {
+                        search::escape(&find)
+                    } else {
+                        find
+                    };
+                }
+                bar.focus = true;
+            }
+            Command::Tags => self.tag_browser.show_panel(&self.root),
+            Command::Outline => self.outline.open = !self.outline.open,
+            Command::Settings => self.settings = Some(self.config.clone()),
+            Command::Split => self.toggle_split(),
+            Command::Window => self.detach_note(),
+            Command::Undo => self.undo(ctx, id, false),
+            Command::Redo => self.undo(ctx, id, true),
+            Command::DemoteHeading => self.shift_heading(ctx, id, true),
+            Command::PromoteHeading => self.shift_heading(ctx, id, false),
+            Command::ExpandSelection => self.expand_selection(ctx, id),
+            Command::MoveLinesUp => self.move_lines(ctx, id, true),
+            Command::MoveLinesDown => self.move_lines(ctx, id, false),
+            Command::DuplicateLines => self.duplicate_lines(ctx, id),
+            Command::SortLines => self.sort_lines(ctx, id),
+            Command::UpperCase => self.transform_case(ctx, id, TextCase::Upper),
+            Command::LowerCase => self.transform_case(ctx, id, TextCase::Lower),
+            Command::TitleCase => self.transform_case(ctx, id, TextCase::Title),
+            Command::PasteImage => self.paste_image(ctx, id),
+            Command::InsertDate => self.insert_stamp(ctx, id, Stamp::Date),
+            Command::InsertTime => self.insert_stamp(ctx, id, Stamp::Time),
+            Command::InsertTimestamp => self.insert_stamp(ctx, id, Stamp::Timestamp),
+            Command::SelectNextOccurrence => self.select_next_occurrence(ctx, id),
+            Command::NextOccurrence => self.jump_to_occurrence(ctx, id, true),
+            Command::PreviousOccurrence => self.jump_to_occurrence(ctx, id, false),
+        }
+        // side panels are drawn before the editor, they show up on the next frame
+        ctx.request_repaint();
+    }
+
+    // save once the typing has settled
+    fn poll_autosave(&mut self, ctx: &egui::Context) {
+        let now = Instant::now();
+        if self.autosave.is_due(now) {
+            tracing::debug!("autosaving");
+            self.save_file(false);
+        }
+        if let Some(wait) = self.autosave.wait(now) {
+            ctx.request_repaint_after(wait);
+        }
+    }
+
+    fn set_autosave(&mut self, enabled: bool) {
+        self.autosave.enabled = enabled;
+        self.config.autosave = enabled;
+        self.save_config();
+    }
+
+    fn save_config(&mut self) {
+        let Some(path) = config::path() else {
+            return;
+        };
+        if let Err(e) = self.config.save(&path) {
+            self.toasts
+                .error(trf("Couldn't save the settings: {}", &[&e]));
+        }
+    }
+
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let Some(settings) = &mut self.settings else {
+            return;
+        };
+        let mut open = true;
+        let mut save = false;
+        egui::Window::new(tr("Settings"))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("settings").num_columns(2).show(ui, |ui| {
+                    ui.label(tr("Vault"));
+                    path_edit(ui, &mut settings.vault)
+                        .on_hover_text(tr("Takes effect the next time NoteRs starts"));
+                    ui.end_row();
+                    ui.label(tr("Theme"));
+                    let selected = match settings.theme.as_str() {
+                        "system" => tr("System").to_string(),
+                        name => name.to_string(),
+                    };
+                    egui::ComboBox::from_id_salt("theme")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut settings.theme,
+                                "system".to_string(),
+                                tr("System"),
+                            );
+                            for theme in &settings.themes {
+                                ui.selectable_value(
+                                    &mut settings.theme,
+                                    theme.name.clone(),
+                                    &theme.name,
+                                );
+                            }
+                        });
+                    ui.end_row();
+                    // shown as the theme has them until they're changed
+                    let theme = settings.theme(self.platform_theme.current());
+                    ui.label(tr("Background"));
+                    let mut background = theme.background;
+                    if ui.color_edit_button_srgba(&mut background).changed() {
+                        settings.background = Some(background);
+                    }
+                    ui.end_row();
+                    ui.label(tr("Text"));
+                    let mut text = theme.text;
+                    if ui.color_edit_button_srgba(&mut text).changed() {
+                        settings.foreground = Some(text);
+                    }
+                    ui.end_row();
+                    ui.label("");
+                    if ui
+                        .add_enabled(
+                            settings.background.is_some() || settings.foreground.is_some(),
+                            egui::Button::new(tr("Use the theme's colors")),
+                        )
+                        .clicked()
+                    {
+                        settings.background = None;
+                        settings.foreground = None;
+                    }
+                    ui.end_row();
+                    let fonts = &mut settings.fonts;
+                    ui.label(tr("Font"));
+                    path_edit(ui, &mut fonts.proportional)
+                        .on_hover_text(tr("A .ttf or .otf file, empty for the built in font"));
+                    ui.end_row();
+                    ui.label(tr("Font size"));
+                    ui.add(egui::DragValue::new(&mut fonts.size).range(6.0..=72.0));
+                    ui.end_row();
+                    ui.label(tr("Monospace font"));
+                    path_edit(ui, &mut fonts.monospace)
+                        .on_hover_text(tr("A .ttf or .otf file, empty for the built in font"));
+                    ui.end_row();
+                    ui.label(tr("Monospace font size"));
+                    ui.add(egui::DragValue::new(&mut fonts.monospace_size).range(6.0..=72.0));
+                    ui.end_row();
+                    ui.checkbox(&mut settings.autosave, tr("Autosave"));
+                    ui.add_enabled(
+                        settings.autosave,
+                        egui::DragValue::new(&mut settings.autosave_delay)
+                            .range(0.0..=600.0)
+                            .suffix(tr(" s")),
+                    );
+                    ui.end_row();
+                });
+                save = ui.button(tr("Save")).clicked();
+            });
+        if save {
+            let settings = self.settings.take().unwrap();
+            let fonts_changed = settings.fonts != self.config.fonts;
+            self.config = settings;
+            if fonts_changed {
+                self.load_fonts(ctx);
+            }
+            self.apply_config(ctx);
+            self.save_config();
+        } else if !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.settings = None;
+        }
+    }
+
+    fn split_pane(&mut self, ctx: &egui::Context) {
+        let blocks = self.plugins.blocks();
+        let look = Look {
+            blocks: &blocks,
+            known: self.known_notes.as_deref(),
+            style: Style {
+                theme: &self.theme,
+                fonts: &self.config.fonts,
+                sheet: &self.stylesheet,
+            },
+        };
+        let followed = self
+            .pane
+            .show(ctx, &self.root, (&self.path, &mut self.note), &look);
+        match followed {
+            Some(Followed::Here(note)) => self.pane.open_note(&self.root, note, &mut self.saver),
+            Some(Followed::Other(note)) if note != self.note_name() => self.visit(note),
+            _ => {}
+        }
+        if !self.pane.open {
+            self.pane.save(&mut self.saver);
+        }
+    }
+
+    fn note_windows(&mut self, ctx: &egui::Context) {
+        let blocks = self.plugins.blocks();
+        let look = Look {
+            blocks: &blocks,
+            known: self.known_notes.as_deref(),
+            style: Style {
+                theme: &self.theme,
+                fonts: &self.config.fonts,
+                sheet: &self.stylesheet,
+            },
+        };
+        let mut followed = Vec::new();
+        for (id, pane) in &mut self.windows {
+            let link = pane.window(ctx, *id, &self.root, (&self.path, &mut self.note), &look);
+            followed.push(link);
+        }
+        for (i, link) in followed.into_iter().enumerate() {
+            match link {
+                Some(Followed::Here(note)) => {
+                    self.windows[i]
+                        .1
+                        .open_note(&self.root, note, &mut self.saver)
+                }
+                Some(Followed::Other(note)) if note != self.note_name() => self.visit(note),
+                _ => {}
+            }
+        }
+        for (_, pane) in &mut self.windows {
+            if !pane.open {
+                pane.save(&mut self.saver);
+            }
+        }
+        self.windows.retain(|(_, pane)| pane.open);
+    }
+
+    // show the open note in a window of its own as well
+    fn detach_note(&mut self) {
+        if self.path.as_os_str().is_empty() {
+            return;
+        }
+        self.opened_windows += 1;
+        let id = egui::ViewportId::from_hash_of(("note window", self.opened_windows));
+        let mut pane = Pane::default();
+        pane.split(&self.path, &mut self.saver);
+        self.windows.push((id, pane));
+    }
+
+    fn outline_panel(&mut self, ctx: &egui::Context) {
+        if !self.outline.open {
+            return;
+        }
+        let headings = self.note.headings();
+        let text = self.note.as_str();
+        let cursor = self
+            .note
+            .translate(byte_index(text, self.cursor_range.primary.index));
+        let current = headings.iter().rposition(|h| h.pos <= cursor);
+        match self.outline.show(ctx, &headings, current) {
+            Some(Picked::Goto(pos)) => self.pending_position = Some(pos),
+            Some(Picked::Fold(pos)) => self.pending_fold = Some(pos),
+            None => {}
+        }
+    }
+
+    // each heading gets a fold chevron in the gutter beside its first row, in touch mode a
+    //   finger sized one at the end of the row, returns the heading whose chevron was clicked
+    fn fold_gutter(
+        &self,
+        ui: &egui::Ui,
+        galley: &Galley,
+        galley_pos: egui::Pos2,
+        editor: egui::Rect,
+    ) -> Option<usize> {
+        let text = self.note.as_str();
+        let mut clicked = None;
+        let headings = self.note.headings();
+        for (i, heading) in headings.iter().enumerate().filter(|(_, h)| h.visible) {
+            let pos = char_index(text, self.note.inv_translate(heading.pos).min(text.len()));
+            let row = galley
+                .pos_from_cursor(CCursor::new(pos))
+                .translate(galley_pos.to_vec2());
+            let (rect, size) = if self.touch.enabled {
+                (Touch::row_target(row, editor.right()), touch::TARGET * 0.6)
+            } else {
+                let rect = egui::Rect::from_min_max(
+                    egui::pos2(editor.left() - FOLD_GUTTER, row.top()),
+                    egui::pos2(editor.left(), row.bottom()),
+                );
+                (rect, row.height().min(FOLD_GUTTER))
+            };
+            let response = ui
+                .interact(rect, ui.id().with(("fold", i)), egui::Sense::click())
+                .on_hover_cursor(CursorIcon::PointingHand);
+            ui.painter().text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                if heading.expanded { "▾" } else { "▸" },
+                FontId::proportional(size),
+                if response.hovered() {
+                    ui.visuals().strong_text_color()
+                } else {
+                    ui.visuals().weak_text_color()
+                },
+            );
+            if response.clicked() {
+                clicked = Some(pos);
+            }
+        }
+        return clicked;
+    }
+
+    // the web link under the cursor, if there is one
+    fn web_link(&self) -> Option<String> {
+        let text = self.note.as_str();
+        let byte = byte_index(text, self.cursor_range.primary.index);
+        return links::web_link_at(text, byte).map(|(url, _)| url.to_string());
+    }
+
+    fn archive_link(&mut self, url: String) {
+        let root = self.root.clone();
+        self.toasts
+            .info(trf("Saving a readable copy of {}", &[&url]));
+        self.archiving = Some(Task::spawn(move || archive::save(&root, &url)));
+    }
+
+    // link the saved copy in right after the link it came from
+    fn poll_archiving(&mut self, ctx: &egui::Context) {
+        let Some(task) = &self.archiving else {
+            return;
+        };
+        let Some(result) = task.poll() else {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        self.archiving = None;
+        let archived = match result {
+            Ok(archived) => archived,
+            Err(e) => {
+                tracing::warn!("{}", e);
+                self.toasts.error(e);
+                return;
+            }
+        };
+        self.toasts.info(trf(
+            "Saved \"{}\" as {}",
+            &[&archived.title, &archived.name],
+        ));
+        let text = self.note.as_str();
+        let end = text
+            .match_indices(&archived.url)
+            .find_map(|(i, _)| links::web_link_at(text, i).map(|(_, end)| end));
+        if let Some(end) = end {
+            self.apply(Edit::Insert {
+                pos: char_index(text, end),
+                text: format!(" @@{}", archived.name),
+            });
+        }
+    }
+
+    // the image embedded under the cursor, as written in the note
+    fn image_link(&self) -> Option<String> {
+        let text = self.note.as_str();
+        let byte = byte_index(text, self.cursor_range.primary.index);
+        return links::image_links(text)
+            .into_iter()
+            .find(|link| link.range.start <= byte && byte <= link.range.end)
+            .map(|link| link.target.to_string())
+            .filter(|target| ocr::can_read(Path::new(target)));
+    }
+
+    fn extract_text(&mut self, target: String) {
+        let path = self.root.join(&target);
+        self.toasts.info(trf("Extracting text from {}", &[&target]));
+        self.extracting = Some(Task::spawn(move || (target, ocr::extract(&path))));
+    }
+
+    fn poll_extracting(&mut self, ctx: &egui::Context) {
+        let Some(task) = &self.extracting else {
+            return;
+        };
+        let Some((target, result)) = task.poll() else {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        self.extracting = None;
+        let extracted = match result {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                tracing::warn!("{}", e);
+                self.toasts.error(e);
+                return;
+            }
+        };
+        let text = self.note.as_str();
+        let Some(embed) = links::image_links(text)
+            .into_iter()
+            .find(|link| link.target == target)
+        else {
+            self.toasts
+                .warning(trf("{} isn't in the note any more", &[&target]));
+            return;
+        };
+        let end = text[embed.range.end..]
+            .find('\n')
+            .map_or(text.len(), |i| embed.range.end + i);
+        self.apply(Edit::Insert {
+            pos: char_index(text, end),
+            text: format!("\n{}", extracted),
+        });
+    }
+
+    // send the selection, or the section or note around the cursor, to the model
+    fn run_assist(&mut self, command: assist::Command) {
+        let Some(config) = self.assist.clone() else {
+            return;
+        };
+        let primary = self.cursor_range.primary.index;
+        let secondary = self.cursor_range.secondary.index;
+        let all = 0..self.note.as_str().chars().count();
+        let range = match command {
+            _ if primary != secondary => primary.min(secondary)..primary.max(secondary),
+            assist::Command::Summarize => self.note.section_range(primary).unwrap_or(all),
+            _ => all,
+        };
+        let text: String = self
+            .note
+            .as_str()
+            .chars()
+            .skip(range.start)
+            .take(range.len())
+            .collect();
+        if text.trim().is_empty() {
+            self.toasts.warning(tr("There's no text to send"));
+            return;
+        }
+        self.toasts.info(format!("{}…", command.label()));
+        let end = range.end;
+        self.assisting = Some(Task::spawn(move || {
+            (end, assist::run(&config, command, &text))
+        }));
+    }
+
+    fn poll_assisting(&mut self, ctx: &egui::Context) {
+        let Some(task) = &self.assisting else {
+            return;
+        };
+        let Some((end, result)) = task.poll() else {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        self.assisting = None;
+        let answer = match result {
+            Ok(answer) => answer,
+            Err(e) => {
+                tracing::warn!("{}", e);
+                self.toasts.error(e);
+                return;
+            }
+        };
+        let text = self.note.as_str();
+        let byte = byte_index(text, end);
+        let before = if byte == 0 || text[..byte].ends_with("\n\n") {
+            ""
+        } else if text[..byte].ends_with('\n') {
+            "\n"
+        } else {
+            "\n\n"
+        };
+        self.apply(Edit::Insert {
+            pos: char_index(text, byte),
+            text: format!("{}{}\n", before, answer),
+        });
+    }
+
+    fn goto_dialog(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some(mut dialog) = self.goto_dialog.take() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut chosen: Option<usize> = None;
+        egui::Window::new(tr("Go to"))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                dialog.picker.query_box(ui, tr("Line number or heading"));
+
+                if let Ok(line) = dialog.picker.query.trim().parse::<usize>() {
+                    ui.label(trf("Go to line {}", &[&line]));
+                    if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        chosen = Some(self.line_position(line));
+                    }
+                    return;
+                }
+
+                if let Some(i) = dialog.picker.list(ui, &dialog.titles) {
+                    chosen = Some(dialog.headings[i].pos);
+                }
+            });
+
+        if let Some(pos) = chosen {
+            self.goto_position(ctx, id, pos);
+        } else if open && !ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.goto_dialog = Some(dialog);
+        }
+    }
+
+    // step through the note's own history, egui's undo knows nothing of folds or structured edits
+    fn undo(&mut self, ctx: &egui::Context, id: egui::Id, redo: bool) {
+        let pos = if redo {
+            self.note.redo()
+        } else {
+            self.note.undo()
+        };
+        if let Some(pos) = pos {
+            self.set_cursor(ctx, id, pos, pos);
+            self.events.publish(Event::NoteModified);
+        }
+    }
+
+    fn move_lines(&mut self, ctx: &egui::Context, id: egui::Id, up: bool) {
+        let primary = byte_index(self.note.as_str(), self.cursor_range.primary.index);
+        let secondary = byte_index(self.note.as_str(), self.cursor_range.secondary.index);
+        if let Some((primary, secondary)) = self.note.move_lines(primary, secondary, up) {
+            let primary = char_index(self.note.as_str(), primary);
+            let secondary = char_index(self.note.as_str(), secondary);
+            self.set_cursor(ctx, id, primary, secondary);
+        }
+    }
+
+    // one # more or less on the heading of the section the cursor is in
+    fn shift_heading(&mut self, ctx: &egui::Context, id: egui::Id, deeper: bool) {
+        let pos = byte_index(self.note.as_str(), self.cursor_range.primary.index);
+        if let Some(pos) = self.note.shift_heading(pos, deeper) {
+            self.events.publish(Event::NoteModified);
+            let pos = char_index(self.note.as_str(), pos);
+            self.set_cursor(ctx, id, pos, pos);
+        }
+    }
+
+    fn expand_selection(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let (start, end) = self.note.expand_selection(
+            byte_index(self.note.as_str(), self.cursor_range.primary.index),
+            byte_index(self.note.as_str(), self.cursor_range.secondary.index),
+        );
+        let start = char_index(self.note.as_str(), start);
+        let end = char_index(self.note.as_str(), end);
+        self.set_cursor(ctx, id, end, start);
+    }
+
+    fn sort_lines(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let primary = byte_index(self.note.as_str(), self.cursor_range.primary.index);
+        let secondary = byte_index(self.note.as_str(), self.cursor_range.secondary.index);
+        let (start, end) = self.note.sort_lines(primary, secondary, self.sort_options);
+        let start = char_index(self.note.as_str(), start);
+        let end = char_index(self.note.as_str(), end);
+        self.set_cursor(ctx, id, end, start);
+    }
+
+    fn transform_case(&mut self, ctx: &egui::Context, id: egui::Id, case: TextCase) {
+        let primary = byte_index(self.note.as_str(), self.cursor_range.primary.index);
+        let secondary = byte_index(self.note.as_str(), self.cursor_range.secondary.index);
+        let (start, end) = self.note.transform_case(primary, secondary, case);
+        let start = char_index(self.note.as_str(), start);
+        let end = char_index(self.note.as_str(), end);
+        self.set_cursor(ctx, id, end, start);
+    }
+
+    // Enter in a list item or quote starts the next one, on an empty item it ends the list instead
+    fn list_continuation_input(&mut self, ctx: &egui::Context, id: egui::Id) {
+        if self.carets.is_some()
+            || self.link_completion.is_open()
+            || self.cursor_range.primary != self.cursor_range.secondary
+            || !ctx.input(|i| i.key_pressed(egui::Key::Enter) && i.modifiers.is_none())
+        {
+            return;
+        }
+
+        let cursor = self.cursor_range.primary.index;
+        let text = self.note.as_str();
+        let byte = byte_index(text, cursor);
+        let line_end = text[byte..].find('\n').map_or(text.len(), |i| byte + i);
+        let Some((remove, insert)) = lists::on_enter(&text[..byte], &text[byte..line_end]) else {
+            return;
+        };
+        ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Enter));
+        let pos = cursor - remove + insert.chars().count();
+        self.apply(Edit::Replace {
+            range: cursor - remove..cursor,
+            text: insert,
+        });
+        self.set_cursor(ctx, id, pos, pos);
+    }
+
+    // markdown delimiters go around the selection or in pairs before the TextEdit inserts them
+    fn auto_pair_input(&mut self, ctx: &egui::Context, id: egui::Id) {
+        if self.carets.is_some() {
+            return;
+        }
+
+        let events = ctx.input_mut(|i| std::mem::take(&mut i.events));
+        let mut kept = Vec::with_capacity(events.len());
+        for event in events {
+            if let egui::Event::Text(typed) = &event
+                && let Some(c) = typed.chars().next()
+                && typed.chars().count() == 1
+            {
+                let primary = self.cursor_range.primary.index;
+                let secondary = self.cursor_range.secondary.index;
+                let (start, end) = (primary.min(secondary), primary.max(secondary));
+                let text = self.note.as_str();
+                let (from, to) = (byte_index(text, start), byte_index(text, end));
+                let pairing = pairs::pair(&text[..from], &text[from..to], &text[to..], c);
+                match pairing {
+                    Some(Pairing::Wrap(before, after)) => {
+                        self.apply(Edit::Wrap {
+                            range: start..end,
+                            before: before.to_string(),
+                            after: after.to_string(),
+                        });
+                        // the selection keeps the way it was made
+                        self.set_cursor(ctx, id, primary + 1, secondary + 1);
+                        continue;
+                    }
+                    Some(Pairing::Insert(text, offset)) => {
+                        self.apply(Edit::Insert { pos: start, text });
+                        self.set_cursor(ctx, id, start + offset, start + offset);
+                        continue;
+                    }
+                    Some(Pairing::Skip) => {
+                        self.set_cursor(ctx, id, start + 1, start + 1);
+                        continue;
+                    }
+                    None => {}
+                }
+            }
+            kept.push(event);
+        }
+        ctx.input_mut(|i| i.events = kept);
+    }
+
+    // swap typed quotes, dashes and dots for their typographic forms before the TextEdit inserts them
+    fn smart_typography_input(&mut self, ctx: &egui::Context, id: egui::Id) {
+        if self.cursor_range.primary != self.cursor_range.secondary {
+            return;
+        }
+
+        let events = ctx.input_mut(|i| std::mem::take(&mut i.events));
+        let mut kept = Vec::with_capacity(events.len());
+        for event in events {
+            if let egui::Event::Text(typed) = &event
+                && let Some(c) = typed.chars().next()
+                && typed.chars().count() == 1
+            {
+                let cursor = self.cursor_range.primary.index;
+                let text = self.note.as_str();
+                let byte = byte_index(text, cursor);
+                if let Some((remove, replacement)) = typography::substitute(&text[..byte], c) {
+                    let pos = cursor - remove + replacement.chars().count();
+                    self.apply(Edit::Replace {
+                        range: cursor - remove..cursor,
+                        text: replacement,
+                    });
+                    self.set_cursor(ctx, id, pos, pos);
+                    continue;
+                }
+            }
+            kept.push(event);
+        }
+        ctx.input_mut(|i| i.events = kept);
+    }
+
+    // pass this frame's events to everything listening for them
+    fn dispatch_events(&mut self) {
+        let events =
+            self.events
+                .dispatch(&mut [&mut self.hooks, &mut self.plugins, &mut self.reminders]);
+        for event in &events {
+            match event {
+                Event::NoteModified => self.autosave.edited(Instant::now()),
+                Event::SectionToggled(_) => self.folds.remember(&self.path, &self.note),
+                Event::NoteSaved(path) if *path == self.path => {
+                    self.last_saved = Some(Local::now())
+                }
+                _ => {}
+            }
+        }
+        let changed = events
+            .iter()
+            .any(|e| matches!(e, Event::NoteModified | Event::SectionToggled(_)));
+        if changed && cfg!(debug_assertions) {
+            self.validate_note(true);
+        }
+    }
+
+    // check the note's tree for corruption and show what's wrong,
+    //   when quiet only new problems are shown and a clean result says nothing
+    fn validate_note(&mut self, quiet: bool) {
+        let problems = self.note.validate();
+        if problems.is_empty() && !quiet {
+            self.toasts.info(tr("No problems found in the note"));
+        }
+        for problem in &problems {
+            if !quiet || !self.known_problems.contains(problem) {
+                tracing::error!("note invariant broken: {}", problem);
+                self.toasts.error(problem.clone());
+            }
+        }
+        self.known_problems = problems;
+    }
+
+    // remember which characters are on screen so the next layout highlights around them
+    fn track_visible(&mut self, ui: &egui::Ui, galley: &Galley, galley_pos: egui::Pos2) {
+        let clip = ui.clip_rect();
+        let top = galley.cursor_from_pos(egui::vec2(0.0, clip.top() - galley_pos.y));
+        let bottom = galley.cursor_from_pos(egui::vec2(0.0, clip.bottom() - galley_pos.y));
+        let visible = Some(top.index..bottom.index);
+        if visible != self.visible {
+            self.visible = visible;
+            ui.ctx().request_repaint();
+        }
+    }
+
+    // make a structured edit, the edits here come from the cursor so a failure is only logged
+    fn apply(&mut self, edit: Edit) -> bool {
+        if let Err(e) = self.note.apply(edit) {
+            tracing::warn!("edit failed: {}", e);
+            return false;
+        }
+        self.events.publish(Event::NoteModified);
+        return true;
+    }
+
+    fn run_plugin(&mut self, plugin: usize, command: &str) {
+        let primary = self.cursor_range.primary.index;
+        let secondary = self.cursor_range.secondary.index;
+        let selection = primary.min(secondary)..primary.max(secondary);
+        if let Some(edit) = self.plugins.run(plugin, command, &self.note, selection) {
+            self.apply(edit);
+        }
+    }
+
+    // run a script command, then carry out whatever it asked for
+    fn run_script(&mut self, ctx: &egui::Context, id: egui::Id, command: usize) {
+        let selection = self.selected_text();
+        let note = self.note.full().to_string();
+        let actions = match self.scripts.run(command, &note, &selection) {
+            Ok(actions) => actions,
+            Err(e) => {
+                tracing::warn!("{}", e);
+                self.toasts.error(e);
+                return;
+            }
+        };
+        for action in actions {
+            match action {
+                Action::SetNote(text) => {
+                    let end = self.note.as_str().chars().count();
+                    self.apply(Edit::Replace {
+                        range: 0..end,
+                        text,
+                    });
+                    self.set_cursor(ctx, id, 0, 0);
+                }
+                Action::ReplaceSelection(text) => self.replace_selection(ctx, id, &text),
+                Action::Open(name) => self.open_file(name),
+                Action::Toast(text) => self.toasts.info(text),
+            }
+        }
+    }
+
+    fn replace_selection(&mut self, ctx: &egui::Context, id: egui::Id, text: &str) {
+        let primary = self.cursor_range.primary.index;
+        let secondary = self.cursor_range.secondary.index;
+        let start = primary.min(secondary);
+        self.apply(Edit::Replace {
+            range: start..primary.max(secondary),
+            text: text.to_string(),
+        });
+        let pos = start + text.chars().count();
+        self.set_cursor(ctx, id, pos, pos);
+    }
+
+    fn selected_text(&self) -> String {
+        let primary = self.cursor_range.primary.index;
+        let secondary = self.cursor_range.secondary.index;
+        return self
+            .note
+            .as_str()
+            .chars()
+            .skip(primary.min(secondary))
+            .take(primary.abs_diff(secondary))
+            .collect();
+    }
+
+    // for chat apps and the like that show markdown as it's typed
+    fn copy_plain(&mut self, ctx: &egui::Context) {
+        let mut text = self.selected_text();
+        if text.is_empty() {
+            text = self.note.full().to_string();
+        }
+        ctx.copy_text(render::to_plain(&text));
+    }
+
+    // replace a byte range of the note as shown and select a byte range of the result
+    fn replace_and_select(
+        &mut self,
+        ctx: &egui::Context,
+        id: egui::Id,
+        (range, with, selection): (Range<usize>, String, Range<usize>),
+    ) {
+        let text = self.note.as_str();
+        let start = char_index(text, range.start);
+        let end = char_index(text, range.end);
+        let from = start + with[..selection.start - range.start].chars().count();
+        let to = start + with[..selection.end - range.start].chars().count();
+        self.apply(Edit::Replace {
+            range: start..end,
+            text: with,
+        });
+        self.set_cursor(ctx, id, to, from);
+    }
+
+    // take bold, italics or code off the span the cursor is in, or put it on the selection or word
+    //   markers only go in at the ends, so a folded section's hidden body is never rewritten
+    fn toggle_format(&mut self, ctx: &egui::Context, id: egui::Id, format: Format) {
+        let text = self.note.as_str();
+        let range = self.selected_bytes();
+        match delimiters::toggle(text, range, format) {
+            Some(Toggled::Unwrap(range, with, selection)) => {
+                self.replace_and_select(ctx, id, (range, with, selection));
+            }
+            Some(Toggled::Wrap(range, marker)) => {
+                let start = char_index(text, range.start);
+                let end = char_index(text, range.end);
+                let width = marker.chars().count();
+                if self.apply(Edit::Wrap {
+                    range: start..end,
+                    before: marker.to_string(),
+                    after: marker.to_string(),
+                }) {
+                    self.set_cursor(ctx, id, end + width, start + width);
+                }
+            }
+            None => self
+                .toasts
+                .info(tr("Bold, italics and code only go on part of one line")),
+        }
+    }
+
+    // turn the link the cursor is in back into plain text, or link the selection or word
+    fn toggle_link(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let text = self.note.as_str();
+        let range = self.selected_bytes();
+        if let Some(unlinked) = delimiters::unlink(text, range.clone()) {
+            self.replace_and_select(ctx, id, unlinked);
+            return;
+        }
+        if range.is_empty() {
+            let word = delimiters::word_at(text, range.start);
+            let (from, to) = (char_index(text, word.start), char_index(text, word.end));
+            self.set_cursor(ctx, id, to, from);
+        }
+        self.link_dialog = Some(LinkDialog {
+            notes: vault::list_notes(&self.root),
+            ..Default::default()
+        });
+    }
+
+    // the selection as a byte range of the note as shown
+    fn selected_bytes(&self) -> Range<usize> {
+        let primary = self.cursor_range.primary.index;
+        let secondary = self.cursor_range.secondary.index;
+        let text = self.note.as_str();
+        return byte_index(text, primary.min(secondary))..byte_index(text, primary.max(secondary));
+    }
+
+    // links to a web address use the selection as their text, note links are written the vault's way
+    fn insert_link(&mut self, ctx: &egui::Context, id: egui::Id, target: &str) {
+        let text = self.selected_text();
+        let link = if target.contains("://") {
+            format!(
+                "[{}]({})",
+                if text.is_empty() { target } else { &text },
+                target
+            )
+        } else {
+            self.link_syntax.write(target, &text)
+        };
+        self.replace_selection(ctx, id, &link);
+    }
+
+    // swap the @@ being typed for a link to the note, written the vault's way
+    fn complete_link(
+        &mut self,
+        ctx: &egui::Context,
+        id: egui::Id,
+        typed: Range<usize>,
+        target: &str,
+    ) {
+        let link = self.link_syntax.write(target, "");
+        let pos = typed.start + link.chars().count();
+        self.apply(Edit::Replace {
+            range: typed,
+            text: link,
+        });
+        self.set_cursor(ctx, id, pos, pos);
+        ctx.memory_mut(|m| m.request_focus(id));
+    }
+
+    fn open_new_note_dialog(&mut self) {
+        self.new_note_dialog = Some(NewNoteDialog {
+            folders: vault::list_folders(&self.root),
+            templates: templates::list(&self.root),
+            ..Default::default()
+        });
+    }
+
+    // open the note a link goes to, then go to the heading named after its # if there is one
+    fn follow_link(&mut self, target: String) {
+        let (note, anchor) = links::split_anchor(&target);
+        self.pending_anchor = anchor.map(str::to_string);
+        self.events.publish(Event::LinkFollowed(target.clone()));
+        if note.is_empty() || note == self.note_name() {
+            return;
+        }
+        self.visit(note.to_string());
+    }
+
+    fn toggle_split(&mut self) {
+        if self.pane.open {
+            self.pane.close(&mut self.saver);
+        } else {
+            self.pane.split(&self.path, &mut self.saver);
+        }
+    }
+
+    // open another note, leaving this one on the back history
+    fn visit(&mut self, note: String) {
+        self.nav_history
+            .push(self.path.to_str().unwrap().to_string());
+        self.nav_forward.clear();
+        self.open_file(note);
+    }
+
+    // put the cursor on the heading an anchor names, in the note that's open
+    fn goto_anchor(&mut self, ctx: &egui::Context, id: egui::Id, anchor: &str) {
+        let heading = self
+            .note
+            .headings()
+            .into_iter()
+            .find(|h| links::anchor_matches(&h.title, anchor));
+        match heading {
+            Some(heading) => self.goto_position(ctx, id, heading.pos),
+            None => self
+                .toasts
+                .warning(trf("There's no heading \"{}\" in this note", &[&anchor])),
+        }
+    }
+
+    // a link to a note that isn't there opens the new note dialog ready to make it
+    fn offer_new_note(&mut self, target: &str) {
+        self.open_new_note_dialog();
+        let Some(dialog) = &mut self.new_note_dialog else {
+            return;
+        };
+        let target = target.trim_matches('/');
+        let (folder, name) = target.rsplit_once('/').unwrap_or(("", target));
+        dialog.name = name.to_string();
+        if dialog.folders.iter().any(|f| f == folder) {
+            dialog.folder = folder.to_string();
+        } else {
+            dialog.new_folder = folder.to_string();
+        }
+    }
+
+    // make the note the dialog describes and open it, linking to it from the note left behind
+    fn create_note(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some(dialog) = &mut self.new_note_dialog else {
+            return;
+        };
+        let name: Vec<&str> = [&dialog.folder, &dialog.new_folder, &dialog.name]
+            .into_iter()
+            .map(|part| part.trim().trim_matches('/'))
+            .filter(|part| !part.is_empty())
+            .collect();
+        let name = name.join("/");
+        let title = dialog.name.trim().to_string();
+        let text = match &dialog.template {
+            Some(template) => {
+                match std::fs::read_to_string(self.root.join(format!("{}.md", template))) {
+                    Ok(text) => templates::fill(&text, &title, &self.stamp_formats, &Local::now()),
+                    Err(e) => {
+                        dialog.error = Some(trf("Couldn't read {}: {}", &[&template, &e]));
+                        return;
+                    }
+                }
+            }
+            None => String::new(),
+        };
+        let path = match vault::create_note(&self.root, &name, &text) {
+            Ok(path) => path,
+            Err(e) => {
+                dialog.error = Some(trf("Couldn't create {}: {}", &[&name, &e]));
+                return;
+            }
+        };
+        let link = dialog.link;
+        self.new_note_dialog = None;
+        self.events.publish(Event::NoteCreated(path.clone()));
+        if !self.path.as_os_str().is_empty() {
+            if link {
+                self.insert_link(ctx, id, &name);
+                self.save_file(false);
+            }
+            self.nav_history
+                .push(self.path.to_str().unwrap().to_string());
+            self.nav_forward.clear();
+        }
+        self.open_file(path.display().to_string());
+    }
+
+    fn new_note_dialog(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let has_note = !self.path.as_os_str().is_empty();
+        let Some(dialog) = &mut self.new_note_dialog else {
+            return;
+        };
+        let mut open = true;
+        let mut create = false;
+        egui::Window::new(tr("New note"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("new note fields").show(ui, |ui| {
+                    ui.label(tr("Name"));
+                    let name = ui.text_edit_singleline(&mut dialog.name);
+                    if ui.memory(|m| m.focused().is_none()) {
+                        name.request_focus();
+                    }
+                    create |= name.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    ui.end_row();
+
+                    ui.label(tr("Folder"));
+                    let top = tr("Top of the vault");
+                    egui::ComboBox::from_id_salt("new note folder")
+                        .selected_text(if dialog.folder.is_empty() {
+                            top
+                        } else {
+                            dialog.folder.as_str()
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut dialog.folder, String::new(), top);
+                            for folder in &dialog.folders {
+                                ui.selectable_value(&mut dialog.folder, folder.clone(), folder);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label(tr("New folder"));
+                    ui.add(
+                        egui::TextEdit::singleline(&mut dialog.new_folder)
+                            .hint_text(tr("optional, made inside the folder")),
+                    );
+                    ui.end_row();
+
+                    ui.label(tr("Template"));
+                    egui::ComboBox::from_id_salt("new note template")
+                        .selected_text(dialog.template.as_deref().unwrap_or(tr("Blank")))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut dialog.template, None, tr("Blank"));
+                            for template in &dialog.templates {
+                                ui.selectable_value(
+                                    &mut dialog.template,
+                                    Some(template.clone()),
+                                    template,
+                                );
+                            }
+                        });
+                    ui.end_row();
+                });
+                if dialog.templates.is_empty() {
+                    ui.weak(trf(
+                        "Notes in the {} folder can be used as templates",
+                        &[&templates::FOLDER],
+                    ));
+                }
+                if has_note {
+                    ui.checkbox(&mut dialog.link, tr("Link to it from this note"));
+                }
+                if let Some(error) = &dialog.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                create |= ui
+                    .add_enabled(
+                        !dialog.name.trim().is_empty(),
+                        egui::Button::new(tr("Create")),
+                    )
+                    .clicked();
+            });
+        if !open {
+            self.new_note_dialog = None;
+        } else if create {
+            self.create_note(ctx, id);
+        }
+    }
+
+    // move the open note to the name in the dialog, pointing every link to it at the new name
+    //   its own links are rewritten as an edit that can be undone, the rest of the vault on disk
+    fn rename_note(&mut self) {
+        let from = self.note_name();
+        let Some(dialog) = &mut self.rename_dialog else {
+            return;
+        };
+        let to = dialog.name.trim().trim_matches('/').to_string();
+        if to == from {
+            self.rename_dialog = None;
+            return;
+        }
+        // a save still on its way would bring the old file back
+        if self.saver.is_saving() {
+            dialog.error = Some(tr("Wait for the note to finish saving").to_string());
+            return;
+        }
+        let (path, changed) = match vault::rename_note(&self.root, &from, &to, &from) {
+            Ok(renamed) => renamed,
+            Err(e) => {
+                dialog.error = Some(trf("Couldn't rename {}: {}", &[&from, &e]));
+                return;
+            }
+        };
+        self.rename_dialog = None;
+        let (text, count) = links::retarget(self.note.full(), &from, &to);
+        if count > 0 {
+            self.note.rewrite(&text);
+            self.events.publish(Event::NoteModified);
+        }
+        let old = self.path.to_str().unwrap().to_string();
+        for entry in self
+            .nav_history
+            .iter_mut()
+            .chain(self.nav_forward.iter_mut())
+        {
+            if *entry == old {
+                *entry = path.to_str().unwrap().to_string();
+            }
+        }
+        self.saver.track(path.clone(), None);
+        self.path = path;
+        self.save_file(false);
+        self.read_known_notes();
+        self.toasts.info(trf(
+            "Renamed to {} and updated {} links",
+            &[&to, &(changed + count)],
+        ));
+    }
+
+    fn rename_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.rename_dialog else {
+            return;
+        };
+        let mut open = true;
+        let mut rename = false;
+        egui::Window::new(tr("Rename note"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(tr("New name, with any folders it goes in"));
+                let name = ui.text_edit_singleline(&mut dialog.name);
+                if ui.memory(|m| m.focused().is_none()) {
+                    name.request_focus();
+                }
+                rename |= name.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if let Some(error) = &dialog.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                rename |= ui
+                    .add_enabled(
+                        !dialog.name.trim().is_empty(),
+                        egui::Button::new(tr("Rename")),
+                    )
+                    .clicked();
+            });
+        if !open {
+            self.rename_dialog = None;
+        } else if rename {
+            self.rename_note();
+        }
+    }
+
+    fn link_dialog(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some(mut dialog) = self.link_dialog.take() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut chosen: Option<String> = None;
+        egui::Window::new(tr("Insert link"))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                dialog.picker.query_box(ui, tr("Note name or URL"));
+
+                if dialog.picker.query.contains("://") {
+                    ui.label(trf("Link to {}", &[&dialog.picker.query]));
+                    if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        chosen = Some(dialog.picker.query.clone());
+                    }
+                    return;
+                }
+
+                if let Some(i) = dialog.picker.list(ui, &dialog.notes) {
+                    chosen = Some(dialog.notes[i].clone());
+                }
+            });
+
+        if let Some(target) = chosen {
+            self.insert_link(ctx, id, &target);
+        } else if open && !ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.link_dialog = Some(dialog);
+        }
+    }
+
+    fn switch_dialog(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.switch_dialog.take() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut chosen: Option<String> = None;
+        egui::Window::new(tr("Open note"))
+            .open(&mut open)
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 40.0))
+            .show(ctx, |ui| {
+                dialog.picker.query_box(ui, tr("Note name"));
+                if let Some(i) = dialog.picker.list(ui, &dialog.notes) {
+                    chosen = Some(dialog.notes[i].clone());
+                }
+            });
+
+        if let Some(note) = chosen {
+            if note != self.note_name() {
+                self.visit(note);
+            }
+        } else if open && !ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.switch_dialog = Some(dialog);
+        }
+    }
+
+    fn logs_window(&mut self, ctx: &egui::Context) {
+        if !self.show_logs {
+            return;
+        }
+        let lines = self.logs.lines();
+        egui::Window::new(tr("Logs"))
+            .open(&mut self.show_logs)
+            .default_size([600.0, 300.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Copy")).clicked() {
+                        ctx.copy_text(lines.join("\n"));
+                    }
+                    if let Some(dir) = logging::log_dir() {
+                        ui.label(trf("Full logs in {}", &[&dir.display()]));
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::both()
+                    .stick_to_bottom(true)
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        for line in &lines {
+                            ui.monospace(line);
+                        }
+                    });
+            });
+    }
+
+    // pressing inside the selection starts dragging it instead of making a new selection
+    fn start_text_drag(&mut self, ctx: &egui::Context) {
+        let start = self
+            .cursor_range
+            .primary
+            .index
+            .min(self.cursor_range.secondary.index);
+        let end = self
+            .cursor_range
+            .primary
+            .index
+            .max(self.cursor_range.secondary.index);
+        let Some((galley, galley_pos)) = &self.editor_galley else {
+            return;
+        };
+
+        let pressed = ctx.input(|i| {
+            if i.pointer.primary_pressed() {
+                i.pointer.interact_pos()
+            } else {
+                None
+            }
+        });
+        if let Some(pos) = pressed
+            && galley.rect.translate(galley_pos.to_vec2()).contains(pos)
+        {
+            let index = galley.cursor_from_pos(pos - *galley_pos).index;
+            if start < index && index < end {
+                self.text_drag = Some(start..end);
+            }
+        }
+    }
+
+    // draw the drop caret while dragging, and move the text once it's released
+    fn update_text_drag(
+        &mut self,
+        ui: &egui::Ui,
+        id: egui::Id,
+        galley: &Galley,
+        galley_pos: egui::Pos2,
+    ) {
+        let Some(range) = self.text_drag.clone() else {
+            return;
+        };
+        let Some(pos) = ui.ctx().input(|i| i.pointer.hover_pos()) else {
+            self.text_drag = None;
+            return;
+        };
+
+        let drop = galley.cursor_from_pos(pos - galley_pos);
+        if ui.ctx().input(|i| i.pointer.primary_down()) {
+            ui.ctx().set_cursor_icon(CursorIcon::Grabbing);
+            let caret = galley.pos_from_cursor(drop).translate(galley_pos.to_vec2());
+            ui.painter().vline(
+                caret.min.x,
+                caret.y_range(),
+                Stroke::new(2.0, ui.visuals().text_cursor.stroke.color),
+            );
+            return;
+        }
+
+        self.text_drag = None;
+        self.move_text(ui.ctx(), id, range, drop.index);
+        ui.ctx().memory_mut(|m| m.request_focus(id));
+    }
+
+    // alt+press starts a block selection, ctrl+press adds a caret, any other press inside the
+    //   editor ends them
+    fn start_block_selection(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some((galley, galley_pos)) = &self.editor_galley else {
+            return;
+        };
+        let pressed = ctx.input(|i| {
+            if i.pointer.primary_pressed() {
+                i.pointer
+                    .interact_pos()
+                    .map(|pos| (pos, i.modifiers.alt, i.modifiers.command))
+            } else {
+                None
+            }
+        });
+        let Some((pos, alt, command)) = pressed else {
+            return;
+        };
+        if !galley.rect.translate(galley_pos.to_vec2()).contains(pos) {
+            return;
+        }
+
+        let index = galley.cursor_from_pos(pos - *galley_pos).index;
+        if alt {
+            self.block_anchor = Some(index);
+            self.carets = Some(Carets::block(self.note.as_str(), index, index));
+        } else if command {
+            let mut carets = self
+                .carets
+                .take()
+                .unwrap_or_else(|| self.selection_carets());
+            carets.add(index..index);
+            self.carets = Some(carets);
+            ctx.memory_mut(|m| m.request_focus(id));
+        } else if self.carets.is_some() {
+            self.carets = None;
+            self.set_cursor(ctx, id, index, index);
+            ctx.memory_mut(|m| m.request_focus(id));
+        }
+    }
+
+    fn update_block_selection(&mut self, ui: &egui::Ui, galley: &Galley, galley_pos: egui::Pos2) {
+        let Some(anchor) = self.block_anchor else {
+            return;
+        };
+        if !ui.ctx().input(|i| i.pointer.primary_down()) {
+            self.block_anchor = None;
+            return;
+        }
+        if let Some(pos) = ui.ctx().input(|i| i.pointer.hover_pos()) {
+            let head = galley.cursor_from_pos(pos - galley_pos).index;
+            self.carets = Some(Carets::block(self.note.as_str(), anchor, head));
+        }
+    }
+
+    // the editor's own selection, as the first of several carets
+    fn selection_carets(&self) -> Carets {
+        let primary = self.cursor_range.primary.index;
+        let secondary = self.cursor_range.secondary.index;
+        return Carets::single(primary.min(secondary)..primary.max(secondary));
+    }
+
+    // select the word at the caret, then add a caret at each next place the selection shows up
+    fn select_next_occurrence(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let mut carets = match self.carets.take() {
+            Some(carets) => carets,
+            None if self.cursor_range.primary == self.cursor_range.secondary => {
+                self.expand_selection(ctx, id);
+                return;
+            }
+            None => self.selection_carets(),
+        };
+        if let Some(next) = carets.next_occurrence(self.note.as_str()) {
+            carets.add(next);
+        }
+        self.carets = Some(carets);
+        ctx.memory_mut(|m| m.request_focus(id));
+    }
+
+    // typing while there are several carets goes to all of them
+    fn caret_input(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some(carets) = &mut self.carets else {
+            return;
+        };
+        if self.block_anchor.is_some() {
+            return;
+        }
+
+        let mut finished = false;
+        let events = ctx.input_mut(|i| std::mem::take(&mut i.events));
+        let mut kept = Vec::with_capacity(events.len());
+        for event in events {
+            match &event {
+                egui::Event::Text(text) | egui::Event::Paste(text) => {
+                    carets.insert(&mut self.note, text);
+                }
+                egui::Event::Copy => {
+                    let text = self.note.as_str();
+                    let parts: Vec<String> = carets
+                        .ranges
+                        .iter()
+                        .map(|r| text.chars().skip(r.start).take(r.len()).collect())
+                        .collect();
+                    ctx.copy_text(parts.join("\n"));
+                }
+                egui::Event::Key {
+                    key, pressed: true, ..
+                } => match key {
+                    egui::Key::Backspace => carets.backspace(&mut self.note),
+                    egui::Key::Delete => carets.delete(&mut self.note),
+                    egui::Key::Escape
+                    | egui::Key::Enter
+                    | egui::Key::ArrowUp
+                    | egui::Key::ArrowDown
+                    | egui::Key::ArrowLeft
+                    | egui::Key::ArrowRight => finished = true,
+                    _ => kept.push(event),
+                },
+                _ => kept.push(event),
+            }
+        }
+        ctx.input_mut(|i| i.events = kept);
+
+        if finished {
+            let end = carets.ranges.last().map_or(0, |r| r.end);
+            self.carets = None;
+            self.set_cursor(ctx, id, end, end);
+            ctx.memory_mut(|m| m.request_focus(id));
+        }
+    }
+
+    fn paint_carets(&self, ui: &egui::Ui, galley: &Galley, galley_pos: egui::Pos2) {
+        let Some(carets) = &self.carets else {
+            return;
+        };
+        let stroke = ui.visuals().text_cursor.stroke;
+        let fill = ui.visuals().selection.bg_fill.gamma_multiply(0.5);
+        for range in &carets.ranges {
+            let start = galley
+                .pos_from_cursor(CCursor::new(range.start))
+                .translate(galley_pos.to_vec2());
+            if range.is_empty() {
+                ui.painter().vline(start.min.x, start.y_range(), stroke);
+            } else {
+                let end = galley
+                    .pos_from_cursor(CCursor::new(range.end))
+                    .translate(galley_pos.to_vec2());
+                ui.painter()
+                    .rect_filled(egui::Rect::from_min_max(start.min, end.max), 0.0, fill);
+            }
+        }
+    }
+
+    fn move_text(&mut self, ctx: &egui::Context, id: egui::Id, range: Range<usize>, to: usize) {
+        if range.contains(&to) || to == range.end {
+            self.set_cursor(ctx, id, to, to);
+            return;
+        }
+
+        let text: String = self
+            .note
+            .as_str()
+            .chars()
+            .skip(range.start)
+            .take(range.len())
+            .collect();
+        self.apply(Edit::Batch(vec![
+            Edit::Delete(range.clone()),
+            Edit::Insert { pos: to, text },
+        ]));
+        let to = if to > range.end { to - range.len() } else { to };
+        self.set_cursor(ctx, id, to + range.len(), to);
+    }
+
+    fn selected_word(&self) -> Option<String> {
+        let text = self.selected_text();
+        if search::is_single_word(&text) {
+            return Some(text);
+        }
+        return None;
+    }
+
+    // what the find bar matches in the note as shown, None while it's closed or empty
+    fn find_bar_matches(&self, replace: bool) -> Option<Result<Vec<Replacement>, String>> {
+        let bar = self.find_bar.as_ref().filter(|bar| !bar.find.is_empty())?;
+        let pattern = match Pattern::new(&bar.find, bar.regex) {
+            Ok(pattern) => pattern,
+            Err(e) => return Some(Err(e.to_string())),
+        };
+        let with = if replace { bar.replace.as_str() } else { "" };
+        return Some(Ok(search::find_replacements(
+            self.note.as_str(),
+            &pattern,
+            with,
+        )));
+    }
+
+    // where the find bar's matches are, or failing that the selected word's
+    fn occurrences(&self) -> Vec<Range<usize>> {
+        if let Some(found) = self.find_bar_matches(false) {
+            return found
+                .map(|found| found.into_iter().map(|r| r.range).collect())
+                .unwrap_or_default();
+        }
+        return match self.selected_word() {
+            Some(word) => search::find_word(self.note.as_str(), &word),
+            None => Vec::new(),
+        };
+    }
+
+    // select the next (or previous) occurrence, wrapping around the note
+    fn jump_to_occurrence(&mut self, ctx: &egui::Context, id: egui::Id, forward: bool) {
+        let found = self.occurrences();
+        let text = self.note.as_str();
+        let primary = self.cursor_range.primary.index;
+        let secondary = self.cursor_range.secondary.index;
+        let start = byte_index(text, primary.min(secondary));
+        let next = if forward {
+            found.iter().find(|r| r.start > start).or(found.first())
+        } else {
+            found
+                .iter()
+                .rev()
+                .find(|r| r.start < start)
+                .or(found.last())
+        };
+
+        if let Some(range) = next {
+            let from = text[..range.start].chars().count();
+            let to = from + text[range.clone()].chars().count();
+            self.set_cursor(ctx, id, to, from);
+        }
+    }
+
+    // replace the selected match, groups and all, then move on to the next one
+    fn replace_occurrence(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some(Ok(found)) = self.find_bar_matches(true) else {
+            return;
+        };
+        let selected = self.selected_bytes();
+        if let Some(replacement) = found.iter().find(|r| r.range == selected) {
+            self.replace_selection(ctx, id, &replacement.with.clone());
+        }
+        self.jump_to_occurrence(ctx, id, true);
+    }
+
+    // replace every match in the note, folded sections included, as one edit that can be undone
+    fn replace_all_occurrences(&mut self) {
+        let Some(bar) = &self.find_bar else {
+            return;
+        };
+        let Ok(pattern) = Pattern::new(&bar.find, bar.regex) else {
+            return;
+        };
+        let found = search::find_replacements(self.note.full(), &pattern, &bar.replace);
+        if found.is_empty() {
+            return;
+        }
+        if let Some(text) = search::apply_replacements(self.note.full(), &found) {
+            self.note.rewrite(&text);
+            self.events.publish(Event::NoteModified);
+            self.toasts
+                .info(trf("Replaced {} matches", &[&found.len()]));
+        }
+    }
+
+    fn find_bar(&mut self, ctx: &egui::Context) {
+        let found = self.find_bar_matches(false);
+        let Some(bar) = &mut self.find_bar else {
+            return;
+        };
+        let mut open = true;
+        let mut replace_all = false;
+        egui::TopBottomPanel::bottom("find").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(tr("Find"));
+                let field = ui.text_edit_singleline(&mut bar.find);
+                if std::mem::take(&mut bar.focus) {
+                    field.request_focus();
+                }
+                if field.lost_focus() {
+                    if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        let back = ui.input(|i| i.modifiers.shift);
+                        bar.pending = Some(if back {
+                            FindStep::Previous
+                        } else {
+                            FindStep::Next
+                        });
+                        field.request_focus();
+                    } else if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                        open = false;
+                    }
+                }
+                ui.checkbox(&mut bar.regex, tr("Regular expression"))
+                    .on_hover_text(tr("The replacement can use groups as $1 or ${name}"));
+                ui.label(tr("Replace with"));
+                ui.text_edit_singleline(&mut bar.replace);
+                let matched = matches!(&found, Some(Ok(found)) if !found.is_empty());
+                if ui
+                    .add_enabled(matched, egui::Button::new(tr("Replace")))
+                    .clicked()
+                {
+                    bar.pending = Some(FindStep::Replace);
+                }
+                replace_all = ui
+                    .add_enabled(matched, egui::Button::new(tr("Replace all")))
+                    .clicked();
+                match &found {
+                    Some(Ok(found)) => {
+                        ui.weak(trf("{} matches (F3 / Shift+F3)", &[&found.len()]));
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                    None => {}
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    open &= !ui.button("✖").on_hover_text(tr("Close")).clicked();
+                });
+            });
+        });
+        if replace_all {
+            self.replace_all_occurrences();
+        }
+        if !open {
+            self.find_bar = None;
+        }
+    }
+
+    fn insert_stamp(&mut self, ctx: &egui::Context, id: egui::Id, stamp: Stamp) {
+        let text = self.stamp_formats.format(stamp, &Local::now());
+        self.replace_selection(ctx, id, &text);
+    }
+
+    // a new empty sketch attachment linked on its own line, opened straight away for drawing
+    fn insert_sketch(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let name = format!(
+            "attachments/sketch-{}.svg",
+            Local::now().format("%Y%m%d-%H%M%S")
+        );
+        let editor = SketchEditor::open(self.root.join(&name));
+        if let Err(e) = editor.save() {
+            self.toasts
+                .error(trf("Couldn't create {}: {}", &[&editor.path.display(), &e]));
+            return;
+        }
+        let start = self
+            .cursor_range
+            .primary
+            .index
+            .min(self.cursor_range.secondary.index);
+        let text = self.note.as_str();
+        let byte = byte_index(text, start);
+        let mut line = sketch::link(&name) + "\n";
+        if byte > 0 && !text[..byte].ends_with('\n') {
+            line.insert(0, '\n');
+        }
+        self.replace_selection(ctx, id, &line);
+        self.sketch_editor = Some(editor);
+    }
+
+    // the image on the clipboard saved as an attachment and linked at the cursor
+    fn paste_image(&mut self, ctx: &egui::Context, id: egui::Id) {
+        match attachments::paste_image(&self.root, &Local::now()) {
+            Ok(name) => self.replace_selection(ctx, id, &attachments::image_link(&name)),
+            Err(e) => self.toasts.error(trf("Couldn't paste an image: {}", &[&e])),
+        }
+    }
+
+    fn sketch_editor(&mut self, ctx: &egui::Context) {
+        let Some(editor) = &mut self.sketch_editor else {
+            return;
+        };
+        match editor.show(ctx) {
+            Some(true) => {
+                if let Err(e) = editor.save() {
+                    self.toasts
+                        .error(trf("Couldn't save {}: {}", &[&editor.path.display(), &e]));
+                    return;
+                }
+                self.sketches.forget(&editor.path);
+                self.sketch_editor = None;
+            }
+            Some(false) => self.sketch_editor = None,
+            None => {}
+        }
+    }
+
+    // local pdfs the note links to get a preview pane, picking between them if there's several
+    fn pdf_panel(&mut self, ctx: &egui::Context) {
+        let pdfs: Vec<PathBuf> = links::file_links(self.note.as_str())
+            .iter()
+            .filter(|link| link.is_pdf())
+            .map(|link| self.root.join(link.target))
+            .collect();
+        let Some(first) = pdfs.first() else {
+            self.pdf_preview = None;
+            return;
+        };
+        let mut shown = match &self.pdf_preview {
+            Some(preview) if pdfs.contains(&preview.path) => preview.path.clone(),
+            _ => first.clone(),
+        };
+        let panel = egui::SidePanel::right("pdf preview")
+            .default_width(self.pdf_width)
+            .show(ctx, |ui| {
+                if pdfs.len() > 1 {
+                    egui::ComboBox::from_id_salt("pdf choice")
+                        .selected_text(pdf::title(&shown))
+                        .show_ui(ui, |ui| {
+                            for pdf in &pdfs {
+                                ui.selectable_value(&mut shown, pdf.clone(), pdf::title(pdf));
+                            }
+                        });
+                } else {
+                    ui.strong(pdf::title(&shown));
+                }
+                if self.pdf_preview.as_ref().map(|p| &p.path) != Some(&shown) {
+                    self.pdf_preview = Some(PdfPreview::new(shown.clone()));
+                }
+                if let Some(preview) = &mut self.pdf_preview {
+                    preview.show(ui);
+                }
+            });
+        self.pdf_width = panel.response.rect.width();
+    }
+
+    // the values of table formulas, except on the cursor's line where the formula is being edited
+    fn paint_formulas(&self, ui: &egui::Ui, galley: &Galley, galley_pos: egui::Pos2) {
+        let text = self.note.as_str();
+        let byte = byte_index(text, self.cursor_range.primary.index);
+        let line_start = text[..byte].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[byte..].find('\n').map_or(text.len(), |i| byte + i);
+        for computed in formula::evaluate(text) {
+            if computed.range.end >= line_start && computed.range.start <= line_end {
+                continue;
+            }
+            let pos = galley
+                .pos_from_cursor(CCursor::new(char_index(text, computed.range.start)))
+                .translate(galley_pos.to_vec2());
+            let color = match computed.value {
+                Ok(_) => ui.visuals().strong_text_color(),
+                Err(_) => ui.visuals().error_fg_color,
+            };
+            ui.painter().text(
+                pos.left_top(),
+                egui::Align2::LEFT_TOP,
+                computed.text(),
+                FontId::default(),
+                color,
+            );
+        }
+    }
+
+    // a done/total badge after each heading with checkboxes under it, folded or not
+    fn paint_progress(&self, ui: &egui::Ui, galley: &Galley, galley_pos: egui::Pos2) {
+        let text = self.note.as_str();
+        for heading in self.note.headings() {
+            let Some(progress) = heading.progress.filter(|_| heading.visible) else {
+                continue;
+            };
+            let start = self.note.inv_translate(heading.pos).min(text.len());
+            let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+            let row = galley
+                .pos_from_cursor(CCursor::new(char_index(text, end)))
+                .translate(galley_pos.to_vec2());
+            let color = if progress.done == progress.total {
+                self.theme.done
+            } else {
+                ui.visuals().weak_text_color()
+            };
+            let font = FontId::proportional(row.height() * 0.6);
+            let label = format!("{}/{}", progress.done, progress.total);
+            let galley = ui.painter().layout_no_wrap(label, font, color);
+            let rect = egui::Rect::from_min_size(
+                egui::pos2(row.max.x + 12.0, row.center().y - galley.size().y / 2.0),
+                galley.size(),
+            )
+            .expand2(egui::vec2(5.0, 1.0));
+            ui.painter().rect_stroke(
+                rect,
+                6.0,
+                Stroke::new(1.0, color),
+                egui::StrokeKind::Outside,
+            );
+            ui.painter()
+                .galley(rect.min + egui::vec2(5.0, 1.0), galley, color);
+        }
+    }
+
+    // the parsed pieces of the lines the editor highlights in full, and the byte they start at
+    fn highlighted(&self) -> (usize, Vec<MarkdownStr<'_>>) {
+        let text = self.note.as_str();
+        let window = match &self.visible {
+            Some(chars) => viewport::line_window(
+                text,
+                byte_index(text, chars.start)..byte_index(text, chars.end),
+            ),
+            None => viewport::line_window(text, 0..0),
+        };
+        return (window.start, highlight_parse(&text[window]));
+    }
+
+    // each diagram under its opening fence, plus a switch to its source at the end of that line,
+    //   returns where the switches went and which diagram each is for
+    fn paint_diagrams(
+        &mut self,
+        ui: &egui::Ui,
+        galley: &Galley,
+        galley_pos: egui::Pos2,
+    ) -> Vec<(egui::Rect, u64)> {
+        let text = self.note.as_str();
+        let cursor = byte_index(text, self.cursor_range.primary.index);
+        let (start, strings) = self.highlighted();
+        let blocks: Vec<(Range<usize>, String, String)> = diagram_spans(&strings)
+            .into_iter()
+            .map(|(range, language)| {
+                let range = range.start + start..range.end + start;
+                return (range.clone(), language, text[range].to_string());
+            })
+            .collect();
+        let row_at = |byte: usize| {
+            return galley
+                .pos_from_cursor(CCursor::new(char_index(self.note.as_str(), byte)))
+                .translate(galley_pos.to_vec2());
+        };
+        let rows: Vec<(egui::Rect, egui::Rect)> = blocks
+            .iter()
+            .map(|(range, _, _)| (row_at(range.start), row_at(range.end - 1)))
+            .collect();
+
+        let mut switches = Vec::new();
+        let right = galley_pos.x + galley.rect.width();
+        let font = FontId::proportional(11.0);
+        let weak = ui.visuals().weak_text_color();
+        for ((range, language, block), (open, close)) in blocks.into_iter().zip(rows) {
+            let key = diagrams::key(&block);
+            let label = if self.diagrams.sources.contains(&key) {
+                tr("Show diagram")
+            } else {
+                tr("Show source")
+            };
+            let label = ui
+                .painter()
+                .layout_no_wrap(label.to_string(), font.clone(), weak);
+            let at = egui::pos2(
+                right - label.size().x - 4.0,
+                open.center().y - label.size().y / 2.0,
+            );
+            switches.push((egui::Rect::from_min_size(at, label.size()).expand(2.0), key));
+            ui.painter().galley(at, label, weak);
+            if self.diagrams.sources.contains(&key)
+                || range.contains(&cursor)
+                || range.end == cursor
+            {
+                continue;
+            }
+
+            let area = egui::Rect::from_min_max(
+                egui::pos2(galley_pos.x, close.min.y + 4.0),
+                egui::pos2(right, close.min.y + diagrams::ROW_HEIGHT - 4.0),
+            );
+            match self.diagrams.get(ui.ctx(), &language, &block) {
+                Shown::Rendering => {
+                    ui.painter().text(
+                        area.left_top(),
+                        egui::Align2::LEFT_TOP,
+                        tr("Drawing the diagram…"),
+                        font.clone(),
+                        weak,
+                    );
+                }
+                Shown::Failed(e) => {
+                    ui.painter().text(
+                        area.left_top(),
+                        egui::Align2::LEFT_TOP,
+                        e,
+                        font.clone(),
+                        ui.visuals().error_fg_color,
+                    );
+                }
+                Shown::Done(texture) => {
+                    let size = texture.size_vec2();
+                    let scale = (area.width() / size.x).min(area.height() / size.y).min(1.0);
+                    ui.painter().image(
+                        texture.id(),
+                        egui::Rect::from_min_size(area.min, size * scale),
+                        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                        Color32::WHITE,
+                    );
+                }
+            }
+        }
+        return switches;
+    }
+
+    // math laid out in unicode over its hidden source, unless the cursor is in it
+    fn paint_math(&self, ui: &egui::Ui, galley: &Galley, galley_pos: egui::Pos2) {
+        let text = self.note.as_str();
+        let cursor = byte_index(text, self.cursor_range.primary.index);
+        let (start, strings) = self.highlighted();
+        for range in math_spans(&strings) {
+            let range = range.start + start..range.end + start;
+            if range.contains(&cursor) || range.end == cursor {
+                continue;
+            }
+            let row = galley
+                .pos_from_cursor(CCursor::new(char_index(text, range.start)))
+                .translate(galley_pos.to_vec2());
+            ui.painter().text(
+                egui::pos2(row.min.x, row.center().y),
+                egui::Align2::LEFT_CENTER,
+                math::layout(&text[range]),
+                self.config.fonts.body(),
+                self.theme.math,
+            );
+        }
+    }
+
+    // a bullet over each unordered list marker, filled, hollow then square the deeper it's nested,
+    //   and a checkbox over each task's, returns where the checkboxes went and the char that ticks them
+    fn paint_list_markers(
+        &self,
+        ui: &egui::Ui,
+        galley: &Galley,
+        galley_pos: egui::Pos2,
+    ) -> Vec<(egui::Rect, usize)> {
+        let text = self.note.as_str();
+        let width = ui.fonts_mut(|f| f.glyph_width(&FontId::default(), '-'));
+        let row_at = |byte: usize| {
+            return galley
+                .pos_from_cursor(CCursor::new(char_index(text, byte)))
+                .translate(galley_pos.to_vec2());
+        };
+        let mut boxes = Vec::new();
+        let (mut pos, strings) = self.highlighted();
+        for s in strings {
+            let marker = pos + s.text.len() - s.text.trim_start().len();
+            match s.mdtype {
+                MarkdownType::Bullet(depth) => {
+                    let rect = row_at(marker);
+                    let center = egui::pos2(rect.min.x + width / 2.0, rect.center().y);
+                    let radius = rect.height() * 0.15;
+                    let bullet = match depth % 3 {
+                        0 => egui::Shape::circle_filled(center, radius, self.theme.list_marker),
+                        1 => egui::Shape::circle_stroke(
+                            center,
+                            radius,
+                            Stroke::new(1.0, self.theme.list_marker),
+                        ),
+                        _ => egui::Shape::rect_filled(
+                            egui::Rect::from_center_size(center, egui::Vec2::splat(radius * 2.0)),
+                            0.0,
+                            self.theme.list_marker,
+                        ),
+                    };
+                    ui.painter().add(bullet);
+                }
+                MarkdownType::Task(done) => {
+                    // the box sits over the brackets, the dash before them isn't drawn
+                    let end = pos + s.text.len();
+                    let (open, close) = (row_at(end - 3), row_at(end));
+                    let size = open.height() * 0.6;
+                    let center = egui::pos2((open.min.x + close.min.x) / 2.0, open.center().y);
+                    let rect = egui::Rect::from_center_size(center, egui::Vec2::splat(size));
+                    let stroke = Stroke::new(1.5, self.theme.list_marker);
+                    if done {
+                        ui.painter().rect_filled(rect, 2.0, self.theme.list_marker);
+                        let tick = Stroke::new(2.0, ui.visuals().extreme_bg_color);
+                        ui.painter().line(
+                            vec![
+                                rect.lerp_inside(egui::vec2(0.2, 0.5)),
+                                rect.lerp_inside(egui::vec2(0.42, 0.72)),
+                                rect.lerp_inside(egui::vec2(0.8, 0.28)),
+                            ],
+                            tick,
+                        );
+                    } else {
+                        ui.painter()
+                            .rect_stroke(rect, 2.0, stroke, egui::StrokeKind::Inside);
+                    }
+                    boxes.push((rect.expand(2.0), char_index(text, end - 2)));
+                }
+                _ => {}
+            }
+            pos += s.text.len();
+        }
+        return boxes;
+    }
+
+    // tick or untick the task whose box was clicked, the text stays `[ ]` or `[x]`
+    fn toggle_task(&mut self, mark: usize) {
+        let text = self.note.as_str();
+        let byte = byte_index(text, mark);
+        let ticking = text[byte..].starts_with(' ');
+        self.apply(Edit::Replace {
+            range: mark..mark + 1,
+            text: if ticking { "x" } else { " " }.to_string(),
+        });
+    }
+
+    // swap every table formula in the note for the value it works out to
+    fn bake_formulas(&mut self) {
+        let text = self.note.as_str();
+        let edits: Vec<Edit> = formula::evaluate(text)
+            .iter()
+            .map(|computed| Edit::Replace {
+                range: char_index(text, computed.range.start)..char_index(text, computed.range.end),
+                text: computed.text(),
+            })
+            .collect();
+        if edits.is_empty() {
+            self.toasts
+                .info(tr("There are no table formulas in this note"));
+            return;
+        }
+        self.apply(Edit::Batch(edits));
+    }
+
+    // draw each sketch under its link, returns where they went so a click can open them
+    fn paint_sketches(
+        &mut self,
+        ui: &egui::Ui,
+        galley: &Galley,
+        galley_pos: egui::Pos2,
+    ) -> Vec<(egui::Rect, PathBuf)> {
+        let text = self.note.as_str();
+        let mut placed = Vec::new();
+        for (range, link) in sketch::links(text) {
+            let row = galley
+                .pos_from_cursor(CCursor::new(char_index(text, range.start)))
+                .translate(galley_pos.to_vec2());
+            let top = row.min.y + ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+            let rect = egui::Rect::from_min_max(
+                egui::pos2(row.min.x, top),
+                egui::pos2(
+                    galley_pos.x + galley.rect.width(),
+                    row.min.y + sketches::ROW_HEIGHT - 4.0,
+                ),
+            );
+            let path = self.root.join(link);
+            let color = ui.visuals().text_color();
+            sketches::paint(ui.painter(), rect, self.sketches.get(&path), color);
+            placed.push((rect, path));
+        }
+        return placed;
+    }
+
+    // tables go on their own line, the cursor ends up in the first header cell
+    fn insert_table(&mut self, ctx: &egui::Context, id: egui::Id, rows: usize, columns: usize) {
+        let start = self
+            .cursor_range
+            .primary
+            .index
+            .min(self.cursor_range.secondary.index);
+        let text = self.note.as_str();
+        let byte = byte_index(text, start);
+        let mut table = table::empty_table(rows, columns);
+        if byte > 0 && !text[..byte].ends_with('\n') {
+            table.insert(0, '\n');
+        }
+        let cell = start + table.find('|').unwrap_or(0) + 2;
+        self.replace_selection(ctx, id, &table);
+        self.set_cursor(ctx, id, cell, cell);
+    }
+
+    fn table_dialog(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some((mut rows, mut columns)) = self.table_dialog else {
+            return;
+        };
+
+        let mut open = true;
+        let mut insert = false;
+        egui::Window::new(tr("Insert table"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("table_size").show(ui, |ui| {
+                    ui.label(tr("Rows"));
+                    ui.add(egui::DragValue::new(&mut rows).range(0..=100));
+                    ui.end_row();
+                    ui.label(tr("Columns"));
+                    ui.add(egui::DragValue::new(&mut columns).range(1..=20));
+                    ui.end_row();
+                });
+                insert = ui.button(tr("Insert")).clicked();
+            });
+
+        if insert {
+            self.insert_table(ctx, id, rows, columns);
+            self.table_dialog = None;
+        } else if open {
+            self.table_dialog = Some((rows, columns));
+        } else {
+            self.table_dialog = None;
+        }
+    }
+
+    // hold back a paste that looks like spreadsheet cells so the user can pick how it goes in
+    fn catch_table_paste(&mut self, ctx: &egui::Context) {
+        if self.carets.is_some() || self.table_paste.is_some() {
+            return;
+        }
+        ctx.input_mut(|i| {
+            i.events.retain(|event| match event {
+                egui::Event::Paste(text) if table::parse_delimited(text).is_some() => {
+                    self.table_paste = Some(text.clone());
+                    return false;
+                }
+                _ => return true,
+            });
+        });
+    }
+
+    fn table_paste_prompt(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some(pasted) = &self.table_paste else {
+            return;
+        };
+        let Some(rows) = table::parse_delimited(pasted) else {
+            self.table_paste = None;
+            return;
+        };
+
+        let mut open = true;
+        let mut choice = None;
+        egui::Window::new(tr("Paste table"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(trf(
+                    "The pasted text looks like a table with {} rows and {} columns.",
+                    &[&rows.len(), &rows[0].len()],
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Paste as table")).clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button(tr("Paste as text")).clicked() {
+                        choice = Some(false);
+                    }
+                });
+            });
+
+        let Some(as_table) = choice else {
+            if !open {
+                self.table_paste = None;
+            }
+            return;
+        };
+        let pasted = self.table_paste.take().unwrap_or_default();
+        if !as_table {
+            self.replace_selection(ctx, id, &pasted);
+            return;
+        }
+        let start = self
+            .cursor_range
+            .primary
+            .index
+            .min(self.cursor_range.secondary.index);
+        let text = self.note.as_str();
+        let byte = byte_index(text, start);
+        let mut markdown = table::from_rows(&rows);
+        if byte > 0 && !text[..byte].ends_with('\n') {
+            markdown.insert(0, '\n');
+        }
+        self.replace_selection(ctx, id, &markdown);
+    }
+
+    // expand a `;;date` style abbreviation that was just typed
+    fn expand_abbreviation(&mut self, ctx: &egui::Context, id: egui::Id) {
+        if self.cursor_range.primary != self.cursor_range.secondary {
+            return;
+        }
+
+        let cursor = self.cursor_range.primary.index;
+        let text = self.note.as_str();
+        let byte = byte_index(text, cursor);
+        if let Some((remove, expansion)) = self.stamp_formats.expand(&text[..byte], &Local::now()) {
+            let pos = cursor - remove + expansion.chars().count();
+            self.apply(Edit::Replace {
+                range: cursor - remove..cursor,
+                text: expansion,
+            });
+            self.set_cursor(ctx, id, pos, pos);
+        }
+    }
+
+    // who we are to the others in a collaboration session
+    fn collab_name() -> String {
+        return env::var("USER")
+            .ok()
+            .filter(|u| !u.is_empty())
+            .unwrap_or_else(|| "someone".to_string());
+    }
+
+    fn host_session(&mut self) {
+        let text = self.note.full().to_string();
+        match Collab::host(collab::DEFAULT_PORT, &text, &Self::collab_name()) {
+            Ok(collab) => {
+                self.toasts.warning(trf(
+                    "Sharing {} on port {}, guests need the token {}. The session isn't encrypted, only share on a network you trust",
+                    &[&self.path.display(), &collab::DEFAULT_PORT, &collab.token],
+                ));
+                self.collab = Some(collab);
+            }
+            Err(e) => self
+                .toasts
+                .error(trf("Couldn't start a collaboration session: {}", &[&e])),
+        }
+    }
+
+    fn join_session(&mut self, address: &str, token: &str) {
+        let address = if address.contains(':') {
+            address.to_string()
+        } else {
+            format!("{}:{}", address, collab::DEFAULT_PORT)
+        };
+        match Collab::join(&address, token, &Self::collab_name()) {
+            Ok(collab) => {
+                // the note is the host's, it's saved on their side and not over ours
+                self.path = PathBuf::new();
+                self.unsaved_new = false;
+                self.note = NoteBuffer(Note::new(String::new()));
+                self.cursor_range = CCursorRange::default();
+                self.collab = Some(collab);
+            }
+            Err(e) => self
+                .toasts
+                .error(trf("Couldn't join {}: {}", &[&address, &e])),
+        }
+    }
+
+    fn join_dialog(&mut self, ctx: &egui::Context) {
+        let Some((mut address, mut token)) = self.join_dialog.take() else {
+            return;
+        };
+        let mut open = true;
+        let mut join = false;
+        egui::Window::new(tr("Join session"))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(tr("Host address, the port defaults to 7878"));
+                let response = ui.text_edit_singleline(&mut address);
+                if ui.memory(|m| m.focused().is_none()) {
+                    response.request_focus();
+                }
+                ui.label(tr("Token the host was given"));
+                let response = ui.text_edit_singleline(&mut token);
+                ui.weak(tr(
+                    "The session isn't encrypted, anyone on the network can read the note and the token",
+                ));
+                join = ui.button(tr("Join")).clicked()
+                    || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)));
+            });
+        if join && !address.trim().is_empty() {
+            self.join_session(address.trim(), token.trim());
+        } else if open && !ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.join_dialog = Some((address, token));
+        }
+    }
+
+    // a caret on screen as a char in the full text and back again
+    fn full_char(&mut self, pos: usize) -> usize {
+        let byte = self.note.translate(byte_index(self.note.as_str(), pos));
+        return char_index(self.note.full(), byte);
+    }
+
+    fn display_char(&mut self, pos: usize) -> usize {
+        let byte = byte_index(self.note.full(), pos);
+        let byte = self.note.inv_translate(byte);
+        return char_index(self.note.as_str(), byte.min(self.note.as_str().len()));
+    }
+
+    // trade edits with the other instances, remote ones are merged under the caret
+    fn sync_collab(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let Some(mut collab) = self.collab.take() else {
+            return;
+        };
+        let primary = self.full_char(self.cursor_range.primary.index);
+        let secondary = self.full_char(self.cursor_range.secondary.index);
+        let text = self.note.full().to_string();
+        if let Some(merged) = collab.sync(&text, primary)
+            && let Some((range, inserted)) = noters_core::collab::splice(&text, &merged)
+        {
+            let inserted = inserted.chars().count();
+            let primary = noters_core::collab::moved(primary, &range, inserted);
+            let secondary = noters_core::collab::moved(secondary, &range, inserted);
+            self.note.merge_remote(&merged);
+            let (primary, secondary) = (self.display_char(primary), self.display_char(secondary));
+            self.cursor_range = CCursorRange {
+                primary: CCursor::new(primary),
+                secondary: CCursor::new(secondary),
+                h_pos: None,
+            };
+            if let Some(mut state) = TextEditState::load(ctx, id) {
+                state.cursor.set_char_range(Some(self.cursor_range));
+                state.store(ctx, id);
+            }
+            self.events.publish(Event::NoteModified);
+        }
+        if collab.is_closed() {
+            self.toasts.warning(tr("The collaboration session ended"));
+            return;
+        }
+        self.collab = Some(collab);
+        ctx.request_repaint_after(Duration::from_millis(100));
+    }
+
+    // everyone else's caret in their own color with their name over it
+    fn paint_remote_cursors(&mut self, ui: &egui::Ui, galley: &Galley, galley_pos: egui::Pos2) {
+        let Some(cursors) = self.collab.as_ref().map(Collab::remote_cursors) else {
+            return;
+        };
+        for (name, pos) in cursors {
+            let pos = self.display_char(pos);
+            let hue = name
+                .bytes()
+                .fold(0u32, |h, b| h.wrapping_mul(31).wrapping_add(b as u32));
+            let color: Color32 =
+                egui::ecolor::Hsva::new((hue % 360) as f32 / 360.0, 0.7, 0.9, 1.0).into();
+            let caret = galley
+                .pos_from_cursor(CCursor::new(pos))
+                .translate(galley_pos.to_vec2());
+            ui.painter()
+                .vline(caret.min.x, caret.y_range(), Stroke::new(2.0, color));
+            ui.painter().text(
+                caret.left_top(),
+                egui::Align2::LEFT_BOTTOM,
+                name,
+                FontId::proportional(10.0),
+                color,
+            );
+        }
+    }
+
+    fn set_lan_sync(&mut self, enabled: bool) {
+        if !enabled {
+            self.lan_sync = None;
+            return;
+        }
+        match LanSync::start(&self.root) {
+            Ok(sync) => self.lan_sync = Some(sync),
+            Err(e) => self.toasts.error(e),
+        }
+    }
+
+    // a note sync wrote to disk is the open one: take it if nothing was typed since, otherwise
+    //   ask the same way as when another program changes it
+    fn synced_note(&mut self, path: PathBuf, before: &str) {
+        if path != self.path {
+            return;
+        }
+        let name = path
+            .strip_prefix(&self.root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .to_string();
+        match files::load(&self.root, &name) {
+            Ok((_, Some(disk))) => {
+                if self.note.full() == before {
+                    self.take_disk(disk, false);
+                } else {
+                    self.conflict = Some(disk);
+                }
+            }
+            Ok((_, None)) => {}
+            Err(e) => self.toasts.error(e.to_string()),
+        }
+    }
+
+    fn poll_lan_sync(&mut self, ctx: &egui::Context) {
+        let Some(sync) = &mut self.lan_sync else {
+            return;
+        };
+        for (path, before) in sync.poll(ctx) {
+            self.synced_note(path, &before);
+        }
+    }
+
+    fn sync_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_sync;
+        let mut enabled = self.lan_sync.is_some();
+        let mut resolved = None;
+        egui::Window::new(tr("LAN sync"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.checkbox(&mut enabled, tr("Sync this vault with devices on the network"));
+                let Some(sync) = &mut self.lan_sync else {
+                    return;
+                };
+                let fingerprint = &sync.identity().fingerprint;
+                ui.label(trf(
+                    "This device: {} ({}), port {}",
+                    &[&sync.identity().name, &lansync::short_fingerprint(fingerprint), &sync.port],
+                ))
+                .on_hover_text(fingerprint);
+                ui.label(sync.label());
+                if let lansync::Status::Failed(e) = &sync.status {
+                    ui.colored_label(ui.visuals().warn_fg_color, e);
+                }
+
+                ui.separator();
+                ui.strong(tr("Devices"));
+                if sync.devices.is_empty() {
+                    ui.label(tr("None found yet, NoteRs has to be running with sync on"));
+                }
+                let devices: Vec<lansync::Device> =
+                    sync.devices.iter().map(|(d, _)| d.clone()).collect();
+                for device in devices.iter().chain(sync.strangers.clone().iter()) {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{} ({})",
+                            device.name,
+                            lansync::short_fingerprint(&device.fingerprint)
+                        ))
+                        .on_hover_text(&device.fingerprint);
+                        let trusted = sync.is_trusted(&device.fingerprint);
+                        if trusted && ui.button(tr("Forget")).clicked() {
+                            sync.trust(device, false);
+                        } else if !trusted
+                            && ui
+                                .button(tr("Trust"))
+                                .on_hover_text(
+                                    tr("Check the fingerprint matches the one shown on that device"),
+                                )
+                                .clicked()
+                        {
+                            sync.trust(device, true);
+                        }
+                    });
+                }
+                if ui.button(tr("Sync now")).clicked() {
+                    sync.sync_now();
+                }
+
+                if !sync.conflicts.is_empty() {
+                    ui.separator();
+                    ui.strong(tr("Edited on both sides"));
+                }
+                for conflict in &sync.conflicts {
+                    ui.collapsing(&conflict.name, |ui| {
+                        ui.label(tr("Both devices changed this note while apart, it holds the merged text for now."));
+                        ui.horizontal(|ui| {
+                            for (label, resolution) in [
+                                (tr("Keep merged"), Resolution::Merged),
+                                (tr("Keep mine"), Resolution::Mine),
+                                (tr("Keep theirs"), Resolution::Theirs),
+                                (tr("Mark up both"), Resolution::Markers),
+                            ] {
+                                if ui.button(label).clicked() {
+                                    resolved = Some((conflict.name.clone(), resolution));
+                                }
+                            }
+                        });
+                    });
+                }
+            });
+        self.show_sync = open;
+        if enabled != self.lan_sync.is_some() {
+            self.set_lan_sync(enabled);
+        }
+        if let Some((name, resolution)) = resolved
+            && let Some(sync) = &mut self.lan_sync
+        {
+            let before = self.note.full().to_string();
+            match sync.resolve(&name, resolution) {
+                Ok(path) => self.synced_note(path, &before),
+                Err(e) => self.toasts.error(e),
+            }
+        }
+    }
+
+    fn share_window(&mut self, ctx: &egui::Context) {
+        if self.share.as_ref().is_some_and(Share::is_expired) {
+            self.share = None;
+            self.toasts.info(tr("The share link expired"));
+        }
+        let mut open = self.show_share;
+        let mut start = None;
+        let mut stop = false;
+        egui::Window::new(tr("Share note"))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let Some(share) = &self.share else {
+                    ui.label(tr("Anyone on your network with the link can read this note, as it is now, until the link runs out."));
+                    ui.horizontal(|ui| {
+                        for (label, lasts) in share::DURATIONS {
+                            if ui.button(trf("For {}", &[&tr(label)])).clicked() {
+                                start = Some(lasts);
+                            }
+                        }
+                    });
+                    return;
+                };
+                ui.label(trf("{} is shared at", &[&share.title]));
+                ui.horizontal(|ui| {
+                    ui.add(egui::Label::new(egui::RichText::new(&share.url).monospace()).selectable(true));
+                    if ui.button(tr("Copy link")).clicked() {
+                        ctx.copy_text(share.url.clone());
+                    }
+                });
+                ui.label(trf("Expires in {}", &[&share.remaining()]));
+                stop = ui.button(tr("Stop sharing")).clicked();
+            });
+        self.show_share = open;
+        if stop {
+            self.share = None;
+        }
+        if let Some(lasts) = start {
+            let title = self
+                .path
+                .file_stem()
+                .map_or(tr("Note").to_string(), |s| s.to_string_lossy().to_string());
+            let text = self.note.full().to_string();
+            match Share::start(&title, &text, lasts) {
+                Ok(share) => self.share = Some(share),
+                Err(e) => self.toasts.error(trf("Couldn't share the note: {}", &[&e])),
+            }
+        }
+        if self.share.is_some() {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+    }
+
+    // the open note's name as links write it
+    fn note_name(&self) -> String {
+        let relative = self.path.strip_prefix(&self.root).unwrap_or(&self.path);
+        return relative
+            .with_extension("")
+            .to_string_lossy()
+            .replace('\\', "/");
+    }
+
+    fn set_link_syntax(&mut self, syntax: LinkSyntax) {
+        self.link_syntax = syntax;
+        if let Err(e) = vault::set_setting(&self.root, "link-syntax", syntax.key()) {
+            self.toasts
+                .error(trf("Couldn't save the vault settings: {}", &[&e]));
+        }
+    }
+
+    // every note's links rewritten into the vault's syntax, the open one as an edit that can be undone
+    fn convert_links(&mut self, from: Option<LinkSyntax>) {
+        let to = self.link_syntax;
+        let mut changed = 0;
+        let (text, count) = links::convert(self.note.full(), from, to);
+        if count > 0 {
+            self.note.rewrite(&text);
+            self.events.publish(Event::NoteModified);
+            changed += 1;
+        }
+        match vault::convert_links(&self.root, from, to, &self.note_name()) {
+            Ok(notes) => {
+                changed += notes.len();
+                self.toasts
+                    .info(trf("Converted the links in {} notes", &[&changed]));
+            }
+            Err(e) => self
+                .toasts
+                .error(trf("Couldn't convert the links: {}", &[&e])),
+        }
+    }
+
+    fn links_window(&mut self, ctx: &egui::Context) {
+        let Some(mut from) = self.links_window else {
+            return;
+        };
+        let mut open = true;
+        let mut syntax = self.link_syntax;
+        let mut convert = false;
+        egui::Window::new(tr("Links"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(tr("New links in this vault are written as"));
+                for option in LinkSyntax::ALL {
+                    ui.radio_value(&mut syntax, option, option.example());
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(tr("Convert links written as"));
+                    egui::ComboBox::from_id_salt("convert from")
+                        .selected_text(from.map_or(tr("any other syntax"), LinkSyntax::example))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut from, None, tr("any other syntax"));
+                            for option in LinkSyntax::ALL {
+                                ui.selectable_value(&mut from, Some(option), option.example());
+                            }
+                        });
+                });
+                convert = ui
+                    .add_enabled(
+                        from != Some(syntax),
+                        egui::Button::new(trf(
+                            "Rewrite them as {} in every note",
+                            &[&syntax.example()],
+                        )),
+                    )
+                    .clicked();
+            });
+        if syntax != self.link_syntax {
+            self.set_link_syntax(syntax);
+        }
+        self.links_window = open.then_some(from);
+        if convert {
+            self.convert_links(from);
+        }
+    }
+
+    // what the replace would change in every note, the open one as it is in the editor
+    fn preview_replace(&mut self) {
+        let name = self.note_name();
+        let Some(dialog) = &mut self.replace_dialog else {
+            return;
+        };
+        dialog.found.clear();
+        dialog.error = None;
+        dialog.searched = true;
+        let pattern = match Pattern::new(&dialog.find, dialog.regex) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                dialog.error = Some(e.to_string());
+                return;
+            }
+        };
+        let mut found = Vec::new();
+        let open = search::find_replacements(self.note.full(), &pattern, &dialog.replace);
+        if !open.is_empty() {
+            found.push((name.clone(), open));
+        }
+        match vault::find_replacements(&self.root, &pattern, &dialog.replace, &name) {
+            Ok(others) => found.extend(others),
+            Err(e) => dialog.error = Some(trf("Couldn't search the notes: {}", &[&e])),
+        }
+        // everything starts out ticked
+        dialog.found = found
+            .into_iter()
+            .map(|(name, replacements)| {
+                return (name, replacements.into_iter().map(|r| (r, true)).collect());
+            })
+            .collect();
+    }
+
+    // make the ticked replacements, the open note's as an edit that can be undone
+    fn apply_replace(&mut self) {
+        let Some(dialog) = &mut self.replace_dialog else {
+            return;
+        };
+        let found = std::mem::take(&mut dialog.found);
+        dialog.searched = false;
+        let name = self.note_name();
+        let mut replaced = 0;
+        let mut notes = 0;
+        for (note, replacements) in found {
+            let chosen: Vec<Replacement> = replacements
+                .into_iter()
+                .filter_map(|(r, chosen)| chosen.then_some(r))
+                .collect();
+            if chosen.is_empty() {
+                continue;
+            }
+            let result = if note == name {
+                match search::apply_replacements(self.note.full(), &chosen) {
+                    Some(text) => {
+                        self.note.rewrite(&text);
+                        self.events.publish(Event::NoteModified);
+                        Ok(())
+                    }
+                    None => Err(tr("it changed since the search").to_string()),
+                }
+            } else {
+                vault::apply_replacements(&self.root, &note, &chosen).map_err(|e| e.to_string())
+            };
+            match result {
+                Ok(()) => {
+                    replaced += chosen.len();
+                    notes += 1;
+                }
+                Err(e) => self
+                    .toasts
+                    .error(trf("Couldn't replace in {}: {}", &[&note, &e])),
+            }
+        }
+        self.toasts
+            .info(trf("Replaced {} matches in {} notes", &[&replaced, &notes]));
+    }
+
+    fn replace_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.replace_dialog else {
+            return;
+        };
+        let mut open = true;
+        let mut preview = false;
+        let mut apply = false;
+        egui::Window::new(tr("Replace in all notes"))
+            .open(&mut open)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                egui::Grid::new("replace fields").show(ui, |ui| {
+                    ui.label(tr("Find"));
+                    preview |= ui.text_edit_singleline(&mut dialog.find).lost_focus()
+                        && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    ui.end_row();
+                    ui.label(tr("Replace with"));
+                    ui.text_edit_singleline(&mut dialog.replace);
+                    ui.end_row();
+                });
+                ui.checkbox(&mut dialog.regex, tr("Regular expression"))
+                    .on_hover_text(tr("The replacement can use groups as $1 or ${name}"));
+                ui.horizontal(|ui| {
+                    preview |= ui.button(tr("Preview")).clicked();
+                    let chosen: usize = dialog
+                        .found
+                        .iter()
+                        .map(|(_, r)| r.iter().filter(|(_, chosen)| *chosen).count())
+                        .sum();
+                    apply = ui
+                        .add_enabled(
+                            chosen > 0,
+                            egui::Button::new(trf("Replace {} matches", &[&chosen])),
+                        )
+                        .clicked();
+                });
+                if let Some(error) = &dialog.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                } else if dialog.searched && dialog.found.is_empty() {
+                    ui.weak(tr("No note has a match"));
+                }
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        for (note, replacements) in &mut dialog.found {
+                            let mut all = replacements.iter().all(|(_, chosen)| *chosen);
+                            ui.horizontal(|ui| {
+                                if ui.checkbox(&mut all, "").changed() {
+                                    for (_, chosen) in replacements.iter_mut() {
+                                        *chosen = all;
+                                    }
+                                }
+                                ui.strong(note.as_str());
+                            });
+                            ui.indent(note.as_str(), |ui| {
+                                for (replacement, chosen) in replacements.iter_mut() {
+                                    ui.checkbox(
+                                        chosen,
+                                        trf(
+                                            "line {}: {}",
+                                            &[&replacement.line, &replacement.context],
+                                        ),
+                                    )
+                                    .on_hover_text(trf(
+                                        "{} → {}",
+                                        &[&replacement.found, &replacement.with],
+                                    ));
+                                }
+                            });
+                        }
+                    });
+            });
+        if !open {
+            self.replace_dialog = None;
+        } else if apply {
+            self.apply_replace();
+        } else if preview {
+            self.preview_replace();
+        }
+    }
+
+    fn open_print_dialog(&mut self) {
+        self.print_dialog = Some(print::Options {
+            include_collapsed: true,
+            paper: 0,
+            pdf: print::pdf_path(&self.path).display().to_string(),
+        });
+    }
+
+    fn print_dialog(&mut self, ctx: &egui::Context) {
+        let Some(options) = &mut self.print_dialog else {
+            return;
+        };
+        let mut open = true;
+        let mut print_it = false;
+        let mut save_it = false;
+        let busy = self.printing.is_some();
+        egui::Window::new(tr("Print"))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.checkbox(
+                    &mut options.include_collapsed,
+                    tr("Include collapsed sections"),
+                );
+                ui.horizontal(|ui| {
+                    ui.label(tr("Paper"));
+                    for (i, (label, _)) in print::PAPERS.iter().enumerate() {
+                        ui.radio_value(&mut options.paper, i, tr(label));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label(tr("PDF"));
+                    ui.text_edit_singleline(&mut options.pdf);
+                });
+                ui.horizontal(|ui| {
+                    print_it = ui
+                        .add_enabled(!busy, egui::Button::new(tr("Print")))
+                        .clicked();
+                    save_it = ui
+                        .add_enabled(
+                            !busy && !options.pdf.trim().is_empty(),
+                            egui::Button::new(tr("Save as PDF")),
+                        )
+                        .clicked();
+                    if busy {
+                        ui.spinner();
+                    }
+                });
+            });
+        if !open {
+            self.print_dialog = None;
+            return;
+        }
+        if !print_it && !save_it {
+            return;
+        }
+        let options = self.print_dialog.take().unwrap();
+        // the displayed text leaves folded sections out, the full text has everything
+        let text = if options.include_collapsed {
+            self.note.full().to_string()
+        } else {
+            self.note.as_str().to_string()
+        };
+        let paper = print::PAPERS[options.paper].1;
+        let root = self.root.clone();
+        let title = self
+            .path
+            .file_stem()
+            .map_or(tr("Note").to_string(), |s| s.to_string_lossy().to_string());
+        self.printing = Some(if print_it {
+            Task::spawn(move || print::print(&root, &title, &text, paper))
+        } else {
+            let out = PathBuf::from(options.pdf.trim());
+            Task::spawn(move || {
+                let pages = print::write_pdf(&root, &text, paper, &out)?;
+                return Ok(trf("Saved {} pages to {}", &[&pages, &out.display()]));
+            })
+        });
+    }
+
+    fn poll_printing(&mut self, ctx: &egui::Context) {
+        let Some(task) = &self.printing else {
+            return;
+        };
+        let Some(result) = task.poll() else {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        };
+        self.printing = None;
+        match result {
+            Ok(done) => self.toasts.info(done),
+            Err(e) => {
+                tracing::warn!("{}", e);
+                self.toasts.error(e);
+            }
+        }
+    }
+
+    fn duplicate_lines(&mut self, ctx: &egui::Context, id: egui::Id) {
+        let primary = byte_index(self.note.as_str(), self.cursor_range.primary.index);
+        let secondary = byte_index(self.note.as_str(), self.cursor_range.secondary.index);
+        let (primary, secondary) = self.note.duplicate_lines(primary, secondary);
+        let primary = char_index(self.note.as_str(), primary);
+        let secondary = char_index(self.note.as_str(), secondary);
+        self.set_cursor(ctx, id, primary, secondary);
+    }
+}
+
+impl eframe::App for NoteRs {
+    fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        self.perf.begin(self.note.reparses());
+        self.poll_files(ctx);
+        self.poll_reminders(ctx);
+        self.poll_archiving(ctx);
+        self.poll_extracting(ctx);
+        self.poll_assisting(ctx);
+        self.poll_lan_sync(ctx);
+        self.poll_printing(ctx);
+        self.poll_autosave(ctx);
+        self.poll_platform_theme(ctx);
+        self.catch_close(ctx);
+        self.touch.input(ctx);
+        self.toasts.show(ctx);
+
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if let Some(share) = &self.share
+                    && ui
+                        .link(trf("🔗 Shared for {}", &[&share.remaining()]))
+                        .clicked()
+                {
+                    self.show_share = true;
+                }
+                if let Some(sync) = &self.lan_sync
+                    && ui.link(sync.label()).clicked()
+                {
+                    self.show_sync = true;
+                }
+                if let Some(collab) = &self.collab {
+                    ui.label(trf(
+                        "{}, {} connected",
+                        &[&collab.description, &collab.peer_count()],
+                    ));
+                    ui.separator();
+                }
+                if self.note.is_modified() {
+                    ui.weak(tr("Unsaved changes"));
+                    ui.separator();
+                } else if let Some(saved) = self.last_saved {
+                    ui.weak(trf("Saved at {}", &[&saved.format("%H:%M:%S")]));
+                    ui.separator();
+                }
+                if let Some(word) = self.selected_word() {
+                    let count = search::find_word(self.note.as_str(), &word).len();
+                    ui.label(trf(
+                        "{} occurrences of \"{}\" (F3 / Shift+F3)",
+                        &[&count, &word],
+                    ));
+                }
+            });
+        });
+
+        if let Some((note, pos)) = self.finder.show(ctx, &self.root) {
+            // the open note is gone to as it is, edits and all, rather than read again
+            if note != self.note_name() {
+                self.visit(note);
+            }
+            self.pending_position = Some(pos);
+        }
+        if let Some(note) = self.tag_browser.show(ctx, &self.root)
+            && note != self.note_name()
+        {
+            self.visit(note);
+        }
+        self.find_bar(ctx);
+        self.outline_panel(ctx);
+        self.split_pane(ctx);
+        self.note_windows(ctx);
+        self.plugins.panels(ctx, &self.note);
+        self.pdf_panel(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let text_edit_id = ui.make_persistent_id("editor");
+            if self.loading.is_none()
+                && let Some(line) = self.pending_line.take()
+            {
+                let pos = self.line_position(line);
+                self.goto_position(ctx, text_edit_id, pos);
+                self.pending_view = None;
+            }
+            if self.loading.is_none()
+                && let Some(pos) = self.pending_position.take()
+            {
+                self.goto_position(ctx, text_edit_id, pos);
+                self.pending_view = None;
+            }
+            if let Some(pos) = self.pending_fold.take() {
+                let shown = self.note.inv_translate(pos);
+                let pos = char_index(self.note.as_str(), shown);
+                self.toggle_section(ctx, text_edit_id, pos);
+            }
+            if self.loading.is_none()
+                && let Some(anchor) = self.pending_anchor.take()
+            {
+                self.goto_anchor(ctx, text_edit_id, &anchor);
+                self.pending_view = None;
+            }
+            if self.loading.is_none()
+                && let Some(view) = self.pending_view.take()
+            {
+                self.restore_view(ctx, text_edit_id, view);
+            }
+            match self.find_bar.as_mut().and_then(|bar| bar.pending.take()) {
+                Some(FindStep::Next) => self.jump_to_occurrence(ctx, text_edit_id, true),
+                Some(FindStep::Previous) => self.jump_to_occurrence(ctx, text_edit_id, false),
+                Some(FindStep::Replace) => self.replace_occurrence(ctx, text_edit_id),
+                None => {}
+            }
+            self.sync_collab(ctx, text_edit_id);
+            ui.horizontal(|ui| {
+                // a dot after the name while there are edits that haven't been saved
+                let mut title = self.path.display().to_string();
+                if self.note.is_modified() {
+                    title.push_str(" ●");
+                }
+                ui.heading(title);
+                if self.loading.is_some() {
+                    ui.spinner();
+                    ui.label(tr("Opening…"));
+                } else if self.saver.is_saving() {
+                    ui.spinner();
+                    ui.label(tr("Saving…"));
+                }
+            });
+            let mut scroll_area = egui::ScrollArea::vertical();
+            if let Some(offset) = self.restore_scroll.take() {
+                scroll_area = scroll_area.vertical_scroll_offset(offset);
+            }
+            let scrolled = scroll_area.show(ui, |ui| {
+                self.touch.scroll(ui);
+                if ctx.memory(|m| m.has_focus(text_edit_id)) {
+                    self.list_continuation_input(ctx, text_edit_id);
+                    self.auto_pair_input(ctx, text_edit_id);
+                    if self.smart_typography {
+                        self.smart_typography_input(ctx, text_edit_id);
+                    }
+                }
+
+                // shortcuts have to be taken before the TextEdit sees the keys
+                for command in self.commands.pressed(ctx) {
+                    self.run_command(ctx, text_edit_id, command);
+                }
+
+                let cursor = self.cursor_range.primary.index;
+                let selection_start = self
+                    .cursor_range
+                    .primary
+                    .index
+                    .min(self.cursor_range.secondary.index);
+                let selected_word = self.selected_word();
+                let find_pattern = self
+                    .find_bar
+                    .as_ref()
+                    .filter(|bar| !bar.find.is_empty())
+                    .and_then(|bar| Pattern::new(&bar.find, bar.regex).ok());
+                let hide_markup = self.hide_markup;
+                let visible = self.visible.clone();
+                let diagram_sources = self.diagrams.sources.clone();
+                let blocks = self.plugins.blocks();
+                let known_notes = self.known_notes.clone();
+                let generated = self.note.generated();
+                let theme = self.theme.clone();
+                let fonts = self.config.fonts.clone();
+                let sheet = self.stylesheet.clone();
+                let zoom = self.touch.zoom;
+                let parse_time = Cell::new(Duration::ZERO);
+                let layout_time = Cell::new(Duration::ZERO);
+                let mut layouter = |ui: &egui::Ui, buf: &dyn TextBuffer, wrap_width: f32| {
+                    // only the lines around what was on screen last frame get parsed and highlighted
+                    let text = buf.as_str();
+                    let window = match &visible {
+                        Some(chars) => viewport::line_window(
+                            text,
+                            byte_index(text, chars.start)..byte_index(text, chars.end),
+                        ),
+                        None => viewport::line_window(text, 0..0),
+                    };
+                    let shown = &text[window.clone()];
+                    let shift =
+                        |range: Range<usize>| range.start + window.start..range.end + window.start;
+
+                    let mut job = LayoutJob::default();
+                    let started = Instant::now();
+                    let strings = highlight_parse(shown);
+                    parse_time.set(parse_time.get() + started.elapsed());
+                    let maths = math_spans(&strings);
+                    let drawn = diagram_spans(&strings);
+                    let style = Style {
+                        theme: &theme,
+                        fonts: &fonts,
+                        sheet: &sheet,
+                    };
+                    render_outline(&mut job, &style, &text[..window.start]);
+                    render_markdown(&mut job, &style, strings, &blocks, known_notes.as_deref());
+                    render_outline(&mut job, &style, &text[window.end..]);
+                    job.wrap.max_width = wrap_width;
+
+                    // math the cursor isn't in makes room for its laid out form, paint_math draws
+                    //   that over the source, which is sized to match and hidden
+                    let cursor_byte = byte_index(text, cursor);
+                    for range in maths.into_iter().map(shift) {
+                        if range.contains(&cursor_byte) || range.end == cursor_byte {
+                            continue;
+                        }
+                        let source = ui.fonts_mut(|f| {
+                            f.layout_no_wrap(
+                                text[range.clone()].to_string(),
+                                fonts.monospace(),
+                                theme.math,
+                            )
+                        });
+                        let laid_out = ui.fonts_mut(|f| {
+                            f.layout_no_wrap(
+                                math::layout(&text[range.clone()]),
+                                fonts.body(),
+                                theme.math,
+                            )
+                        });
+                        let size =
+                            fonts.monospace_size * laid_out.size().x / source.size().x.max(1.0);
+                        restyle_range(&mut job, range, |format| {
+                            format.font_id.size = size.max(0.1);
+                            format.color = Color32::TRANSPARENT;
+                        });
+                    }
+
+                    // a table of contents is made from the headings rather than typed, and looks it
+                    for range in &generated {
+                        restyle_range(&mut job, range.clone(), |format| {
+                            format.color = theme.toc;
+                        });
+                    }
+
+                    // diagrams the cursor isn't in fold down to their opening fence, the last
+                    //   line grows to hold the picture paint_diagrams draws there
+                    for (range, _) in drawn {
+                        let range = shift(range);
+                        let block = &text[range.clone()];
+                        if range.contains(&cursor_byte)
+                            || range.end == cursor_byte
+                            || diagram_sources.contains(&diagrams::key(block))
+                        {
+                            continue;
+                        }
+                        let open = block.find('\n').map_or(range.end, |i| range.start + i + 1);
+                        restyle_range(&mut job, open..range.end, |format| {
+                            format.font_id.size = 0.1;
+                            format.color = Color32::TRANSPARENT;
+                        });
+                        restyle_range(&mut job, range.end - 1..range.end, |format| {
+                            format.line_height = Some(diagrams::ROW_HEIGHT);
+                            format.valign = egui::Align::TOP;
+                        });
+                    }
+
+                    let near_cursor = if window.contains(&cursor_byte) || cursor_byte == window.end
+                    {
+                        delimiters::matching(shown, cursor_byte - window.start)
+                    } else {
+                        Vec::new()
+                    };
+                    let markers: Vec<(Range<usize>, Color32)> = near_cursor
+                        .into_iter()
+                        .map(|(range, matched)| {
+                            let color = if matched {
+                                ui.visuals().selection.bg_fill.gamma_multiply(0.5)
+                            } else {
+                                ui.visuals().error_fg_color.gamma_multiply(0.5)
+                            };
+                            (shift(range), color)
+                        })
+                        .collect();
+                    for (range, color) in markers {
+                        restyle_range(&mut job, range, |format| format.background = color);
+                    }
+
+                    // the find bar's matches, or else the other places the selected word is
+                    let occurrences = match (&find_pattern, &selected_word) {
+                        (Some(pattern), _) => search::find(shown, pattern)
+                            .into_iter()
+                            .map(|m| m.range)
+                            .collect(),
+                        (None, Some(word)) => search::find_word(shown, word),
+                        (None, None) => Vec::new(),
+                    };
+                    if !occurrences.is_empty() {
+                        let selected = byte_index(text, selection_start);
+                        for range in occurrences.into_iter().map(shift) {
+                            if range.start != selected {
+                                let color = ui.visuals().selection.bg_fill.gamma_multiply(0.35);
+                                restyle_range(&mut job, range, |format| format.background = color);
+                            }
+                        }
+                    }
+
+                    // markup outside the line being edited collapses to nothing
+                    if hide_markup {
+                        let byte = byte_index(text, cursor);
+                        let line_start = text[..byte].rfind('\n').map_or(0, |i| i + 1);
+                        let line_end = text[byte..].find('\n').map_or(text.len(), |i| byte + i);
+                        for range in delimiters::markers(shown).into_iter().map(shift) {
+                            if range.end < line_start || range.start > line_end {
+                                restyle_range(&mut job, range, |format| {
+                                    format.font_id.size = 0.1;
+                                    format.color = Color32::TRANSPARENT;
+                                    format.underline = Stroke::NONE;
+                                });
+                            }
+                        }
+                    }
+
+                    // formulas away from the cursor line make way for their values, painted over them
+                    let byte = byte_index(text, cursor);
+                    let line_start = text[..byte].rfind('\n').map_or(0, |i| i + 1);
+                    let line_end = text[byte..].find('\n').map_or(text.len(), |i| byte + i);
+                    for computed in formula::evaluate(text) {
+                        if computed.range.end < line_start || computed.range.start > line_end {
+                            restyle_range(&mut job, computed.range, |format| {
+                                format.color = Color32::TRANSPARENT;
+                            });
+                        }
+                    }
+
+                    // sketch lines get tall enough to draw the sketch under the link
+                    for (range, _) in sketch::links(text) {
+                        restyle_range(&mut job, range, |format| {
+                            format.line_height = Some(sketches::ROW_HEIGHT);
+                            format.valign = egui::Align::TOP;
+                        });
+                    }
+
+                    // pinching in touch mode sizes the whole editor's text
+                    if zoom != 1.0 {
+                        for section in &mut job.sections {
+                            section.format.font_id.size *= zoom;
+                            if let Some(height) = &mut section.format.line_height {
+                                *height *= zoom;
+                            }
+                        }
+                    }
+
+                    let started = Instant::now();
+                    let galley = ui.fonts_mut(|f| f.layout_job(job));
+                    layout_time.set(layout_time.get() + started.elapsed());
+                    galley
+                };
+                let mut width = f32::INFINITY;
+                let mut side = 0.0;
+                if let Some(columns) = self.wrap_column {
+                    let column_width = ui.fonts_mut(|f| f.glyph_width(&FontId::default(), 'n'));
+                    // the TextEdit's own margin sits inside the desired width
+                    width = column_width * columns as f32 + 8.0;
+                    side = ((ui.available_width() - width - FOLD_GUTTER) / 2.0).max(0.0);
+                }
+
+                // the editor background is painted here so the line highlight can go under the text
+                let background = ui.painter().add(egui::Shape::Noop);
+                self.caret_input(ctx, text_edit_id);
+                self.catch_table_paste(ctx);
+                self.start_text_drag(ctx);
+                self.start_block_selection(ctx, text_edit_id);
+                if let Some((typed, note)) = self.link_completion.keys(ctx) {
+                    self.complete_link(ctx, text_edit_id, typed, &note);
+                }
+                let builder = egui::TextEdit::multiline(&mut self.note)
+                    .background_color(Color32::TRANSPARENT)
+                    .interactive(
+                        self.text_drag.is_none()
+                            && self.carets.is_none()
+                            && self.loading.is_none()
+                            && !self.touch.is_scrolling(),
+                    )
+                    .desired_width(width)
+                    .desired_rows((ctx.content_rect().height() / 16f32) as usize)
+                    .layouter(&mut layouter)
+                    .id(text_edit_id);
+                let editor = ui
+                    .horizontal_top(|ui| {
+                        ui.add_space(side + FOLD_GUTTER);
+                        builder.show(ui)
+                    })
+                    .inner;
+                let response = editor.response;
+                self.perf.add_parse(parse_time.get());
+                self.perf.add_layout(layout_time.get());
+                let galley = editor.galley;
+                self.update_text_drag(ui, text_edit_id, &galley, editor.galley_pos);
+                self.update_block_selection(ui, &galley, editor.galley_pos);
+                self.paint_carets(ui, &galley, editor.galley_pos);
+                self.paint_remote_cursors(ui, &galley, editor.galley_pos);
+                self.editor_galley = Some((galley.clone(), editor.galley_pos));
+                self.track_visible(ui, &galley, editor.galley_pos);
+                if self.scroll_to_cursor {
+                    self.scroll_to_cursor = false;
+                    let caret = galley
+                        .pos_from_cursor(self.cursor_range.primary)
+                        .translate(editor.galley_pos.to_vec2());
+                    ui.scroll_to_rect(caret, None);
+                }
+                let painter = ui.painter();
+
+                let mut background_shapes = vec![egui::Shape::rect_filled(
+                    response.rect,
+                    ui.visuals().widgets.inactive.corner_radius,
+                    ui.visuals().text_edit_bg_color(),
+                )];
+                if self.highlight_line && self.text_drag.is_none() {
+                    let cursor = editor.cursor_range.unwrap_or(self.cursor_range).primary;
+                    let caret = galley
+                        .pos_from_cursor(cursor)
+                        .translate(editor.galley_pos.to_vec2());
+                    background_shapes.push(egui::Shape::rect_filled(
+                        egui::Rect::from_x_y_ranges(response.rect.x_range(), caret.y_range()),
+                        0.0,
+                        ui.visuals().faint_bg_color,
+                    ));
+                }
+                painter.set(background, egui::Shape::Vec(background_shapes));
+                let sketch_rects = self.paint_sketches(ui, &galley, editor.galley_pos);
+                let folded = self.fold_gutter(ui, &galley, editor.galley_pos, response.rect);
+                self.paint_formulas(ui, &galley, editor.galley_pos);
+                self.paint_progress(ui, &galley, editor.galley_pos);
+                let task_boxes = self.paint_list_markers(ui, &galley, editor.galley_pos);
+                self.paint_math(ui, &galley, editor.galley_pos);
+                let diagram_switches = self.paint_diagrams(ui, &galley, editor.galley_pos);
+
+                if let Some(cursor_range) = editor.cursor_range {
+                    if self.cursor_range.primary.index != cursor_range.primary.index
+                        || self.cursor_range.secondary.index != cursor_range.primary.index
+                    {
+                        tracing::trace!("cursor moved: {:?}", cursor_range);
+                    }
+                    self.cursor_range = cursor_range;
+                }
+                if response.has_focus()
+                    && self.carets.is_none()
+                    && self.cursor_range.primary == self.cursor_range.secondary
+                {
+                    let cursor = self.cursor_range.primary.index;
+                    self.link_completion
+                        .update(&self.root, self.note.as_str(), cursor);
+                } else if !self.link_completion.hovered(ctx) {
+                    self.link_completion.close();
+                }
+                let caret = galley
+                    .pos_from_cursor(self.cursor_range.primary)
+                    .translate(editor.galley_pos.to_vec2());
+                if let Some((typed, note)) = self.link_completion.show(ctx, caret) {
+                    self.complete_link(ctx, text_edit_id, typed, &note);
+                }
+
+                if response.changed() {
+                    self.events.publish(Event::NoteModified);
+                    self.expand_abbreviation(ctx, text_edit_id);
+                }
+
+                if let Some(heading) = folded {
+                    self.toggle_section(ctx, text_edit_id, heading);
+                }
+                if response.clicked() {
+                    let pos = response.interact_pointer_pos();
+                    let sketch = sketch_rects
+                        .iter()
+                        .find(|(rect, _)| pos.is_some_and(|pos| rect.contains(pos)));
+                    let task = task_boxes
+                        .iter()
+                        .find(|(rect, _)| pos.is_some_and(|pos| rect.contains(pos)));
+                    let diagram = diagram_switches
+                        .iter()
+                        .find(|(rect, _)| pos.is_some_and(|pos| rect.contains(pos)));
+                    if let Some((_, path)) = sketch {
+                        self.sketch_editor = Some(SketchEditor::open(path.clone()));
+                    } else if let Some((_, mark)) = task {
+                        self.toggle_task(*mark);
+                    } else if let Some((_, key)) = diagram {
+                        self.diagrams.toggle(*key);
+                    } else if let Some(heading) = pos.and_then(|pos| {
+                        let cursor = galley.cursor_from_pos(pos - editor.galley_pos);
+                        return self.note.toc_target(cursor.index);
+                    }) {
+                        self.goto_position(ctx, text_edit_id, heading);
+                    } else if let Some(url) = pos
+                        .map(|pos| galley.cursor_from_pos(pos - editor.galley_pos).index)
+                        .filter(|index| self.note.get_node(*index).mdtype == MarkdownType::WebLink)
+                        .and_then(|index| {
+                            let text = self.note.as_str();
+                            return links::web_link_at(text, byte_index(text, index));
+                        })
+                    {
+                        // web addresses go to the browser rather than being looked for in the vault
+                        ctx.open_url(egui::OpenUrl::new_tab(url.0));
+                    } else if let Some(pos) = pos {
+                        // a tap that only just misses a link still follows it
+                        let node = self
+                            .touch
+                            .nearby(pos)
+                            .into_iter()
+                            .map(|spot| {
+                                let cursor = galley.cursor_from_pos(spot - editor.galley_pos);
+                                self.note.get_node(cursor.index)
+                            })
+                            .find(|node| node.mdtype == MarkdownType::Link);
+                        // markdown style note links aren't highlighted as links, look them up
+                        let target = match node {
+                            Some(node) => Some(links::link_target(&node.text).to_string()),
+                            None => {
+                                let cursor = galley.cursor_from_pos(pos - editor.galley_pos);
+                                let text = self.note.as_str();
+                                links::note_link_at(text, byte_index(text, cursor.index))
+                                    .map(str::to_string)
+                            }
+                        };
+                        let missing = |target: &String| {
+                            return self
+                                .known_notes
+                                .as_ref()
+                                .is_some_and(|known| !note_exists(known, target));
+                        };
+                        if let Some(target) = target.as_ref().filter(|target| missing(target)) {
+                            self.offer_new_note(links::split_anchor(target).0);
+                        } else if let Some(target) = &target
+                            && ctx.input(|i| i.modifiers.alt)
+                        {
+                            // alt+click opens the link beside this note rather than in its place
+                            let note = links::split_anchor(target).0.to_string();
+                            self.pane.open_note(&self.root, note, &mut self.saver);
+                        } else if let Some(target) = target {
+                            self.follow_link(target);
+                        }
+                    }
+                } else {
+                    // change the cursor icon when moving the mouse
+                    if let Some(p) = ctx.input_mut(|i| i.pointer.hover_pos()) {
+                        let local_pos = p - editor.galley_pos;
+                        let cursor = galley.cursor_from_pos(local_pos);
+                        let idx = cursor.index;
+                        let node = self.note.get_node(idx);
+                        match node.mdtype {
+                            MarkdownType::Link => {
+                                ctx.output_mut(|out| out.cursor_icon = CursorIcon::PointingHand);
+                                egui::Tooltip::always_open(
+                                    ctx.clone(),
+                                    ui.layer_id(),
+                                    response.id.with("link tip"),
+                                    egui::PopupAnchor::Pointer,
+                                )
+                                .show(|ui| ui.label(tr("Alt+click to open in the other pane")));
+                            }
+                            MarkdownType::WebLink | MarkdownType::Generated => {
+                                ctx.output_mut(|out| out.cursor_icon = CursorIcon::PointingHand)
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+
+                // the usual context menu, which a long press opens as well in touch mode
+                let open_menu =
+                    if response.secondary_clicked() || self.touch.long_press(ctx, &response) {
+                        Some(true)
+                    } else if response.clicked() {
+                        Some(false)
+                    } else {
+                        None
+                    };
+                egui::Popup::context_menu(&response)
+                    .open_memory(open_menu.map(egui::containers::SetOpenCommand::Bool))
+                    .show(|ui| {
+                        ui.menu_button(tr("Sort lines"), |ui| {
+                            ui.checkbox(
+                                &mut self.sort_options.case_insensitive,
+                                tr("Case-insensitive"),
+                            );
+                            ui.checkbox(&mut self.sort_options.reverse, tr("Reverse"));
+                            ui.checkbox(&mut self.sort_options.unique, tr("Remove duplicates"));
+                            if ui.button(tr("Sort (F9)")).clicked() {
+                                self.sort_lines(ctx, text_edit_id);
+                                ui.close();
+                            }
+                        });
+                        if ui
+                            .button(tr("Copy as plain text"))
+                            .on_hover_text(tr(
+                                "Copies the selection, or the whole note, without markdown",
+                            ))
+                            .clicked()
+                        {
+                            self.copy_plain(ctx);
+                            ui.close();
+                        }
+                        ui.menu_button(tr("Transform case"), |ui| {
+                            for (label, case) in [
+                                (tr("UPPERCASE"), TextCase::Upper),
+                                (tr("lowercase"), TextCase::Lower),
+                                (tr("Title Case"), TextCase::Title),
+                            ] {
+                                if ui.button(label).clicked() {
+                                    self.transform_case(ctx, text_edit_id, case);
+                                    ui.close();
+                                }
+                            }
+                        });
+                        ui.menu_button(tr("Insert"), |ui| {
+                            for (label, stamp) in [
+                                (tr("Date"), Stamp::Date),
+                                (tr("Time"), Stamp::Time),
+                                (tr("Timestamp"), Stamp::Timestamp),
+                            ] {
+                                if ui.button(label).clicked() {
+                                    self.insert_stamp(ctx, text_edit_id, stamp);
+                                    ui.close();
+                                }
+                            }
+                            ui.separator();
+                            if ui.button(tr("Table…")).clicked() {
+                                self.table_dialog = Some((2, 2));
+                                ui.close();
+                            }
+                            if ui.button(tr("Sketch…")).clicked() {
+                                self.insert_sketch(ctx, text_edit_id);
+                                ui.close();
+                            }
+                            if ui
+                                .button(tr("Image from clipboard (Ctrl+Shift+I)"))
+                                .clicked()
+                            {
+                                self.paste_image(ctx, text_edit_id);
+                                ui.close();
+                            }
+                        });
+                        let web_link = self.web_link();
+                        if ui
+                            .add_enabled(
+                                web_link.is_some() && self.archiving.is_none(),
+                                egui::Button::new(tr("Save readable copy")),
+                            )
+                            .on_disabled_hover_text(tr("Put the cursor on a web link"))
+                            .clicked()
+                            && let Some(url) = web_link
+                        {
+                            self.archive_link(url);
+                            ui.close();
+                        }
+                        let image_link = self.image_link();
+                        if ui
+                            .add_enabled(
+                                image_link.is_some() && self.extracting.is_none(),
+                                egui::Button::new(tr("Extract text from image")),
+                            )
+                            .on_disabled_hover_text(tr("Put the cursor on an image embed"))
+                            .clicked()
+                            && let Some(target) = image_link
+                        {
+                            self.extract_text(target);
+                            ui.close();
+                        }
+                        if ui.button(tr("Bake table formulas")).clicked() {
+                            self.bake_formulas();
+                            ui.close();
+                        }
+                        if self.assist.is_some() {
+                            let selected = self.cursor_range.primary.index
+                                != self.cursor_range.secondary.index;
+                            ui.menu_button(tr("Assistant"), |ui| {
+                                for command in assist::Command::ALL {
+                                    let enabled = self.assisting.is_none()
+                                        && (selected || command != assist::Command::Rewrite);
+                                    if ui
+                                        .add_enabled(enabled, egui::Button::new(command.label()))
+                                        .clicked()
+                                    {
+                                        self.run_assist(command);
+                                        ui.close();
+                                    }
+                                }
+                            });
+                        }
+                        if ui.button(tr("New note…")).clicked() {
+                            self.open_new_note_dialog();
+                            ui.close();
+                        }
+                        if ui
+                            .add_enabled(
+                                !self.path.as_os_str().is_empty(),
+                                egui::Button::new(tr("Rename note…")),
+                            )
+                            .clicked()
+                        {
+                            self.rename_dialog = Some(RenameDialog {
+                                name: self.note_name(),
+                                ..Default::default()
+                            });
+                            ui.close();
+                        }
+                        if ui.button(tr("Search all notes…")).clicked() {
+                            self.finder.show_panel();
+                            ui.close();
+                        }
+                        if ui.button(tr("Tags…")).clicked() {
+                            self.tag_browser.show_panel(&self.root);
+                            ui.close();
+                        }
+                        if ui.button(tr("Replace in all notes…")).clicked() {
+                            self.replace_dialog = Some(ReplaceDialog::default());
+                            ui.close();
+                        }
+                        if ui.button(tr("Links…")).clicked() {
+                            self.links_window = Some(None);
+                            ui.close();
+                        }
+                        if ui.button(tr("Print…")).clicked() {
+                            self.open_print_dialog();
+                            ui.close();
+                        }
+                        if ui.button(tr("Share note…")).clicked() {
+                            self.show_share = true;
+                            ui.close();
+                        }
+                        if ui.button(tr("LAN sync…")).clicked() {
+                            self.show_sync = true;
+                            ui.close();
+                        }
+                        ui.menu_button(tr("Collaborate"), |ui| {
+                            if self.collab.is_some() {
+                                if ui.button(tr("Leave session")).clicked() {
+                                    self.collab = None;
+                                    ui.close();
+                                }
+                                return;
+                            }
+                            if ui
+                                .button(tr("Share this note"))
+                                .on_hover_text(tr(
+                                    "The session isn't encrypted, anyone on the network can read the note and the token",
+                                ))
+                                .clicked()
+                            {
+                                self.host_session();
+                                ui.close();
+                            }
+                            if ui.button(tr("Join session…")).clicked() {
+                                self.join_dialog = Some((String::new(), String::new()));
+                                ui.close();
+                            }
+                        });
+                        let commands = self.plugins.commands();
+                        ui.menu_button(tr("Plugins"), |ui| {
+                            for (plugin, command) in commands {
+                                if ui.button(&command.label).clicked() {
+                                    self.run_plugin(plugin, &command.id);
+                                    ui.close();
+                                }
+                            }
+                            ui.separator();
+                            if ui.button(tr("Manage plugins…")).clicked() {
+                                self.show_plugins = true;
+                                ui.close();
+                            }
+                        });
+                        ui.menu_button(tr("Scripts"), |ui| {
+                            let labels: Vec<String> = self
+                                .scripts
+                                .commands()
+                                .iter()
+                                .map(|c| c.label.clone())
+                                .collect();
+                            for (i, label) in labels.into_iter().enumerate() {
+                                if ui.button(label).clicked() {
+                                    self.run_script(ctx, text_edit_id, i);
+                                    ui.close();
+                                }
+                            }
+                            ui.separator();
+                            if ui.button(tr("Reload scripts and hooks")).clicked() {
+                                let mut errors = self.scripts.load(&self.root);
+                                errors.extend(self.hooks.load(&self.root));
+                                for error in &errors {
+                                    self.toasts.error(error);
+                                }
+                                if errors.is_empty() {
+                                    self.toasts.info(trf(
+                                        "Loaded {} script commands",
+                                        &[&self.scripts.commands().len()],
+                                    ));
+                                }
+                                ui.close();
+                            }
+                        });
+                        ui.separator();
+                        if ui.button(tr("Settings…")).clicked() {
+                            self.settings = Some(self.config.clone());
+                            ui.close();
+                        }
+                        ui.checkbox(&mut self.smart_typography, tr("Smart typography"));
+                        ui.checkbox(&mut self.hide_markup, tr("Hide markup"));
+                        ui.checkbox(&mut self.highlight_line, tr("Highlight current line"));
+                        let mut autosave = self.autosave.enabled;
+                        if ui.checkbox(&mut autosave, tr("Autosave")).changed() {
+                            self.set_autosave(autosave);
+                        }
+                        ui.checkbox(&mut self.outline.open, tr("Outline"));
+                        let mut split = self.pane.open;
+                        if ui.checkbox(&mut split, tr("Split view")).changed() {
+                            self.toggle_split();
+                        }
+                        if ui.button(tr("Open in new window")).clicked() {
+                            self.detach_note();
+                            ui.close();
+                        }
+                        ui.menu_button(tr("Wrap"), |ui| {
+                            ui.radio_value(&mut self.wrap_column, None, tr("Window width"));
+                            for columns in [80, 100, 120] {
+                                ui.radio_value(
+                                    &mut self.wrap_column,
+                                    Some(columns),
+                                    trf("{} columns", &[&columns]),
+                                );
+                            }
+                        });
+                        ui.separator();
+                        // a debugging aid, so only offered in debug builds or with shift held
+                        if (cfg!(debug_assertions) || ui.input(|i| i.modifiers.shift))
+                            && ui.button(tr("Validate note")).clicked()
+                        {
+                            self.validate_note(false);
+                            ui.close();
+                        }
+                        ui.checkbox(&mut self.perf.enabled, tr("Performance overlay"));
+                        let mut touch = self.touch.enabled;
+                        if ui.checkbox(&mut touch, tr("Touch mode")).changed() {
+                            self.touch.set_enabled(ctx, touch);
+                        }
+                        if ui.button(tr("Show logs")).clicked() {
+                            self.show_logs = true;
+                            ui.close();
+                        }
+                    });
+
+                self.table_dialog(ctx, text_edit_id);
+                self.table_paste_prompt(ctx, text_edit_id);
+                self.link_dialog(ctx, text_edit_id);
+                self.new_note_dialog(ctx, text_edit_id);
+                self.rename_dialog(ctx);
+                self.goto_dialog(ctx, text_edit_id);
+                self.switch_dialog(ctx);
+                if let Some(command) = self.commands.palette(ctx) {
+                    self.run_command(ctx, text_edit_id, command);
+                }
+            });
+            self.scroll_offset = scrolled.state.offset.y;
+        });
+        self.logs_window(ctx);
+        self.conflict_window(ctx);
+        self.close_prompt(ctx);
+        self.hooks_prompt(ctx);
+        self.sketch_editor(ctx);
+        self.join_dialog(ctx);
+        self.sync_window(ctx);
+        self.share_window(ctx);
+        self.print_dialog(ctx);
+        self.links_window(ctx);
+        self.replace_dialog(ctx);
+        self.settings_window(ctx);
+        egui::Window::new(tr("Plugins"))
+            .open(&mut self.show_plugins)
+            .show(ctx, |ui| self.plugins.manager(ui));
+        self.dispatch_events();
+        self.perf.show(ctx);
+        self.perf.end(self.note.reparses());
+    }
+
+    // called now and then as well as on the way out, so a crash loses little
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // no vault to keep it for
+        if self.root.as_os_str().is_empty() {
+            return;
+        }
+        self.remember_view();
+        // undo and jumps unfold sections without a toggle being heard about
+        if !self.path.as_os_str().is_empty() {
+            self.folds.remember(&self.path, &self.note);
+        }
+        let session = Session {
+            note: self
+                .path
+                .to_str()
+                .filter(|p| !p.is_empty())
+                .map(str::to_string),
+            back: self.nav_history.clone(),
+            forward: self.nav_forward.clone(),
+            show_logs: self.show_logs,
+            show_plugins: self.show_plugins,
+            show_outline: self.outline.open,
+            pdf_width: Some(self.pdf_width),
+            view: self.views.get(&self.path),
+        };
+        session.save(storage, &self.root);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // no vault to keep it in
+        if self.root.as_os_str().is_empty() {
+            return;
+        }
+        // nothing typed is lost by closing unless that was asked for, the writes finish before
+        //   the app goes
+        if self.note.is_modified() && !self.discard_on_close {
+            self.save_file(false);
+        }
+        self.pane.save(&mut self.saver);
+        for (_, pane) in &mut self.windows {
+            pane.save(&mut self.saver);
+        }
+        for saved in self.saver.wait() {
+            if let Err(e) = saved {
+                tracing::warn!("{}", e);
+            }
+        }
     }
 }
@@ -0,0 +1,87 @@
+use crate::i18n::trf;
+use std::path::Path;
+use std::process::Command;
+
+// reading text out of images with an external ocr program, tesseract unless NOTERS_OCR says otherwise
+//   the command runs through sh with {image} swapped for the image's path and prints the text
+
+const DEFAULT_COMMAND: &str = "tesseract {image} - 2>/dev/null";
+
+// images ocr programs can read, sketches are svg and have no text to find
+const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp", "tif", "tiff", "webp"];
+
+pub fn can_read(path: &Path) -> bool {
+    return path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXTENSIONS.contains(&e.to_lowercase().as_str()));
+}
+
+fn command() -> String {
+    return std::env::var("NOTERS_OCR")
+        .ok()
+        .filter(|c| !c.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_COMMAND.to_string());
+}
+
+// single quoted for sh
+fn quote(path: &Path) -> String {
+    return format!("'{}'", path.display().to_string().replace('\'', r"'\''"));
+}
+
+// tidy what came back: trailing spaces and runs of blank lines go
+fn clean(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() && lines.last().is_none_or(|l| l.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    return lines.join("\n");
+}
+
+// run the ocr command on an image, returns the text it found
+pub fn extract(image: &Path) -> Result<String, String> {
+    let command = command().replace("{image}", &quote(image));
+    tracing::info!("running ocr `{}`", command);
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .output()
+        .map_err(|e| trf("Couldn't run `{}`: {}", &[&command, &e]))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(trf(
+            "Text extraction failed ({}), is tesseract installed? {}",
+            &[&output.status, &stderr.trim()],
+        ));
+    }
+    let text = clean(&String::from_utf8_lossy(&output.stdout));
+    if text.is_empty() {
+        return Err(trf("No text found in {}", &[&image.display()]));
+    }
+    return Ok(text);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ocr::{can_read, clean, quote};
+    use std::path::Path;
+
+    #[test]
+    fn test_can_read() {
+        assert!(can_read(Path::new("attachments/shot.PNG")));
+        assert!(!can_read(Path::new("attachments/sketch-1.svg")));
+        assert!(!can_read(Path::new("notes")));
+    }
+
+    #[test]
+    fn test_clean() {
+        assert_eq!("one\n\ntwo", clean("\n\none  \n\n\n\ntwo   \n\n\x0c"));
+        assert_eq!("'it'\\''s.png'", quote(Path::new("it's.png")));
+    }
+}
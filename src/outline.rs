@@ -0,0 +1,72 @@
+use crate::i18n::tr;
+use eframe::egui;
+use noters_core::note::Heading;
+
+// the open note's headings in a panel down the side, nested by level
+//   clicking one goes to it, the arrow beside it folds or unfolds its section
+
+pub enum Picked {
+    // a heading's position in the full text
+    Goto(usize),
+    Fold(usize),
+}
+
+#[derive(Default)]
+pub struct Outline {
+    pub open: bool,
+}
+
+impl Outline {
+    // the panel, `current` is the heading the cursor is under
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        headings: &[Heading],
+        current: Option<usize>,
+    ) -> Option<Picked> {
+        if !self.open {
+            return None;
+        }
+        let mut picked = None;
+        egui::SidePanel::left("outline")
+            .default_width(220.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(tr("Outline"));
+                    if ui.small_button("✕").on_hover_text(tr("Close")).clicked() {
+                        self.open = false;
+                    }
+                });
+                if headings.is_empty() {
+                    ui.weak(tr("This note has no headings"));
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (i, heading) in headings.iter().enumerate() {
+                        // what's inside a folded section is out of sight in the outline too
+                        if !heading.visible {
+                            continue;
+                        }
+                        ui.horizontal(|ui| {
+                            ui.add_space(12.0 * (heading.level - 1) as f32);
+                            let arrow = if heading.expanded { "▾" } else { "▸" };
+                            if ui
+                                .small_button(arrow)
+                                .on_hover_text(tr("Fold or unfold"))
+                                .clicked()
+                            {
+                                picked = Some(Picked::Fold(heading.pos));
+                            }
+                            if ui
+                                .selectable_label(current == Some(i), &heading.title)
+                                .clicked()
+                            {
+                                picked = Some(Picked::Goto(heading.pos));
+                            }
+                        });
+                    }
+                });
+            });
+        return picked;
+    }
+}
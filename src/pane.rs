@@ -0,0 +1,247 @@
+use crate::buffer::NoteBuffer;
+use crate::files::{self, Loaded, Saver, Task};
+use crate::i18n::tr;
+use crate::plugins::BlockRenderer;
+use crate::{Style, render_markdown};
+use eframe::egui::{self, CursorIcon, TextBuffer, text::LayoutJob};
+use noters_core::encoding::Encoding;
+use noters_core::graphemes::byte_index;
+use noters_core::links;
+use noters_core::note::{MarkdownType, Note, highlight_parse};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+// a second editor beside the main one or in a window of its own, for keeping a note in view while
+//   writing another, it keeps its own note and cursor, and shares the main editor's note when both
+//   show the same file
+
+// how notes are drawn, shared with the main editor
+pub struct Look<'a> {
+    pub blocks: &'a HashMap<String, BlockRenderer>,
+    // the vault's notes, for telling links to missing ones apart
+    pub known: Option<&'a HashSet<String>>,
+    pub style: Style<'a>,
+}
+
+// a link clicked in the pane
+pub enum Followed {
+    // opened in the pane itself
+    Here(String),
+    // alt+click, for the main editor
+    Other(String),
+}
+
+#[derive(Default)]
+pub struct Pane {
+    pub open: bool,
+    pub path: PathBuf,
+    note: NoteBuffer,
+    encoding: Encoding,
+    loading: Option<Task<Loaded>>,
+    // edits that haven't been saved yet
+    modified: bool,
+    // showing the main editor's note last frame
+    was_shared: bool,
+}
+
+impl Pane {
+    pub fn open_note(&mut self, root: &Path, name: String, saver: &mut Saver) {
+        self.save(saver);
+        self.open = true;
+        let root = root.to_path_buf();
+        self.loading = Some(Task::spawn(move || files::load(&root, &name)));
+    }
+
+    // show the main editor's note, until another one is opened here
+    pub fn split(&mut self, path: &Path, saver: &mut Saver) {
+        self.save(saver);
+        self.open = true;
+        self.path = path.to_path_buf();
+        self.loading = None;
+    }
+
+    pub fn close(&mut self, saver: &mut Saver) {
+        self.save(saver);
+        self.open = false;
+    }
+
+    pub fn save(&mut self, saver: &mut Saver) {
+        if !self.modified {
+            return;
+        }
+        self.modified = false;
+        saver.save(
+            self.path.clone(),
+            self.note.full().to_string(),
+            self.encoding,
+            false,
+        );
+    }
+
+    // pick up a note that finished loading, returns the error if it couldn't be read
+    pub fn poll(&mut self, ctx: &egui::Context, saver: &mut Saver) -> Option<String> {
+        let loaded = self.loading.as_ref().and_then(Task::poll);
+        if self.loading.is_some() && loaded.is_none() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+        match loaded? {
+            Ok((path, disk)) => {
+                self.loading = None;
+                saver.track(path.clone(), disk.as_ref().map(|d| d.fingerprint));
+                let (text, encoding) = disk.map(|d| (d.text, d.encoding)).unwrap_or_default();
+                self.path = path;
+                self.encoding = encoding;
+                self.note = NoteBuffer(Note::new(text));
+                return None;
+            }
+            Err(e) => {
+                self.loading = None;
+                return Some(e.to_string());
+            }
+        }
+    }
+
+    // the pane beside the main editor, `main` is the main editor's note
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        root: &Path,
+        main: (&Path, &mut NoteBuffer),
+        look: &Look,
+    ) -> Option<Followed> {
+        if !self.open {
+            return None;
+        }
+        let mut followed = None;
+        egui::SidePanel::right("split pane")
+            .resizable(true)
+            .default_width(ctx.content_rect().width() / 2.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(self.name(root));
+                    if self.loading.is_some() {
+                        ui.spinner();
+                    }
+                    if ui.small_button("✕").on_hover_text(tr("Close")).clicked() {
+                        self.open = false;
+                    }
+                });
+                followed = self.editor(ui, root, main, look);
+            });
+        return followed;
+    }
+
+    // the pane as a native window of its own
+    pub fn window(
+        &mut self,
+        ctx: &egui::Context,
+        id: egui::ViewportId,
+        root: &Path,
+        main: (&Path, &mut NoteBuffer),
+        look: &Look,
+    ) -> Option<Followed> {
+        if !self.open {
+            return None;
+        }
+        let mut followed = None;
+        let builder = egui::ViewportBuilder::default()
+            .with_title(self.name(root))
+            .with_inner_size([600.0, 800.0]);
+        ctx.show_viewport_immediate(id, builder, |ctx, _| {
+            if ctx.input(|i| i.viewport().close_requested()) {
+                self.open = false;
+            }
+            egui::CentralPanel::default().show(ctx, |ui| {
+                followed = self.editor(ui, root, (main.0, &mut *main.1), look);
+            });
+        });
+        return followed;
+    }
+
+    fn name(&self, root: &Path) -> String {
+        let name = self.path.strip_prefix(root).unwrap_or(&self.path);
+        return name.display().to_string();
+    }
+
+    fn editor(
+        &mut self,
+        ui: &mut egui::Ui,
+        root: &Path,
+        main: (&Path, &mut NoteBuffer),
+        look: &Look,
+    ) -> Option<Followed> {
+        let shared = self.path == main.0;
+        // the main editor went to another note, this one is read back from disk to carry on with
+        if self.was_shared && !shared && self.loading.is_none() {
+            let root = root.to_path_buf();
+            let name = self.path.to_string_lossy().to_string();
+            self.loading = Some(Task::spawn(move || files::load(&root, &name)));
+        }
+        self.was_shared = shared;
+        let note = if shared { main.1 } else { &mut self.note };
+        let mut layouter = |ui: &egui::Ui, buf: &dyn TextBuffer, wrap_width: f32| {
+            let mut job = LayoutJob::default();
+            render_markdown(
+                &mut job,
+                &look.style,
+                highlight_parse(buf.as_str()),
+                look.blocks,
+                look.known,
+            );
+            job.wrap.max_width = wrap_width;
+            return ui.fonts_mut(|f| f.layout_job(job));
+        };
+        let mut followed = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            // each window has its own viewport, which keeps the editors' states apart
+            let id = ui.make_persistent_id(("pane editor", ui.ctx().viewport_id()));
+            let editor = egui::TextEdit::multiline(note)
+                .desired_width(f32::INFINITY)
+                .desired_rows((ui.available_height() / 16.0) as usize)
+                .interactive(self.loading.is_none())
+                .layouter(&mut layouter)
+                .id(id)
+                .show(ui);
+            if editor.response.changed() && !shared {
+                self.modified = true;
+            }
+
+            let pointer = ui.input(|i| i.pointer.interact_pos());
+            let Some(index) = pointer
+                .filter(|pos| editor.response.rect.contains(*pos))
+                .map(|pos| editor.galley.cursor_from_pos(pos - editor.galley_pos).index)
+            else {
+                return;
+            };
+            let node = note.get_node(index);
+            let target = match node.mdtype {
+                MarkdownType::Link => Some(links::link_target(&node.text).to_string()),
+                _ => None,
+            };
+            if node.mdtype == MarkdownType::WebLink {
+                ui.ctx()
+                    .output_mut(|out| out.cursor_icon = CursorIcon::PointingHand);
+                let text = note.as_str();
+                if editor.response.clicked()
+                    && let Some((url, _)) = links::web_link_at(text, byte_index(text, index))
+                {
+                    ui.ctx().open_url(egui::OpenUrl::new_tab(url));
+                }
+            }
+            let Some(target) = target else {
+                return;
+            };
+            ui.ctx()
+                .output_mut(|out| out.cursor_icon = CursorIcon::PointingHand);
+            if editor.response.clicked() {
+                let note = links::split_anchor(&target).0.to_string();
+                followed = Some(if ui.input(|i| i.modifiers.alt) {
+                    Followed::Other(note)
+                } else {
+                    Followed::Here(note)
+                });
+            }
+        });
+        return followed;
+    }
+}
@@ -0,0 +1,161 @@
+use crate::files::Task;
+use crate::i18n::{tr, trf};
+use eframe::egui;
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
+use std::path::{Path, PathBuf};
+
+// how wide pages are rendered, the panel scales them down to fit
+const RENDER_WIDTH: i32 = 600;
+
+struct Page {
+    count: u16,
+    image: egui::ColorImage,
+}
+
+// pdfium is loaded from the system each time, so a missing library only costs the preview
+fn render(path: &Path, index: u16) -> Result<Page, String> {
+    let bindings = Pdfium::bind_to_system_library()
+        .map_err(|e| trf("PDF preview needs the pdfium library: {}", &[&e]))?;
+    let pdfium = Pdfium::new(bindings);
+    let document = pdfium
+        .load_pdf_from_file(path, None)
+        .map_err(|e| trf("Couldn't open {}: {}", &[&path.display(), &e]))?;
+    let count = document.pages().len();
+    let page = document
+        .pages()
+        .get(index.min(count.saturating_sub(1)))
+        .map_err(|e| trf("Couldn't read page {}: {}", &[&(index + 1), &e]))?;
+    let bitmap = page
+        .render_with_config(&PdfRenderConfig::new().set_target_width(RENDER_WIDTH))
+        .map_err(|e| trf("Couldn't render page {}: {}", &[&(index + 1), &e]))?;
+    let size = [bitmap.width() as usize, bitmap.height() as usize];
+    return Ok(Page {
+        count,
+        image: egui::ColorImage::from_rgba_unmultiplied(size, &bitmap.as_rgba_bytes()),
+    });
+}
+
+// what the pane calls a pdf, its file name without the folders
+pub fn title(path: &Path) -> String {
+    return path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string());
+}
+
+// one page of a pdf at a time, rendered in the background
+pub struct PdfPreview {
+    pub path: PathBuf,
+    page: u16,
+    count: u16,
+    texture: Option<egui::TextureHandle>,
+    error: Option<String>,
+    rendering: Option<Task<Result<Page, String>>>,
+}
+
+impl PdfPreview {
+    pub fn new(path: PathBuf) -> Self {
+        let mut preview = Self {
+            path,
+            page: 0,
+            count: 0,
+            texture: None,
+            error: None,
+            rendering: None,
+        };
+        preview.turn_to(0);
+        return preview;
+    }
+
+    fn turn_to(&mut self, page: u16) {
+        self.page = page;
+        let path = self.path.clone();
+        self.rendering = Some(Task::spawn(move || render(&path, page)));
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        if let Some(rendered) = self.rendering.as_ref().and_then(Task::poll) {
+            self.rendering = None;
+            match rendered {
+                Ok(page) => {
+                    self.count = page.count;
+                    self.error = None;
+                    self.texture = Some(ui.ctx().load_texture(
+                        "pdf page",
+                        page.image,
+                        egui::TextureOptions::LINEAR,
+                    ));
+                }
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                    self.error = Some(e);
+                }
+            }
+        }
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().warn_fg_color, error);
+            return;
+        }
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.page > 0, egui::Button::new("◀"))
+                .clicked()
+            {
+                self.turn_to(self.page - 1);
+            }
+            ui.label(trf(
+                "Page {} of {}",
+                &[&(self.page + 1), &self.count.max(1)],
+            ));
+            if ui
+                .add_enabled(self.page + 1 < self.count, egui::Button::new("▶"))
+                .clicked()
+            {
+                self.turn_to(self.page + 1);
+            }
+            if self.rendering.is_some() {
+                ui.spinner();
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_millis(50));
+            }
+        });
+        if let Some(texture) = &self.texture {
+            let image = egui::Image::new(texture)
+                .max_width(ui.available_width())
+                .sense(egui::Sense::click());
+            // clicking the page turns to the next one, and back round to the start
+            if ui.add(image).on_hover_text(tr("Next page")).clicked() && self.count > 0 {
+                self.turn_to((self.page + 1) % self.count);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pdf::{PdfPreview, title};
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_title() {
+        assert_eq!("paper.pdf", title(Path::new("/vault/docs/paper.pdf")));
+        assert_eq!("/", title(Path::new("/")));
+    }
+
+    #[test]
+    fn test_missing_pdf_is_an_error() {
+        let ctx = eframe::egui::Context::default();
+        let mut preview = PdfPreview::new(PathBuf::from("/no/such/file.pdf"));
+        let started = Instant::now();
+        while preview.rendering.is_some() && started.elapsed() < Duration::from_secs(5) {
+            let _ = ctx.run(Default::default(), |ctx| {
+                eframe::egui::CentralPanel::default().show(ctx, |ui| preview.show(ui));
+            });
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(preview.error.is_some());
+        assert!(preview.texture.is_none());
+    }
+}
@@ -0,0 +1,137 @@
+use eframe::egui;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// frames kept for the averages
+const SAMPLES: usize = 120;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Sample {
+    frame: Duration,
+    parse: Duration,
+    layout: Duration,
+    reparses: usize,
+}
+
+// timings for the last few frames, drawn over the editor while it's switched on
+#[derive(Default)]
+pub struct Perf {
+    pub enabled: bool,
+    started: Option<(Instant, usize)>,
+    current: Sample,
+    samples: VecDeque<Sample>,
+}
+
+impl Perf {
+    // `reparses` is the note's running count, what it goes up by during the frame is recorded
+    pub fn begin(&mut self, reparses: usize) {
+        self.started = Some((Instant::now(), reparses));
+        self.current = Sample::default();
+    }
+
+    pub fn add_parse(&mut self, time: Duration) {
+        self.current.parse += time;
+    }
+
+    pub fn add_layout(&mut self, time: Duration) {
+        self.current.layout += time;
+    }
+
+    pub fn end(&mut self, reparses: usize) {
+        let Some((start, before)) = self.started.take() else {
+            return;
+        };
+        self.current.frame = start.elapsed();
+        // opening another note starts its count over
+        self.current.reparses = reparses.saturating_sub(before);
+        self.record(self.current);
+    }
+
+    fn record(&mut self, sample: Sample) {
+        self.samples.push_back(sample);
+        if self.samples.len() > SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    fn average(&self, time: impl Fn(&Sample) -> Duration) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        return self.samples.iter().map(time).sum::<Duration>() / self.samples.len() as u32;
+    }
+
+    fn reparses(&self) -> usize {
+        return self.samples.iter().map(|s| s.reparses).sum();
+    }
+
+    pub fn show(&self, ctx: &egui::Context) {
+        if !self.enabled {
+            return;
+        }
+        let last = self.samples.back().copied().unwrap_or_default();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        egui::Area::new(egui::Id::new("perf overlay"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-12.0, 12.0))
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    egui::Grid::new("perf").num_columns(3).show(ui, |ui| {
+                        ui.weak("");
+                        ui.weak("last");
+                        ui.weak(format!("avg of {}", self.samples.len()));
+                        ui.end_row();
+                        for (label, value, average) in [
+                            ("frame", last.frame, self.average(|s| s.frame)),
+                            ("parse", last.parse, self.average(|s| s.parse)),
+                            ("layout", last.layout, self.average(|s| s.layout)),
+                        ] {
+                            ui.label(label);
+                            ui.monospace(format!("{:6.2} ms", ms(value)));
+                            ui.monospace(format!("{:6.2} ms", ms(average)));
+                            ui.end_row();
+                        }
+                        ui.label("reparses");
+                        ui.monospace(format!("{:6}", last.reparses));
+                        ui.monospace(format!("{:6}", self.reparses()));
+                        ui.end_row();
+                    });
+                });
+            });
+        // keep the numbers moving even when nothing else asks for a repaint
+        ctx.request_repaint_after(Duration::from_millis(500));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::perf::{Perf, SAMPLES, Sample};
+    use std::time::Duration;
+
+    #[test]
+    fn test_samples() {
+        let mut perf = Perf::default();
+        perf.begin(5);
+        perf.add_parse(Duration::from_millis(2));
+        perf.add_parse(Duration::from_millis(1));
+        perf.end(7);
+        assert_eq!(Duration::from_millis(3), perf.samples[0].parse);
+        assert_eq!(2, perf.samples[0].reparses);
+
+        // a fresh note counts from zero again
+        perf.begin(7);
+        perf.end(1);
+        assert_eq!(0, perf.samples[1].reparses);
+
+        for _ in 0..SAMPLES {
+            perf.record(Sample {
+                layout: Duration::from_millis(4),
+                ..Default::default()
+            });
+        }
+        assert_eq!(SAMPLES, perf.samples.len());
+        assert_eq!(Duration::from_millis(4), perf.average(|s| s.layout));
+        assert_eq!(0, perf.reparses());
+    }
+}
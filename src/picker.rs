@@ -0,0 +1,46 @@
+use eframe::egui;
+use noters_core::fuzzy;
+
+// a query box over a fuzzy filtered list that's driven from the keyboard
+#[derive(Default)]
+pub struct Picker {
+    pub query: String,
+    selected: usize,
+}
+
+impl Picker {
+    pub fn query_box(&mut self, ui: &mut egui::Ui, hint: &str) {
+        ui.add(egui::TextEdit::singleline(&mut self.query).hint_text(hint))
+            .request_focus();
+    }
+
+    // list the best matches for the query, returns the index of the item picked with enter or a click
+    pub fn list(&mut self, ui: &mut egui::Ui, items: &[String]) -> Option<usize> {
+        let matches: Vec<usize> = fuzzy::rank(&self.query, items.iter().map(String::as_str))
+            .into_iter()
+            .take(12)
+            .collect();
+
+        if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown)) {
+            self.selected += 1;
+        }
+        if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp)) {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut chosen = None;
+        for (i, m) in matches.iter().enumerate() {
+            if ui
+                .selectable_label(i == self.selected, &items[*m])
+                .clicked()
+            {
+                chosen = Some(*m);
+            }
+        }
+        if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            chosen = chosen.or(matches.get(self.selected).copied());
+        }
+        return chosen;
+    }
+}
@@ -0,0 +1,171 @@
+use crate::files::Task;
+use crate::make_color32;
+use crate::theme::Theme;
+use eframe::egui::{self, Color32};
+use std::time::{Duration, Instant};
+
+// following the desktop's colors, picked with `theme = "system"` in config.toml
+//   KDE's color scheme and GTK's theme are read on Linux, elsewhere the window system only says
+//   whether it's dark or light, they're looked at again now and then so a change shows up live
+
+// how often the desktop's colors are read again
+const RECHECK: Duration = Duration::from_secs(5);
+
+// the colors a desktop's theme set
+#[derive(Clone, Debug, PartialEq)]
+pub struct Colors {
+    pub background: Color32,
+    pub text: Color32,
+    pub accent: Option<Color32>,
+}
+
+// the colors in a KDE kdeglobals file
+fn kde(text: &str) -> Option<Colors> {
+    let mut section = "";
+    let (mut background, mut text_color, mut accent) = (None, None, None);
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name;
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let rgb: Vec<u8> = value
+            .split(',')
+            .filter_map(|c| c.trim().parse().ok())
+            .collect();
+        let &[r, g, b] = rgb.as_slice() else {
+            continue;
+        };
+        let color = Some(Color32::from_rgb(r, g, b));
+        match (section, key.trim()) {
+            ("Colors:Window", "BackgroundNormal") => background = color,
+            ("Colors:Window", "ForegroundNormal") => text_color = color,
+            ("Colors:View", "ForegroundLink") => accent = color,
+            _ => {}
+        }
+    }
+    return Some(Colors {
+        background: background?,
+        text: text_color?,
+        accent,
+    });
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn read() -> Option<Colors> {
+    let home = std::env::home_dir()?;
+    let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+    if desktop.to_uppercase().contains("KDE") {
+        let text = std::fs::read_to_string(home.join(".config/kdeglobals")).ok()?;
+        return kde(&text);
+    }
+    // it looks in $HOME itself and can't do without it
+    std::env::var_os("HOME")?;
+    let colors = linux_theme::gtk::current::current().0;
+    let color = |name: &str| colors.get(name).map(make_color32);
+    return Some(Colors {
+        background: color("window_bg_color")?,
+        text: color("window_fg_color")?,
+        accent: color("accent_color"),
+    });
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn read() -> Option<Colors> {
+    return None;
+}
+
+// the theme for the desktop's colors, or for whether the window system is in dark mode
+pub fn theme(colors: Option<&Colors>, system: Option<egui::Theme>) -> Option<Theme> {
+    let Some(colors) = colors else {
+        return system.map(|system| match system {
+            egui::Theme::Dark => Theme::dark(),
+            egui::Theme::Light => Theme::light(),
+        });
+    };
+    let [r, g, b, _] = colors.background.to_array();
+    let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    let mut theme = if luma < 128.0 {
+        Theme::dark()
+    } else {
+        Theme::light()
+    };
+    theme.background = colors.background;
+    theme.text = colors.text;
+    if let Some(accent) = colors.accent {
+        theme.link = accent;
+    }
+    return Some(theme);
+}
+
+#[derive(Default)]
+pub struct PlatformTheme {
+    reading: Option<Task<Option<Colors>>>,
+    read_at: Option<Instant>,
+    colors: Option<Colors>,
+    theme: Option<Theme>,
+}
+
+impl PlatformTheme {
+    pub fn current(&self) -> Option<&Theme> {
+        return self.theme.as_ref();
+    }
+
+    // read the desktop's colors again when it's time, returns true when the theme has changed
+    pub fn poll(&mut self, ctx: &egui::Context) -> bool {
+        let now = Instant::now();
+        if self.reading.is_none() && self.read_at.is_none_or(|at| now >= at + RECHECK) {
+            self.read_at = Some(now);
+            self.reading = Some(Task::spawn(read));
+        }
+        if let Some(colors) = self.reading.as_ref().and_then(Task::poll) {
+            self.reading = None;
+            self.colors = colors;
+        }
+        ctx.request_repaint_after(RECHECK);
+        let theme = theme(self.colors.as_ref(), ctx.system_theme());
+        if theme == self.theme {
+            return false;
+        }
+        self.theme = theme;
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::platform_theme::{Colors, kde, theme};
+    use crate::theme::Theme;
+    use eframe::egui::{self, Color32};
+
+    #[test]
+    fn test_kde() {
+        let text = "[General]\nColorScheme=BreezeDark\n\n[Colors:View]\nForegroundLink=29,153,243\n\n[Colors:Window]\nBackgroundNormal=32,35,38\nForegroundNormal=252,252,252\n";
+        assert_eq!(
+            Some(Colors {
+                background: Color32::from_rgb(32, 35, 38),
+                text: Color32::from_rgb(252, 252, 252),
+                accent: Some(Color32::from_rgb(29, 153, 243)),
+            }),
+            kde(text)
+        );
+        assert_eq!(None, kde("[Colors:Window]\nBackgroundNormal=32,35\n"));
+    }
+
+    #[test]
+    fn test_theme() {
+        let colors = Colors {
+            background: Color32::from_rgb(239, 240, 241),
+            text: Color32::from_rgb(35, 38, 41),
+            accent: None,
+        };
+        let light = theme(Some(&colors), Some(egui::Theme::Dark)).unwrap();
+        assert!(!light.dark);
+        assert_eq!(colors.background, light.background);
+        assert_eq!(Theme::light().link, light.link);
+        assert_eq!(Some(Theme::dark()), theme(None, Some(egui::Theme::Dark)));
+        assert_eq!(None, theme(None, None));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::events::{Event, Subscriber};
+use crate::i18n::{tr, trf};
+use crate::script_plugin;
+use eframe::egui::{self, Color32, TextFormat, text::LayoutJob};
+use noters_core::edit::Edit;
+use noters_core::note::Note;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::rc::Rc;
+
+// draws the whole of a fenced block, fences included, into the editor's layout
+//   it has to append exactly the text it's given so positions in the editor still line up
+pub type BlockRenderer = Rc<dyn Fn(&mut LayoutJob, &str)>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Command {
+    pub id: String,
+    pub label: String,
+}
+
+// something that extends the editor, everything has a default so a plugin only fills in what it uses
+pub trait Plugin {
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str {
+        return "";
+    }
+
+    // entries for the editor's Plugins menu
+    fn commands(&self) -> Vec<Command> {
+        return Vec::new();
+    }
+
+    // run one of this plugin's commands against the note and the selected characters
+    fn run(&mut self, _command: &str, _note: &Note, _selection: Range<usize>) -> Option<Edit> {
+        return None;
+    }
+
+    // fenced block languages this plugin draws itself, like ```warning
+    fn blocks(&self) -> Vec<(String, BlockRenderer)> {
+        return Vec::new();
+    }
+
+    fn has_panel(&self) -> bool {
+        return false;
+    }
+
+    // a side panel next to the editor
+    fn panel(&mut self, _ui: &mut egui::Ui, _note: &Note) {}
+
+    fn on_event(&mut self, _event: &Event) {}
+}
+
+struct Entry {
+    plugin: Box<dyn Plugin>,
+    enabled: bool,
+}
+
+// the registered plugins, and which of them are switched on
+#[derive(Default)]
+pub struct Plugins {
+    entries: Vec<Entry>,
+}
+
+impl Subscriber for Plugins {
+    fn notify(&mut self, event: &Event) {
+        for plugin in self.enabled() {
+            plugin.on_event(event);
+        }
+    }
+}
+
+impl Plugins {
+    pub fn builtin() -> Self {
+        let mut plugins = Self::default();
+        plugins.register(Box::new(Callouts));
+        plugins.register(Box::new(WordCount));
+        return plugins;
+    }
+
+    // register the rhai plugins in a folder, see script_plugin for what they can do
+    //   returns a message for each one that failed to load
+    pub fn load(&mut self, dir: &Path) -> Vec<String> {
+        let (loaded, errors) = script_plugin::load(dir);
+        for plugin in loaded {
+            self.register(Box::new(plugin));
+        }
+        return errors;
+    }
+
+    pub fn register(&mut self, plugin: Box<dyn Plugin>) {
+        tracing::info!("registered plugin {}", plugin.name());
+        self.entries.push(Entry {
+            plugin,
+            enabled: true,
+        });
+    }
+
+    fn enabled(&mut self) -> impl Iterator<Item = &mut Box<dyn Plugin>> {
+        return self
+            .entries
+            .iter_mut()
+            .filter(|e| e.enabled)
+            .map(|e| &mut e.plugin);
+    }
+
+    // every enabled command, tagged with the plugin it belongs to
+    pub fn commands(&self) -> Vec<(usize, Command)> {
+        return self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.enabled)
+            .flat_map(|(i, e)| e.plugin.commands().into_iter().map(move |c| (i, c)))
+            .collect();
+    }
+
+    pub fn run(
+        &mut self,
+        plugin: usize,
+        command: &str,
+        note: &Note,
+        selection: Range<usize>,
+    ) -> Option<Edit> {
+        let entry = self.entries.get_mut(plugin)?;
+        tracing::debug!("running {} from {}", command, entry.plugin.name());
+        return entry.plugin.run(command, note, selection);
+    }
+
+    // the block renderers of enabled plugins by language, the first plugin to claim one wins
+    pub fn blocks(&self) -> HashMap<String, BlockRenderer> {
+        let mut blocks = HashMap::new();
+        for entry in self.entries.iter().filter(|e| e.enabled) {
+            for (language, renderer) in entry.plugin.blocks() {
+                blocks.entry(language).or_insert(renderer);
+            }
+        }
+        return blocks;
+    }
+
+    pub fn panels(&mut self, ctx: &egui::Context, note: &Note) {
+        for plugin in self.enabled().filter(|p| p.has_panel()) {
+            egui::SidePanel::right(egui::Id::new(("plugin panel", plugin.name().to_string())))
+                .show(ctx, |ui| {
+                    ui.heading(plugin.name());
+                    plugin.panel(ui, note);
+                });
+        }
+    }
+
+    // the plugin manager, a checkbox per plugin
+    pub fn manager(&mut self, ui: &mut egui::Ui) {
+        for entry in &mut self.entries {
+            ui.checkbox(&mut entry.enabled, entry.plugin.name());
+            let description = entry.plugin.description();
+            if !description.is_empty() {
+                ui.indent(entry.plugin.name().to_string(), |ui| ui.weak(description));
+            }
+        }
+    }
+}
+
+const CALLOUTS: [(&str, Color32); 3] = [
+    ("note", Color32::from_rgb(40, 60, 90)),
+    ("tip", Color32::from_rgb(40, 80, 50)),
+    ("warning", Color32::from_rgb(100, 70, 20)),
+];
+
+// a whole block in light text on a colored background
+pub fn draw_block(job: &mut LayoutJob, text: &str, background: Color32) {
+    job.append(
+        text,
+        0.0,
+        TextFormat {
+            color: Color32::from_rgb(230, 230, 230),
+            background,
+            ..Default::default()
+        },
+    );
+}
+
+// ```note, ```tip and ```warning blocks drawn on a colored background
+struct Callouts;
+
+impl Plugin for Callouts {
+    fn name(&self) -> &str {
+        return tr("Callouts");
+    }
+
+    fn description(&self) -> &str {
+        return tr("Colored note, tip and warning blocks");
+    }
+
+    fn commands(&self) -> Vec<Command> {
+        return CALLOUTS
+            .iter()
+            .map(|(name, _)| Command {
+                id: name.to_string(),
+                label: match *name {
+                    "note" => tr("Insert note callout"),
+                    "tip" => tr("Insert tip callout"),
+                    _ => tr("Insert warning callout"),
+                }
+                .to_string(),
+            })
+            .collect();
+    }
+
+    fn run(&mut self, command: &str, _note: &Note, selection: Range<usize>) -> Option<Edit> {
+        return Some(Edit::Wrap {
+            range: selection,
+            before: format!("```{}\n", command),
+            after: "\n```".to_string(),
+        });
+    }
+
+    fn blocks(&self) -> Vec<(String, BlockRenderer)> {
+        return CALLOUTS
+            .iter()
+            .map(|(name, color)| {
+                let color = *color;
+                let renderer: BlockRenderer =
+                    Rc::new(move |job, text| draw_block(job, text, color));
+                return (name.to_string(), renderer);
+            })
+            .collect();
+    }
+}
+
+// word, character and line counts for the open note
+struct WordCount;
+
+impl Plugin for WordCount {
+    fn name(&self) -> &str {
+        return tr("Word count");
+    }
+
+    fn description(&self) -> &str {
+        return tr("A panel with the note's word and character counts");
+    }
+
+    fn has_panel(&self) -> bool {
+        return true;
+    }
+
+    fn panel(&mut self, ui: &mut egui::Ui, note: &Note) {
+        let text = note.as_str();
+        ui.label(trf("{} words", &[&text.split_whitespace().count()]));
+        ui.label(trf("{} characters", &[&text.chars().count()]));
+        ui.label(trf("{} lines", &[&text.lines().count()]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plugins::Plugins;
+    use noters_core::edit::Edit;
+    use noters_core::note::Note;
+
+    #[test]
+    fn test_plugins() {
+        let mut plugins = Plugins::builtin();
+        assert!(plugins.blocks().contains_key("tip"));
+
+        let (plugin, command) = plugins.commands().into_iter().next().unwrap();
+        let note = Note::new("abc".to_string());
+        assert_eq!(
+            Some(Edit::Wrap {
+                range: 0..3,
+                before: "```note\n".to_string(),
+                after: "\n```".to_string(),
+            }),
+            plugins.run(plugin, &command.id, &note, 0..3)
+        );
+
+        plugins.entries[0].enabled = false;
+        assert!(!plugins.blocks().contains_key("tip"));
+        assert!(plugins.commands().is_empty());
+    }
+}
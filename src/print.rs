@@ -0,0 +1,183 @@
+use crate::i18n::trf;
+use noters_core::render::{self, Face, Paper, Placed};
+use pdfium_render::prelude::{
+    PdfFontToken, PdfPageObjectsCommon, PdfPagePaperSize, PdfPoints, Pdfium, PdfiumError,
+};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// printing a note: it's laid out on pages, written to a pdf with pdfium, and either kept or sent
+//   to the printer with the command in NOTERS_PRINT, lp unless that says otherwise
+//   the command runs through sh with {pdf} swapped for the file's path
+
+const DEFAULT_COMMAND: &str = "lp {pdf}";
+
+pub const PAPERS: [(&str, Paper); 2] = [("A4", render::A4), ("Letter", render::LETTER)];
+
+// images are taken to be 96 dpi, anything too big for the page is scaled down to fit
+const POINTS_PER_PIXEL: f32 = 0.75;
+
+fn command() -> String {
+    return std::env::var("NOTERS_PRINT")
+        .ok()
+        .filter(|c| !c.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_COMMAND.to_string());
+}
+
+// single quoted for sh
+fn quote(path: &Path) -> String {
+    return format!("'{}'", path.display().to_string().replace('\'', r"'\''"));
+}
+
+// an embedded image's size in points, None if it isn't a file that can be read
+fn image_size(root: &Path, target: &str) -> Option<(f32, f32)> {
+    let (width, height) = image::image_dimensions(root.join(target)).ok()?;
+    return Some((
+        width as f32 * POINTS_PER_PIXEL,
+        height as f32 * POINTS_PER_PIXEL,
+    ));
+}
+
+struct Fonts {
+    regular: PdfFontToken,
+    bold: PdfFontToken,
+    italic: PdfFontToken,
+    mono: PdfFontToken,
+}
+
+impl Fonts {
+    fn get(&self, face: Face) -> PdfFontToken {
+        return match face {
+            Face::Regular => self.regular,
+            Face::Bold => self.bold,
+            Face::Italic => self.italic,
+            Face::Mono => self.mono,
+        };
+    }
+}
+
+// lay the note out and write it to a pdf, images are found relative to the vault
+pub fn write_pdf(root: &Path, text: &str, paper: Paper, out: &Path) -> Result<usize, String> {
+    let pages = render::to_pages(text, paper, &|target| image_size(root, target));
+    let failed = |e: PdfiumError| trf("Couldn't write {}: {}", &[&out.display(), &e]);
+
+    let bindings = Pdfium::bind_to_system_library()
+        .map_err(|e| trf("Printing needs the pdfium library: {}", &[&e]))?;
+    let pdfium = Pdfium::new(bindings);
+    let mut document = pdfium.create_new_pdf().map_err(failed)?;
+    let fonts = Fonts {
+        regular: document.fonts_mut().helvetica(),
+        bold: document.fonts_mut().helvetica_bold(),
+        italic: document.fonts_mut().helvetica_oblique(),
+        mono: document.fonts_mut().courier(),
+    };
+    let size =
+        PdfPagePaperSize::new_custom(PdfPoints::new(paper.width), PdfPoints::new(paper.height));
+    for placed in &pages {
+        let mut page = document
+            .pages_mut()
+            .create_page_at_end(size)
+            .map_err(failed)?;
+        // pdf pages measure from the bottom left
+        for item in placed {
+            match item {
+                Placed::Text {
+                    x,
+                    y,
+                    size,
+                    face,
+                    text,
+                } => {
+                    page.objects_mut()
+                        .create_text_object(
+                            PdfPoints::new(*x),
+                            PdfPoints::new(paper.height - y),
+                            text,
+                            fonts.get(*face),
+                            PdfPoints::new(*size),
+                        )
+                        .map_err(failed)?;
+                }
+                Placed::Image {
+                    x,
+                    y,
+                    width,
+                    height,
+                    target,
+                } => {
+                    let image = image::open(root.join(target))
+                        .map_err(|e| trf("Couldn't read {}: {}", &[&target, &e]))?;
+                    page.objects_mut()
+                        .create_image_object(
+                            PdfPoints::new(*x),
+                            PdfPoints::new(paper.height - y - height),
+                            &image,
+                            Some(PdfPoints::new(*width)),
+                            Some(PdfPoints::new(*height)),
+                        )
+                        .map_err(failed)?;
+                }
+            }
+        }
+    }
+    document.save_to_file(out).map_err(failed)?;
+    return Ok(pages.len());
+}
+
+// what the print dialog is set to while it's open
+pub struct Options {
+    pub include_collapsed: bool,
+    // an index into PAPERS
+    pub paper: usize,
+    // where Save as PDF writes to
+    pub pdf: String,
+}
+
+// where a note's print-ready pdf goes by default, next to the note
+pub fn pdf_path(note: &Path) -> PathBuf {
+    return note.with_extension("pdf");
+}
+
+// write the note to a temporary pdf and hand it to the print command
+pub fn print(root: &Path, title: &str, text: &str, paper: Paper) -> Result<String, String> {
+    let pdf = std::env::temp_dir().join(format!("noters-print-{}.pdf", title.replace('/', "-")));
+    let pages = write_pdf(root, text, paper, &pdf)?;
+    let command = command().replace("{pdf}", &quote(&pdf));
+    tracing::info!("printing with `{}`", command);
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .output()
+        .map_err(|e| trf("Couldn't run `{}`: {}", &[&command, &e]))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(trf(
+            "Printing failed ({}): {}",
+            &[&output.status, &stderr.trim()],
+        ));
+    }
+    return Ok(trf("Sent {} to the printer, {} pages", &[&title, &pages]));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::print::{image_size, pdf_path, quote};
+    use noters_core::testing::TempDir;
+    use std::path::Path;
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(Path::new("/v/a/b.pdf"), pdf_path(Path::new("/v/a/b.md")));
+        assert_eq!("'/tmp/it'\\''s.pdf'", quote(Path::new("/tmp/it's.pdf")));
+    }
+
+    #[test]
+    fn test_image_size() {
+        let dir = TempDir::new("print");
+        image::RgbImage::new(40, 20)
+            .save(dir.join("pic.png"))
+            .unwrap();
+        assert_eq!(Some((30.0, 15.0)), image_size(&dir, "pic.png"));
+        assert_eq!(None, image_size(&dir, "missing.png"));
+    }
+}
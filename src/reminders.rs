@@ -0,0 +1,146 @@
+use crate::events::{Event, Subscriber};
+use crate::files::{self, Task};
+use crate::i18n::trf;
+use chrono::NaiveDateTime;
+use noters_core::reminders::{self, Reminder};
+use noters_core::vault;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// a reminder and the note it was found in
+pub type Due = (String, Reminder);
+
+// every note's reminders, read on a background thread
+fn scan(root: &Path) -> Vec<Due> {
+    let mut found = Vec::new();
+    for name in vault::list_notes(root) {
+        if let Ok((_, Some(disk))) = files::load(root, &name) {
+            for reminder in reminders::parse(&disk.text) {
+                found.push((name.clone(), reminder));
+            }
+        }
+    }
+    tracing::debug!("found {} reminders", found.len());
+    return found;
+}
+
+// show a desktop notification and wait to see if it's clicked
+//   notify-send only reports the click with --wait, so this runs on its own thread
+fn notify(note: &str, reminder: &Reminder) -> Result<bool, String> {
+    let body = if reminder.text.is_empty() {
+        note.to_string()
+    } else {
+        format!("{}\n{}", reminder.text, note)
+    };
+    let output = Command::new("notify-send")
+        .args(["--app-name=NoteRs", "--action=open=Open note", "--wait"])
+        .arg("Reminder")
+        .arg(body)
+        .output()
+        .map_err(|e| trf("Couldn't show a notification: {}", &[&e]))?;
+    return Ok(String::from_utf8_lossy(&output.stdout).trim() == "open");
+}
+
+// fires reminders as their time comes round, while the app is running
+#[derive(Default)]
+pub struct Scheduler {
+    root: PathBuf,
+    reminders: Vec<Due>,
+    scanning: Option<Task<Vec<Due>>>,
+    // reminders at or before this have already had their turn
+    checked: Option<NaiveDateTime>,
+    notifying: Vec<Task<(Due, Result<bool, String>)>>,
+}
+
+impl Scheduler {
+    pub fn rescan(&mut self, root: &Path) {
+        self.root = root.to_path_buf();
+        let root = root.to_path_buf();
+        self.scanning = Some(Task::spawn(move || scan(&root)));
+    }
+
+    // the reminders that came due since the last call
+    //   the first call only marks the time, reminders from before the app started don't fire
+    pub fn due(&mut self, now: NaiveDateTime) -> Vec<Due> {
+        if let Some(found) = self.scanning.as_ref().and_then(Task::poll) {
+            self.scanning = None;
+            self.reminders = found;
+        }
+        let Some(checked) = self.checked.replace(now) else {
+            return Vec::new();
+        };
+        return self
+            .reminders
+            .iter()
+            .filter(|(_, r)| checked < r.at && r.at <= now)
+            .cloned()
+            .collect();
+    }
+
+    pub fn fire(&mut self, due: Due) {
+        tracing::info!("reminder in {} at line {}", due.0, due.1.line);
+        self.notifying.push(Task::spawn(move || {
+            let clicked = notify(&due.0, &due.1);
+            return (due, clicked);
+        }));
+    }
+
+    // notifications that were closed since last time, with whether they were clicked
+    pub fn poll(&mut self) -> Vec<(Due, Result<bool, String>)> {
+        let mut finished = Vec::new();
+        self.notifying.retain(|task| match task.poll() {
+            Some(result) => {
+                finished.push(result);
+                return false;
+            }
+            None => return true,
+        });
+        return finished;
+    }
+}
+
+impl Subscriber for Scheduler {
+    fn notify(&mut self, event: &Event) {
+        if let Event::NoteSaved(_) = event {
+            let root = self.root.clone();
+            self.rescan(&root);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reminders::Scheduler;
+    use chrono::NaiveDate;
+    use noters_core::reminders::Reminder;
+
+    #[test]
+    fn test_due() {
+        let at = |h, m| {
+            NaiveDate::from_ymd_opt(2024, 7, 1)
+                .unwrap()
+                .and_hms_opt(h, m, 0)
+                .unwrap()
+        };
+        let reminder = |h, m| Reminder {
+            at: at(h, m),
+            text: String::new(),
+            line: 1,
+        };
+        let mut scheduler = Scheduler {
+            reminders: vec![
+                ("a".to_string(), reminder(8, 0)),
+                ("b".to_string(), reminder(9, 0)),
+                ("c".to_string(), reminder(9, 30)),
+            ],
+            ..Default::default()
+        };
+
+        assert!(scheduler.due(at(8, 30)).is_empty());
+        let due: Vec<String> = scheduler.due(at(9, 0)).into_iter().map(|d| d.0).collect();
+        assert_eq!(vec!["b".to_string()], due);
+        assert!(scheduler.due(at(9, 10)).is_empty());
+        let due: Vec<String> = scheduler.due(at(10, 0)).into_iter().map(|d| d.0).collect();
+        assert_eq!(vec!["c".to_string()], due);
+    }
+}
@@ -0,0 +1,302 @@
+use crate::config::parse_color;
+use crate::events::Event;
+use crate::plugins::{BlockRenderer, Command, Plugin, draw_block};
+use eframe::egui;
+use noters_core::edit::Edit;
+use noters_core::graphemes::byte_index;
+use noters_core::note::Note;
+use rhai::{AST, Dynamic, Engine, Scope};
+use std::cell::RefCell;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// plugins written in rhai, loaded at start from the plugins folder beside config.toml so they can
+//   be added without building the editor, a plugin's top level says what it adds:
+//
+//   plugin("Shout", "Upper cases the selection");
+//   register_command("Shout", "shout");   // fn shout(note, selection), returns the replacement or ()
+//   register_block("danger", "#802020");  // ```danger blocks drawn on that background
+//   register_panel("counts");             // fn counts(note), returns the panel's text or lines
+//   fn on_event(kind, detail) {}          // opened, created, saved, modified, link or section
+
+// plugins run on the ui thread, every frame for panels, so they get less room than scripts
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+// what a plugin's top level registered
+#[derive(Default)]
+struct Registered {
+    name: Option<String>,
+    description: String,
+    commands: Vec<(String, String)>,
+    blocks: Vec<(String, String)>,
+    panel: Option<String>,
+}
+
+pub struct ScriptPlugin {
+    name: String,
+    description: String,
+    engine: Engine,
+    ast: AST,
+    // (label, function)
+    commands: Vec<(String, String)>,
+    blocks: Vec<(String, egui::Color32)>,
+    panel: Option<String>,
+    // a panel that failed shows why instead of running again every frame
+    panel_error: Option<String>,
+    has_on_event: bool,
+}
+
+fn engine(registered: &Rc<RefCell<Registered>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(|text| tracing::info!("plugin: {}", text));
+
+    let r = registered.clone();
+    engine.register_fn("plugin", move |name: &str, description: &str| {
+        let mut r = r.borrow_mut();
+        r.name = Some(name.to_string());
+        r.description = description.to_string();
+    });
+    let r = registered.clone();
+    engine.register_fn("register_command", move |label: &str, function: &str| {
+        r.borrow_mut()
+            .commands
+            .push((label.to_string(), function.to_string()));
+    });
+    let r = registered.clone();
+    engine.register_fn("register_block", move |language: &str, color: &str| {
+        r.borrow_mut()
+            .blocks
+            .push((language.to_string(), color.to_string()));
+    });
+    let r = registered.clone();
+    engine.register_fn("register_panel", move |function: &str| {
+        r.borrow_mut().panel = Some(function.to_string());
+    });
+    return engine;
+}
+
+impl ScriptPlugin {
+    fn load(path: &Path) -> Result<Self, String> {
+        let registered = Rc::new(RefCell::new(Registered::default()));
+        let engine = engine(&registered);
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .and_then(|ast| {
+                engine.run_ast_with_scope(&mut Scope::new(), &ast)?;
+                return Ok(ast);
+            })
+            .map_err(|e| e.to_string())?;
+        let registered = registered.take();
+
+        let mut blocks = Vec::new();
+        for (language, color) in registered.blocks {
+            match parse_color(&color) {
+                Some(color) => blocks.push((language, color)),
+                None => return Err(format!("{:?} isn't a color", color)),
+            }
+        }
+        let has_on_event = ast
+            .iter_functions()
+            .any(|f| f.name == "on_event" && f.params.len() == 2);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        return Ok(Self {
+            name: registered.name.unwrap_or_else(|| stem.to_string()),
+            description: registered.description,
+            engine,
+            ast,
+            commands: registered.commands,
+            blocks,
+            panel: registered.panel,
+            panel_error: None,
+            has_on_event,
+        });
+    }
+
+    fn call(&self, function: &str, args: impl rhai::FuncArgs) -> Result<Dynamic, String> {
+        return self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, function, args)
+            .map_err(|e| format!("{} {}: {}", self.name, function, e));
+    }
+}
+
+// every .rhai file in the folder, and a message for each one that didn't load
+pub fn load(dir: &Path) -> (Vec<ScriptPlugin>, Vec<String>) {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "rhai"))
+            .collect(),
+        Err(_) => return (Vec::new(), Vec::new()),
+    };
+    paths.sort();
+
+    let mut plugins = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        match ScriptPlugin::load(&path) {
+            Ok(plugin) => {
+                tracing::info!("loaded plugin {}", path.display());
+                plugins.push(plugin);
+            }
+            Err(e) => {
+                tracing::warn!("plugin {} failed: {}", path.display(), e);
+                errors.push(format!("{}: {}", path.display(), e));
+            }
+        }
+    }
+    return (plugins, errors);
+}
+
+impl Plugin for ScriptPlugin {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn description(&self) -> &str {
+        return &self.description;
+    }
+
+    fn commands(&self) -> Vec<Command> {
+        return self
+            .commands
+            .iter()
+            .map(|(label, function)| Command {
+                id: function.clone(),
+                label: label.clone(),
+            })
+            .collect();
+    }
+
+    fn run(&mut self, command: &str, note: &Note, selection: Range<usize>) -> Option<Edit> {
+        let text = note.as_str();
+        let selected = &text[byte_index(text, selection.start)..byte_index(text, selection.end)];
+        return match self.call(command, (text.to_string(), selected.to_string())) {
+            Ok(result) => match result.into_string() {
+                Ok(replacement) => Some(Edit::Replace {
+                    range: selection,
+                    text: replacement,
+                }),
+                Err(_) => None,
+            },
+            Err(e) => {
+                tracing::warn!("{}", e);
+                None
+            }
+        };
+    }
+
+    fn blocks(&self) -> Vec<(String, BlockRenderer)> {
+        return self
+            .blocks
+            .iter()
+            .map(|(language, color)| {
+                let color = *color;
+                let renderer: BlockRenderer =
+                    Rc::new(move |job, text| draw_block(job, text, color));
+                return (language.clone(), renderer);
+            })
+            .collect();
+    }
+
+    fn has_panel(&self) -> bool {
+        return self.panel.is_some();
+    }
+
+    fn panel(&mut self, ui: &mut egui::Ui, note: &Note) {
+        if let Some(error) = &self.panel_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+            return;
+        }
+        let Some(function) = self.panel.clone() else {
+            return;
+        };
+        match self.call(&function, (note.as_str().to_string(),)) {
+            Ok(result) if result.is_array() => {
+                for line in result.into_array().unwrap_or_default() {
+                    ui.label(line.to_string());
+                }
+            }
+            Ok(result) if result.is_unit() => {}
+            Ok(result) => {
+                ui.label(result.to_string());
+            }
+            Err(e) => {
+                tracing::warn!("{}", e);
+                self.panel_error = Some(e);
+            }
+        }
+    }
+
+    fn on_event(&mut self, event: &Event) {
+        if !self.has_on_event {
+            return;
+        }
+        let (kind, detail) = match event {
+            Event::NoteOpened(path) => ("opened", path.display().to_string()),
+            Event::NoteCreated(path) => ("created", path.display().to_string()),
+            Event::NoteSaved(path) => ("saved", path.display().to_string()),
+            Event::NoteModified => ("modified", String::new()),
+            Event::LinkFollowed(target) => ("link", target.clone()),
+            Event::SectionToggled(section) => ("section", format!("{:?}", section)),
+        };
+        if let Err(e) = self.call("on_event", (kind.to_string(), detail)) {
+            tracing::warn!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plugins::Plugins;
+    use noters_core::edit::Edit;
+    use noters_core::note::Note;
+    use noters_core::testing::TempDir;
+    use std::fs;
+
+    const SHOUT: &str = r##"
+plugin("Shout", "Upper cases the selection");
+register_command("Shout", "shout");
+register_block("danger", "#802020");
+
+fn shout(note, selection) {
+    if selection.is_empty() {
+        return;
+    }
+    selection.to_upper()
+}
+"##;
+
+    #[test]
+    fn test_script_plugins() {
+        let dir = TempDir::new("script-plugins");
+        fs::write(dir.join("shout.rhai"), SHOUT).unwrap();
+        fs::write(
+            dir.join("colors.rhai"),
+            r#"register_block("x", "reddish");"#,
+        )
+        .unwrap();
+
+        let mut plugins = Plugins::default();
+        let errors = plugins.load(&dir);
+        assert_eq!(1, errors.len());
+        assert!(errors[0].contains("colors.rhai"));
+        assert!(plugins.blocks().contains_key("danger"));
+
+        let (plugin, command) = plugins.commands().into_iter().next().unwrap();
+        assert_eq!("Shout", command.label);
+        let note = Note::new("say héllo".to_string());
+        assert_eq!(
+            Some(Edit::Replace {
+                range: 4..9,
+                text: "HÉLLO".to_string(),
+            }),
+            plugins.run(plugin, &command.id, &note, 4..9)
+        );
+        assert_eq!(None, plugins.run(plugin, &command.id, &note, 4..4));
+    }
+}
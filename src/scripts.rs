@@ -0,0 +1,312 @@
+use crate::files;
+use noters_core::vault;
+use rhai::{AST, Array, Engine, EvalAltResult, Scope};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// keeps a runaway loop in a script from hanging the editor
+const MAX_OPERATIONS: u64 = 50_000_000;
+
+// something a script asked the editor to do, carried out once the script returns
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    SetNote(String),
+    ReplaceSelection(String),
+    Open(String),
+    Toast(String),
+}
+
+// what the script functions can see and change while a script runs
+#[derive(Default)]
+struct Host {
+    root: PathBuf,
+    note: String,
+    selection: String,
+    actions: Vec<Action>,
+    // (label, function) pairs registered by the script being loaded
+    registered: Vec<(String, String)>,
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+// scripts come with the vault, so the notes they name have to stay inside it
+fn check_name(name: &str) -> ScriptResult<()> {
+    if !vault::is_note_name(name) {
+        return Err(format!("{:?} isn't a note in the vault", name).into());
+    }
+    return Ok(());
+}
+
+fn note_path(root: &Path, name: &str) -> ScriptResult<PathBuf> {
+    check_name(name)?;
+    return files::resolve(root, name).map_err(|e| e.to_string().into());
+}
+
+fn engine(host: &Rc<RefCell<Host>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(|text| tracing::info!("script: {}", text));
+
+    let h = host.clone();
+    engine.register_fn("register_command", move |label: &str, function: &str| {
+        h.borrow_mut()
+            .registered
+            .push((label.to_string(), function.to_string()));
+    });
+
+    let h = host.clone();
+    engine.register_fn("note_text", move || h.borrow().note.clone());
+    let h = host.clone();
+    engine.register_fn("set_note_text", move |text: &str| {
+        let mut host = h.borrow_mut();
+        host.note = text.to_string();
+        host.actions.push(Action::SetNote(text.to_string()));
+    });
+    let h = host.clone();
+    engine.register_fn("selection", move || h.borrow().selection.clone());
+    let h = host.clone();
+    engine.register_fn("replace_selection", move |text: &str| {
+        let mut host = h.borrow_mut();
+        host.selection = text.to_string();
+        host.actions
+            .push(Action::ReplaceSelection(text.to_string()));
+    });
+    let h = host.clone();
+    engine.register_fn("open_note", move |name: &str| {
+        h.borrow_mut().actions.push(Action::Open(name.to_string()));
+    });
+    let h = host.clone();
+    engine.register_fn("toast", move |text: &str| {
+        h.borrow_mut().actions.push(Action::Toast(text.to_string()));
+    });
+
+    let h = host.clone();
+    engine.register_fn("list_notes", move || -> Array {
+        return vault::list_notes(&h.borrow().root)
+            .into_iter()
+            .map(Into::into)
+            .collect();
+    });
+    // a missing note reads as empty, so appending to a new one just works
+    let h = host.clone();
+    engine.register_fn("read_note", move |name: &str| -> ScriptResult<String> {
+        check_name(name)?;
+        return match files::load(&h.borrow().root, name) {
+            Ok((_, disk)) => Ok(disk.map(|d| d.text).unwrap_or_default()),
+            Err(e) => Err(e.to_string().into()),
+        };
+    });
+    let h = host.clone();
+    engine.register_fn(
+        "write_note",
+        move |name: &str, text: &str| -> ScriptResult<()> {
+            let path = note_path(&h.borrow().root, name)?;
+            tracing::info!("script writing {}", path.display());
+            return fs::write(&path, text).map_err(|e| e.to_string().into());
+        },
+    );
+    let h = host.clone();
+    engine.register_fn(
+        "append_note",
+        move |name: &str, text: &str| -> ScriptResult<()> {
+            let path = note_path(&h.borrow().root, name)?;
+            let mut existing = fs::read_to_string(&path).unwrap_or_default();
+            existing.push_str(text);
+            tracing::info!("script appending to {}", path.display());
+            return fs::write(&path, existing).map_err(|e| e.to_string().into());
+        },
+    );
+    return engine;
+}
+
+pub struct ScriptCommand {
+    pub label: String,
+    script: usize,
+    function: String,
+}
+
+// user scripts from the vault's .scripts folder, and the commands they registered
+#[derive(Default)]
+pub struct Scripts {
+    engine: Engine,
+    host: Rc<RefCell<Host>>,
+    scripts: Vec<AST>,
+    commands: Vec<ScriptCommand>,
+}
+
+impl Scripts {
+    pub fn folder(root: &Path) -> PathBuf {
+        return root.join(".scripts");
+    }
+
+    // (re)load every .rhai file, running its top level so it can register commands
+    //   returns a message for each script that failed
+    pub fn load(&mut self, root: &Path) -> Vec<String> {
+        self.host = Rc::new(RefCell::new(Host {
+            root: root.to_path_buf(),
+            ..Default::default()
+        }));
+        self.engine = engine(&self.host);
+        self.scripts.clear();
+        self.commands.clear();
+
+        let mut paths: Vec<PathBuf> = match fs::read_dir(Self::folder(root)) {
+            Ok(entries) => entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e == "rhai"))
+                .collect(),
+            Err(_) => return Vec::new(),
+        };
+        paths.sort();
+
+        let mut errors = Vec::new();
+        for path in paths {
+            let loaded = self.engine.compile_file(path.clone()).and_then(|ast| {
+                self.engine.run_ast_with_scope(&mut Scope::new(), &ast)?;
+                return Ok(ast);
+            });
+            let registered = std::mem::take(&mut self.host.borrow_mut().registered);
+            match loaded {
+                Ok(ast) => {
+                    tracing::info!("loaded script {}", path.display());
+                    for (label, function) in registered {
+                        self.commands.push(ScriptCommand {
+                            label,
+                            script: self.scripts.len(),
+                            function,
+                        });
+                    }
+                    self.scripts.push(ast);
+                }
+                Err(e) => {
+                    tracing::warn!("script {} failed: {}", path.display(), e);
+                    errors.push(format!("{}: {}", path.display(), e));
+                }
+            }
+        }
+        return errors;
+    }
+
+    pub fn commands(&self) -> &[ScriptCommand] {
+        return &self.commands;
+    }
+
+    // run a registered command against the note's full text and the selected text
+    pub fn run(
+        &mut self,
+        command: usize,
+        note: &str,
+        selection: &str,
+    ) -> Result<Vec<Action>, String> {
+        let Some(command) = self.commands.get(command) else {
+            return Ok(Vec::new());
+        };
+        {
+            let mut host = self.host.borrow_mut();
+            host.note = note.to_string();
+            host.selection = selection.to_string();
+            host.actions.clear();
+        }
+        tracing::debug!("running script command {}", command.label);
+        let result = self.engine.call_fn::<rhai::Dynamic>(
+            &mut Scope::new(),
+            &self.scripts[command.script],
+            &command.function,
+            (),
+        );
+        let actions = std::mem::take(&mut self.host.borrow_mut().actions);
+        return match result {
+            Ok(_) => Ok(actions),
+            Err(e) => Err(format!("{} failed: {}", command.label, e)),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scripts::{Action, Scripts};
+    use noters_core::testing::TempDir;
+    use std::fs;
+
+    const ARCHIVE: &str = r#"
+register_command("Archive completed tasks", "archive");
+
+fn archive() {
+    let kept = "";
+    let done = "";
+    let count = 0;
+    for line in note_text().split("\n") {
+        if line.starts_with("- [x]") {
+            done += line + "\n";
+            count += 1;
+        } else {
+            kept += if kept.is_empty() { line } else { "\n" + line };
+        }
+    }
+    if count > 0 {
+        append_note("archive", done);
+        set_note_text(kept);
+        toast(`archived ${count} tasks`);
+    }
+}
+"#;
+
+    #[test]
+    fn test_archive_script() {
+        let root = TempDir::new("archive-script");
+        fs::create_dir_all(Scripts::folder(&root)).unwrap();
+        fs::write(Scripts::folder(&root).join("archive.rhai"), ARCHIVE).unwrap();
+        fs::write(Scripts::folder(&root).join("broken.rhai"), "fn (").unwrap();
+
+        let mut scripts = Scripts::default();
+        let errors = scripts.load(&root);
+        assert_eq!(1, errors.len());
+        assert!(errors[0].contains("broken.rhai"));
+        assert_eq!(1, scripts.commands().len());
+        assert_eq!("Archive completed tasks", scripts.commands()[0].label);
+
+        let actions = scripts
+            .run(0, "# Todo\n- [x] done\n- [ ] open\n- [x] also", "")
+            .unwrap();
+        assert_eq!(
+            vec![
+                Action::SetNote("# Todo\n- [ ] open".to_string()),
+                Action::Toast("archived 2 tasks".to_string()),
+            ],
+            actions
+        );
+        assert_eq!(
+            "- [x] done\n- [x] also\n",
+            fs::read_to_string(root.join("archive.md")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_outside_vault() {
+        let base = TempDir::new("script-outside");
+        let root = base.join("vault");
+        fs::create_dir_all(Scripts::folder(&root)).unwrap();
+        fs::write(base.join("secret.md"), "secret").unwrap();
+        for (name, script) in [
+            ("write.rhai", r#"write_note("../escape", "owned");"#),
+            ("append.rhai", r#"append_note("/tmp/../escape", "owned");"#),
+            ("read.rhai", r#"read_note("../secret");"#),
+        ] {
+            fs::write(Scripts::folder(&root).join(name), script).unwrap();
+        }
+
+        let mut scripts = Scripts::default();
+        let errors = scripts.load(&root);
+        assert_eq!(3, errors.len());
+        assert!(
+            errors
+                .iter()
+                .all(|e| e.contains("isn't a note in the vault"))
+        );
+        assert!(!base.join("escape.md").exists());
+        assert!(!base.join("escape").exists());
+    }
+}
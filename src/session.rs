@@ -0,0 +1,124 @@
+use crate::views::View;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// what was up when NoteRs was last closed, put back on the next start
+//   kept in eframe's storage as `key = value` lines, one entry per vault, the history is a line
+//   per note, the open note's cursor and scroll come back through views when it's opened
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Session {
+    pub note: Option<String>,
+    pub back: Vec<String>,
+    pub forward: Vec<String>,
+    pub show_logs: bool,
+    pub show_plugins: bool,
+    pub show_outline: bool,
+    pub pdf_width: Option<f32>,
+    // where the open note was left
+    pub view: Option<View>,
+}
+
+fn file(root: &Path) -> PathBuf {
+    return root.join(".session");
+}
+
+impl Session {
+    pub fn parse(text: &str) -> Self {
+        let mut session = Self::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().to_string();
+            match key.trim() {
+                "note" => session.note = Some(value),
+                "back" => session.back.push(value),
+                "forward" => session.forward.push(value),
+                "logs" => session.show_logs = value == "true",
+                "plugins" => session.show_plugins = value == "true",
+                "outline" => session.show_outline = value == "true",
+                "pdf-width" => session.pdf_width = value.parse().ok(),
+                "view" => session.view = parse_view(&value),
+                _ => {}
+            }
+        }
+        return session;
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(note) = &self.note {
+            lines.push(format!("note = {}", note));
+        }
+        lines.extend(self.back.iter().map(|n| format!("back = {}", n)));
+        lines.extend(self.forward.iter().map(|n| format!("forward = {}", n)));
+        lines.push(format!("logs = {}", self.show_logs));
+        lines.push(format!("plugins = {}", self.show_plugins));
+        lines.push(format!("outline = {}", self.show_outline));
+        if let Some(width) = self.pdf_width {
+            lines.push(format!("pdf-width = {}", width));
+        }
+        if let Some(view) = self.view {
+            lines.push(format!(
+                "view = {} {} {}",
+                view.scroll, view.cursor, view.anchor
+            ));
+        }
+        return lines.join("\n") + "\n";
+    }
+
+    // the key a vault's session is stored under
+    pub fn key(root: &Path) -> String {
+        return format!("session {}", root.display());
+    }
+
+    // the last session in a vault, an empty one the first time
+    //   sessions used to be kept in a .session file in the vault, which is read when storage hasn't one yet
+    pub fn load(storage: Option<&dyn eframe::Storage>, root: &Path) -> Self {
+        let text = storage
+            .and_then(|storage| storage.get_string(&Self::key(root)))
+            .or_else(|| fs::read_to_string(file(root)).ok())
+            .unwrap_or_default();
+        return Self::parse(&text);
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage, root: &Path) {
+        storage.set_string(&Self::key(root), self.to_text());
+    }
+}
+
+fn parse_view(value: &str) -> Option<View> {
+    let mut parts = value.split(' ');
+    return Some(View {
+        scroll: parts.next()?.parse().ok()?,
+        cursor: parts.next()?.parse().ok()?,
+        anchor: parts.next()?.parse().ok()?,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::session::Session;
+    use crate::views::View;
+
+    #[test]
+    fn test_round_trip() {
+        let session = Session {
+            note: Some("/v/notes/a b.md".to_string()),
+            back: vec!["/v/index.md".to_string(), "/v/x.md".to_string()],
+            forward: vec!["/v/y.md".to_string()],
+            show_logs: true,
+            show_plugins: false,
+            show_outline: true,
+            pdf_width: Some(312.5),
+            view: Some(View {
+                scroll: 80.5,
+                cursor: 14,
+                anchor: 9,
+            }),
+        };
+        assert_eq!(session, Session::parse(&session.to_text()));
+        assert_eq!(Session::default(), Session::parse("note\nwhat = ever\n"));
+    }
+}
@@ -0,0 +1,235 @@
+use crate::lansync;
+use noters_core::render;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// sharing one note read only at a link on the local network, for a limited time
+//   the page is the note as it was when shared, behind a random token so it can't be guessed
+
+pub const DURATIONS: [(&str, Duration); 3] = [
+    ("15 minutes", Duration::from_secs(15 * 60)),
+    ("1 hour", Duration::from_secs(60 * 60)),
+    ("1 day", Duration::from_secs(24 * 60 * 60)),
+];
+
+const STYLE: &str = "body{max-width:46em;margin:2em auto;padding:0 1em;font-family:sans-serif;line-height:1.5;color:#222}pre,code{background:#f4f4f4}pre{padding:.5em;overflow:auto}";
+
+pub fn token() -> String {
+    let mut bytes = [0u8; 16];
+    ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), &mut bytes)
+        .expect("the system has randomness");
+    return bytes.iter().map(|b| format!("{:02x}", b)).collect();
+}
+
+// compares secrets without stopping at the first difference, so timing doesn't give them away
+pub fn same_token(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    return a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0;
+}
+
+// the note as a whole html document
+pub fn page(title: &str, text: &str) -> String {
+    return format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><meta name=\"robots\" content=\"noindex\"><title>{}</title><style>{}</style></head><body>\n{}</body></html>\n",
+        render::escape(title),
+        STYLE,
+        render::to_html_unlinked(text)
+    );
+}
+
+// the path asked for in an http request, only GET is served
+fn requested(request: &str) -> Option<&str> {
+    let mut parts = request.lines().next()?.split_whitespace();
+    if parts.next()? != "GET" {
+        return None;
+    }
+    return parts.next();
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nX-Robots-Tag: noindex\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    stream.write_all(head.as_bytes())?;
+    return stream.write_all(body.as_bytes());
+}
+
+fn handle(stream: &mut TcpStream, path: &str, page: &str, expired: bool) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let is_shared = |asked: &str| same_token(asked.as_bytes(), path.as_bytes());
+    return match requested(&request) {
+        Some(asked) if is_shared(asked) && expired => respond(
+            stream,
+            "410 Gone",
+            "<p>This link has expired, ask for a new one.</p>",
+        ),
+        Some(asked) if is_shared(asked) => respond(stream, "200 OK", page),
+        Some(_) => respond(stream, "404 Not Found", "<p>Nothing here.</p>"),
+        None => respond(stream, "405 Method Not Allowed", ""),
+    };
+}
+
+pub struct Share {
+    pub url: String,
+    pub title: String,
+    pub expires: Instant,
+    stop: Arc<AtomicBool>,
+}
+
+impl Share {
+    pub fn start(title: &str, text: &str, lasts: Duration) -> io::Result<Self> {
+        let listener = TcpListener::bind("0.0.0.0:0")?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        let host = lansync::local_addr().map_or("localhost".to_string(), |ip| ip.to_string());
+        let path = format!("/{}", token());
+        let url = format!("http://{}:{}{}", host, port, path);
+        let page: Arc<str> = page(title, text).into();
+        let path: Arc<str> = path.into();
+        let expires = Instant::now() + lasts;
+        let stop = Arc::new(AtomicBool::new(false));
+        {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                // stays up a little past the end to tell late visitors the link expired
+                while !stop.load(Ordering::Relaxed)
+                    && Instant::now() < expires + Duration::from_secs(60 * 60)
+                {
+                    match listener.accept() {
+                        Ok((mut stream, addr)) => {
+                            let _ = stream.set_nonblocking(false);
+                            let expired = Instant::now() >= expires;
+                            let (path, page) = (path.clone(), page.clone());
+                            // a slow visitor doesn't hold up the others
+                            std::thread::spawn(move || {
+                                if let Err(e) = handle(&mut stream, &path, &page, expired) {
+                                    tracing::info!("shared note request from {}: {}", addr, e);
+                                }
+                            });
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            std::thread::sleep(Duration::from_millis(100));
+                        }
+                        Err(e) => tracing::warn!("share server: {}", e),
+                    }
+                }
+                tracing::info!("stopped sharing on port {}", port);
+            });
+        }
+        tracing::info!("sharing a note at {}", url);
+        return Ok(Self {
+            url,
+            title: title.to_string(),
+            expires,
+            stop,
+        });
+    }
+
+    pub fn is_expired(&self) -> bool {
+        return Instant::now() >= self.expires;
+    }
+
+    // how long is left, rounded up to minutes
+    pub fn remaining(&self) -> String {
+        let secs = self
+            .expires
+            .saturating_duration_since(Instant::now())
+            .as_secs();
+        let minutes = secs.div_ceil(60);
+        if minutes >= 60 {
+            return format!("{}h {}m", minutes / 60, minutes % 60);
+        }
+        return format!("{}m", minutes);
+    }
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::share::{Share, page, requested};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::{Duration, Instant};
+
+    fn get(url: &str, path: &str) -> String {
+        let addr = url
+            .trim_start_matches("http://")
+            .split('/')
+            .next()
+            .unwrap()
+            .to_string();
+        let port = addr.rsplit(':').next().unwrap();
+        let mut stream = TcpStream::connect(("127.0.0.1", port.parse::<u16>().unwrap())).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, addr).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        return response;
+    }
+
+    #[test]
+    fn test_page() {
+        assert_eq!(
+            Some("/abc"),
+            requested("GET /abc HTTP/1.1\r\nHost: x\r\n\r\n")
+        );
+        assert_eq!(None, requested("POST /abc HTTP/1.1\r\n"));
+        let html = page("a <b> \"c\" 'd'", "# Title\nbody");
+        assert!(html.contains("<title>a &lt;b&gt; &quot;c&quot; &#39;d&#39;</title>"));
+        assert!(html.contains("Title"));
+    }
+
+    #[test]
+    fn test_serve() {
+        let share = Share::start(
+            "Plan",
+            "# Plan\nsecret bits, see [[other]]",
+            Duration::from_secs(60),
+        )
+        .unwrap();
+        let path = &share.url[share.url.rfind('/').unwrap()..];
+
+        // someone connecting and saying nothing doesn't keep the page from the others
+        let port = share
+            .url
+            .rsplit(':')
+            .next()
+            .unwrap()
+            .split('/')
+            .next()
+            .unwrap();
+        let _idle = TcpStream::connect(("127.0.0.1", port.parse::<u16>().unwrap())).unwrap();
+        let start = Instant::now();
+        let ok = get(&share.url, path);
+        assert!(start.elapsed() < Duration::from_secs(4));
+        assert!(ok.starts_with("HTTP/1.1 200 OK"));
+        assert!(ok.contains("secret bits, see other"));
+        assert!(!ok.contains("other.html"));
+        let guessed = get(&share.url, "/0123");
+        assert!(guessed.starts_with("HTTP/1.1 404"));
+        assert!(!guessed.contains("secret"));
+        assert_eq!("1m", share.remaining());
+    }
+}
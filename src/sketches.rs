@@ -0,0 +1,125 @@
+use crate::i18n::tr;
+use eframe::egui::{self, Color32, Pos2, Rect, Sense, Stroke};
+use noters_core::sketch::Sketch;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// how tall a sketch line is in the editor, the drawing fits under the link text
+pub const ROW_HEIGHT: f32 = 180.0;
+
+fn load(path: &Path) -> Sketch {
+    return fs::read_to_string(path)
+        .map(|svg| Sketch::from_svg(&svg))
+        .unwrap_or_default();
+}
+
+// draw the strokes scaled to fit inside a rect, keeping their shape
+pub fn paint(painter: &egui::Painter, rect: Rect, sketch: &Sketch, color: Color32) {
+    let scale = (rect.width() / sketch.width).min(rect.height() / sketch.height);
+    let to_screen = |(x, y): &(f32, f32)| rect.min + egui::vec2(x * scale, y * scale);
+    painter.rect_stroke(
+        Rect::from_min_size(rect.min, egui::vec2(sketch.width, sketch.height) * scale),
+        4.0,
+        Stroke::new(1.0, color.gamma_multiply(0.3)),
+        egui::StrokeKind::Inside,
+    );
+    for stroke in &sketch.strokes {
+        let points: Vec<Pos2> = stroke.iter().map(to_screen).collect();
+        if points.len() == 1 {
+            painter.circle_filled(points[0], 1.0, color);
+        } else {
+            painter.add(egui::Shape::line(points, Stroke::new(2.0 * scale, color)));
+        }
+    }
+}
+
+// sketches read from disk, kept until they're edited
+#[derive(Default)]
+pub struct SketchCache {
+    sketches: HashMap<PathBuf, Sketch>,
+}
+
+impl SketchCache {
+    pub fn get(&mut self, path: &Path) -> &Sketch {
+        return self
+            .sketches
+            .entry(path.to_path_buf())
+            .or_insert_with(|| load(path));
+    }
+
+    pub fn forget(&mut self, path: &Path) {
+        self.sketches.remove(path);
+    }
+}
+
+// the drawing canvas for one sketch attachment
+pub struct SketchEditor {
+    pub path: PathBuf,
+    sketch: Sketch,
+    drawing: Vec<(f32, f32)>,
+}
+
+impl SketchEditor {
+    pub fn open(path: PathBuf) -> Self {
+        let sketch = load(&path);
+        return Self {
+            path,
+            sketch,
+            drawing: Vec::new(),
+        };
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        return fs::write(&self.path, self.sketch.to_svg());
+    }
+
+    // Some(true) once it's saved, Some(false) if it was closed without saving
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<bool> {
+        let mut done = None;
+        egui::Window::new(tr("Sketch"))
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let size = egui::vec2(self.sketch.width, self.sketch.height);
+                let (response, painter) = ui.allocate_painter(size, Sense::drag());
+                let rect = response.rect;
+                painter.rect_filled(rect, 4.0, ui.visuals().extreme_bg_color);
+
+                if let Some(pos) = response.interact_pointer_pos()
+                    && response.dragged()
+                {
+                    let local = pos - rect.min;
+                    self.drawing.push((local.x, local.y));
+                }
+                if response.drag_stopped() && !self.drawing.is_empty() {
+                    self.sketch.strokes.push(std::mem::take(&mut self.drawing));
+                }
+
+                let mut shown = self.sketch.clone();
+                if !self.drawing.is_empty() {
+                    shown.strokes.push(self.drawing.clone());
+                }
+                paint(&painter, rect, &shown, ui.visuals().text_color());
+
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Save")).clicked() {
+                        done = Some(true);
+                    }
+                    if ui.button(tr("Undo stroke")).clicked() {
+                        self.sketch.strokes.pop();
+                    }
+                    if ui.button(tr("Clear")).clicked() {
+                        self.sketch.strokes.clear();
+                    }
+                    if ui.button(tr("Cancel")).clicked() {
+                        done = Some(false);
+                    }
+                });
+            });
+        return done;
+    }
+}
@@ -0,0 +1,214 @@
+use crate::make_color32;
+use cssparser::{Delimiter, ParseError, Parser, ParserInput, Token};
+use cssparser_color::Color;
+use eframe::egui::{Color32, Stroke, TextFormat};
+use std::collections::HashMap;
+use std::path::Path;
+
+// restyling the parts of a note with a small stylesheet, style.css beside config.toml
+//
+//   heading1, heading2 { color: #f5a97f; font-size: 30px; }
+//   link { color: teal; text-decoration: none; }
+//   code, code-block { background: rgba(0, 0, 0, 0.25); }
+//
+//   the elements are heading1 to heading3, paragraph, bold, italic, strikethrough, link,
+//   broken-link, web-link, code, code-block, tag, math, frontmatter and toc, and the properties
+//   color, background, font-size and text-decoration (underline, line-through or none)
+
+// what a stylesheet says about one element, anything left out keeps the theme's look
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rule {
+    pub color: Option<Color32>,
+    pub background: Option<Color32>,
+    pub size: Option<f32>,
+    pub underline: Option<bool>,
+    pub strikethrough: Option<bool>,
+}
+
+impl Rule {
+    // later rules for the same element take over the properties they set
+    fn merge(&mut self, other: &Rule) {
+        self.color = other.color.or(self.color);
+        self.background = other.background.or(self.background);
+        self.size = other.size.or(self.size);
+        self.underline = other.underline.or(self.underline);
+        self.strikethrough = other.strikethrough.or(self.strikethrough);
+    }
+
+    pub fn apply(&self, format: &mut TextFormat) {
+        if let Some(color) = self.color {
+            format.color = color;
+        }
+        if let Some(background) = self.background {
+            format.background = background;
+        }
+        if let Some(size) = self.size {
+            // a heading's line is kept as much taller than its text as it was
+            if let Some(height) = &mut format.line_height {
+                *height += size - format.font_id.size;
+            }
+            format.font_id.size = size;
+        }
+        let line = |on: bool| match on {
+            true => Stroke::new(1.0, format.color),
+            false => Stroke::NONE,
+        };
+        if let Some(underline) = self.underline {
+            format.underline = line(underline);
+        }
+        if let Some(strikethrough) = self.strikethrough {
+            format.strikethrough = line(strikethrough);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StyleSheet {
+    rules: HashMap<String, Rule>,
+}
+
+// a declaration that couldn't be understood
+struct Invalid;
+
+type Error<'i> = ParseError<'i, Invalid>;
+
+fn parse_color<'i>(input: &mut Parser<'i, '_>) -> Result<Color32, Error<'i>> {
+    return match Color::parse(input) {
+        Ok(color @ Color::Rgba(_)) => Ok(make_color32(&color)),
+        _ => Err(input.new_custom_error(Invalid)),
+    };
+}
+
+fn parse_declaration<'i>(input: &mut Parser<'i, '_>, rule: &mut Rule) -> Result<(), Error<'i>> {
+    let name = input.expect_ident()?.to_ascii_lowercase();
+    input.expect_colon()?;
+    match name.as_str() {
+        "color" => rule.color = Some(parse_color(input)?),
+        "background" | "background-color" => rule.background = Some(parse_color(input)?),
+        "font-size" => {
+            let size = match input.next()? {
+                Token::Dimension { value, unit, .. } if unit.eq_ignore_ascii_case("px") => *value,
+                Token::Number { value, .. } => *value,
+                _ => return Err(input.new_custom_error(Invalid)),
+            };
+            if size <= 0.0 {
+                return Err(input.new_custom_error(Invalid));
+            }
+            rule.size = Some(size);
+        }
+        "text-decoration" => {
+            let (underline, strikethrough) = match input.expect_ident()?.as_ref() {
+                "underline" => (true, false),
+                "line-through" => (false, true),
+                "none" => (false, false),
+                _ => return Err(input.new_custom_error(Invalid)),
+            };
+            rule.underline = Some(underline);
+            rule.strikethrough = Some(strikethrough);
+        }
+        _ => return Err(input.new_custom_error(Invalid)),
+    }
+    input.expect_exhausted()?;
+    return Ok(());
+}
+
+impl StyleSheet {
+    // the rules in a stylesheet, with a message for each part that couldn't be used
+    pub fn parse(text: &str) -> (Self, Vec<String>) {
+        let mut sheet = StyleSheet::default();
+        let mut errors = Vec::new();
+        let mut input = ParserInput::new(text);
+        let mut input = Parser::new(&mut input);
+        let mut elements: Vec<String> = Vec::new();
+        while let Ok(token) = input.next().cloned() {
+            match token {
+                Token::Ident(name) => elements.push(name.to_ascii_lowercase()),
+                Token::Comma => {}
+                Token::CurlyBracketBlock => {
+                    let mut rule = Rule::default();
+                    let _ = input.parse_nested_block(|input| {
+                        while !input.is_exhausted() {
+                            input.skip_whitespace();
+                            let line = input.current_source_location().line + 1;
+                            let parsed = input.parse_until_after(Delimiter::Semicolon, |input| {
+                                return parse_declaration(input, &mut rule);
+                            });
+                            if parsed.is_err() {
+                                errors.push(format!("style.css: line {} isn't valid", line));
+                            }
+                        }
+                        return Ok::<(), Error>(());
+                    });
+                    for element in elements.drain(..) {
+                        sheet.rules.entry(element).or_default().merge(&rule);
+                    }
+                }
+                _ => {
+                    let line = input.current_source_location().line + 1;
+                    errors.push(format!("style.css: line {} isn't valid", line));
+                    elements.clear();
+                }
+            }
+        }
+        return (sheet, errors);
+    }
+
+    pub fn load(path: &Path) -> (Self, Vec<String>) {
+        return match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (Self::default(), Vec::new()),
+            Err(e) => (Self::default(), vec![format!("{}: {}", path.display(), e)]),
+        };
+    }
+
+    pub fn rule(&self, element: &str) -> Option<&Rule> {
+        return self.rules.get(element);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stylesheet::{Rule, StyleSheet};
+    use eframe::egui::{Color32, FontId, Stroke, TextFormat};
+
+    #[test]
+    fn test_stylesheet() {
+        let text = "/* mine */\nheading1, link { color: #ff0000; font-size: 30px }\nlink {\n  text-decoration: none;\n  font-size: big;\n  glow: 1px;\n}\ncode { background: rgba(0, 0, 0, 0.5); }\n";
+        let (sheet, errors) = StyleSheet::parse(text);
+        assert_eq!(
+            vec![
+                "style.css: line 5 isn't valid",
+                "style.css: line 6 isn't valid"
+            ],
+            errors
+        );
+        assert_eq!(
+            Some(&Rule {
+                color: Some(Color32::RED),
+                size: Some(30.0),
+                underline: Some(false),
+                strikethrough: Some(false),
+                ..Default::default()
+            }),
+            sheet.rule("link")
+        );
+        assert_eq!(Some(30.0), sheet.rule("heading1").unwrap().size);
+        assert_eq!(
+            Some(Color32::from_rgba_unmultiplied(0, 0, 0, 128)),
+            sheet.rule("code").unwrap().background
+        );
+        assert_eq!(None, sheet.rule("paragraph"));
+
+        let mut format = TextFormat {
+            font_id: FontId::proportional(32.0),
+            line_height: Some(36.0),
+            underline: Stroke::new(1.0, Color32::BLUE),
+            ..Default::default()
+        };
+        sheet.rule("link").unwrap().apply(&mut format);
+        assert_eq!(Color32::RED, format.color);
+        assert_eq!(30.0, format.font_id.size);
+        assert_eq!(Some(34.0), format.line_height);
+        assert_eq!(Stroke::NONE, format.underline);
+    }
+}
@@ -0,0 +1,101 @@
+use eframe::egui::Color32;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::LazyLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+// colouring the insides of fenced code blocks by their language
+//   the syntaxes and theme are loaded the first time a block with a language shows up,
+//   and blocks are only coloured again when they change since this runs every frame
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+
+// one for dark themes and one for light ones
+static THEMES: LazyLock<[Theme; 2]> = LazyLock::new(|| {
+    let mut themes = ThemeSet::load_defaults().themes;
+    return [
+        themes.remove("base16-ocean.dark").unwrap_or_default(),
+        themes.remove("InspiredGitHub").unwrap_or_default(),
+    ];
+});
+
+// past this many blocks the cache starts over, it only needs what's on screen
+const CACHE_LEN: usize = 64;
+
+type Spans = Vec<(Color32, Range<usize>)>;
+
+thread_local! {
+    static CACHE: RefCell<HashMap<u64, Option<Spans>>> = RefCell::new(HashMap::new());
+}
+
+fn spans(language: &str, code: &str, dark: bool) -> Option<Spans> {
+    let syntax = SYNTAXES.find_syntax_by_token(language)?;
+    let theme = &THEMES[if dark { 0 } else { 1 }];
+    let mut lines = HighlightLines::new(syntax, theme);
+    let mut output = Vec::new();
+    let mut pos = 0;
+    for line in LinesWithEndings::from(code) {
+        let pieces = lines.highlight_line(line, &SYNTAXES).ok()?;
+        for (style, piece) in pieces {
+            let color =
+                Color32::from_rgb(style.foreground.r, style.foreground.g, style.foreground.b);
+            output.push((color, pos..pos + piece.len()));
+            pos += piece.len();
+        }
+    }
+    return Some(output);
+}
+
+// the code split into pieces by colour for a dark or light background, none when the language
+//   isn't one that's known
+pub fn highlight<'a>(language: &str, code: &'a str, dark: bool) -> Option<Vec<(Color32, &'a str)>> {
+    if language.is_empty() {
+        return None;
+    }
+    let mut hasher = DefaultHasher::new();
+    (language, code, dark).hash(&mut hasher);
+    let key = hasher.finish();
+    let found = CACHE.with_borrow_mut(|cache| {
+        if let Some(found) = cache.get(&key) {
+            return found.clone();
+        }
+        if cache.len() >= CACHE_LEN {
+            cache.clear();
+        }
+        let found = spans(language, code, dark);
+        cache.insert(key, found.clone());
+        return found;
+    });
+    return found.map(|spans| {
+        spans
+            .into_iter()
+            .map(|(color, range)| (color, &code[range]))
+            .collect()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::syntax::highlight;
+
+    #[test]
+    fn test_highlight() {
+        let code = "fn main() {\n    let s = \"text\"; // done\n}\n";
+        let pieces = highlight("rust", code, true).unwrap();
+        assert_eq!(code, pieces.iter().map(|(_, p)| *p).collect::<String>());
+        let color = |text: &str| pieces.iter().find(|(_, p)| p.contains(text)).unwrap().0;
+        assert_ne!(color("fn"), color("main"));
+        assert_ne!(color("text"), color("done"));
+        // cached the second time round
+        assert_eq!(Some(pieces.clone()), highlight("rust", code, true));
+        assert_ne!(Some(pieces), highlight("rust", code, false));
+        assert_eq!(None, highlight("", code, true));
+        assert_eq!(None, highlight("not a language", code, true));
+    }
+}
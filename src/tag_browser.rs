@@ -0,0 +1,97 @@
+use crate::files::Task;
+use crate::i18n::{tr, trf};
+use eframe::egui;
+use noters_core::vault;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// every #tag in the vault in a panel down the side, picking one lists the notes that have it
+//   the vault is read on a thread each time the panel opens or is refreshed
+
+#[derive(Default)]
+pub struct TagBrowser {
+    pub open: bool,
+    tags: BTreeMap<String, Vec<String>>,
+    // the tag whose notes are listed
+    chosen: Option<String>,
+    reading: Option<Task<BTreeMap<String, Vec<String>>>>,
+}
+
+impl TagBrowser {
+    pub fn show_panel(&mut self, root: &Path) {
+        self.open = true;
+        self.refresh(root);
+    }
+
+    fn refresh(&mut self, root: &Path) {
+        let root = root.to_path_buf();
+        self.reading = Some(Task::spawn(move || vault::tags(&root)));
+    }
+
+    // the panel, returns a note that was clicked
+    pub fn show(&mut self, ctx: &egui::Context, root: &Path) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+        if let Some(reading) = &self.reading {
+            match reading.poll() {
+                Some(tags) => {
+                    self.tags = tags;
+                    self.reading = None;
+                }
+                None => ctx.request_repaint_after(std::time::Duration::from_millis(50)),
+            }
+        }
+
+        let mut opened = None;
+        egui::SidePanel::left("tags")
+            .default_width(220.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading(tr("Tags"));
+                    if ui.small_button("⟳").on_hover_text(tr("Refresh")).clicked() {
+                        self.refresh(root);
+                    }
+                    if ui.small_button("✕").on_hover_text(tr("Close")).clicked() {
+                        self.open = false;
+                    }
+                });
+                if self.reading.is_some() {
+                    ui.spinner();
+                } else if self.tags.is_empty() {
+                    ui.weak(tr("No notes have tags yet"));
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| match &self.chosen {
+                    Some(tag) => {
+                        let mut back = false;
+                        ui.horizontal(|ui| {
+                            back = ui.small_button("◀").on_hover_text(tr("All tags")).clicked();
+                            ui.strong(format!("#{}", tag));
+                        });
+                        for note in self.tags.get(tag).into_iter().flatten() {
+                            if ui.selectable_label(false, note.as_str()).clicked() {
+                                opened = Some(note.clone());
+                            }
+                        }
+                        if back {
+                            self.chosen = None;
+                        }
+                    }
+                    None => {
+                        let mut chosen = None;
+                        for (tag, notes) in &self.tags {
+                            let label = trf("#{} ({})", &[tag, &notes.len()]);
+                            if ui.selectable_label(false, label).clicked() {
+                                chosen = Some(tag.clone());
+                            }
+                        }
+                        if chosen.is_some() {
+                            self.chosen = chosen;
+                        }
+                    }
+                });
+            });
+        return opened;
+    }
+}
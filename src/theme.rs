@@ -0,0 +1,146 @@
+use eframe::egui::{self, Color32, Visuals};
+
+// the colors notes and the app are drawn in, dark and light are built in and config.toml can add
+//   more under [themes.<name>], starting from one of them with `base = "light"` and changing any of
+//   background, text, bold, italic, faint, code, link, broken-link, math, toc, list-marker, tag, done
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    // which of egui's looks the rest of the app takes
+    pub dark: bool,
+    pub background: Color32,
+    pub text: Color32,
+    // bold text and headings
+    pub bold: Color32,
+    pub italic: Color32,
+    // struck out text and frontmatter
+    pub faint: Color32,
+    pub code: Color32,
+    pub link: Color32,
+    // a link to a note that isn't there yet
+    pub broken_link: Color32,
+    pub math: Color32,
+    // a generated table of contents
+    pub toc: Color32,
+    pub list_marker: Color32,
+    pub tag: Color32,
+    // a section whose tasks are all ticked off
+    pub done: Color32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        return Theme::dark();
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        return Theme {
+            name: "dark".to_string(),
+            dark: true,
+            background: Color32::from_rgb(30, 32, 48),
+            text: Color32::from_rgb(202, 211, 248),
+            bold: Color32::from_rgb(255, 255, 255),
+            italic: Color32::from_rgb(200, 200, 200),
+            faint: Color32::from_rgb(130, 130, 130),
+            code: Color32::from_rgb(200, 200, 200),
+            link: Color32::from_rgb(80, 140, 255),
+            broken_link: Color32::from_rgb(230, 90, 90),
+            math: Color32::from_rgb(190, 160, 230),
+            toc: Color32::from_rgb(150, 170, 200),
+            list_marker: Color32::from_rgb(130, 150, 200),
+            tag: Color32::from_rgb(110, 190, 160),
+            done: Color32::from_rgb(80, 160, 80),
+        };
+    }
+
+    pub fn light() -> Self {
+        return Theme {
+            name: "light".to_string(),
+            dark: false,
+            background: Color32::from_rgb(248, 248, 244),
+            text: Color32::from_rgb(50, 52, 64),
+            bold: Color32::from_rgb(0, 0, 0),
+            italic: Color32::from_rgb(60, 60, 60),
+            faint: Color32::from_rgb(140, 140, 140),
+            code: Color32::from_rgb(70, 70, 90),
+            link: Color32::from_rgb(20, 90, 200),
+            broken_link: Color32::from_rgb(190, 40, 40),
+            math: Color32::from_rgb(110, 60, 170),
+            toc: Color32::from_rgb(70, 90, 130),
+            list_marker: Color32::from_rgb(70, 90, 150),
+            tag: Color32::from_rgb(30, 130, 100),
+            done: Color32::from_rgb(40, 130, 40),
+        };
+    }
+
+    pub fn builtin() -> Vec<Theme> {
+        return vec![Theme::dark(), Theme::light()];
+    }
+
+    // change one of the colors by its name in config.toml, false if there's no such color
+    pub fn set(&mut self, key: &str, color: Color32) -> bool {
+        let field = match key {
+            "background" => &mut self.background,
+            "text" => &mut self.text,
+            "bold" => &mut self.bold,
+            "italic" => &mut self.italic,
+            "faint" => &mut self.faint,
+            "code" => &mut self.code,
+            "link" => &mut self.link,
+            "broken-link" => &mut self.broken_link,
+            "math" => &mut self.math,
+            "toc" => &mut self.toc,
+            "list-marker" => &mut self.list_marker,
+            "tag" => &mut self.tag,
+            "done" => &mut self.done,
+            _ => return false,
+        };
+        *field = color;
+        return true;
+    }
+
+    // egui keeps a style for dark and one for light and picks by the system's mode, so the
+    //   mode is fixed to the theme's for its visuals to stay in place
+    pub fn apply(&self, ctx: &egui::Context) {
+        ctx.set_theme(if self.dark {
+            egui::Theme::Dark
+        } else {
+            egui::Theme::Light
+        });
+        ctx.set_visuals(self.visuals());
+    }
+
+    pub fn visuals(&self) -> Visuals {
+        let mut visuals = if self.dark {
+            Visuals::dark()
+        } else {
+            Visuals::light()
+        };
+        visuals.window_fill = self.background;
+        visuals.panel_fill = self.background;
+        visuals.override_text_color = Some(self.text);
+        return visuals;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::theme::Theme;
+    use eframe::egui::Color32;
+
+    #[test]
+    fn test_theme() {
+        let mut theme = Theme::light();
+        assert!(theme.set("broken-link", Color32::RED));
+        assert_eq!(Color32::RED, theme.broken_link);
+        assert!(!theme.set("sparkle", Color32::RED));
+
+        let visuals = theme.visuals();
+        assert!(!visuals.dark_mode);
+        assert_eq!(theme.background, visuals.panel_fill);
+        assert!(Theme::dark().visuals().dark_mode);
+    }
+}
@@ -0,0 +1,115 @@
+use eframe::egui;
+
+const INFO_SECONDS: f64 = 3.0;
+const WARNING_SECONDS: f64 = 6.0;
+const ERROR_SECONDS: f64 = 8.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug)]
+struct Toast {
+    level: Level,
+    text: String,
+    // set the first time it's drawn, so messages raised before the first frame still get their time
+    shown_at: Option<f64>,
+}
+
+// short messages stacked in the bottom corner that go away on their own or when clicked
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(Level::Info, text.into());
+    }
+
+    pub fn warning(&mut self, text: impl Into<String>) {
+        self.push(Level::Warning, text.into());
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(Level::Error, text.into());
+    }
+
+    fn push(&mut self, level: Level, text: String) {
+        self.toasts.push(Toast {
+            level,
+            text,
+            shown_at: None,
+        });
+    }
+
+    fn expire(&mut self, now: f64) {
+        for toast in &mut self.toasts {
+            toast.shown_at.get_or_insert(now);
+        }
+        self.toasts.retain(|toast| {
+            let lifetime = match toast.level {
+                Level::Info => INFO_SECONDS,
+                Level::Warning => WARNING_SECONDS,
+                Level::Error => ERROR_SECONDS,
+            };
+            return now - toast.shown_at.unwrap_or(now) < lifetime;
+        });
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.expire(ctx.input(|i| i.time));
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -36.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for (i, toast) in self.toasts.iter().enumerate() {
+                    let color = match toast.level {
+                        Level::Info => ui.visuals().text_color(),
+                        Level::Warning => ui.visuals().warn_fg_color,
+                        Level::Error => ui.visuals().error_fg_color,
+                    };
+                    let response = egui::Frame::popup(ui.style())
+                        .show(ui, |ui| ui.colored_label(color, &toast.text))
+                        .response;
+                    if response.interact(egui::Sense::click()).clicked() {
+                        dismissed = Some(i);
+                    }
+                }
+            });
+        if let Some(i) = dismissed {
+            self.toasts.remove(i);
+        }
+        ctx.request_repaint_after(std::time::Duration::from_millis(250));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::toast::{Level, Toasts};
+
+    #[test]
+    fn test_expire() {
+        let mut toasts = Toasts::default();
+        toasts.info("saved");
+        toasts.error("failed");
+        toasts.expire(10.0);
+        assert_eq!(2, toasts.toasts.len());
+
+        toasts.info("later");
+        toasts.expire(14.0);
+        let levels: Vec<Level> = toasts.toasts.iter().map(|t| t.level).collect();
+        assert_eq!(vec![Level::Error, Level::Info], levels);
+
+        toasts.expire(20.0);
+        assert!(toasts.toasts.is_empty());
+    }
+}
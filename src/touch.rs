@@ -0,0 +1,245 @@
+use eframe::egui::{self, Pos2, Rect, Vec2};
+
+// touch mode for tablets and convertibles: bigger targets, long press for the context menu,
+//   flick scrolling that carries on after the finger lifts and pinching to size the text
+
+// the smallest a thing to tap should be
+pub const TARGET: f32 = 40.0;
+
+// as long as a click can last, so letting go afterwards isn't taken as a tap too
+const LONG_PRESS: f64 = 0.8;
+// how far a finger can wander before a press turns into a drag
+const SLOP: f32 = 8.0;
+// the share of a flick's speed kept each second
+const FRICTION: f32 = 0.05;
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.0;
+
+pub struct Touch {
+    pub enabled: bool,
+    // editor text size from pinching, 1 is as drawn
+    pub zoom: f32,
+    // points per second still to scroll after a flick
+    velocity: f32,
+    // the finger being followed and where it was last frame
+    finger: Option<(u64, Pos2)>,
+    moved: f32,
+    scrolling: bool,
+    long_pressed: bool,
+}
+
+impl Default for Touch {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            zoom: 1.0,
+            velocity: 0.0,
+            finger: None,
+            moved: 0.0,
+            scrolling: false,
+            long_pressed: false,
+        }
+    }
+}
+
+impl Touch {
+    // bigger buttons and spacing everywhere while touch mode is on
+    pub fn set_enabled(&mut self, ctx: &egui::Context, enabled: bool) {
+        self.enabled = enabled;
+        ctx.style_mut(|style| {
+            let default = egui::style::Spacing::default();
+            if enabled {
+                style.spacing.interact_size.y = TARGET;
+                style.spacing.button_padding = egui::vec2(12.0, 8.0);
+                style.spacing.item_spacing = egui::vec2(10.0, 8.0);
+                style.spacing.scroll.bar_width = 16.0;
+            } else {
+                style.spacing.interact_size = default.interact_size;
+                style.spacing.button_padding = default.button_padding;
+                style.spacing.item_spacing = default.item_spacing;
+                style.spacing.scroll.bar_width = default.scroll.bar_width;
+            }
+        });
+    }
+
+    // a finger dragging the editor scrolls it instead of selecting
+    pub fn is_scrolling(&self) -> bool {
+        return self.scrolling;
+    }
+
+    // follow fingers and pinches, called once a frame before the editor is drawn
+    pub fn input(&mut self, ctx: &egui::Context) {
+        let touches = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Touch { id, phase, pos, .. } => Some((id.0, *phase, *pos)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        });
+        // the first touch turns touch mode on by itself
+        if !self.enabled && !touches.is_empty() {
+            tracing::info!("touch input seen, switching to touch mode");
+            self.set_enabled(ctx, true);
+        }
+        if !self.enabled {
+            return;
+        }
+
+        let dt = ctx.input(|i| i.stable_dt).max(0.001);
+        if let Some(pinch) = ctx.input(|i| i.multi_touch()) {
+            self.zoom = (self.zoom * pinch.zoom_delta).clamp(MIN_ZOOM, MAX_ZOOM);
+            self.finger = None;
+            self.scrolling = false;
+            return;
+        }
+        for (id, phase, pos) in touches {
+            match phase {
+                egui::TouchPhase::Start => {
+                    self.finger = Some((id, pos));
+                    self.velocity = 0.0;
+                    self.moved = 0.0;
+                    self.long_pressed = false;
+                }
+                egui::TouchPhase::Move => {
+                    let Some((finger, last)) = self.finger.filter(|(f, _)| *f == id) else {
+                        continue;
+                    };
+                    let dy = pos.y - last.y;
+                    self.moved += (pos - last).length();
+                    if self.moved > SLOP && !self.long_pressed {
+                        self.scrolling = true;
+                    }
+                    if self.scrolling {
+                        self.velocity = dy / dt;
+                    }
+                    self.finger = Some((finger, pos));
+                }
+                egui::TouchPhase::End | egui::TouchPhase::Cancel => {
+                    self.finger = None;
+                    self.scrolling = false;
+                }
+            }
+        }
+    }
+
+    // scroll the area the editor is in, following the finger and then coasting
+    pub fn scroll(&mut self, ui: &egui::Ui) {
+        if !self.enabled || self.velocity.abs() < 1.0 {
+            self.velocity = 0.0;
+            return;
+        }
+        let dt = ui.input(|i| i.stable_dt).max(0.001);
+        ui.scroll_with_delta(Vec2::new(0.0, self.velocity * dt));
+        if self.finger.is_none() {
+            self.velocity *= FRICTION.powf(dt);
+        } else if !self.scrolling {
+            self.velocity = 0.0;
+        }
+        ui.ctx().request_repaint();
+    }
+
+    // true once a press has been held still long enough to stand in for a right click
+    pub fn long_press(&mut self, ctx: &egui::Context, response: &egui::Response) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let (down, held) = ctx.input(|i| {
+            let pointer = &i.pointer;
+            let started = pointer.press_start_time().unwrap_or(i.time);
+            let held = !pointer.is_decidedly_dragging() && i.time - started > LONG_PRESS;
+            return (pointer.primary_down(), pointer.primary_down() && held);
+        });
+        if !down {
+            self.long_pressed = false;
+            return false;
+        }
+        if self.long_pressed || self.scrolling {
+            return false;
+        }
+        if response.long_touched() || (held && response.hovered()) {
+            self.long_pressed = true;
+            return true;
+        }
+        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        return false;
+    }
+
+    // where to look for something under a tap: the spot itself, then rings around it
+    pub fn nearby(&self, pos: Pos2) -> Vec<Pos2> {
+        let mut spots = vec![pos];
+        if !self.enabled {
+            return spots;
+        }
+        for radius in [TARGET / 4.0, TARGET / 2.0] {
+            for i in 0..8 {
+                let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+                spots.push(pos + Vec2::angled(angle) * radius);
+            }
+        }
+        return spots;
+    }
+
+    // a tap sized square at the right hand end of a row
+    pub fn row_target(row: Rect, right: f32) -> Rect {
+        return Rect::from_center_size(
+            egui::pos2(right - TARGET / 2.0, row.center().y),
+            Vec2::splat(TARGET.max(row.height())),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::touch::{TARGET, Touch};
+    use eframe::egui::{self, Pos2, Rect};
+
+    #[test]
+    fn test_nearby() {
+        let mut touch = Touch::default();
+        let pos = Pos2::new(100.0, 100.0);
+        assert_eq!(vec![pos], touch.nearby(pos));
+        touch.enabled = true;
+        let spots = touch.nearby(pos);
+        assert_eq!(17, spots.len());
+        assert!(
+            spots
+                .iter()
+                .all(|spot| spot.distance(pos) <= TARGET / 2.0 + 0.01)
+        );
+    }
+
+    #[test]
+    fn test_pinch_and_flick() {
+        let ctx = egui::Context::default();
+        let mut touch = Touch::default();
+        let finger = |phase, y| egui::Event::Touch {
+            device_id: egui::TouchDeviceId(1),
+            id: egui::TouchId(7),
+            phase,
+            pos: Pos2::new(50.0, y),
+            force: None,
+        };
+        let frames = [
+            vec![finger(egui::TouchPhase::Start, 200.0)],
+            vec![finger(egui::TouchPhase::Move, 180.0)],
+            vec![finger(egui::TouchPhase::Move, 150.0)],
+        ];
+        for events in frames {
+            let input = egui::RawInput {
+                events,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| touch.input(ctx));
+        }
+        assert!(touch.enabled);
+        assert!(touch.is_scrolling());
+        assert!(touch.velocity < 0.0);
+
+        let row = Rect::from_min_size(Pos2::new(0.0, 10.0), egui::vec2(300.0, 20.0));
+        let target = Touch::row_target(row, 300.0);
+        assert_eq!(300.0, target.right());
+        assert_eq!(TARGET, target.height());
+    }
+}
@@ -0,0 +1,53 @@
+use std::ops::Range;
+
+// lines highlighted past the top and bottom of the screen
+const MARGIN_LINES: usize = 50;
+// the window moves in steps of this many lines, so scrolling inside a step keeps the same layout job
+//   and egui can reuse its cached galley
+const CHUNK_LINES: usize = 200;
+
+// the byte range worth fully highlighting, given the bytes visible on screen,
+//   grown by a margin and snapped out to whole chunks of lines
+pub fn line_window(text: &str, visible: Range<usize>) -> Range<usize> {
+    let first_line = text[..visible.start.min(text.len())].matches('\n').count();
+    let last_line = first_line
+        + text[visible.start.min(text.len())..visible.end.clamp(visible.start, text.len())]
+            .matches('\n')
+            .count();
+
+    let start_line = first_line.saturating_sub(MARGIN_LINES) / CHUNK_LINES * CHUNK_LINES;
+    let end_line = (last_line + MARGIN_LINES).div_ceil(CHUNK_LINES) * CHUNK_LINES;
+
+    let mut start = 0;
+    let mut end = text.len();
+    for (line, (i, _)) in text.match_indices('\n').enumerate() {
+        if line + 1 == start_line {
+            start = i + 1;
+        }
+        if line + 1 == end_line {
+            end = i + 1;
+            break;
+        }
+    }
+    return start..end;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::viewport::line_window;
+
+    #[test]
+    fn test_line_window() {
+        let text = "line\n".repeat(1000);
+        // small notes and the top of big ones are covered from the start
+        assert_eq!(0..6, line_window("a\nb\nc\n", 2..3));
+        assert_eq!(0..1000, line_window(&text, 0..50));
+
+        // lines 500 to 520 on screen
+        let window = line_window(&text, 2500..2600);
+        assert_eq!(400 * 5..600 * 5, window);
+        // scrolling a little keeps the same window
+        assert_eq!(window, line_window(&text, 2550..2650));
+        assert_eq!(800 * 5..text.len(), line_window(&text, 4800..text.len()));
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// where each note was left, so going back to it or opening it again picks up in the same place
+//   kept in .views at the vault root, a line per note of `scroll cursor anchor path`
+
+// how a note was being looked at, the cursor and the other end of the selection are chars
+//   in the full text so folding doesn't shift them
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct View {
+    pub scroll: f32,
+    pub cursor: usize,
+    pub anchor: usize,
+}
+
+#[derive(Default)]
+pub struct Views {
+    root: PathBuf,
+    views: HashMap<String, View>,
+}
+
+fn file(root: &Path) -> PathBuf {
+    return root.join(".views");
+}
+
+fn parse_line(line: &str) -> Option<(String, View)> {
+    let mut parts = line.splitn(4, ' ');
+    let scroll = parts.next()?.parse().ok()?;
+    let cursor = parts.next()?.parse().ok()?;
+    let anchor = parts.next()?.parse().ok()?;
+    let path = parts.next().filter(|p| !p.is_empty())?;
+    return Some((
+        path.to_string(),
+        View {
+            scroll,
+            cursor,
+            anchor,
+        },
+    ));
+}
+
+impl Views {
+    // what's been saved for a vault, a missing or mangled file just means starting fresh
+    pub fn load(root: &Path) -> Self {
+        let text = fs::read_to_string(file(root)).unwrap_or_default();
+        return Self {
+            root: root.to_path_buf(),
+            views: text.lines().filter_map(parse_line).collect(),
+        };
+    }
+
+    // notes are keyed by where they are in the vault, so the file moves with it
+    fn key(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        return relative.to_string_lossy().to_string();
+    }
+
+    pub fn get(&self, path: &Path) -> Option<View> {
+        return self.views.get(&self.key(path)).copied();
+    }
+
+    // note where a note was left and write it all out
+    pub fn remember(&mut self, path: &Path, view: View) {
+        let key = self.key(path);
+        if self.views.get(&key) == Some(&view) {
+            return;
+        }
+        self.views.insert(key, view);
+        let mut paths: Vec<&String> = self.views.keys().collect();
+        paths.sort();
+        let lines: Vec<String> = paths
+            .into_iter()
+            .map(|path| {
+                let v = self.views[path];
+                return format!("{} {} {} {}", v.scroll, v.cursor, v.anchor, path);
+            })
+            .collect();
+        if let Err(e) = fs::write(file(&self.root), lines.join("\n") + "\n") {
+            tracing::warn!("Couldn't save where notes were left: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::views::{View, Views, parse_line};
+    use noters_core::testing::TempDir;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            Some((
+                "a folder/my note.md".to_string(),
+                View {
+                    scroll: 120.5,
+                    cursor: 40,
+                    anchor: 32,
+                }
+            )),
+            parse_line("120.5 40 32 a folder/my note.md")
+        );
+        assert_eq!(None, parse_line("120.5 40 32"));
+        assert_eq!(None, parse_line("down 40 32 note.md"));
+    }
+
+    #[test]
+    fn test_remember() {
+        let root = TempDir::new("views");
+        let view = View {
+            scroll: 300.0,
+            cursor: 12,
+            anchor: 12,
+        };
+        let mut views = Views::load(&root);
+        assert_eq!(None, views.get(&root.join("index.md")));
+        views.remember(&root.join("index.md"), view);
+        views.remember(&root.join("b/c.md"), View::default());
+
+        let views = Views::load(&root);
+        assert_eq!(Some(view), views.get(&root.join("index.md")));
+        assert_eq!(Some(View::default()), views.get(&root.join("b/c.md")));
+    }
+}